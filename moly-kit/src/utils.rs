@@ -1,5 +1,14 @@
 //! Internally used to hold utility modules but exposes some very helpful ones.
 
 pub(crate) mod audio;
+#[cfg(feature = "document-extraction")]
+pub mod extraction;
 pub mod makepad;
+pub mod mcp;
+pub mod resources;
 pub(crate) mod scraping;
+pub mod slash_commands;
+pub mod tool_audit;
+pub mod tool_calls;
+pub mod tool_policy;
+pub mod tool_result;
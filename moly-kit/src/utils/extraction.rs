@@ -0,0 +1,533 @@
+//! Text extraction from common document formats.
+//!
+//! Most models can only read text, so documents like PDFs, Word files,
+//! spreadsheets and source archives are converted into markdown before being
+//! handed to them. Each page, sheet or archived file gets its own section so
+//! the model can still reference where something came from.
+
+use crate::aitk::{protocol::Attachment, utils::asynchronous::BoxPlatformSendFuture};
+use std::collections::VecDeque;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{Cursor, Read};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Upper bound for the extracted text of a single attachment, in characters.
+///
+/// Keeps a huge spreadsheet or archive from blowing up the context window.
+pub const MAX_EXTRACTED_CHARS: usize = 200_000;
+
+/// Upper bound for the number of entries looked at inside an archive.
+const MAX_ARCHIVE_ENTRIES: usize = 1_000;
+
+/// File extensions that are read as plain text.
+const TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "md", "markdown", "csv", "tsv", "json", "jsonl", "toml", "yaml", "yml", "xml", "html",
+    "htm", "css", "scss", "js", "jsx", "ts", "tsx", "rs", "py", "rb", "go", "java", "kt", "swift",
+    "c", "h", "cc", "cpp", "hpp", "cs", "php", "sh", "bash", "zsh", "ps1", "sql", "lua", "dart",
+    "ini", "cfg", "conf", "log", "tex", "r", "scala", "zig", "vue", "svelte",
+];
+
+/// Document formats that text can be extracted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    Pdf,
+    Docx,
+    Xlsx,
+    /// A zip archive, usually containing source code.
+    Archive,
+    /// Plain text or source code, passed through as is.
+    Text,
+}
+
+impl DocumentKind {
+    /// Guess the kind of document from its file name and content type.
+    pub fn detect(name: &str, content_type: Option<&str>) -> Option<Self> {
+        let extension = name
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_lowercase())
+            .unwrap_or_default();
+
+        match (content_type.unwrap_or_default(), extension.as_str()) {
+            ("application/pdf", _) | (_, "pdf") => Some(Self::Pdf),
+            ("application/vnd.openxmlformats-officedocument.wordprocessingml.document", _)
+            | (_, "docx") => Some(Self::Docx),
+            ("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", _)
+            | (_, "xlsx") => Some(Self::Xlsx),
+            ("application/zip", _) | ("application/x-zip-compressed", _) | (_, "zip") => {
+                Some(Self::Archive)
+            }
+            (content_type, extension)
+                if content_type.starts_with("text/") || TEXT_EXTENSIONS.contains(&extension) =>
+            {
+                Some(Self::Text)
+            }
+            _ => None,
+        }
+    }
+
+    /// Guess the kind of document of an attachment.
+    pub fn for_attachment(attachment: &Attachment) -> Option<Self> {
+        Self::detect(&attachment.name, attachment.content_type.as_deref())
+    }
+}
+
+/// Check if text can be extracted from the given attachment.
+pub fn can_extract(attachment: &Attachment) -> bool {
+    attachment.is_available() && DocumentKind::for_attachment(attachment).is_some()
+}
+
+#[derive(Debug)]
+pub enum ExtractionError {
+    /// The attachment is not a document we know how to read.
+    Unsupported,
+    /// The attachment content could not be read.
+    Io(std::io::Error),
+    /// The document is corrupted or uses features we can't handle.
+    Malformed(String),
+}
+
+impl std::fmt::Display for ExtractionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractionError::Unsupported => write!(f, "unsupported document format"),
+            ExtractionError::Io(e) => write!(f, "failed to read document: {}", e),
+            ExtractionError::Malformed(e) => write!(f, "malformed document: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExtractionError {}
+
+/// Storage for previously extracted text, so documents are not parsed again
+/// every time they are sent or previewed.
+///
+/// Moly Kit doesn't persist anything by itself, so the host app decides where
+/// (and if) the text is stored.
+pub trait ExtractionCache: Send + Sync {
+    /// Get the cached text for the attachment, if any.
+    fn load(&self, attachment: &Attachment) -> BoxPlatformSendFuture<'static, Option<String>>;
+
+    /// Save the text extracted from the attachment.
+    fn store(&self, attachment: &Attachment, text: &str) -> BoxPlatformSendFuture<'static, ()>;
+}
+
+static CACHE: OnceLock<Box<dyn ExtractionCache>> = OnceLock::new();
+
+/// Set the global cache used by [`extract_attachment`].
+///
+/// Only the first call has effect.
+pub fn set_extraction_cache(cache: impl ExtractionCache + 'static) {
+    if CACHE.set(Box::new(cache)).is_err() {
+        ::log::warn!("Extraction cache was already set");
+    }
+}
+
+/// How many extracted texts are kept in memory, for attachments shown again
+/// and again like the ones in the prompt input or in the chat history.
+const MEMORY_CACHE_SIZE: usize = 32;
+
+/// Recently extracted texts, by [`memory_key`], most recent last.
+static MEMORY_CACHE: Mutex<VecDeque<(String, Arc<str>)>> = Mutex::new(VecDeque::new());
+
+fn memory_get(key: &str) -> Option<Arc<str>> {
+    let cache = MEMORY_CACHE.lock().unwrap();
+    cache
+        .iter()
+        .find(|(cached_key, _)| cached_key == key)
+        .map(|(_, text)| text.clone())
+}
+
+fn memory_put(key: String, text: Arc<str>) {
+    let mut cache = MEMORY_CACHE.lock().unwrap();
+    cache.retain(|(cached_key, _)| *cached_key != key);
+    if cache.len() >= MEMORY_CACHE_SIZE {
+        cache.pop_front();
+    }
+    cache.push_back((key, text));
+}
+
+/// Identifies an attachment by its persistence key or, if it has none yet, by
+/// its name and content.
+fn memory_key(attachment: &Attachment, content: &[u8]) -> String {
+    if let Some(key) = attachment.get_persistence_key() {
+        return key.to_string();
+    }
+
+    let mut hasher = DefaultHasher::new();
+    attachment.name.hash(&mut hasher);
+    content.hash(&mut hasher);
+    format!("content:{:x}", hasher.finish())
+}
+
+/// Extract the text of an attachment as markdown, using the caches if available.
+///
+/// Parsing runs on its own thread so big documents don't hold up the executor.
+pub async fn extract_attachment(attachment: &Attachment) -> Result<String, ExtractionError> {
+    let kind = DocumentKind::for_attachment(attachment).ok_or(ExtractionError::Unsupported)?;
+
+    if let Some(text) = attachment
+        .get_persistence_key()
+        .and_then(|key| memory_get(&key.to_string()))
+    {
+        return Ok(text.to_string());
+    }
+
+    if let Some(cache) = CACHE.get() {
+        if let Some(text) = cache.load(attachment).await {
+            return Ok(text);
+        }
+    }
+
+    let content = attachment.read().await.map_err(ExtractionError::Io)?;
+    let key = memory_key(attachment, &content);
+    if let Some(text) = memory_get(&key) {
+        return Ok(text.to_string());
+    }
+
+    let text = extract_text_off_thread(kind, content).await?;
+    memory_put(key, text.as_str().into());
+
+    if let Some(cache) = CACHE.get() {
+        cache.store(attachment, &text).await;
+    }
+
+    Ok(text)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn extract_text_off_thread(
+    kind: DocumentKind,
+    content: Arc<[u8]>,
+) -> Result<String, ExtractionError> {
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(extract_text(kind, &content));
+    });
+
+    receiver
+        .await
+        .map_err(|_| ExtractionError::Malformed("extraction was interrupted".to_string()))?
+}
+
+/// There are no threads to spare on the web.
+#[cfg(target_arch = "wasm32")]
+async fn extract_text_off_thread(
+    kind: DocumentKind,
+    content: Arc<[u8]>,
+) -> Result<String, ExtractionError> {
+    extract_text(kind, &content)
+}
+
+/// Extract the text of an in-memory document as markdown.
+///
+/// The output is truncated to [`MAX_EXTRACTED_CHARS`].
+pub fn extract_text(kind: DocumentKind, content: &[u8]) -> Result<String, ExtractionError> {
+    let mut text = match kind {
+        DocumentKind::Pdf => extract_pdf(content)?,
+        DocumentKind::Docx => extract_docx(content)?,
+        DocumentKind::Xlsx => extract_xlsx(content)?,
+        DocumentKind::Archive => extract_archive(content)?,
+        DocumentKind::Text => String::from_utf8_lossy(content).into_owned(),
+    };
+
+    truncate(&mut text, MAX_EXTRACTED_CHARS);
+    Ok(text)
+}
+
+fn extract_pdf(content: &[u8]) -> Result<String, ExtractionError> {
+    let document = lopdf::Document::load_mem(content)
+        .map_err(|e| ExtractionError::Malformed(e.to_string()))?;
+
+    let mut output = String::new();
+    for page_number in document.get_pages().keys() {
+        let text = document.extract_text(&[*page_number]).unwrap_or_default();
+        push_section(&mut output, &format!("Page {}", page_number), text.trim());
+    }
+
+    Ok(output)
+}
+
+fn extract_docx(content: &[u8]) -> Result<String, ExtractionError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(content))
+        .map_err(|e| ExtractionError::Malformed(e.to_string()))?;
+
+    let mut xml = String::new();
+    archive
+        .by_name("word/document.xml")
+        .map_err(|e| ExtractionError::Malformed(e.to_string()))?
+        .read_to_string(&mut xml)
+        .map_err(ExtractionError::Io)?;
+
+    Ok(docx_xml_to_text(&xml))
+}
+
+fn extract_xlsx(content: &[u8]) -> Result<String, ExtractionError> {
+    use calamine::{Reader, Xlsx};
+
+    let mut workbook: Xlsx<_> = calamine::open_workbook_from_rs(Cursor::new(content.to_vec()))
+        .map_err(|e: calamine::XlsxError| ExtractionError::Malformed(e.to_string()))?;
+
+    let mut output = String::new();
+    for name in workbook.sheet_names() {
+        let Ok(range) = workbook.worksheet_range(&name) else {
+            continue;
+        };
+
+        let mut table = String::new();
+        for (index, row) in range.rows().enumerate() {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|cell| cell.to_string().replace('|', "\\|").replace('\n', " "))
+                .collect();
+
+            table.push_str(&format!("| {} |\n", cells.join(" | ")));
+            if index == 0 {
+                table.push_str(&format!("|{}\n", " --- |".repeat(cells.len())));
+            }
+        }
+
+        push_section(&mut output, &format!("Sheet: {}", name), table.trim_end());
+    }
+
+    Ok(output)
+}
+
+fn extract_archive(content: &[u8]) -> Result<String, ExtractionError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(content))
+        .map_err(|e| ExtractionError::Malformed(e.to_string()))?;
+
+    let mut output = String::new();
+    let mut skipped = Vec::new();
+
+    for index in 0..archive.len().min(MAX_ARCHIVE_ENTRIES) {
+        let Ok(entry) = archive.by_index(index) else {
+            continue;
+        };
+
+        if entry.is_dir() {
+            continue;
+        }
+
+        let name = entry.name().to_string();
+        if DocumentKind::detect(&name, None) != Some(DocumentKind::Text) {
+            skipped.push(name);
+            continue;
+        }
+
+        // The declared size comes from the archive itself, so it's only used to
+        // skip early. The read is capped regardless of what the header says.
+        if entry.size() > MAX_EXTRACTED_CHARS as u64 {
+            skipped.push(format!("{} (too large)", name));
+            continue;
+        }
+
+        let mut bytes = Vec::new();
+        if entry
+            .take(MAX_EXTRACTED_CHARS as u64)
+            .read_to_end(&mut bytes)
+            .is_err()
+        {
+            skipped.push(name);
+            continue;
+        }
+
+        let language = name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext)
+            .unwrap_or_default();
+        let text = String::from_utf8_lossy(&bytes);
+        push_section(
+            &mut output,
+            &format!("File: {}", name),
+            &format!("```{}\n{}\n```", language, text.trim_end()),
+        );
+
+        if output.len() > MAX_EXTRACTED_CHARS {
+            break;
+        }
+    }
+
+    if archive.len() > MAX_ARCHIVE_ENTRIES {
+        skipped.push(format!(
+            "{} more entries not read",
+            archive.len() - MAX_ARCHIVE_ENTRIES
+        ));
+    }
+
+    if !skipped.is_empty() {
+        push_section(&mut output, "Skipped files", &skipped.join("\n"));
+    }
+
+    Ok(output)
+}
+
+/// Collect the text runs of a `word/document.xml`, one line per paragraph.
+fn docx_xml_to_text(xml: &str) -> String {
+    let mut output = String::new();
+    let mut rest = xml;
+    let mut in_text = false;
+
+    while let Some(start) = rest.find('<') {
+        if in_text {
+            output.push_str(&unescape_xml(&rest[..start]));
+        }
+
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+
+        let tag = &rest[start + 1..start + end];
+        let closing = tag.starts_with('/');
+        let self_closing = tag.ends_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+
+        match name {
+            "w:t" => in_text = !closing && !self_closing,
+            "w:p" if closing => output.push('\n'),
+            "w:tab" => output.push('\t'),
+            "w:br" | "w:cr" => output.push('\n'),
+            _ => {}
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+
+    output.trim().to_string()
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn push_section(output: &mut String, title: &str, body: &str) {
+    output.push_str(&format!("## {}\n\n{}\n\n", title, body));
+}
+
+fn truncate(text: &mut String, max_chars: usize) {
+    if let Some((index, _)) = text.char_indices().nth(max_chars) {
+        text.truncate(index);
+        text.push_str("\n\n[Truncated]");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_kind() {
+        assert_eq!(
+            DocumentKind::detect("report.PDF", None),
+            Some(DocumentKind::Pdf)
+        );
+        assert_eq!(
+            DocumentKind::detect("file", Some("application/pdf")),
+            Some(DocumentKind::Pdf)
+        );
+        assert_eq!(
+            DocumentKind::detect("notes.docx", None),
+            Some(DocumentKind::Docx)
+        );
+        assert_eq!(
+            DocumentKind::detect("budget.xlsx", None),
+            Some(DocumentKind::Xlsx)
+        );
+        assert_eq!(
+            DocumentKind::detect("src.zip", None),
+            Some(DocumentKind::Archive)
+        );
+        assert_eq!(
+            DocumentKind::detect("main.rs", None),
+            Some(DocumentKind::Text)
+        );
+        assert_eq!(
+            DocumentKind::detect("readme", Some("text/plain")),
+            Some(DocumentKind::Text)
+        );
+        assert_eq!(DocumentKind::detect("photo.png", Some("image/png")), None);
+    }
+
+    #[test]
+    fn test_docx_xml_to_text() {
+        let xml = r#"<w:document><w:body>
+            <w:p><w:pPr/><w:r><w:t>Hello</w:t></w:r><w:r><w:t xml:space="preserve"> &amp; welcome</w:t></w:r></w:p>
+            <w:p><w:r><w:t>A</w:t><w:tab/><w:t>B</w:t></w:r></w:p>
+        </w:body></w:document>"#;
+
+        assert_eq!(docx_xml_to_text(xml), "Hello & welcome\nA\tB");
+    }
+
+    #[test]
+    fn test_extract_archive() {
+        let mut buffer = Cursor::new(Vec::new());
+        {
+            let mut writer = zip::ZipWriter::new(&mut buffer);
+            let options = zip::write::SimpleFileOptions::default();
+            writer.start_file("src/main.rs", options).unwrap();
+            std::io::Write::write_all(&mut writer, b"fn main() {}\n").unwrap();
+            writer.start_file("logo.png", options).unwrap();
+            std::io::Write::write_all(&mut writer, &[0x89, 0x50]).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let text = extract_text(DocumentKind::Archive, buffer.get_ref()).unwrap();
+        assert!(text.contains("## File: src/main.rs\n\n```rs\nfn main() {}\n```"));
+        assert!(text.contains("## Skipped files\n\nlogo.png"));
+    }
+
+    #[test]
+    fn test_extract_archive_limits() {
+        let mut buffer = Cursor::new(Vec::new());
+        {
+            let mut writer = zip::ZipWriter::new(&mut buffer);
+            let options = zip::write::SimpleFileOptions::default();
+            writer.start_file("big.txt", options).unwrap();
+            std::io::Write::write_all(&mut writer, &vec![b'a'; MAX_EXTRACTED_CHARS + 1]).unwrap();
+            for index in 0..MAX_ARCHIVE_ENTRIES {
+                writer
+                    .start_file(format!("{}.txt", index), options)
+                    .unwrap();
+            }
+            writer.finish().unwrap();
+        }
+
+        let text = extract_text(DocumentKind::Archive, buffer.get_ref()).unwrap();
+        assert!(!text.contains("aaaa"));
+        assert!(text.contains("big.txt (too large)"));
+        assert!(text.contains("## File: 998.txt"));
+        assert!(!text.contains("## File: 999.txt"));
+        assert!(text.contains("1 more entries not read"));
+    }
+
+    #[test]
+    fn test_memory_cache_keeps_recent_texts() {
+        for index in 0..=MEMORY_CACHE_SIZE {
+            memory_put(format!("test:{}", index), index.to_string().into());
+        }
+
+        assert_eq!(memory_get("test:0"), None);
+        assert_eq!(memory_get("test:1").as_deref(), Some("1"));
+
+        memory_put("test:1".to_string(), "updated".into());
+        assert_eq!(memory_get("test:1").as_deref(), Some("updated"));
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut text = "héllo".to_string();
+        truncate(&mut text, 2);
+        assert_eq!(text, "hé\n\n[Truncated]");
+
+        let mut text = "short".to_string();
+        truncate(&mut text, 10);
+        assert_eq!(text, "short");
+    }
+}
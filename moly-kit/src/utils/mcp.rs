@@ -0,0 +1,959 @@
+//! Client for the MCP servers whose tools are offered to the models.
+//!
+//! [`McpClient`] keeps a session with each server and exposes their tools under
+//! namespaced names, `{server_id}__{tool}`. What servers send on their own,
+//! notifications and requests like sampling, is forwarded as JSON to handlers
+//! set by the app, and any other request can be made with
+//! [`McpClient::send_request`], so callers only deal with the protocol messages.
+//!
+//! MCP servers can't run on the web, where the client simply has no servers.
+
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::aitk::protocol::*;
+use crate::aitk::utils::asynchronous::{BoxPlatformSendFuture, BoxPlatformSendStream, spawn};
+
+#[cfg(not(target_arch = "wasm32"))]
+use {
+    futures::future::{self, Either},
+    rmcp::model::ClientJsonRpcMessage,
+    rmcp::model::{
+        CallToolRequestParam, CallToolResult, CancelledNotification, CancelledNotificationParam,
+        ClientCapabilities, ClientInfo, ClientRequest, ElicitationCapability, ErrorData,
+        Implementation, ListRootsResult, Meta, NumberOrString, ProgressToken, Request, RequestId,
+        ServerNotification, ServerRequest, ServerResult,
+    },
+    rmcp::service::{
+        NotificationContext, Peer, PeerRequestOptions, RequestContext, RoleClient, RunningService,
+        Service, ServiceError, ServiceExt,
+    },
+    rmcp::transport::{
+        IntoTransport, SseClientTransport, StreamableHttpClientTransport,
+        common::client_side_sse::BoxedSseResponse,
+        sse_client::{SseClient, SseClientConfig, SseTransportError},
+        streamable_http_client::{
+            StreamableHttpClient, StreamableHttpClientTransportConfig, StreamableHttpError,
+            StreamableHttpPostResponse,
+        },
+    },
+    serde_json::json,
+    std::collections::HashMap,
+    std::sync::Weak,
+    std::time::Duration,
+};
+
+/// Separates the server id from the tool name in namespaced tool names.
+const NAMESPACE_SEPARATOR: &str = "__";
+
+/// How long a stdio server has to exit once its stdin is closed, and again
+/// after SIGTERM, before it's killed.
+#[cfg(not(target_arch = "wasm32"))]
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+type NotificationHandler = dyn Fn(&str, &str, &Value) + Send + Sync;
+
+type RequestHandler = dyn Fn(&str, &str, &Value) -> BoxPlatformSendFuture<'static, Result<Value, String>>
+    + Send
+    + Sync;
+
+/// Gives the bearer token sent to a network server.
+///
+/// It's called before every request, so it should cache the token and only
+/// refresh it when about to expire, or when it's the token the server just
+/// rejected, which is passed as argument.
+pub type TokenSource =
+    dyn Fn(Option<String>) -> BoxPlatformSendFuture<'static, Result<String, String>> + Send + Sync;
+
+/// How to reach an MCP server.
+#[cfg(not(target_arch = "wasm32"))]
+pub enum McpServerTransport {
+    /// A local process spoken to through its stdin and stdout.
+    Stdio(tokio::process::Command),
+    /// Streamable HTTP, with extra headers sent on every request.
+    Http {
+        url: String,
+        headers: HashMap<String, String>,
+        auth: Option<Arc<TokenSource>>,
+    },
+    /// The legacy HTTP with server-sent events transport.
+    Sse {
+        url: String,
+        headers: HashMap<String, String>,
+        auth: Option<Arc<TokenSource>>,
+    },
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl McpServerTransport {
+    /// Authorize the requests to a network server with the tokens of `source`.
+    pub fn with_auth(self, source: Arc<TokenSource>) -> Self {
+        match self {
+            Self::Stdio(command) => Self::Stdio(command),
+            Self::Http { url, headers, .. } => Self::Http {
+                url,
+                headers,
+                auth: Some(source),
+            },
+            Self::Sse { url, headers, .. } => Self::Sse {
+                url,
+                headers,
+                auth: Some(source),
+            },
+        }
+    }
+}
+
+/// Why a server could not be added.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpConnectError {
+    pub message: String,
+    /// Exit code of a stdio process that exited instead of completing the
+    /// handshake.
+    pub exit_code: Option<i32>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<String> for McpConnectError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            exit_code: None,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<&str> for McpConnectError {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl std::fmt::Display for McpConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct Connection {
+    /// Dropping the service ends the session.
+    service: RunningService<RoleClient, McpHandler>,
+    /// The process of a stdio server, stopped once the connection is dropped.
+    child: Option<tokio::process::Child>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // The service is dropped right after, closing the stdin of the process.
+        if let Some(child) = self.child.take() {
+            spawn(stop_process(child));
+        }
+    }
+}
+
+#[derive(Default)]
+struct McpClientState {
+    #[cfg(not(target_arch = "wasm32"))]
+    connections: BTreeMap<String, Connection>,
+    /// Namespaced tools by server id.
+    tools: BTreeMap<String, Vec<Tool>>,
+    notification_handler: Option<Arc<NotificationHandler>>,
+    request_handler: Option<Arc<RequestHandler>>,
+    dangerous_mode_enabled: bool,
+}
+
+/// Sessions with the MCP servers, by server id.
+///
+/// Cheap to clone, every clone shares the same sessions.
+#[derive(Clone, Default)]
+pub struct McpClient(Arc<Mutex<McpClientState>>);
+
+impl McpClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Called with the server id, method and params of every notification
+    /// servers send.
+    pub fn set_notification_handler(
+        &self,
+        handler: impl Fn(&str, &str, &Value) + Send + Sync + 'static,
+    ) {
+        self.0.lock().unwrap().notification_handler = Some(Arc::new(handler));
+    }
+
+    /// Called with the server id, method and params of the requests servers
+    /// send, like `sampling/createMessage`, and answered with its result.
+    ///
+    /// Servers connected after this is set are told the client supports
+    /// sampling and elicitation.
+    pub fn set_request_handler(
+        &self,
+        handler: impl Fn(&str, &str, &Value) -> BoxPlatformSendFuture<'static, Result<Value, String>>
+        + Send
+        + Sync
+        + 'static,
+    ) {
+        self.0.lock().unwrap().request_handler = Some(Arc::new(handler));
+    }
+
+    /// Let widgets without a tool policy run tool calls without asking first.
+    pub fn set_dangerous_mode_enabled(&self, enabled: bool) {
+        self.0.lock().unwrap().dangerous_mode_enabled = enabled;
+    }
+
+    pub fn get_dangerous_mode_enabled(&self) -> bool {
+        self.0.lock().unwrap().dangerous_mode_enabled
+    }
+
+    /// The namespaced tools of all connected servers.
+    pub fn tools(&self) -> Vec<Tool> {
+        self.0
+            .lock()
+            .unwrap()
+            .tools
+            .values()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    /// The namespaced tools of one server.
+    pub fn server_tools(&self, server_id: &str) -> Vec<Tool> {
+        self.0
+            .lock()
+            .unwrap()
+            .tools
+            .get(server_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Find the server and the tool name behind a namespaced tool name.
+    fn resolve_tool(&self, namespaced_name: &str) -> Option<(String, String)> {
+        let state = self.0.lock().unwrap();
+        state.tools.iter().find_map(|(server_id, tools)| {
+            tools.iter().find(|tool| tool.name == namespaced_name)?;
+            let tool = namespaced_name
+                .strip_prefix(server_id.as_str())?
+                .strip_prefix(NAMESPACE_SEPARATOR)?;
+            Some((server_id.clone(), tool.to_string()))
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl McpClient {
+    /// Connect to a server and list its tools.
+    ///
+    /// Adding a server under an id already in use replaces the previous session.
+    pub async fn add_server(
+        &self,
+        server_id: &str,
+        transport: McpServerTransport,
+    ) -> Result<(), McpConnectError> {
+        match transport {
+            McpServerTransport::Stdio(mut command) => {
+                // Only a fallback, for when the process can't be stopped gracefully
+                // because the runtime is shutting down.
+                command
+                    .stdin(std::process::Stdio::piped())
+                    .stdout(std::process::Stdio::piped())
+                    .kill_on_drop(true);
+                let mut child = command
+                    .spawn()
+                    .map_err(|e| format!("Could not start the process: {}", e))?;
+                let stdout = child.stdout.take().ok_or("The process has no stdout")?;
+                let stdin = child.stdin.take().ok_or("The process has no stdin")?;
+
+                let (service, tools) = match self.start_session(server_id, (stdout, stdin)).await {
+                    Ok(session) => session,
+                    Err(message) => {
+                        // The stdin of the process is closed by now, so it either
+                        // exited already or does shortly.
+                        let waited = tokio::time::timeout(STOP_GRACE_PERIOD, child.wait()).await;
+                        let exit_code = match waited {
+                            Ok(Ok(status)) => status.code(),
+                            _ => {
+                                spawn(stop_process(child));
+                                None
+                            }
+                        };
+                        return Err(McpConnectError { message, exit_code });
+                    }
+                };
+                self.insert(server_id, service, tools, Some(child));
+                Ok(())
+            }
+            McpServerTransport::Http { url, headers, auth } => {
+                let client = AuthClient {
+                    http: http_client(&headers)?,
+                    auth,
+                };
+                let transport = StreamableHttpClientTransport::with_client(
+                    client,
+                    StreamableHttpClientTransportConfig::with_uri(url),
+                );
+                let (service, tools) = self.start_session(server_id, transport).await?;
+                self.insert(server_id, service, tools, None);
+                Ok(())
+            }
+            McpServerTransport::Sse { url, headers, auth } => {
+                let client = AuthClient {
+                    http: http_client(&headers)?,
+                    auth,
+                };
+                let config = SseClientConfig {
+                    sse_endpoint: url.into(),
+                    ..Default::default()
+                };
+                let transport = SseClientTransport::start_with_client(client, config)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let (service, tools) = self.start_session(server_id, transport).await?;
+                self.insert(server_id, service, tools, None);
+                Ok(())
+            }
+        }
+    }
+
+    /// Start a session over `transport` and list the tools of the server.
+    async fn start_session<T, E, A>(
+        &self,
+        server_id: &str,
+        transport: T,
+    ) -> Result<(RunningService<RoleClient, McpHandler>, Vec<Tool>), String>
+    where
+        T: IntoTransport<RoleClient, E, A>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let handler = McpHandler {
+            server_id: server_id.to_string(),
+            state: Arc::downgrade(&self.0),
+            info: self.client_info(),
+        };
+        let service = handler.serve(transport).await.map_err(|e| e.to_string())?;
+
+        let has_tools = service
+            .peer_info()
+            .is_some_and(|info| info.capabilities.tools.is_some());
+        let tools = if has_tools {
+            list_tools(server_id, service.peer()).await?
+        } else {
+            Vec::new()
+        };
+
+        Ok((service, tools))
+    }
+
+    fn insert(
+        &self,
+        server_id: &str,
+        service: RunningService<RoleClient, McpHandler>,
+        tools: Vec<Tool>,
+        child: Option<tokio::process::Child>,
+    ) {
+        let mut state = self.0.lock().unwrap();
+        state.tools.insert(server_id.to_string(), tools);
+        state
+            .connections
+            .insert(server_id.to_string(), Connection { service, child });
+    }
+
+    /// End the session with a server.
+    ///
+    /// Stdio processes are stopped as the MCP spec asks: their stdin is closed,
+    /// then they get SIGTERM and finally SIGKILL if they don't exit in time.
+    pub fn remove_server(&self, server_id: &str) {
+        let mut state = self.0.lock().unwrap();
+        state.tools.remove(server_id);
+        state.connections.remove(server_id);
+    }
+
+    /// Send a request to a server, given its method and params, and return the
+    /// result.
+    pub async fn send_request(
+        &self,
+        server_id: &str,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, String> {
+        let peer = self.peer(server_id)?;
+        let request: ClientRequest =
+            serde_json::from_value(json!({ "method": method, "params": params }))
+                .map_err(|e| format!("Invalid {} request: {}", method, e))?;
+        let result = peer
+            .send_request(request)
+            .await
+            .map_err(|e| e.to_string())?;
+        serde_json::to_value(result).map_err(|e| e.to_string())
+    }
+
+    /// Call a tool given its namespaced name, with `call_id` as progress token.
+    ///
+    /// If `stop` resolves before the call finishes, the server is told to abandon
+    /// it and the reason `stop` resolved with is reported instead. Failures are
+    /// reported as an error result, for the model to see.
+    pub async fn call_tool(
+        &self,
+        namespaced_name: &str,
+        call_id: &str,
+        arguments: Map<String, Value>,
+        stop: BoxPlatformSendFuture<'static, String>,
+    ) -> ToolResult {
+        let result = async {
+            let (server_id, tool) = self
+                .resolve_tool(namespaced_name)
+                .ok_or_else(|| format!("Tool '{}' not found", namespaced_name))?;
+            let peer = self.peer(&server_id)?;
+
+            let mut meta = Meta::new();
+            meta.set_progress_token(ProgressToken(NumberOrString::String(call_id.into())));
+            let request = ClientRequest::CallToolRequest(Request::new(CallToolRequestParam {
+                name: tool.into(),
+                arguments: Some(arguments),
+            }));
+            let handle = peer
+                .send_request_with_option(
+                    request,
+                    PeerRequestOptions {
+                        timeout: None,
+                        meta: Some(meta),
+                    },
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+
+            match future::select(handle.rx, stop).await {
+                Either::Left((Ok(Ok(ServerResult::CallToolResult(result))), _)) => Ok(result),
+                Either::Left((Ok(Ok(_)), _)) => Err(ServiceError::UnexpectedResponse.to_string()),
+                Either::Left((Ok(Err(e)), _)) => Err(e.to_string()),
+                Either::Left((Err(_), _)) => Err(ServiceError::TransportClosed.to_string()),
+                Either::Right((reason, _)) => {
+                    cancel_request(&peer, handle.id, &reason).await;
+                    Err(reason)
+                }
+            }
+        }
+        .await;
+
+        match result {
+            Ok(result) => ToolResult {
+                tool_call_id: call_id.to_string(),
+                content: tool_result_text(&result),
+                is_error: result.is_error.unwrap_or(false),
+            },
+            Err(error) => ToolResult {
+                tool_call_id: call_id.to_string(),
+                content: error,
+                is_error: true,
+            },
+        }
+    }
+
+    fn peer(&self, server_id: &str) -> Result<Peer<RoleClient>, String> {
+        self.0
+            .lock()
+            .unwrap()
+            .connections
+            .get(server_id)
+            .map(|connection| connection.service.peer().clone())
+            .ok_or_else(|| format!("MCP server '{}' is not connected", server_id))
+    }
+
+    fn client_info(&self) -> ClientInfo {
+        let handles_requests = self.0.lock().unwrap().request_handler.is_some();
+        ClientInfo {
+            protocol_version: Default::default(),
+            capabilities: ClientCapabilities {
+                sampling: handles_requests.then(Default::default),
+                elicitation: handles_requests.then(ElicitationCapability::default),
+                ..Default::default()
+            },
+            client_info: Implementation {
+                name: env!("CARGO_PKG_NAME").to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl McpClient {
+    pub async fn send_request(
+        &self,
+        server_id: &str,
+        _method: &str,
+        _params: Value,
+    ) -> Result<Value, String> {
+        Err(format!("MCP server '{}' is not connected", server_id))
+    }
+
+    pub async fn call_tool(
+        &self,
+        namespaced_name: &str,
+        call_id: &str,
+        _arguments: Map<String, Value>,
+        _stop: BoxPlatformSendFuture<'static, String>,
+    ) -> ToolResult {
+        ToolResult {
+            tool_call_id: call_id.to_string(),
+            content: format!("Tool '{}' not found", namespaced_name),
+            is_error: true,
+        }
+    }
+}
+
+/// Receives what a server sends on its own.
+#[cfg(not(target_arch = "wasm32"))]
+struct McpHandler {
+    server_id: String,
+    /// Weak, since the state owns the session this handler belongs to.
+    state: Weak<Mutex<McpClientState>>,
+    info: ClientInfo,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Service<RoleClient> for McpHandler {
+    async fn handle_request(
+        &self,
+        request: ServerRequest,
+        _context: RequestContext<RoleClient>,
+    ) -> Result<rmcp::model::ClientResult, ErrorData> {
+        match request {
+            ServerRequest::PingRequest(_) => return Ok(rmcp::model::ClientResult::empty(())),
+            ServerRequest::ListRootsRequest(_) => {
+                return Ok(ListRootsResult::default().into());
+            }
+            _ => {}
+        }
+
+        let handler = self
+            .state
+            .upgrade()
+            .and_then(|state| state.lock().unwrap().request_handler.clone())
+            .ok_or_else(|| ErrorData::invalid_request("Unsupported request", None))?;
+
+        let (method, params) = split_message(&request);
+        let result = handler(&self.server_id, &method, &params)
+            .await
+            .map_err(|e| ErrorData::internal_error(e, None))?;
+        serde_json::from_value(result).map_err(|e| ErrorData::internal_error(e.to_string(), None))
+    }
+
+    async fn handle_notification(
+        &self,
+        notification: ServerNotification,
+        context: NotificationContext<RoleClient>,
+    ) -> Result<(), ErrorData> {
+        if let ServerNotification::ToolListChangedNotification(_) = notification {
+            match list_tools(&self.server_id, &context.peer).await {
+                Ok(tools) => {
+                    if let Some(state) = self.state.upgrade() {
+                        state
+                            .lock()
+                            .unwrap()
+                            .tools
+                            .insert(self.server_id.clone(), tools);
+                    }
+                }
+                Err(e) => {
+                    ::log::warn!("Failed to reload tools of {}: {}", self.server_id, e);
+                }
+            }
+        }
+
+        let handler = self
+            .state
+            .upgrade()
+            .and_then(|state| state.lock().unwrap().notification_handler.clone());
+        if let Some(handler) = handler {
+            let (method, params) = split_message(&notification);
+            handler(&self.server_id, &method, &params);
+        }
+        Ok(())
+    }
+
+    fn get_info(&self) -> ClientInfo {
+        self.info.clone()
+    }
+}
+
+/// The method and params of a request or notification.
+#[cfg(not(target_arch = "wasm32"))]
+fn split_message(message: &impl serde::Serialize) -> (String, Value) {
+    let mut message = serde_json::to_value(message).unwrap_or_default();
+    let method = message
+        .get("method")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let params = message
+        .get_mut("params")
+        .map(Value::take)
+        .unwrap_or_else(|| Value::Object(Map::new()));
+    (method, params)
+}
+
+/// Tell a server to abandon a request, identified by its JSON-RPC id.
+#[cfg(not(target_arch = "wasm32"))]
+async fn cancel_request(peer: &Peer<RoleClient>, request_id: RequestId, reason: &str) {
+    let notification = CancelledNotification {
+        params: CancelledNotificationParam {
+            request_id,
+            reason: Some(reason.to_string()),
+        },
+        method: Default::default(),
+        extensions: Default::default(),
+    };
+    if let Err(e) = peer.send_notification(notification.into()).await {
+        ::log::warn!("Failed to cancel a request: {}", e);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn list_tools(server_id: &str, peer: &Peer<RoleClient>) -> Result<Vec<Tool>, String> {
+    let tools = peer.list_all_tools().await.map_err(|e| e.to_string())?;
+    Ok(tools
+        .into_iter()
+        .map(|tool| Tool {
+            name: namespaced_name(server_id, &tool.name),
+            description: tool.description.map(|description| description.into_owned()),
+            input_schema: tool.input_schema,
+        })
+        .collect())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn http_client(headers: &HashMap<String, String>) -> Result<reqwest::Client, String> {
+    let mut header_map = reqwest::header::HeaderMap::new();
+    for (name, value) in headers {
+        let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| format!("Invalid header {}: {}", name, e))?;
+        let value = reqwest::header::HeaderValue::from_str(value)
+            .map_err(|e| format!("Invalid value for header {}: {}", name, e))?;
+        header_map.insert(name, value);
+    }
+
+    reqwest::Client::builder()
+        .default_headers(header_map)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// Stop a stdio server whose stdin was closed, escalating to SIGTERM and then
+/// SIGKILL while it keeps running.
+#[cfg(not(target_arch = "wasm32"))]
+async fn stop_process(mut child: tokio::process::Child) {
+    if exits_in_grace_period(&mut child).await {
+        return;
+    }
+
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: `pid` belongs to a child not reaped yet, so it can't be reused.
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGTERM);
+        }
+        if exits_in_grace_period(&mut child).await {
+            return;
+        }
+    }
+
+    let _ = child.kill().await;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn exits_in_grace_period(child: &mut tokio::process::Child) -> bool {
+    tokio::time::timeout(STOP_GRACE_PERIOD, child.wait())
+        .await
+        .is_ok()
+}
+
+/// HTTP client adding the token of a [`TokenSource`] to every request.
+///
+/// A request answered with 401 is retried once with a refreshed token, so
+/// tokens revoked or expired early don't end the session.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone)]
+struct AuthClient {
+    http: reqwest::Client,
+    auth: Option<Arc<TokenSource>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AuthClient {
+    async fn token(&self, rejected: Option<String>) -> std::io::Result<Option<String>> {
+        let Some(auth) = &self.auth else {
+            return Ok(None);
+        };
+        auth(rejected)
+            .await
+            .map(Some)
+            .map_err(std::io::Error::other)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn is_unauthorized(error: &reqwest::Error) -> bool {
+    error.status() == Some(reqwest::StatusCode::UNAUTHORIZED)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn is_streamable_unauthorized(error: &StreamableHttpError<reqwest::Error>) -> bool {
+    match error {
+        StreamableHttpError::AuthRequired(_) => true,
+        StreamableHttpError::Client(error) => is_unauthorized(error),
+        _ => false,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn is_sse_unauthorized(error: &SseTransportError<reqwest::Error>) -> bool {
+    matches!(error, SseTransportError::Client(error) if is_unauthorized(error))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl StreamableHttpClient for AuthClient {
+    type Error = reqwest::Error;
+
+    async fn post_message(
+        &self,
+        uri: Arc<str>,
+        message: ClientJsonRpcMessage,
+        session_id: Option<Arc<str>>,
+        _auth_header: Option<String>,
+    ) -> Result<StreamableHttpPostResponse, StreamableHttpError<reqwest::Error>> {
+        let token = self.token(None).await?;
+        let result = StreamableHttpClient::post_message(
+            &self.http,
+            uri.clone(),
+            message.clone(),
+            session_id.clone(),
+            token.clone(),
+        )
+        .await;
+        match result {
+            Err(error) if token.is_some() && is_streamable_unauthorized(&error) => {
+                let token = self.token(token).await?;
+                StreamableHttpClient::post_message(&self.http, uri, message, session_id, token)
+                    .await
+            }
+            result => result,
+        }
+    }
+
+    async fn delete_session(
+        &self,
+        uri: Arc<str>,
+        session_id: Arc<str>,
+        _auth_header: Option<String>,
+    ) -> Result<(), StreamableHttpError<reqwest::Error>> {
+        let token = self.token(None).await?;
+        StreamableHttpClient::delete_session(&self.http, uri, session_id, token).await
+    }
+
+    async fn get_stream(
+        &self,
+        uri: Arc<str>,
+        session_id: Arc<str>,
+        last_event_id: Option<String>,
+        _auth_header: Option<String>,
+    ) -> Result<BoxedSseResponse, StreamableHttpError<reqwest::Error>> {
+        let token = self.token(None).await?;
+        let result = StreamableHttpClient::get_stream(
+            &self.http,
+            uri.clone(),
+            session_id.clone(),
+            last_event_id.clone(),
+            token.clone(),
+        )
+        .await;
+        match result {
+            Err(error) if token.is_some() && is_streamable_unauthorized(&error) => {
+                let token = self.token(token).await?;
+                StreamableHttpClient::get_stream(&self.http, uri, session_id, last_event_id, token)
+                    .await
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SseClient for AuthClient {
+    type Error = reqwest::Error;
+
+    async fn post_message(
+        &self,
+        uri: http::Uri,
+        message: ClientJsonRpcMessage,
+        _auth_token: Option<String>,
+    ) -> Result<(), SseTransportError<reqwest::Error>> {
+        let token = self.token(None).await?;
+        let result =
+            SseClient::post_message(&self.http, uri.clone(), message.clone(), token.clone()).await;
+        match result {
+            Err(error) if token.is_some() && is_sse_unauthorized(&error) => {
+                let token = self.token(token).await?;
+                SseClient::post_message(&self.http, uri, message, token).await
+            }
+            result => result,
+        }
+    }
+
+    async fn get_stream(
+        &self,
+        uri: http::Uri,
+        last_event_id: Option<String>,
+        _auth_token: Option<String>,
+    ) -> Result<BoxedSseResponse, SseTransportError<reqwest::Error>> {
+        let token = self.token(None).await?;
+        let result = SseClient::get_stream(
+            &self.http,
+            uri.clone(),
+            last_event_id.clone(),
+            token.clone(),
+        )
+        .await;
+        match result {
+            Err(error) if token.is_some() && is_sse_unauthorized(&error) => {
+                let token = self.token(token).await?;
+                SseClient::get_stream(&self.http, uri, last_event_id, token).await
+            }
+            result => result,
+        }
+    }
+}
+
+/// The text a tool result reaches the chat with.
+///
+/// Plain text results are kept as text. Anything richer, like images or
+/// embedded resources, is kept as the JSON of the whole result, which
+/// [`crate::utils::tool_result`] knows how to render.
+#[cfg(not(target_arch = "wasm32"))]
+fn tool_result_text(result: &CallToolResult) -> String {
+    if result.content.is_empty() {
+        return result
+            .structured_content
+            .as_ref()
+            .map(Value::to_string)
+            .unwrap_or_default();
+    }
+
+    let texts: Option<Vec<&str>> = result
+        .content
+        .iter()
+        .map(|content| content.as_text().map(|text| text.text.as_str()))
+        .collect();
+
+    match texts {
+        Some(texts) => texts.join("\n"),
+        None => serde_json::to_string(result).unwrap_or_default(),
+    }
+}
+
+/// The name a tool of a server is exposed with.
+pub fn namespaced_name(server_id: &str, tool: &str) -> String {
+    format!("{}{}{}", server_id, NAMESPACE_SEPARATOR, tool)
+}
+
+/// Split a namespaced tool name into its server id (if any) and tool name.
+///
+/// Splits at the first separator, so tools whose own name contains it keep it.
+/// Calls are routed with [`McpClient`]'s lookup of the connected server ids
+/// instead, which also handles server ids containing the separator.
+pub fn split_namespaced(namespaced_name: &str) -> (Option<&str>, &str) {
+    match namespaced_name.split_once(NAMESPACE_SEPARATOR) {
+        Some((server_id, tool)) if !server_id.is_empty() => (Some(server_id), tool),
+        _ => (None, namespaced_name),
+    }
+}
+
+/// Wraps a client to offer the tools of an [`McpClient`] to the requests made
+/// without tools.
+pub struct McpToolsClient {
+    inner: Box<dyn BotClient>,
+    mcp: McpClient,
+}
+
+impl Clone for McpToolsClient {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone_box(),
+            mcp: self.mcp.clone(),
+        }
+    }
+}
+
+impl McpToolsClient {
+    pub fn new(inner: Box<dyn BotClient>, mcp: McpClient) -> Self {
+        Self { inner, mcp }
+    }
+}
+
+impl BotClient for McpToolsClient {
+    fn bots(&mut self) -> BoxPlatformSendFuture<'static, ClientResult<Vec<Bot>>> {
+        self.inner.bots()
+    }
+
+    fn clone_box(&self) -> Box<dyn BotClient> {
+        Box::new(self.clone())
+    }
+
+    fn send(
+        &mut self,
+        bot_id: &BotId,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> BoxPlatformSendStream<'static, ClientResult<MessageContent>> {
+        if tools.is_empty() {
+            let tools = self.mcp.tools();
+            return self.inner.send(bot_id, messages, &tools);
+        }
+
+        self.inner.send(bot_id, messages, tools)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use rmcp::model::Content;
+
+    #[test]
+    fn test_tool_result_text() {
+        let text = CallToolResult::success(vec![Content::text("one"), Content::text("two")]);
+        assert_eq!(tool_result_text(&text), "one\ntwo");
+
+        let image = CallToolResult::success(vec![
+            Content::text("A chart"),
+            Content::image("aGVsbG8=", "image/png"),
+        ]);
+        let json: Value = serde_json::from_str(&tool_result_text(&image)).unwrap();
+        assert_eq!(json["content"][1]["type"], "image");
+
+        let mut structured = CallToolResult::structured(json!({ "rows": 3 }));
+        structured.content.clear();
+        assert_eq!(tool_result_text(&structured), r#"{"rows":3}"#);
+    }
+
+    #[test]
+    fn test_split_message() {
+        let request: ClientRequest = serde_json::from_value(json!({
+            "method": "resources/read",
+            "params": { "uri": "file:///notes.md" }
+        }))
+        .unwrap();
+        let (method, params) = split_message(&request);
+        assert_eq!(method, "resources/read");
+        assert_eq!(params["uri"], "file:///notes.md");
+    }
+}
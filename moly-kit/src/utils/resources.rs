@@ -0,0 +1,38 @@
+//! External content that can be attached to a prompt, like files, database rows
+//! or documents exposed by MCP servers.
+
+use crate::aitk::{protocol::Attachment, utils::asynchronous::BoxPlatformSendFuture};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextResource {
+    /// Identifies the resource in its source. Not shown to the user.
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// Where the resource comes from, like the server exposing it.
+    pub origin: String,
+}
+
+/// Lists the resources that can be attached and reads them.
+pub trait ResourceSource: Send + Sync {
+    /// Resources currently available.
+    fn resources(&self) -> Vec<ContextResource>;
+
+    /// Read a resource into an attachment.
+    fn read(
+        &self,
+        resource: &ContextResource,
+    ) -> BoxPlatformSendFuture<'static, Result<Attachment, String>>;
+
+    /// Called after a resource is attached so the source can start watching it.
+    ///
+    /// Updated content is handed back with
+    /// [`PromptInput::refresh_resource`](crate::widgets::prompt_input::PromptInput::refresh_resource).
+    fn watch(&self, _resource: &ContextResource) {}
+
+    /// Called when a watched resource is no longer attached.
+    ///
+    /// A resource attached several times gets as many calls to [`Self::watch`]
+    /// and to this.
+    fn unwatch(&self, _resource: &ContextResource) {}
+}
@@ -1,5 +1,6 @@
 use reqwest::header::{HeaderValue, USER_AGENT};
-use scraper::Selector;
+use scraper::{ElementRef, Node, Selector};
+use url::Url;
 
 /// Perform a GET request and return the raw bytes.
 pub(crate) async fn fetch_bytes(url: &str) -> Result<Vec<u8>, ()> {
@@ -63,3 +64,298 @@ pub(crate) fn extract_title(document: &Html) -> Option<String> {
         .and_then(|element| element.text().next())
         .map(|text| text.trim().to_string())
 }
+
+/// Elements that never contain the main content of a page.
+const IGNORED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "nav", "header", "footer", "aside", "form",
+    "iframe", "svg", "button", "select",
+];
+
+/// The main readable content of a web page, converted to markdown.
+pub(crate) struct ReadablePage {
+    pub(crate) title: Option<String>,
+    pub(crate) markdown: String,
+}
+
+/// Extract the main content of a page, readability style.
+///
+/// Prefers semantic containers like `<article>` and `<main>`, falling back to the
+/// element holding the most paragraph text. Navigation, scripts and other clutter
+/// are dropped. Relative links are resolved against `base_url`.
+pub(crate) fn extract_readable(document: &Html, base_url: &Url) -> ReadablePage {
+    let title = extract_title(document);
+    let document = document.to_scraper();
+
+    let mut markdown = String::new();
+    write_children(find_main_content(&document), base_url, &mut markdown);
+
+    ReadablePage {
+        title,
+        markdown: normalize_blank_lines(&markdown),
+    }
+}
+
+/// Fetch a page and extract its main content.
+pub(crate) async fn fetch_readable(url: &Url) -> Result<ReadablePage, ()> {
+    let document = fetch_html(url.as_str()).await?;
+    Ok(extract_readable(&document, url))
+}
+
+/// Find `http(s)` URLs in free text.
+pub(crate) fn find_urls(text: &str) -> Vec<Url> {
+    text.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| "()[]<>\"'.,;!?".contains(c)))
+        .filter(|word| word.starts_with("http://") || word.starts_with("https://"))
+        .filter_map(|word| Url::parse(word).ok())
+        .filter(|url| url.host_str().is_some())
+        .collect()
+}
+
+fn find_main_content(document: &scraper::Html) -> ElementRef<'_> {
+    for selector in ["article", "main", "[role=main]"] {
+        let selector = Selector::parse(selector).unwrap();
+        if let Some(element) = document
+            .select(&selector)
+            .max_by_key(|element| text_len(*element))
+            .filter(|element| text_len(*element) > 200)
+        {
+            return element;
+        }
+    }
+
+    // Score containers by the text of the paragraphs directly inside them.
+    let candidates = Selector::parse("div, section, td").unwrap();
+    let best = document
+        .select(&candidates)
+        .map(|element| {
+            let score: usize = element
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|child| child.value().name() == "p")
+                .map(text_len)
+                .sum();
+            (score, element)
+        })
+        .filter(|(score, _)| *score > 0)
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, element)| element);
+
+    let body = Selector::parse("body").unwrap();
+    best.or_else(|| document.select(&body).next())
+        .unwrap_or_else(|| document.root_element())
+}
+
+fn text_len(element: ElementRef) -> usize {
+    element.text().map(|text| text.trim().len()).sum()
+}
+
+fn write_children(element: ElementRef, base_url: &Url, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => push_inline_text(out, text),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    write_element(child, base_url, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn write_element(element: ElementRef, base_url: &Url, out: &mut String) {
+    let name = element.value().name();
+    if IGNORED_TAGS.contains(&name) {
+        return;
+    }
+
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = name[1..].parse::<usize>().unwrap_or(1);
+            let text = inline_text(element, base_url);
+            if !text.is_empty() {
+                out.push_str(&format!("\n\n{} {}\n\n", "#".repeat(level), text));
+            }
+        }
+        "p" | "div" | "section" | "article" | "main" | "table" | "tr" | "figure" => {
+            out.push_str("\n\n");
+            write_children(element, base_url, out);
+            out.push_str("\n\n");
+        }
+        "br" => out.push('\n'),
+        "hr" => out.push_str("\n\n---\n\n"),
+        "ul" | "ol" => {
+            out.push('\n');
+            write_children(element, base_url, out);
+            out.push('\n');
+        }
+        "li" => {
+            out.push_str("\n- ");
+            write_children(element, base_url, out);
+        }
+        "pre" => {
+            let code: String = element.text().collect();
+            out.push_str(&format!("\n\n```\n{}\n```\n\n", code.trim_end()));
+        }
+        "code" => {
+            let code: String = element.text().collect();
+            out.push_str(&format!("`{}`", code.trim()));
+        }
+        "strong" | "b" => {
+            let text = inline_text(element, base_url);
+            if !text.is_empty() {
+                out.push_str(&format!("**{}**", text));
+            }
+        }
+        "em" | "i" => {
+            let text = inline_text(element, base_url);
+            if !text.is_empty() {
+                out.push_str(&format!("*{}*", text));
+            }
+        }
+        "a" => {
+            let text = inline_text(element, base_url);
+            let href = element
+                .value()
+                .attr("href")
+                .and_then(|href| base_url.join(href).ok())
+                .filter(|href| matches!(href.scheme(), "http" | "https"));
+
+            match href {
+                Some(href) if !text.is_empty() => out.push_str(&format!("[{}]({})", text, href)),
+                _ => out.push_str(&text),
+            }
+        }
+        "img" => {
+            let alt = element.value().attr("alt").unwrap_or_default().trim();
+            let src = element
+                .value()
+                .attr("src")
+                .and_then(|src| base_url.join(src).ok());
+
+            if let (false, Some(src)) = (alt.is_empty(), src) {
+                out.push_str(&format!("![{}]({})", alt, src));
+            }
+        }
+        "blockquote" => {
+            let mut inner = String::new();
+            write_children(element, base_url, &mut inner);
+            out.push_str("\n\n");
+            for line in normalize_blank_lines(&inner).lines() {
+                out.push_str("> ");
+                out.push_str(line);
+                out.push('\n');
+            }
+            out.push('\n');
+        }
+        _ => write_children(element, base_url, out),
+    }
+}
+
+/// Render the element as markdown in a single line.
+fn inline_text(element: ElementRef, base_url: &Url) -> String {
+    let mut text = String::new();
+    write_children(element, base_url, &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Push text collapsing whitespace the same way a browser would.
+fn push_inline_text(out: &mut String, text: &str) {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let needs_space = !out.is_empty() && !out.ends_with([' ', '\n']);
+
+    if collapsed.is_empty() {
+        if !text.is_empty() && needs_space {
+            out.push(' ');
+        }
+        return;
+    }
+
+    if text.starts_with(char::is_whitespace) && needs_space {
+        out.push(' ');
+    }
+
+    out.push_str(&collapsed);
+
+    if text.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+}
+
+/// Trim trailing spaces and collapse runs of blank lines into one.
+///
+/// Code fences are left untouched.
+fn normalize_blank_lines(text: &str) -> String {
+    let mut output = String::new();
+    let mut blank_lines = 0;
+    let mut in_fence = false;
+
+    for line in text.lines().map(|line| line.trim_end()) {
+        if line.is_empty() && !in_fence {
+            blank_lines += 1;
+            continue;
+        }
+
+        if !output.is_empty() {
+            output.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+
+        if in_fence {
+            output.push_str(line);
+        } else {
+            // Lines start with a space after collapsing inline text in blocks.
+            output.push_str(line.trim_start_matches(' '));
+        }
+
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+
+        blank_lines = 0;
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_readable() {
+        let html = Html(
+            r#"<html><head><title>Example post</title></head><body>
+            <nav><a href="/">Home</a></nav>
+            <article>
+                <h1>Hello   world</h1>
+                <p>This is <strong>important</strong> text with a <a href="/docs">link</a>.</p>
+                <ul><li>One</li><li>Two</li></ul>
+                <script>alert(1)</script>
+                <p>Padding so the article is long enough to be picked as the main content of
+                the page, which requires a couple hundred characters of actual text in it.</p>
+            </article>
+            <footer>Copyright</footer>
+            </body></html>"#
+                .to_string(),
+        );
+
+        let base_url = Url::parse("https://example.com/blog/post").unwrap();
+        let page = extract_readable(&html, &base_url);
+
+        assert_eq!(page.title.as_deref(), Some("Example post"));
+        assert!(page.markdown.starts_with(
+            "# Hello world\n\nThis is **important** text with a [link](https://example.com/docs)."
+        ));
+        assert!(page.markdown.contains("- One\n- Two"));
+        assert!(!page.markdown.contains("Home"));
+        assert!(!page.markdown.contains("alert"));
+        assert!(!page.markdown.contains("Copyright"));
+    }
+
+    #[test]
+    fn test_find_urls() {
+        let urls = find_urls("see (https://example.com/a?b=1), and http://x.org. not ftp://y.org");
+        let urls: Vec<&str> = urls.iter().map(|url| url.as_str()).collect();
+        assert_eq!(urls, vec!["https://example.com/a?b=1", "http://x.org/"]);
+    }
+}
@@ -0,0 +1,150 @@
+//! Commands typed as `/name` in the prompt input that expand into messages.
+//!
+//! The commands themselves come from the app through a [`SlashCommandSource`],
+//! for example from the prompts exposed by MCP servers.
+
+use crate::aitk::{protocol::MessageContent, utils::asynchronous::BoxPlatformSendFuture};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlashCommandArgument {
+    pub name: String,
+    pub description: String,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlashCommand {
+    /// Identifies the command in its source. Not shown to the user.
+    pub id: String,
+    /// What the user types after the slash.
+    pub name: String,
+    pub description: String,
+    pub arguments: Vec<SlashCommandArgument>,
+}
+
+/// Who a message produced by a command is attributed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandRole {
+    User,
+    Assistant,
+}
+
+/// A message produced by expanding a command.
+#[derive(Debug, Clone)]
+pub struct CommandMessage {
+    pub role: CommandRole,
+    pub content: MessageContent,
+}
+
+/// Provides the commands available in a prompt input and expands them.
+pub trait SlashCommandSource: Send + Sync {
+    /// Commands currently available. Called whenever the user types a slash.
+    fn commands(&self) -> Vec<SlashCommand>;
+
+    /// Expand a command into the messages to insert in the chat.
+    fn expand(
+        &self,
+        command: &SlashCommand,
+        arguments: BTreeMap<String, String>,
+    ) -> BoxPlatformSendFuture<'static, Result<Vec<CommandMessage>, String>>;
+}
+
+/// Get the command name being typed, if the text is a slash command in progress.
+pub fn parse_query(text: &str) -> Option<&str> {
+    let query = text.strip_prefix('/')?;
+    if query.contains(char::is_whitespace) {
+        None
+    } else {
+        Some(query)
+    }
+}
+
+/// Commands matching what the user typed after the slash.
+///
+/// Prefix matches come first, followed by commands containing the query.
+pub fn matching_commands(commands: &[SlashCommand], query: &str) -> Vec<SlashCommand> {
+    let query = query.to_lowercase();
+
+    let (mut prefixed, contained): (Vec<_>, Vec<_>) = commands
+        .iter()
+        .filter(|command| command.name.to_lowercase().contains(&query))
+        .cloned()
+        .partition(|command| command.name.to_lowercase().starts_with(&query));
+
+    prefixed.extend(contained);
+    prefixed
+}
+
+/// Names of the required arguments left empty.
+pub fn missing_arguments(
+    command: &SlashCommand,
+    arguments: &BTreeMap<String, String>,
+) -> Vec<String> {
+    command
+        .arguments
+        .iter()
+        .filter(|arg| {
+            arg.required
+                && arguments
+                    .get(&arg.name)
+                    .is_none_or(|value| value.trim().is_empty())
+        })
+        .map(|arg| arg.name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(name: &str) -> SlashCommand {
+        SlashCommand {
+            id: name.to_string(),
+            name: name.to_string(),
+            description: String::new(),
+            arguments: vec![SlashCommandArgument {
+                name: "topic".to_string(),
+                description: String::new(),
+                required: true,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(parse_query("/"), Some(""));
+        assert_eq!(parse_query("/summ"), Some("summ"));
+        assert_eq!(parse_query("/summarize this"), None);
+        assert_eq!(parse_query("summarize"), None);
+    }
+
+    #[test]
+    fn test_matching_commands() {
+        let commands = vec![
+            command("git_review"),
+            command("review"),
+            command("summarize"),
+        ];
+
+        let names: Vec<String> = matching_commands(&commands, "rev")
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, vec!["review", "git_review"]);
+        assert_eq!(matching_commands(&commands, "").len(), 3);
+    }
+
+    #[test]
+    fn test_missing_arguments() {
+        let command = command("review");
+        let mut arguments = BTreeMap::new();
+        assert_eq!(missing_arguments(&command, &arguments), vec!["topic"]);
+
+        arguments.insert("topic".to_string(), "  ".to_string());
+        assert_eq!(missing_arguments(&command, &arguments), vec!["topic"]);
+
+        arguments.insert("topic".to_string(), "rust".to_string());
+        assert!(missing_arguments(&command, &arguments).is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+//! Reporting of tool calls to the application, so it can keep an audit trail
+//! independent from the messages of a chat.
+//!
+//! Widgets running tools report when a call is decided and, if it ran, when it
+//! finished. Timing and storage are left to the application.
+
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use crate::aitk::protocol::{BotId, Message};
+use crate::utils::tool_policy::ToolApproval;
+
+/// Messages searched for results, as calls are executed one message at a time.
+const RECENT_MESSAGES: usize = 8;
+
+/// A tool call requested by a model.
+#[derive(Debug, Clone)]
+pub struct ToolAuditCall {
+    /// Id of the call given by the model, shared with its [`ToolAuditEvent::Finished`].
+    pub id: String,
+    /// Namespaced tool name, as offered to the model.
+    pub name: String,
+    pub arguments: Value,
+    pub bot_id: Option<BotId>,
+    /// Requested during a realtime (voice) session.
+    pub realtime: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum ToolAuditEvent {
+    /// The call was approved or denied. Denied calls end here.
+    Decided {
+        call: ToolAuditCall,
+        approved: bool,
+        approval: ToolApproval,
+    },
+    /// An approved call finished running.
+    Finished {
+        id: String,
+        content: String,
+        is_error: bool,
+    },
+}
+
+/// Receives the [`ToolAuditEvent`]s of the widgets it is given to.
+///
+/// Events may be reported from any thread.
+#[derive(Clone)]
+pub struct ToolAuditHook(Arc<dyn Fn(ToolAuditEvent) + Send + Sync>);
+
+impl ToolAuditHook {
+    pub fn new(hook: impl Fn(ToolAuditEvent) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+
+    pub fn report(&self, event: ToolAuditEvent) {
+        (self.0)(event)
+    }
+}
+
+#[derive(Default)]
+struct AuditorState {
+    hook: Option<ToolAuditHook>,
+    /// Calls executed by the chat controller whose results didn't show up yet.
+    running: HashSet<String>,
+}
+
+/// Reports the tool calls of a chat, whose results arrive as messages.
+#[derive(Clone, Default)]
+pub(crate) struct ToolAuditor(Arc<Mutex<AuditorState>>);
+
+impl ToolAuditor {
+    pub fn set_hook(&self, hook: Option<ToolAuditHook>) {
+        let mut state = self.0.lock().unwrap();
+        state.hook = hook;
+        state.running.clear();
+    }
+
+    pub fn hook(&self) -> Option<ToolAuditHook> {
+        self.0.lock().unwrap().hook.clone()
+    }
+
+    pub fn decided(&self, call: ToolAuditCall, approved: bool, approval: ToolApproval) {
+        let hook = {
+            let mut state = self.0.lock().unwrap();
+            if state.hook.is_some() && approved {
+                state.running.insert(call.id.clone());
+            }
+            state.hook.clone()
+        };
+
+        if let Some(hook) = hook {
+            hook.report(ToolAuditEvent::Decided {
+                call,
+                approved,
+                approval,
+            });
+        }
+    }
+
+    /// Report the results of running calls found in `messages`.
+    pub fn check_results(&self, messages: &[Message]) {
+        let (hook, finished) = {
+            let mut state = self.0.lock().unwrap();
+            if state.running.is_empty() {
+                return;
+            }
+
+            let mut finished = Vec::new();
+            // Results come after their calls, so recent messages are enough.
+            for message in messages.iter().rev().take(RECENT_MESSAGES) {
+                for result in &message.content.tool_results {
+                    if state.running.remove(&result.tool_call_id) {
+                        finished.push(ToolAuditEvent::Finished {
+                            id: result.tool_call_id.clone(),
+                            content: result.content.clone(),
+                            is_error: result.is_error,
+                        });
+                    }
+                }
+            }
+            (state.hook.clone(), finished)
+        };
+
+        if let Some(hook) = hook {
+            for event in finished {
+                hook.report(event);
+            }
+        }
+    }
+}
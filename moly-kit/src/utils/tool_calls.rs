@@ -0,0 +1,303 @@
+//! Execution of approved tool calls with progress, cancellation and timeouts.
+//!
+//! Calls are sent with the tool call id as progress token, so the
+//! `notifications/progress` MCP servers send about them can be matched to the
+//! call. [`ToolCalls`] keeps the last progress of each running call so it can be
+//! drawn. Calls the user cancels or that run longer than their timeout are
+//! abandoned with `notifications/cancelled` and end with an error result, so the
+//! model learns what happened instead of waiting forever. Calls to tools the user
+//! disabled end with an error result without reaching their server.
+
+use futures::channel::oneshot;
+use futures::future::{self, Either};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::aitk::protocol::ToolResult;
+use crate::aitk::utils::asynchronous::{BoxPlatformSendFuture, sleep};
+use crate::aitk::utils::tool::display_name_from_namespaced;
+use crate::utils::mcp::{McpClient, split_namespaced};
+
+/// Time limits for tool calls, by server and by tool.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolTimeouts {
+    servers: BTreeMap<String, Duration>,
+    /// Keyed by server and tool name.
+    tools: BTreeMap<(String, String), Duration>,
+}
+
+impl ToolTimeouts {
+    /// Limit every tool of `server`, unless the tool has its own limit.
+    pub fn set_server_timeout(&mut self, server: &str, timeout: Duration) {
+        self.servers.insert(server.to_string(), timeout);
+    }
+
+    pub fn set_tool_timeout(&mut self, server: &str, tool: &str, timeout: Duration) {
+        self.tools
+            .insert((server.to_string(), tool.to_string()), timeout);
+    }
+
+    /// The limit of a tool given its namespaced name, if any.
+    pub fn timeout(&self, namespaced_name: &str) -> Option<Duration> {
+        let (server, tool) = split_namespaced(namespaced_name);
+        self.timeout_for(server?, tool)
+    }
+
+    fn timeout_for(&self, server: &str, tool: &str) -> Option<Duration> {
+        self.tools
+            .get(&(server.to_string(), tool.to_string()))
+            .or_else(|| self.servers.get(server))
+            .copied()
+    }
+}
+
+/// Progress last reported by a server for a running call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCallProgress {
+    pub progress: f64,
+    pub total: Option<f64>,
+    pub message: Option<String>,
+}
+
+impl ToolCallProgress {
+    /// Parse the params of a `notifications/progress`, returning the progress token.
+    pub fn from_notification(params: &Value) -> Option<(String, Self)> {
+        let token = match params.get("progressToken")? {
+            Value::String(token) => token.clone(),
+            Value::Number(token) => token.to_string(),
+            _ => return None,
+        };
+
+        let progress = Self {
+            progress: params.get("progress")?.as_f64()?,
+            total: params.get("total").and_then(Value::as_f64),
+            message: params
+                .get("message")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        };
+        Some((token, progress))
+    }
+
+    /// Completed part between 0 and 1, when the total is known.
+    pub fn fraction(&self) -> Option<f64> {
+        self.total
+            .filter(|total| *total > 0.0)
+            .map(|total| (self.progress / total).clamp(0.0, 1.0))
+    }
+}
+
+/// Whether a tool, given its namespaced name, is disabled and must not run.
+pub type DisabledCheck = dyn Fn(&str) -> bool + Send + Sync;
+
+struct RunningCall {
+    progress: Option<ToolCallProgress>,
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+#[derive(Default)]
+struct ToolCallsState {
+    client: Option<McpClient>,
+    timeouts: ToolTimeouts,
+    disabled: Option<Arc<DisabledCheck>>,
+    running: HashMap<String, RunningCall>,
+}
+
+/// Tool calls running in the chats, and the client and timeouts they run with.
+///
+/// Cheap to clone, every clone shares the same state.
+#[derive(Clone, Default)]
+pub struct ToolCalls(Arc<Mutex<ToolCallsState>>);
+
+impl ToolCalls {
+    /// Set the client calls run with, `None` while MCP servers are disabled.
+    pub fn set_client(&self, client: Option<McpClient>) {
+        self.0.lock().unwrap().client = client;
+    }
+
+    pub fn client(&self) -> Option<McpClient> {
+        self.0.lock().unwrap().client.clone()
+    }
+
+    pub fn set_timeouts(&self, timeouts: ToolTimeouts) {
+        self.0.lock().unwrap().timeouts = timeouts;
+    }
+
+    /// Set what tells the disabled tools apart, checked before each call.
+    pub fn set_disabled_check(&self, disabled: Option<Arc<DisabledCheck>>) {
+        self.0.lock().unwrap().disabled = disabled;
+    }
+
+    /// Record the progress reported by a `notifications/progress`.
+    ///
+    /// Returns `false` if it isn't about a running call.
+    pub fn handle_progress(&self, params: &Value) -> bool {
+        let Some((token, progress)) = ToolCallProgress::from_notification(params) else {
+            return false;
+        };
+
+        match self.0.lock().unwrap().running.get_mut(&token) {
+            Some(call) => {
+                call.progress = Some(progress);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_running(&self, call_id: &str) -> bool {
+        self.0.lock().unwrap().running.contains_key(call_id)
+    }
+
+    pub fn progress(&self, call_id: &str) -> Option<ToolCallProgress> {
+        self.0
+            .lock()
+            .unwrap()
+            .running
+            .get(call_id)
+            .and_then(|call| call.progress.clone())
+    }
+
+    /// Stop a running call and tell its server to abandon it.
+    pub fn cancel(&self, call_id: &str) {
+        let cancel = self
+            .0
+            .lock()
+            .unwrap()
+            .running
+            .get_mut(call_id)
+            .and_then(|call| call.cancel.take());
+
+        if let Some(cancel) = cancel {
+            let _ = cancel.send(());
+        }
+    }
+
+    /// Run an approved call until it finishes, is cancelled or times out.
+    pub async fn run(
+        &self,
+        name: &str,
+        call_id: &str,
+        arguments: Map<String, Value>,
+    ) -> ToolResult {
+        let disabled = self.0.lock().unwrap().disabled.clone();
+        if disabled.is_some_and(|is_disabled| is_disabled(name)) {
+            return ToolResult {
+                tool_call_id: call_id.to_string(),
+                content: format!(
+                    "Tool '{}' is disabled in the settings.",
+                    display_name_from_namespaced(name)
+                ),
+                is_error: true,
+            };
+        }
+
+        let Some(client) = self.client() else {
+            return ToolResult {
+                tool_call_id: call_id.to_string(),
+                content: "MCP servers are not running".to_string(),
+                is_error: true,
+            };
+        };
+
+        let (cancel, cancelled) = oneshot::channel();
+        let timeout = {
+            let mut state = self.0.lock().unwrap();
+            state.running.insert(
+                call_id.to_string(),
+                RunningCall {
+                    progress: None,
+                    cancel: Some(cancel),
+                },
+            );
+            state.timeouts.timeout(name)
+        };
+
+        let display_name = display_name_from_namespaced(name).to_string();
+        let timed_out = Box::pin(async move {
+            match timeout {
+                Some(timeout) => {
+                    sleep(timeout).await;
+                    timeout
+                }
+                None => future::pending().await,
+            }
+        });
+        let stop: BoxPlatformSendFuture<'static, String> = Box::pin(async move {
+            match future::select(cancelled, timed_out).await {
+                Either::Left(_) => format!("Tool '{}' was cancelled by the user.", display_name),
+                Either::Right((timeout, _)) => format!(
+                    "Tool '{}' timed out after {} seconds.",
+                    display_name,
+                    timeout.as_secs()
+                ),
+            }
+        });
+
+        let result = client.call_tool(name, call_id, arguments, stop).await;
+        self.0.lock().unwrap().running.remove(call_id);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_timeouts() {
+        let mut timeouts = ToolTimeouts::default();
+        timeouts.set_server_timeout("git", Duration::from_secs(30));
+        timeouts.set_tool_timeout("git", "clone", Duration::from_secs(300));
+
+        assert_eq!(
+            timeouts.timeout_for("git", "clone"),
+            Some(Duration::from_secs(300))
+        );
+        assert_eq!(
+            timeouts.timeout_for("git", "log"),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(timeouts.timeout_for("filesystem", "clone"), None);
+    }
+
+    #[test]
+    fn test_progress_notification() {
+        let params = json!({
+            "progressToken": "call_1",
+            "progress": 5,
+            "total": 20,
+            "message": "Indexing"
+        });
+        let (token, progress) = ToolCallProgress::from_notification(&params).unwrap();
+        assert_eq!(token, "call_1");
+        assert_eq!(progress.fraction(), Some(0.25));
+        assert_eq!(progress.message.as_deref(), Some("Indexing"));
+
+        let (token, progress) =
+            ToolCallProgress::from_notification(&json!({ "progressToken": 7, "progress": 3 }))
+                .unwrap();
+        assert_eq!(token, "7");
+        assert_eq!(progress.fraction(), None);
+
+        let calls = ToolCalls::default();
+        assert!(!calls.handle_progress(&params));
+    }
+
+    #[test]
+    fn test_disabled_tools_do_not_run() {
+        let calls = ToolCalls::default();
+        calls.set_disabled_check(Some(Arc::new(|name: &str| name == "git__push")));
+
+        let result = futures::executor::block_on(calls.run("git__push", "call_1", Map::new()));
+        assert!(result.is_error);
+        assert_eq!(result.tool_call_id, "call_1");
+        assert!(result.content.contains("disabled"));
+
+        let result = futures::executor::block_on(calls.run("git__log", "call_2", Map::new()));
+        assert_eq!(result.content, "MCP servers are not running");
+    }
+}
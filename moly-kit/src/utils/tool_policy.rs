@@ -0,0 +1,494 @@
+//! Rules deciding if a tool call can run without asking the user.
+//!
+//! A [`ToolPolicy`] is a list of rules matching tools by server, name and,
+//! optionally, argument patterns. The most specific matching rule wins, so a
+//! broad "always ask for `filesystem`" can coexist with a narrow "always allow
+//! `filesystem/read_file` when `path` is under `~/projects`".
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::aitk::utils::tool::display_name_from_namespaced;
+use crate::utils::mcp::split_namespaced;
+
+/// Matches any server or tool in a [`ToolPolicyRule`].
+pub const ANY: &str = "*";
+
+/// What to do when a tool call is requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolPermission {
+    /// Run the tool without asking.
+    Allow,
+    /// Ask the user every time.
+    #[default]
+    Ask,
+    /// Never run the tool.
+    Deny,
+}
+
+/// What decided about a tool call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolApproval {
+    /// The user, when asked.
+    Manual,
+    /// A rule of the policy.
+    Policy,
+    /// The default permission allowing anything no rule covers, which is how
+    /// dangerous mode is enforced.
+    DangerousMode,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolPolicyRule {
+    /// Server id as configured in the MCP settings, or [`ANY`].
+    pub server: String,
+    /// Tool name as exposed by the server, or [`ANY`].
+    pub tool: String,
+    /// Patterns that the named arguments must match for the rule to apply.
+    ///
+    /// Patterns support `*` as a wildcard and a leading `~` for the home directory.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub arguments: BTreeMap<String, String>,
+    pub permission: ToolPermission,
+}
+
+impl ToolPolicyRule {
+    pub fn new(server: &str, tool: &str, permission: ToolPermission) -> Self {
+        Self {
+            server: server.to_string(),
+            tool: tool.to_string(),
+            arguments: BTreeMap::new(),
+            permission,
+        }
+    }
+
+    pub fn with_argument(mut self, name: &str, pattern: &str) -> Self {
+        self.arguments.insert(name.to_string(), pattern.to_string());
+        self
+    }
+
+    fn matches(&self, server: Option<&str>, tool: &str, arguments: &Map<String, Value>) -> bool {
+        let server_matches = self.server == ANY || Some(self.server.as_str()) == server;
+        let tool_matches = self.tool == ANY || self.tool == tool;
+
+        server_matches
+            && tool_matches
+            && self.arguments.iter().all(|(name, pattern)| {
+                arguments
+                    .get(name)
+                    .is_some_and(|value| argument_matches(pattern, value))
+            })
+    }
+
+    /// Higher means more specific. Argument constraints weigh the most.
+    fn specificity(&self) -> usize {
+        (self.server != ANY) as usize + (self.tool != ANY) as usize * 2 + self.arguments.len() * 4
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolPolicy {
+    #[serde(default)]
+    pub rules: Vec<ToolPolicyRule>,
+    /// Used when no rule matches.
+    #[serde(default)]
+    pub default_permission: ToolPermission,
+}
+
+impl ToolPolicy {
+    /// Decide what to do with a call to the given (namespaced) tool.
+    ///
+    /// When several rules match with the same specificity, the most restrictive wins.
+    pub fn evaluate(
+        &self,
+        namespaced_name: &str,
+        arguments: &Map<String, Value>,
+    ) -> ToolPermission {
+        let (server, tool) = split_namespaced(namespaced_name);
+        self.decide(server, tool, arguments)
+    }
+
+    /// Same as [`Self::evaluate`], also telling what took the decision.
+    ///
+    /// Calls the policy asks about are left to the user, so they are reported
+    /// as [`ToolApproval::Manual`].
+    pub fn evaluate_with_approval(
+        &self,
+        namespaced_name: &str,
+        arguments: &Map<String, Value>,
+    ) -> (ToolPermission, ToolApproval) {
+        let (server, tool) = split_namespaced(namespaced_name);
+        let (permission, approval) = match self.matching_rule(server, tool, arguments) {
+            Some(rule) => (rule.permission, ToolApproval::Policy),
+            None if self.default_permission == ToolPermission::Allow => {
+                (ToolPermission::Allow, ToolApproval::DangerousMode)
+            }
+            None => (self.default_permission, ToolApproval::Policy),
+        };
+
+        match permission {
+            ToolPermission::Ask => (permission, ToolApproval::Manual),
+            _ => (permission, approval),
+        }
+    }
+
+    fn decide(
+        &self,
+        server: Option<&str>,
+        tool: &str,
+        arguments: &Map<String, Value>,
+    ) -> ToolPermission {
+        self.matching_rule(server, tool, arguments)
+            .map(|rule| rule.permission)
+            .unwrap_or(self.default_permission)
+    }
+
+    fn matching_rule(
+        &self,
+        server: Option<&str>,
+        tool: &str,
+        arguments: &Map<String, Value>,
+    ) -> Option<&ToolPolicyRule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(server, tool, arguments))
+            .max_by_key(|rule| (rule.specificity(), restrictiveness(rule.permission)))
+    }
+
+    /// Same as [`Self::evaluate`] but with arguments as a JSON string.
+    ///
+    /// Unparseable arguments only match rules without argument patterns.
+    pub fn evaluate_json(&self, namespaced_name: &str, arguments: &str) -> ToolPermission {
+        let arguments = serde_json::from_str::<Map<String, Value>>(arguments).unwrap_or_default();
+        self.evaluate(namespaced_name, &arguments)
+    }
+
+    /// Set the permission for a whole tool, replacing any rule for exactly that tool
+    /// without argument patterns.
+    pub fn set_tool_permission(&mut self, namespaced_name: &str, permission: ToolPermission) {
+        let (server, tool) = split_namespaced(namespaced_name);
+        let server = server.unwrap_or(ANY);
+
+        self.rules.retain(|rule| {
+            !(rule.server == server && rule.tool == tool && rule.arguments.is_empty())
+        });
+        self.rules
+            .push(ToolPolicyRule::new(server, tool, permission));
+    }
+}
+
+/// A [`ToolPolicy`] shared between widgets, notifying the owner when it changes
+/// so it can be persisted.
+#[derive(Clone, Default)]
+pub struct SharedToolPolicy {
+    policy: Arc<Mutex<ToolPolicy>>,
+    on_change: Arc<Mutex<Option<Box<dyn FnMut(&ToolPolicy) + Send>>>>,
+}
+
+impl SharedToolPolicy {
+    pub fn new(policy: ToolPolicy) -> Self {
+        Self {
+            policy: Arc::new(Mutex::new(policy)),
+            on_change: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Get a copy of the current policy.
+    pub fn get(&self) -> ToolPolicy {
+        self.policy.lock().unwrap().clone()
+    }
+
+    /// Replace the policy without triggering the change callback.
+    pub fn set(&self, policy: ToolPolicy) {
+        *self.policy.lock().unwrap() = policy;
+    }
+
+    pub fn evaluate(
+        &self,
+        namespaced_name: &str,
+        arguments: &Map<String, Value>,
+    ) -> ToolPermission {
+        self.policy
+            .lock()
+            .unwrap()
+            .evaluate(namespaced_name, arguments)
+    }
+
+    pub fn evaluate_json(&self, namespaced_name: &str, arguments: &str) -> ToolPermission {
+        self.policy
+            .lock()
+            .unwrap()
+            .evaluate_json(namespaced_name, arguments)
+    }
+
+    pub fn evaluate_with_approval(
+        &self,
+        namespaced_name: &str,
+        arguments: &Map<String, Value>,
+    ) -> (ToolPermission, ToolApproval) {
+        self.policy
+            .lock()
+            .unwrap()
+            .evaluate_with_approval(namespaced_name, arguments)
+    }
+
+    /// Modify the policy and notify the change callback.
+    pub fn update(&self, f: impl FnOnce(&mut ToolPolicy)) {
+        let policy = {
+            let mut policy = self.policy.lock().unwrap();
+            f(&mut policy);
+            policy.clone()
+        };
+
+        if let Some(on_change) = self.on_change.lock().unwrap().as_mut() {
+            on_change(&policy);
+        }
+    }
+
+    /// Set the callback invoked after [`Self::update`].
+    pub fn set_on_change(&self, on_change: impl FnMut(&ToolPolicy) + Send + 'static) {
+        *self.on_change.lock().unwrap() = Some(Box::new(on_change));
+    }
+}
+
+impl ToolApproval {
+    /// Who refused a tool call decided this way, as told to the bot and the user.
+    fn denier(self) -> &'static str {
+        match self {
+            ToolApproval::Manual => "the user",
+            ToolApproval::Policy | ToolApproval::DangerousMode => "the tool policy",
+        }
+    }
+}
+
+/// The result given to the bot for a tool call that was not run.
+pub fn denial_message(namespaced_name: &str, approval: ToolApproval) -> String {
+    format!(
+        "Tool execution was denied by {}. Tool '{}' was not executed.",
+        approval.denier(),
+        display_name_from_namespaced(namespaced_name)
+    )
+}
+
+/// A line telling the user that tool calls were not run.
+pub fn denial_notice(approval: ToolApproval) -> String {
+    format!(
+        "\u{1f6ab} Tool execution was denied by {}.",
+        approval.denier()
+    )
+}
+
+fn restrictiveness(permission: ToolPermission) -> u8 {
+    match permission {
+        ToolPermission::Allow => 0,
+        ToolPermission::Ask => 1,
+        ToolPermission::Deny => 2,
+    }
+}
+
+fn argument_matches(pattern: &str, value: &Value) -> bool {
+    let value = match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+
+    let pattern = expand_home(pattern);
+
+    // Paths are normalized so `~/projects/../.ssh` doesn't pass as being under `~/projects`.
+    let value = if value.contains('/') || value.contains('\\') {
+        normalize_path(&expand_home(&value))
+    } else {
+        value
+    };
+
+    wildcard_match(&pattern, &value)
+}
+
+fn expand_home(text: &str) -> String {
+    let home = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE"));
+
+    match (text.strip_prefix('~'), home) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            format!("{}{}", home.trim_end_matches(['/', '\\']), rest)
+        }
+        _ => text.to_string(),
+    }
+}
+
+/// Lexically resolve `.` and `..` components.
+fn normalize_path(path: &str) -> String {
+    let absolute = path.starts_with('/');
+    let mut parts: Vec<&str> = Vec::new();
+
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+
+    let joined = parts.join("/");
+    if absolute {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+/// Match `text` against `pattern` where `*` matches any sequence of characters.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn args(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_denial_message_names_who_denied() {
+        assert_eq!(
+            denial_message("shell", ToolApproval::Policy),
+            "Tool execution was denied by the tool policy. Tool 'shell' was not executed."
+        );
+        assert_eq!(
+            denial_notice(ToolApproval::Manual),
+            "\u{1f6ab} Tool execution was denied by the user."
+        );
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("/home/*", "/home/user/file"));
+        assert!(wildcard_match("*.rs", "main.rs"));
+        assert!(wildcard_match("a*b*c", "aXXbYYc"));
+        assert!(!wildcard_match("a*b", "aXXc"));
+        assert!(wildcard_match("*", ""));
+    }
+
+    #[test]
+    fn test_most_specific_rule_wins() {
+        let policy = ToolPolicy {
+            rules: vec![
+                ToolPolicyRule::new("filesystem", ANY, ToolPermission::Ask),
+                ToolPolicyRule::new("filesystem", "read_file", ToolPermission::Allow)
+                    .with_argument("path", "/projects/*"),
+                ToolPolicyRule::new(ANY, "delete_file", ToolPermission::Deny),
+                ToolPolicyRule::new(ANY, "delete_file", ToolPermission::Allow),
+            ],
+            default_permission: ToolPermission::Allow,
+        };
+
+        let inside = args(json!({ "path": "/projects/moly/README.md" }));
+        let escaping = args(json!({ "path": "/projects/../etc/passwd" }));
+
+        assert_eq!(
+            policy.decide(Some("filesystem"), "read_file", &inside),
+            ToolPermission::Allow
+        );
+        assert_eq!(
+            policy.decide(Some("filesystem"), "read_file", &escaping),
+            ToolPermission::Ask
+        );
+        assert_eq!(
+            policy.decide(Some("filesystem"), "write_file", &inside),
+            ToolPermission::Ask
+        );
+        // Ties resolve to the most restrictive permission.
+        assert_eq!(
+            policy.decide(Some("git"), "delete_file", &Map::new()),
+            ToolPermission::Deny
+        );
+        assert_eq!(
+            policy.decide(Some("git"), "status", &Map::new()),
+            ToolPermission::Allow
+        );
+    }
+
+    #[test]
+    fn test_tool_names_with_separator() {
+        assert_eq!(
+            split_namespaced("files__read__all"),
+            (Some("files"), "read__all")
+        );
+        assert_eq!(split_namespaced("__init"), (None, "__init"));
+
+        let policy = ToolPolicy {
+            rules: vec![ToolPolicyRule::new(
+                "files",
+                "read__all",
+                ToolPermission::Deny,
+            )],
+            default_permission: ToolPermission::Allow,
+        };
+
+        assert_eq!(
+            policy.evaluate("files__read__all", &Map::new()),
+            ToolPermission::Deny
+        );
+        assert_eq!(
+            policy.evaluate("files__read", &Map::new()),
+            ToolPermission::Allow
+        );
+    }
+
+    #[test]
+    fn test_approval_source() {
+        let mut policy = ToolPolicy {
+            rules: vec![ToolPolicyRule::new(
+                ANY,
+                "delete_file",
+                ToolPermission::Deny,
+            )],
+            default_permission: ToolPermission::Allow,
+        };
+
+        assert_eq!(
+            policy.evaluate_with_approval("delete_file", &Map::new()),
+            (ToolPermission::Deny, ToolApproval::Policy)
+        );
+        assert_eq!(
+            policy.evaluate_with_approval("status", &Map::new()),
+            (ToolPermission::Allow, ToolApproval::DangerousMode)
+        );
+
+        policy.default_permission = ToolPermission::Ask;
+        assert_eq!(
+            policy.evaluate_with_approval("status", &Map::new()),
+            (ToolPermission::Ask, ToolApproval::Manual)
+        );
+    }
+}
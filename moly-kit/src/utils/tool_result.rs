@@ -0,0 +1,366 @@
+//! Interpretation of tool results by content type, for rendering.
+//!
+//! Results reach the chat as text. MCP tools often return JSON, content blocks
+//! with images or embedded resources, or long logs, so the text is split into
+//! [`ToolResultPart`]s that can be rendered differently.
+
+use base64::Engine;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+/// Containers nested deeper than this start collapsed in a [`json_rows`] tree.
+const EXPANDED_DEPTH: usize = 2;
+
+/// Children listed per container before the rest are summarized.
+const MAX_CHILDREN: usize = 100;
+
+/// A piece of a tool result.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolResultPart {
+    Text(String),
+    Json(Value),
+    /// Base64 image data.
+    Image {
+        mime_type: String,
+        data: String,
+    },
+    /// Base64 audio data.
+    Audio {
+        mime_type: String,
+        data: String,
+    },
+    /// A resource embedded in the result, with either text or base64 content.
+    Resource {
+        uri: String,
+        mime_type: Option<String>,
+        text: Option<String>,
+        blob: Option<String>,
+    },
+    /// A link to a resource the server can provide.
+    ResourceLink {
+        uri: String,
+        name: Option<String>,
+        description: Option<String>,
+    },
+}
+
+/// Split the content of a tool result into parts.
+///
+/// Recognizes a JSON document, MCP content blocks (alone, as a list, or inside a
+/// `CallToolResult`), and content blocks on their own lines among plain text.
+pub fn parse_tool_result(content: &str) -> Vec<ToolResultPart> {
+    let trimmed = content.trim();
+    if trimmed.is_empty() {
+        return Vec::new();
+    }
+
+    if let Ok(value) = serde_json::from_str::<Value>(trimmed)
+        && (value.is_object() || value.is_array())
+    {
+        return parts_from_json(value);
+    }
+
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    for line in content.lines() {
+        let block = line
+            .trim()
+            .starts_with('{')
+            .then(|| serde_json::from_str::<Value>(line.trim()).ok())
+            .flatten()
+            .and_then(|value| part_from_block(&value));
+
+        match block {
+            Some(part) => {
+                push_text(&mut parts, std::mem::take(&mut text));
+                parts.push(part);
+            }
+            None => {
+                if !text.is_empty() {
+                    text.push('\n');
+                }
+                text.push_str(line);
+            }
+        }
+    }
+    push_text(&mut parts, text);
+    parts
+}
+
+fn parts_from_json(value: Value) -> Vec<ToolResultPart> {
+    // A `CallToolResult`, as returned by the `tools/call` request.
+    if let Some(blocks) = value.get("content").and_then(Value::as_array)
+        && blocks.iter().all(is_block)
+    {
+        let mut parts: Vec<_> = blocks.iter().filter_map(part_from_block).collect();
+        if let Some(structured) = value.get("structuredContent")
+            && !structured.is_null()
+        {
+            parts.push(ToolResultPart::Json(structured.clone()));
+        }
+        return parts;
+    }
+
+    if let Some(blocks) = value.as_array()
+        && !blocks.is_empty()
+        && blocks.iter().all(is_block)
+    {
+        return blocks.iter().filter_map(part_from_block).collect();
+    }
+
+    if let Some(part) = part_from_block(&value) {
+        return vec![part];
+    }
+
+    vec![ToolResultPart::Json(value)]
+}
+
+fn is_block(value: &Value) -> bool {
+    part_from_block(value).is_some()
+}
+
+/// Interpret an MCP content block.
+fn part_from_block(value: &Value) -> Option<ToolResultPart> {
+    let block = value.as_object()?;
+    let string = |object: &Map<String, Value>, key: &str| {
+        object.get(key).and_then(Value::as_str).map(str::to_string)
+    };
+
+    match block.get("type")?.as_str()? {
+        "text" => {
+            let text = string(block, "text")?;
+            Some(text_part(text))
+        }
+        "image" => Some(ToolResultPart::Image {
+            mime_type: string(block, "mimeType").unwrap_or_else(|| "image/png".to_string()),
+            data: string(block, "data")?,
+        }),
+        "audio" => Some(ToolResultPart::Audio {
+            mime_type: string(block, "mimeType").unwrap_or_else(|| "audio/wav".to_string()),
+            data: string(block, "data")?,
+        }),
+        "resource" => {
+            let resource = block.get("resource")?.as_object()?;
+            Some(ToolResultPart::Resource {
+                uri: string(resource, "uri")?,
+                mime_type: string(resource, "mimeType"),
+                text: string(resource, "text"),
+                blob: string(resource, "blob"),
+            })
+        }
+        "resource_link" => Some(ToolResultPart::ResourceLink {
+            uri: string(block, "uri")?,
+            name: string(block, "name"),
+            description: string(block, "description"),
+        }),
+        _ => None,
+    }
+}
+
+/// Text blocks frequently carry JSON documents.
+fn text_part(text: String) -> ToolResultPart {
+    match serde_json::from_str::<Value>(text.trim()) {
+        Ok(value) if value.is_object() || value.is_array() => ToolResultPart::Json(value),
+        _ => ToolResultPart::Text(text),
+    }
+}
+
+fn push_text(parts: &mut Vec<ToolResultPart>, text: String) {
+    if !text.trim().is_empty() {
+        parts.push(ToolResultPart::Text(text));
+    }
+}
+
+/// Decode standard base64 content, as used by MCP for binary data.
+pub fn decode_base64(data: &str) -> Result<Vec<u8>, String> {
+    base64::engine::general_purpose::STANDARD
+        .decode(data.trim())
+        .map_err(|e| format!("Invalid base64 content: {}", e))
+}
+
+/// A visible line of a collapsible JSON tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonRow {
+    /// Identifies the node across renders, like `/items/0/name`.
+    pub path: String,
+    pub depth: usize,
+    pub text: String,
+    /// Whether the node is an object or array with children.
+    pub expandable: bool,
+    pub expanded: bool,
+}
+
+/// Flatten `value` into the rows currently visible.
+///
+/// Containers start expanded up to a small depth. Paths in `toggled` flip that
+/// initial state.
+pub fn json_rows(value: &Value, toggled: &HashSet<String>) -> Vec<JsonRow> {
+    let mut rows = Vec::new();
+    push_json_rows(&mut rows, value, None, String::new(), 0, toggled);
+    rows
+}
+
+fn push_json_rows(
+    rows: &mut Vec<JsonRow>,
+    value: &Value,
+    key: Option<&str>,
+    path: String,
+    depth: usize,
+    toggled: &HashSet<String>,
+) {
+    let prefix = key.map(|key| format!("{}: ", key)).unwrap_or_default();
+    let children: Vec<(String, &Value)> = match value {
+        Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v))
+            .collect(),
+        _ => {
+            rows.push(JsonRow {
+                path,
+                depth,
+                text: format!("{}{}", prefix, value),
+                expandable: false,
+                expanded: false,
+            });
+            return;
+        }
+    };
+
+    let expandable = !children.is_empty();
+    let expanded = expandable && ((depth < EXPANDED_DEPTH) != toggled.contains(&path));
+    let (open, close, unit) = match value {
+        Value::Object(_) => ('{', '}', "key"),
+        _ => ('[', ']', "item"),
+    };
+    let text = if expanded {
+        format!("▾ {}{}", prefix, open)
+    } else if expandable {
+        let plural = if children.len() == 1 { "" } else { "s" };
+        format!(
+            "▸ {}{}… {} {}{}{}",
+            prefix,
+            open,
+            children.len(),
+            unit,
+            plural,
+            close
+        )
+    } else {
+        format!("{}{}{}", prefix, open, close)
+    };
+
+    rows.push(JsonRow {
+        path: path.clone(),
+        depth,
+        text,
+        expandable,
+        expanded,
+    });
+
+    if !expanded {
+        return;
+    }
+
+    for (child_key, child) in children.iter().take(MAX_CHILDREN) {
+        let child_path = format!("{}/{}", path, child_key);
+        push_json_rows(rows, child, Some(child_key), child_path, depth + 1, toggled);
+    }
+
+    if children.len() > MAX_CHILDREN {
+        rows.push(JsonRow {
+            path: format!("{}/…", path),
+            depth: depth + 1,
+            text: format!("… {} more", children.len() - MAX_CHILDREN),
+            expandable: false,
+            expanded: false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_content_blocks() {
+        let content = json!({
+            "content": [
+                { "type": "text", "text": "{\"files\": 2}" },
+                { "type": "image", "data": "aGk=", "mimeType": "image/jpeg" },
+                { "type": "resource_link", "uri": "file:///a.txt", "name": "a.txt" },
+                {
+                    "type": "resource",
+                    "resource": { "uri": "file:///b.txt", "text": "hello" }
+                }
+            ],
+            "isError": false
+        })
+        .to_string();
+
+        let parts = parse_tool_result(&content);
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[0], ToolResultPart::Json(json!({ "files": 2 })));
+        assert_eq!(
+            parts[1],
+            ToolResultPart::Image {
+                mime_type: "image/jpeg".to_string(),
+                data: "aGk=".to_string()
+            }
+        );
+        assert!(
+            matches!(&parts[2], ToolResultPart::ResourceLink { uri, .. } if uri == "file:///a.txt")
+        );
+        assert!(
+            matches!(&parts[3], ToolResultPart::Resource { text: Some(text), .. } if text == "hello")
+        );
+        assert_eq!(decode_base64("aGk=").unwrap(), b"hi");
+    }
+
+    #[test]
+    fn test_parse_text_and_json() {
+        assert_eq!(
+            parse_tool_result("[1, 2]"),
+            vec![ToolResultPart::Json(json!([1, 2]))]
+        );
+        assert_eq!(
+            parse_tool_result("plain output\nsecond line"),
+            vec![ToolResultPart::Text(
+                "plain output\nsecond line".to_string()
+            )]
+        );
+
+        let mixed = "Screenshot taken\n{\"type\":\"image\",\"data\":\"aGk=\",\"mimeType\":\"image/png\"}\n{not json";
+        let parts = parse_tool_result(mixed);
+        assert_eq!(parts.len(), 3);
+        assert_eq!(
+            parts[0],
+            ToolResultPart::Text("Screenshot taken".to_string())
+        );
+        assert!(matches!(parts[1], ToolResultPart::Image { .. }));
+        assert_eq!(parts[2], ToolResultPart::Text("{not json".to_string()));
+
+        assert!(parse_tool_result("  ").is_empty());
+    }
+
+    #[test]
+    fn test_json_rows_collapse() {
+        let value = json!({ "a": 1, "b": { "c": { "d": true } }, "e": [] });
+
+        let rows = json_rows(&value, &HashSet::new());
+        let texts: Vec<_> = rows.iter().map(|row| row.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec!["▾ {", "a: 1", "▾ b: {", "▸ c: {… 1 key}", "e: []"]
+        );
+        assert_eq!(rows[3].path, "/b/c");
+        assert_eq!(rows[3].depth, 2);
+
+        let toggled = HashSet::from(["/b/c".to_string(), "".to_string()]);
+        let rows = json_rows(&value, &toggled);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].text, "▸ {… 3 keys}");
+    }
+}
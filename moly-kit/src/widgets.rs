@@ -33,6 +33,7 @@ pub mod moly_modal;
 pub mod prompt_input;
 pub mod realtime;
 pub mod stt_input;
+pub mod tool_result_content;
 
 pub fn script_mod(vm: &mut makepad_widgets::ScriptVm) {
     theme_moly_kit_light::script_mod(vm);
@@ -50,6 +51,7 @@ pub fn script_mod(vm: &mut makepad_widgets::ScriptVm) {
     slot::script_mod(vm);
     message_thinking_block::script_mod(vm);
     standard_message_content::script_mod(vm);
+    tool_result_content::script_mod(vm);
     chat_line::script_mod(vm);
     messages::script_mod(vm);
     stt_input::script_mod(vm);
@@ -40,6 +40,21 @@ script_mod! {
             image := ImageView { contain: true }
         }
 
+        text_wrapper := View {
+            visible: false
+            padding: 8
+            show_bg: true
+            draw_bg +: { color: #fff }
+            text_preview := Label {
+                width: Fill
+                text: ""
+                draw_text +: {
+                    text_style +: { font_size: 8 }
+                    color: #333
+                }
+            }
+        }
+
         tag_wrapper := View {
             visible: false
             align: Align { x: 1 }
@@ -101,6 +116,7 @@ impl AttachmentView {
 
             self.icon_wrapper_ref(cx).set_visible(cx, true);
             self.image_wrapper_ref(cx).set_visible(cx, false);
+            self.text_wrapper_ref(cx).set_visible(cx, false);
 
             tag_label.set_text(
                 cx,
@@ -126,6 +142,9 @@ impl AttachmentView {
                 if self.attachment.is_image() {
                     icon.set_text(cx, "\u{f03e}");
                     self.try_load_preview();
+                } else if can_extract(&self.attachment) {
+                    icon.set_text(cx, "\u{f15c}");
+                    self.try_load_text_preview();
                 } else {
                     icon.set_text(cx, "\u{f15b}");
                 }
@@ -164,6 +183,10 @@ impl AttachmentView {
         self.view(cx, ids!(icon_wrapper))
     }
 
+    fn text_wrapper_ref(&self, cx: &Cx) -> ViewRef {
+        self.view(cx, ids!(text_wrapper))
+    }
+
     fn tag_bg_ref(&self, cx: &Cx) -> ViewRef {
         self.view(cx, ids!(tag_bg))
     }
@@ -219,8 +242,51 @@ impl AttachmentView {
             let _ = future.await;
         });
     }
+
+    #[cfg(feature = "document-extraction")]
+    fn try_load_text_preview(&mut self) {
+        let ui = self.ui_runner();
+        let attachment = self.attachment.clone();
+
+        let future = async move {
+            let text = match crate::utils::extraction::extract_attachment(&attachment).await {
+                Ok(text) => text,
+                Err(e) => {
+                    ::log::warn!("Failed to extract text from {}: {}", attachment.name, e);
+                    return;
+                }
+            };
+
+            let snippet: String = text.chars().take(TEXT_PREVIEW_CHARS).collect();
+            ui.defer_with_redraw(move |me: &mut AttachmentView, cx, _| {
+                me.label(cx, ids!(text_preview)).set_text(cx, &snippet);
+                me.icon_wrapper_ref(cx).set_visible(cx, false);
+                me.text_wrapper_ref(cx).set_visible(cx, true);
+                let color = preview_color();
+                let mut tag_bg = me.tag_bg_ref(cx);
+                script_apply_eval!(cx, tag_bg, {
+                    draw_bg +: {
+                        color: #(color)
+                    }
+                });
+            });
+        };
+
+        let (future, abort_on_drop) = abort_on_drop(future);
+        self.abort_on_drop = Some(abort_on_drop);
+        spawn(async move {
+            let _ = future.await;
+        });
+    }
+
+    #[cfg(not(feature = "document-extraction"))]
+    fn try_load_text_preview(&mut self) {}
 }
 
+/// How much of the extracted text is shown in the preview.
+#[cfg(feature = "document-extraction")]
+const TEXT_PREVIEW_CHARS: usize = 1500;
+
 /// Red-ish to catch the attention.
 fn unavailable_color() -> Vec4 {
     hex_rgb_color(0xec003f)
@@ -239,5 +305,20 @@ fn preview_color() -> Vec4 {
 /// If this widget could generate a preview for the attachment.
 pub fn can_preview(attachment: &Attachment) -> bool {
     attachment.is_available()
-        && crate::widgets::image_view::can_load(attachment.content_type_or_octet_stream())
+        && (crate::widgets::image_view::can_load(attachment.content_type_or_octet_stream())
+            || can_extract(attachment))
+}
+
+/// If a text preview can be extracted from the attachment.
+fn can_extract(attachment: &Attachment) -> bool {
+    #[cfg(feature = "document-extraction")]
+    {
+        crate::utils::extraction::can_extract(attachment)
+    }
+
+    #[cfg(not(feature = "document-extraction"))]
+    {
+        let _ = attachment;
+        false
+    }
 }
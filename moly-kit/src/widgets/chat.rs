@@ -1,11 +1,18 @@
 use makepad_widgets::defer_with_redraw::DeferWithRedraw;
 use makepad_widgets::*;
 use std::cell::{Ref, RefMut};
+use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 
-use crate::aitk::utils::tool::display_name_from_namespaced;
+use crate::aitk::utils::asynchronous::spawn;
 use crate::prelude::*;
 use crate::utils::makepad::events::EventExt;
+use crate::utils::slash_commands::{CommandMessage, CommandRole};
+use crate::utils::tool_audit::{ToolAuditCall, ToolAuditHook, ToolAuditor};
+use crate::utils::tool_calls::ToolCalls;
+use crate::utils::tool_policy::{
+    SharedToolPolicy, ToolApproval, ToolPermission, denial_message, denial_notice,
+};
 use crate::widgets::stt_input::*;
 
 // Re-export type needed to configure STT.
@@ -50,6 +57,21 @@ pub struct Chat {
 
     #[rust]
     plugin_id: Option<ChatControllerPluginRegistrationId>,
+
+    /// Rules to approve or deny tool calls without asking.
+    #[rust]
+    tool_policy: Option<SharedToolPolicy>,
+
+    #[rust]
+    tool_auditor: ToolAuditor,
+
+    /// Approved tool calls run through this, to report progress and enforce timeouts.
+    #[rust]
+    tool_calls: ToolCalls,
+
+    /// Ids of the tool calls the policy denied, whose denial isn't reported yet.
+    #[rust]
+    policy_denied: HashSet<String>,
 }
 
 impl Widget for Chat {
@@ -117,6 +139,14 @@ impl Chat {
             self.handle_submit(cx);
         }
 
+        let expanded = self
+            .prompt_input_ref(cx)
+            .read()
+            .command_expanded(event.actions());
+        if let Some(messages) = expanded {
+            self.handle_command_messages(messages);
+        }
+
         let call_pressed = self
             .prompt_input_ref(cx)
             .read()
@@ -328,62 +358,38 @@ impl Chat {
                     }
                 }
                 MessagesAction::ToolApprove(index) => {
-                    let mut lock = chat_controller.lock().unwrap();
-
-                    let mut updated_message = lock.state().messages[index].clone();
-
-                    for tool_call in &mut updated_message.content.tool_calls {
-                        tool_call.permission_status = ToolCallPermissionStatus::Approved;
+                    self.decide_pending_tool_calls(index, true);
+                }
+                MessagesAction::ToolApproveAlways(index) => {
+                    if let Some(policy) = &self.tool_policy {
+                        let names: Vec<String> = chat_controller.lock().unwrap().state().messages
+                            [index]
+                            .content
+                            .tool_calls
+                            .iter()
+                            .filter(|tc| tc.permission_status == ToolCallPermissionStatus::Pending)
+                            .map(|tc| tc.name.clone())
+                            .collect();
+
+                        policy.update(|policy| {
+                            for name in &names {
+                                policy.set_tool_permission(name, ToolPermission::Allow);
+                            }
+                        });
                     }
 
-                    lock.dispatch_mutation(VecMutation::Update(index, updated_message));
-
-                    let tools = lock.state().messages[index].content.tool_calls.clone();
-                    let bot_id = lock.state().bot_id.clone();
-                    lock.dispatch_task(ChatTask::Execute(tools, bot_id));
+                    self.decide_pending_tool_calls(index, true);
                 }
                 MessagesAction::ToolDeny(index) => {
-                    let mut lock = chat_controller.lock().unwrap();
-
-                    let mut updated_message = lock.state().messages[index].clone();
-
-                    updated_message.update_content(|content| {
-                        for tool_call in &mut content.tool_calls {
-                            tool_call.permission_status = ToolCallPermissionStatus::Denied;
-                        }
-                    });
-
-                    lock.dispatch_mutation(VecMutation::Update(index, updated_message));
-
-                    let tool_results: Vec<ToolResult> = lock.state().messages[index]
+                    self.decide_pending_tool_calls(index, false);
+                }
+                MessagesAction::ToolCancel(index) => {
+                    for tool_call in &chat_controller.lock().unwrap().state().messages[index]
                         .content
                         .tool_calls
-                        .iter()
-                        .map(|tc| {
-                            let display_name = display_name_from_namespaced(&tc.name);
-                            ToolResult {
-                                tool_call_id: tc.id.clone(),
-                                content: format!(
-                                    "Tool execution was denied by the user. \
-                                     Tool '{}' was not executed.",
-                                    display_name
-                                ),
-                                is_error: true,
-                            }
-                        })
-                        .collect();
-
-                    lock.dispatch_mutation(VecMutation::Push(Message {
-                        from: EntityId::Tool,
-                        content: MessageContent {
-                            text: "\u{1f6ab} Tool execution was denied \
-                                   by the user."
-                                .to_string(),
-                            tool_results,
-                            ..Default::default()
-                        },
-                        ..Default::default()
-                    }));
+                    {
+                        self.tool_calls.cancel(&tool_call.id);
+                    }
                 }
                 MessagesAction::None => {}
             }
@@ -430,10 +436,47 @@ impl Chat {
                 .unwrap()
                 .dispatch_task(ChatTask::Send);
         } else if prompt.read().has_stop_task() {
-            chat_controller
-                .lock()
-                .unwrap()
-                .dispatch_task(ChatTask::Stop);
+            let mut lock = chat_controller.lock().unwrap();
+            for message in &lock.state().messages {
+                for tool_call in &message.content.tool_calls {
+                    self.tool_calls.cancel(&tool_call.id);
+                }
+            }
+            lock.dispatch_task(ChatTask::Stop);
+        }
+    }
+
+    /// Insert the messages of an expanded slash command, sending them if the
+    /// last one is from the user.
+    fn handle_command_messages(&mut self, messages: Vec<CommandMessage>) {
+        let Some(chat_controller) = self.chat_controller.clone() else {
+            return;
+        };
+
+        let mut lock = chat_controller.lock().unwrap();
+        let Some(bot_id) = lock.state().bot_id.clone() else {
+            return;
+        };
+
+        let ends_with_user = messages
+            .last()
+            .is_some_and(|message| message.role == CommandRole::User);
+
+        for message in messages {
+            let from = match message.role {
+                CommandRole::User => EntityId::User,
+                CommandRole::Assistant => EntityId::Bot(bot_id.clone()),
+            };
+
+            lock.dispatch_mutation(VecMutation::Push(Message {
+                from,
+                content: message.content,
+                ..Default::default()
+            }));
+        }
+
+        if ends_with_user {
+            lock.dispatch_task(ChatTask::Send);
         }
     }
 
@@ -489,7 +532,7 @@ impl Chat {
         if let Some(controller) = self.chat_controller.as_ref() {
             let mut guard = controller.lock().unwrap();
 
-            let plugin = Plugin::new(self.ui_runner());
+            let plugin = Plugin::new(self.ui_runner(), self.tool_auditor.clone());
             self.plugin_id = Some(guard.append_plugin(plugin));
         }
     }
@@ -518,8 +561,251 @@ impl Chat {
 
     fn handle_streaming_end(&mut self, cx: &mut Cx) {
         self.prompt_input_ref(cx).write().set_send();
+        self.apply_tool_policy();
         self.redraw(cx);
     }
+
+    /// Set the rules used to approve or deny tool calls without asking the user.
+    ///
+    /// Also shared with the realtime widget.
+    pub fn set_tool_policy(&mut self, cx: &mut Cx, tool_policy: Option<SharedToolPolicy>) {
+        self.realtime(cx, ids!(realtime))
+            .set_tool_policy(tool_policy.clone());
+        self.tool_policy = tool_policy;
+    }
+
+    /// Report every tool call, its decision and its result to `hook`.
+    ///
+    /// Also shared with the realtime widget.
+    pub fn set_tool_audit(&mut self, cx: &mut Cx, hook: Option<ToolAuditHook>) {
+        self.realtime(cx, ids!(realtime))
+            .set_tool_audit(hook.clone());
+        self.tool_auditor.set_hook(hook);
+    }
+
+    /// Share the running tool calls and their timeouts, so progress reported
+    /// by the servers reaches the chat.
+    ///
+    /// Also shared with the realtime widget.
+    pub fn set_tool_calls(&mut self, cx: &mut Cx, tool_calls: ToolCalls) {
+        self.messages_ref(cx)
+            .write()
+            .set_tool_calls(tool_calls.clone());
+        self.realtime(cx, ids!(realtime))
+            .set_tool_calls(tool_calls.clone());
+        self.tool_calls = tool_calls;
+    }
+
+    /// Decide the pending tool calls of the last message based on the tool policy.
+    ///
+    /// Calls the policy asks about are left pending for the user.
+    fn apply_tool_policy(&mut self) {
+        let (Some(policy), Some(controller)) =
+            (self.tool_policy.clone(), self.chat_controller.clone())
+        else {
+            return;
+        };
+
+        let mut lock = controller.lock().unwrap();
+        let Some(index) = lock.state().messages.len().checked_sub(1) else {
+            return;
+        };
+
+        let mut message = lock.state().messages[index].clone();
+        let bot_id = lock.state().bot_id.clone();
+        let mut changed = false;
+
+        for tool_call in &mut message.content.tool_calls {
+            if tool_call.permission_status != ToolCallPermissionStatus::Pending {
+                continue;
+            }
+
+            let (permission, approval) =
+                policy.evaluate_with_approval(&tool_call.name, &tool_call.arguments);
+            let approved = match permission {
+                ToolPermission::Allow => true,
+                ToolPermission::Deny => false,
+                ToolPermission::Ask => continue,
+            };
+
+            tool_call.permission_status = if approved {
+                ToolCallPermissionStatus::Approved
+            } else {
+                self.policy_denied.insert(tool_call.id.clone());
+                ToolCallPermissionStatus::Denied
+            };
+            changed = true;
+            self.tool_auditor
+                .decided(audit_call(tool_call, &bot_id), approved, approval);
+        }
+
+        if changed {
+            lock.dispatch_mutation(VecMutation::Update(index, message));
+            let approved = report_decided_tool_calls(&mut lock, index, &mut self.policy_denied);
+            drop(lock);
+            self.run_tool_calls(approved);
+        }
+    }
+
+    /// Approve or deny all the tool calls still pending in the message at `index`.
+    fn decide_pending_tool_calls(&mut self, index: usize, approve: bool) {
+        let chat_controller = self.chat_controller.clone().unwrap();
+        let mut lock = chat_controller.lock().unwrap();
+
+        let mut updated_message = lock.state().messages[index].clone();
+        let bot_id = lock.state().bot_id.clone();
+        let auditor = self.tool_auditor.clone();
+
+        updated_message.update_content(|content| {
+            for tool_call in &mut content.tool_calls {
+                if tool_call.permission_status == ToolCallPermissionStatus::Pending {
+                    tool_call.permission_status = if approve {
+                        ToolCallPermissionStatus::Approved
+                    } else {
+                        ToolCallPermissionStatus::Denied
+                    };
+                    auditor.decided(
+                        audit_call(tool_call, &bot_id),
+                        approve,
+                        ToolApproval::Manual,
+                    );
+                }
+            }
+        });
+
+        lock.dispatch_mutation(VecMutation::Update(index, updated_message));
+        let approved = report_decided_tool_calls(&mut lock, index, &mut self.policy_denied);
+        drop(lock);
+        self.run_tool_calls(approved);
+    }
+
+    /// Run approved tool calls, then send their results to the bot.
+    ///
+    /// The chat streams while they run, so stopping it cancels them.
+    fn run_tool_calls(&mut self, tool_calls: Vec<ToolCall>) {
+        if tool_calls.is_empty() {
+            return;
+        }
+
+        let Some(controller) = self.chat_controller.clone() else {
+            return;
+        };
+
+        controller
+            .lock()
+            .unwrap()
+            .dispatch_mutation(ChatStateMutation::SetIsStreaming(true));
+
+        let running = self.tool_calls.clone();
+        spawn(async move {
+            let results = futures::future::join_all(tool_calls.into_iter().map(|tool_call| {
+                let running = running.clone();
+                async move {
+                    running
+                        .run(&tool_call.name, &tool_call.id, tool_call.arguments)
+                        .await
+                }
+            }))
+            .await;
+
+            let failed = results.iter().filter(|result| result.is_error).count();
+            let text = if failed == 0 {
+                "\u{1f527} Tool execution finished."
+            } else if failed == results.len() {
+                "\u{26a0}\u{fe0f} Tool execution failed."
+            } else {
+                "\u{26a0}\u{fe0f} Some tools failed."
+            };
+
+            let mut lock = controller.lock().unwrap();
+            // Stopped by the user, the results are kept but not sent.
+            let stopped = !lock.state().is_streaming;
+
+            lock.dispatch_mutation(VecMutation::Push(Message {
+                from: EntityId::Tool,
+                content: MessageContent {
+                    text: text.to_string(),
+                    tool_results: results,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }));
+
+            if !stopped {
+                lock.dispatch_mutation(ChatStateMutation::SetIsStreaming(false));
+                lock.dispatch_task(ChatTask::Send);
+            }
+        });
+    }
+}
+
+fn audit_call(tool_call: &ToolCall, bot_id: &Option<BotId>) -> ToolAuditCall {
+    ToolAuditCall {
+        id: tool_call.id.clone(),
+        name: tool_call.name.clone(),
+        arguments: serde_json::Value::Object(tool_call.arguments.clone()),
+        bot_id: bot_id.clone(),
+        realtime: false,
+    }
+}
+
+/// Once no tool call is pending in the message at `index`, report the denied ones
+/// to the bot and return the approved ones to be executed.
+///
+/// Denials are attributed to the policy for the calls in `policy_denied`, which
+/// forgets them once reported, and to the user otherwise.
+fn report_decided_tool_calls(
+    controller: &mut ChatController,
+    index: usize,
+    policy_denied: &mut HashSet<String>,
+) -> Vec<ToolCall> {
+    let tool_calls = &controller.state().messages[index].content.tool_calls;
+
+    if tool_calls
+        .iter()
+        .any(|tc| tc.permission_status == ToolCallPermissionStatus::Pending)
+    {
+        return Vec::new();
+    }
+
+    let approved: Vec<_> = tool_calls
+        .iter()
+        .filter(|tc| tc.permission_status == ToolCallPermissionStatus::Approved)
+        .cloned()
+        .collect();
+
+    let mut notice_approval = ToolApproval::Policy;
+    let tool_results: Vec<ToolResult> = tool_calls
+        .iter()
+        .filter(|tc| tc.permission_status == ToolCallPermissionStatus::Denied)
+        .map(|tc| {
+            let approval = if policy_denied.remove(&tc.id) {
+                ToolApproval::Policy
+            } else {
+                notice_approval = ToolApproval::Manual;
+                ToolApproval::Manual
+            };
+            ToolResult {
+                tool_call_id: tc.id.clone(),
+                content: denial_message(&tc.name, approval),
+                is_error: true,
+            }
+        })
+        .collect();
+
+    if !tool_results.is_empty() {
+        controller.dispatch_mutation(VecMutation::Push(Message {
+            from: EntityId::Tool,
+            content: MessageContent {
+                text: denial_notice(notice_approval),
+                tool_results,
+                ..Default::default()
+            },
+            ..Default::default()
+        }));
+    }
+
+    approved
 }
 
 // TODO: Since `ChatRef` is generated by a macro, I can't document this
@@ -562,16 +848,19 @@ impl Drop for Chat {
 
 struct Plugin {
     ui: UiRunner<Chat>,
+    tool_auditor: ToolAuditor,
 }
 
 impl Plugin {
-    fn new(ui: UiRunner<Chat>) -> Self {
-        Self { ui }
+    fn new(ui: UiRunner<Chat>, tool_auditor: ToolAuditor) -> Self {
+        Self { ui, tool_auditor }
     }
 }
 
 impl ChatControllerPlugin for Plugin {
-    fn on_state_ready(&mut self, _state: &ChatState, mutations: &[ChatStateMutation]) {
+    fn on_state_ready(&mut self, state: &ChatState, mutations: &[ChatStateMutation]) {
+        self.tool_auditor.check_results(&state.messages);
+
         for mutation in mutations {
             match mutation {
                 ChatStateMutation::SetIsStreaming(true) => {
@@ -365,9 +365,13 @@ script_mod! {
         spacing: 5,
         padding: Inset { bottom: 8 }
         approve := ToolApprovalButton {
-            text: "Approve",
+            text: "Allow once",
             draw_bg +: { color: #x4CAF50, color_hover: #x45a049 }
         }
+        approve_always := ToolApprovalButton {
+            text: "Always allow",
+            draw_bg +: { color: #x1976d2, color_hover: #x1565c0 }
+        }
         deny := ToolApprovalButton {
             text: "Deny",
             draw_bg +: { color: #xf44336, color_hover: #xd32f2f }
@@ -386,6 +390,38 @@ script_mod! {
             content_section +: {
                 flow: Down
                 tool_actions := ToolApprovalActions { visible: false }
+                progress_view := View {
+                    visible: false
+                    width: Fill, height: Fit,
+                    flow: Down
+                    spacing: 6
+                    padding: Inset { bottom: 8 }
+                    View {
+                        width: Fill, height: Fit,
+                        align: Align { y: 0.5 }
+                        spacing: 8
+                        progress_label := Label {
+                            width: Fill
+                            draw_text +: {
+                                text_style: theme.font_regular { font_size: 10 },
+                                color: #555
+                            }
+                        }
+                        cancel_tool := ToolApprovalButton {
+                            text: "Cancel",
+                            draw_bg +: { color: #x757575, color_hover: #x616161 }
+                        }
+                    }
+                    progress_track := RoundedView {
+                        width: 240, height: 6,
+                        flow: Overlay
+                        draw_bg +: { color: #x0001, border_radius: 3.0 }
+                        progress_bar := RoundedView {
+                            width: 0, height: Fill,
+                            draw_bg +: { color: #xff9800, border_radius: 3.0 }
+                        }
+                    }
+                }
                 status_view := View {
                     visible: false
                     width: Fill, height: Fit,
@@ -411,6 +447,9 @@ script_mod! {
                     grapheme +: { draw_bg +: { color: #x1a5b9c } }
                 }
             }
+            content_section +: {
+                content +: { default: ToolResultContent {} }
+            }
         }
     }
 }
@@ -424,7 +463,9 @@ pub enum ChatLineAction {
     SaveAndRegenerate,
     EditCancel,
     ToolApprove,
+    ToolApproveAlways,
     ToolDeny,
+    ToolCancel,
     EditorChanged,
     ErrorDetailsToggle,
     #[default]
@@ -481,10 +522,18 @@ impl Widget for ChatLine {
             cx.widget_action(self.widget_uid(), ChatLineAction::ToolApprove);
         }
 
+        if self.approve_always_ref(cx).clicked(actions) {
+            cx.widget_action(self.widget_uid(), ChatLineAction::ToolApproveAlways);
+        }
+
         if self.deny_ref(cx).clicked(actions) {
             cx.widget_action(self.widget_uid(), ChatLineAction::ToolDeny);
         }
 
+        if self.button(cx, ids!(cancel_tool)).clicked(actions) {
+            cx.widget_action(self.widget_uid(), ChatLineAction::ToolCancel);
+        }
+
         if self.input_ref(cx).changed(actions).is_some() {
             cx.widget_action(self.widget_uid(), ChatLineAction::EditorChanged);
         }
@@ -521,6 +570,10 @@ impl ChatLine {
         self.button(cx, ids!(approve))
     }
 
+    fn approve_always_ref(&self, cx: &mut Cx) -> ButtonRef {
+        self.button(cx, ids!(approve_always))
+    }
+
     fn deny_ref(&self, cx: &mut Cx) -> ButtonRef {
         self.button(cx, ids!(deny))
     }
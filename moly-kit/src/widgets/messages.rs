@@ -5,8 +5,11 @@ use std::{
 };
 
 use crate::{
-    aitk::{controllers::chat::ChatController, protocol::*},
+    aitk::{
+        controllers::chat::ChatController, protocol::*, utils::tool::display_name_from_namespaced,
+    },
     utils::makepad::{events::EventExt, portal_list::ItemsRangeIter, ui_runner::DeferRedraw},
+    utils::tool_calls::ToolCalls,
     widgets::{
         avatar::AvatarWidgetRefExt, chat_line::ChatLineAction,
         message_loading::MessageLoadingWidgetRefExt,
@@ -19,8 +22,12 @@ use super::{
     citation::CitationAction,
     slot::{Slot, SlotWidgetRefExt},
     standard_message_content::StandardMessageContentWidgetRefExt,
+    tool_result_content::ToolResultContentWidgetRefExt,
 };
 
+/// Width of the progress bar of running tool calls, as set in `ToolRequestLine`.
+const PROGRESS_BAR_WIDTH: f64 = 240.0;
+
 script_mod! {
     use mod.prelude.widgets.*
     use mod.widgets.*
@@ -95,12 +102,19 @@ pub enum MessagesAction {
     /// history should be regenerated from here.
     EditRegenerate(usize),
 
-    /// The tool request at the given index should be approved and executed.
+    /// The pending tool calls at the given index should be approved once and executed.
     ToolApprove(usize),
 
+    /// Same as [`MessagesAction::ToolApprove`], but the tools involved should also
+    /// be allowed without asking from now on.
+    ToolApproveAlways(usize),
+
     /// The tool request at the given index should be denied.
     ToolDeny(usize),
 
+    /// The running tool calls of the message at the given index should be cancelled.
+    ToolCancel(usize),
+
     #[default]
     None,
 }
@@ -112,6 +126,10 @@ struct Editor {
     buffer: String,
 }
 
+/// Renders the content of bot and tool messages in place of the built-in widgets.
+///
+/// Tool messages carry their results in [`MessageContent::tool_results`]. The first
+/// registered renderer returning a widget is used.
 pub trait CustomContent {
     fn content_widget(
         &mut self,
@@ -161,6 +179,10 @@ pub struct Messages {
     /// Tracks which error message indices have their details expanded.
     #[rust]
     expanded_error_details: HashSet<usize>,
+
+    /// Running tool calls, to draw their progress.
+    #[rust]
+    tool_calls: ToolCalls,
 }
 
 impl Widget for Messages {
@@ -311,10 +333,19 @@ impl Messages {
                     item.label(cx, ids!(name)).set_text(cx, "Tool");
 
                     if !message.metadata.is_writing() {
-                        item.slot(cx, ids!(content))
-                            .current()
-                            .as_standard_message_content()
-                            .set_content(cx, &message.content);
+                        let mut slot = item.slot(cx, ids!(content));
+                        if let Some(custom_content) = self
+                            .custom_contents
+                            .iter_mut()
+                            .find_map(|cw| cw.content_widget(cx, slot.current(), &message.content))
+                        {
+                            slot.replace(custom_content);
+                        } else {
+                            slot.restore();
+                            slot.default()
+                                .as_tool_result_content()
+                                .set_content(cx, &message.content);
+                        }
                     }
 
                     self.apply_editor_visibility(cx, &item, index);
@@ -470,9 +501,20 @@ impl Messages {
                             item.view(cx, ids!(tool_actions))
                                 .set_visible(cx, has_pending);
 
+                            let has_approved = message.content.tool_calls.iter().any(|tc| {
+                                tc.permission_status == ToolCallPermissionStatus::Approved
+                            });
+
+                            self.draw_tool_progress(cx, &item, &message.content.tool_calls);
+
                             if has_denied {
+                                let status = if has_approved {
+                                    "Partially denied"
+                                } else {
+                                    "Denied"
+                                };
                                 item.view(cx, ids!(status_view)).set_visible(cx, true);
-                                item.label(cx, ids!(approved_status)).set_text(cx, "Denied");
+                                item.label(cx, ids!(approved_status)).set_text(cx, status);
                             } else {
                                 item.view(cx, ids!(status_view)).set_visible(cx, false);
                             }
@@ -589,6 +631,51 @@ impl Messages {
         }
     }
 
+    /// Share the tool calls whose progress should be drawn.
+    pub fn set_tool_calls(&mut self, tool_calls: ToolCalls) {
+        self.tool_calls = tool_calls;
+    }
+
+    /// Show the progress of the running calls of a tool request, if any.
+    fn draw_tool_progress(&self, cx: &mut Cx, item: &WidgetRef, tool_calls: &[ToolCall]) {
+        let running: Vec<_> = tool_calls
+            .iter()
+            .filter(|tc| self.tool_calls.is_running(&tc.id))
+            .collect();
+
+        item.view(cx, ids!(progress_view))
+            .set_visible(cx, !running.is_empty());
+        let Some(first) = running.first() else {
+            return;
+        };
+
+        // Several calls run at once rarely, the first one stands for all of them.
+        let progress = self.tool_calls.progress(&first.id);
+        let mut text = format!("Running {}", display_name_from_namespaced(&first.name));
+        if running.len() > 1 {
+            text.push_str(&format!(" and {} more", running.len() - 1));
+        }
+        match progress.as_ref().and_then(|p| p.fraction()) {
+            Some(fraction) => text.push_str(&format!(" · {:.0}%", fraction * 100.0)),
+            None => text.push('…'),
+        }
+        if let Some(message) = progress.as_ref().and_then(|p| p.message.as_ref()) {
+            text.push_str(&format!(" · {}", message));
+        }
+        item.label(cx, ids!(progress_label)).set_text(cx, &text);
+
+        let fraction = progress.and_then(|p| p.fraction());
+        item.view(cx, ids!(progress_track))
+            .set_visible(cx, fraction.is_some());
+        if let Some(fraction) = fraction {
+            let mut bar = item.view(cx, ids!(progress_bar));
+            let width = fraction * PROGRESS_BAR_WIDTH;
+            script_apply_eval!(cx, bar, {
+                width: #(width)
+            });
+        }
+    }
+
     /// Show or hide the editor for a message.
     ///
     /// Limitation: Only one editor can be shown at a time. If you
@@ -664,9 +751,18 @@ impl Messages {
                     ChatLineAction::ToolApprove => {
                         cx.widget_action(self.widget_uid(), MessagesAction::ToolApprove(index));
                     }
+                    ChatLineAction::ToolApproveAlways => {
+                        cx.widget_action(
+                            self.widget_uid(),
+                            MessagesAction::ToolApproveAlways(index),
+                        );
+                    }
                     ChatLineAction::ToolDeny => {
                         cx.widget_action(self.widget_uid(), MessagesAction::ToolDeny(index));
                     }
+                    ChatLineAction::ToolCancel => {
+                        cx.widget_action(self.widget_uid(), MessagesAction::ToolCancel(index));
+                    }
                     ChatLineAction::EditorChanged => {
                         let text = item.text_input(cx, ids!(input)).text();
                         self.current_editor.as_mut().unwrap().buffer = text;
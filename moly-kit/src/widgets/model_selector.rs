@@ -366,6 +366,22 @@ impl ModelSelectorRef {
             }
         }
     }
+
+    /// Set a function giving a short line shown under each bot name, like
+    /// its context window or pricing.
+    pub fn set_details<F>(&mut self, cx: &Cx, details: F)
+    where
+        F: Fn(&Bot) -> Option<String> + 'static,
+    {
+        if let Some(inner) = self.borrow_mut() {
+            if let Some(mut list) = inner
+                .widget(cx, ids!(options.list_container.list))
+                .borrow_mut::<ModelSelectorList>()
+            {
+                list.details = Some(Box::new(details));
+            }
+        }
+    }
 }
 
 /// Default grouping: groups all bots under "All" category.
@@ -44,11 +44,27 @@ script_mod! {
             }
         }
 
-        label := Label {
+        View {
             width: Fill
-            draw_text +: {
-                text_style: theme.font_regular { font_size: 11 }
-                color: #000
+            height: Fit
+            flow: Down
+            spacing: 2
+
+            label := Label {
+                width: Fill
+                draw_text +: {
+                    text_style: theme.font_regular { font_size: 11 }
+                    color: #000
+                }
+            }
+
+            details := Label {
+                width: Fill
+                visible: false
+                draw_text +: {
+                    text_style: theme.font_regular { font_size: 8.5 }
+                    color: #667085
+                }
             }
         }
 
@@ -91,6 +107,9 @@ pub struct ModelSelectorItem {
     #[rust]
     selected: bool,
 
+    #[rust]
+    details: Option<String>,
+
     #[apply_default]
     animator: Animator,
 }
@@ -133,6 +152,10 @@ impl Widget for ModelSelectorItem {
         if let Some(bot) = &self.bot {
             self.label(cx, ids!(label)).set_text(cx, &bot.name);
 
+            let details = self.label(cx, ids!(details));
+            details.set_visible(cx, self.details.is_some());
+            details.set_text(cx, self.details.as_deref().unwrap_or_default());
+
             // Show tick icon if this bot is selected
             self.view(cx, ids!(icon_tick_view))
                 .set_visible(cx, self.selected);
@@ -150,6 +173,13 @@ impl ModelSelectorItemRef {
         }
     }
 
+    /// Sets a short line shown under the bot name, like its context window.
+    pub fn set_details(&mut self, details: Option<String>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.details = details;
+        }
+    }
+
     /// Sets whether this selector item is currently selected.
     pub fn set_selected(&mut self, selected: bool) {
         if let Some(mut inner) = self.borrow_mut() {
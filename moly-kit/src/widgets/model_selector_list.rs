@@ -10,6 +10,7 @@ use std::sync::{Arc, Mutex};
 
 // We need a type alias, so Makepad's `#[rust(...)]` macro attribute works.
 type ErasedGroupingClosure = Box<dyn Fn(&Bot) -> BotGroup>;
+type ErasedDetailsClosure = Box<dyn Fn(&Bot) -> Option<String>>;
 
 /// Trait for filtering which bots to show in the model selector.
 pub trait BotFilter {
@@ -102,6 +103,9 @@ pub struct ModelSelectorList {
     #[rust(Box::new(default_grouping) as ErasedGroupingClosure)]
     pub grouping: ErasedGroupingClosure,
 
+    #[rust]
+    pub details: Option<ErasedDetailsClosure>,
+
     #[rust]
     pub filter: Option<Box<dyn BotFilter>>,
 }
@@ -306,6 +310,7 @@ impl ModelSelectorList {
 
                 let mut item = item_widget.as_model_selector_item();
                 item.set_bot(bot.clone());
+                item.set_details(self.details.as_ref().and_then(|details| details(bot)));
 
                 let is_selected = selected_bot_id == Some(&bot.id);
                 item.set_selected(is_selected);
@@ -360,4 +365,14 @@ impl ModelSelectorListRef {
             inner.grouping = Box::new(grouping);
         }
     }
+
+    /// Sets a function giving the line shown under each bot name, if any.
+    pub fn set_details<F>(&mut self, details: F)
+    where
+        F: Fn(&Bot) -> Option<String> + 'static,
+    {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.details = Some(Box::new(details));
+        }
+    }
 }
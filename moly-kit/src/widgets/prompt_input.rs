@@ -1,11 +1,18 @@
 use makepad_widgets::defer_with_redraw::DeferWithRedraw;
 use makepad_widgets::*;
-use std::cell::{Ref, RefMut};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use url::Url;
 
 #[allow(unused)]
 use crate::{
-    aitk::protocol::*,
+    aitk::{protocol::*, utils::asynchronous::spawn},
     utils::makepad::events::EventExt,
+    utils::resources::{ContextResource, ResourceSource},
+    utils::scraping::{ReadablePage, fetch_readable, find_urls},
+    utils::slash_commands::{self, CommandMessage, SlashCommand, SlashCommandSource},
     widgets::attachment_list::{AttachmentListRef, AttachmentListWidgetExt},
 };
 
@@ -81,6 +88,161 @@ script_mod! {
         }
     }
 
+    let AttachUrlButton = AttachButton {
+        text: "\u{f0c1}" // fa-link
+    }
+
+    let UrlBarButton = Button {
+        width: Fit, height: Fit
+        padding: Inset { left: 10, right: 10, top: 6, bottom: 6 }
+        draw_text +: {
+            text_style +: { font_size: 10 }
+            color: #333,
+            color_hover: #111,
+            color_focus: #111
+            color_down: #000
+        }
+        draw_bg +: {
+            border_radius: 7.
+            border_size: 0.
+            color: #xf2
+            color_hover: #xe4
+            color_down: #xd6
+        }
+    }
+
+    let UrlBar = View {
+        visible: false
+        height: Fit
+        flow: Down
+        spacing: 4
+        padding: Inset { bottom: 8 }
+        url_status := Label {
+            text: "Attach the readable content of a web page"
+            draw_text +: {
+                color: #x667085
+                text_style +: { font_size: 9 }
+            }
+        }
+        View {
+            height: Fit
+            spacing: 6
+            align: Align { y: 0.5 }
+            url_input := TextInput {
+                width: Fill
+                height: Fit
+                empty_text: "https://"
+                draw_text +: {
+                    color: #000
+                    text_style +: { font_size: 10 }
+                }
+            }
+            url_attach := UrlBarButton { text: "Attach" }
+            url_cancel := UrlBarButton { text: "Cancel" }
+        }
+    }
+
+    let AttachResourceButton = AttachButton {
+        text: "\u{f1c0}" // fa-database
+    }
+
+    let PickerItem = View {
+        visible: false
+        height: Fit
+        flow: Down
+        spacing: 2
+        padding: Inset { left: 8, right: 8, top: 5, bottom: 5 }
+        cursor: MouseCursor.Hand
+        show_bg: true
+        draw_bg +: {
+            color: #xf7
+        }
+        item_title := Label {
+            draw_text +: {
+                color: #000
+                text_style +: { font_size: 10 }
+            }
+        }
+        item_subtitle := Label {
+            width: Fill
+            draw_text +: {
+                color: #x667085
+                text_style +: { font_size: 9 }
+            }
+        }
+    }
+
+    let PickerMenu = View {
+        visible: false
+        height: Fit
+        flow: Down
+        spacing: 2
+        padding: Inset { bottom: 8 }
+        menu_header := View { height: Fit }
+        item_0 := PickerItem {}
+        item_1 := PickerItem {}
+        item_2 := PickerItem {}
+        item_3 := PickerItem {}
+        item_4 := PickerItem {}
+        item_5 := PickerItem {}
+    }
+
+    let ArgumentRow = View {
+        visible: false
+        height: Fit
+        spacing: 6
+        align: Align { y: 0.5 }
+        arg_label := Label {
+            width: 120
+            draw_text +: {
+                color: #333
+                text_style +: { font_size: 10 }
+            }
+        }
+        arg_input := TextInput {
+            width: Fill
+            height: Fit
+            draw_text +: {
+                color: #000
+                text_style +: { font_size: 10 }
+            }
+        }
+    }
+
+    let CommandForm = View {
+        visible: false
+        height: Fit
+        flow: Down
+        spacing: 4
+        padding: Inset { bottom: 8 }
+        command_title := Label {
+            draw_text +: {
+                color: #000
+                text_style: theme.font_bold { font_size: 10 }
+            }
+        }
+        arg_0 := ArgumentRow {}
+        arg_1 := ArgumentRow {}
+        arg_2 := ArgumentRow {}
+        arg_3 := ArgumentRow {}
+        arg_4 := ArgumentRow {}
+        arg_5 := ArgumentRow {}
+        View {
+            height: Fit
+            spacing: 6
+            align: Align { y: 0.5 }
+            command_status := Label {
+                width: Fill
+                draw_text +: {
+                    color: #x667085
+                    text_style +: { font_size: 9 }
+                }
+            }
+            command_run := UrlBarButton { text: "Insert" }
+            command_cancel := UrlBarButton { text: "Cancel" }
+        }
+    }
+
     let AudioButton = Button {
         visible: false
         width: 28, height: 28
@@ -144,6 +306,23 @@ script_mod! {
             }
             top := View {
                 height: Fit
+                flow: Down
+                url_bar := UrlBar {}
+                command_menu := PickerMenu {}
+                command_form := CommandForm {}
+                resource_menu := PickerMenu {
+                    menu_header +: {
+                        resource_filter := TextInput {
+                            width: Fill
+                            height: Fit
+                            empty_text: "Filter resources..."
+                            draw_text +: {
+                                color: #000
+                                text_style +: { font_size: 10 }
+                            }
+                        }
+                    }
+                }
                 attachments := mod.widgets.DenseAttachmentList {}
             }
             center := View {
@@ -188,6 +367,8 @@ script_mod! {
                     width: Fit, height: Fit
                     align: Align { x: 0.0, y: 0.5 }
                     attach := AttachButton {}
+                    attach_url := AttachUrlButton {}
+                    attach_resource := AttachResourceButton {}
                     model_selector := mod.widgets.ModelSelector {}
                 }
                 width: Fill, height: Fit
@@ -212,6 +393,28 @@ pub enum Interactivity {
     Disabled,
 }
 
+#[derive(Clone, Debug, Default)]
+pub enum PromptInputAction {
+    /// A slash command was expanded into these messages.
+    CommandExpanded(Vec<CommandMessage>),
+    #[default]
+    None,
+}
+
+/// A resource attached to the prompt, watched by its source while attached.
+struct AttachedResource {
+    /// Position of its attachment in the attachment list.
+    index: usize,
+    resource: ContextResource,
+    source: Arc<dyn ResourceSource>,
+}
+
+/// Max number of entries shown in the command and resource menus.
+const MAX_MENU_ITEMS: usize = 6;
+
+/// Max number of arguments that can be filled for a slash command.
+const MAX_COMMAND_ARGUMENTS: usize = 6;
+
 /// A prepared text input for conversation with bots.
 ///
 /// This is mostly a dummy widget. Prefer using and adapting [crate::widgets::chat::Chat] instead.
@@ -228,6 +431,12 @@ pub struct PromptInput {
     #[live(String::from("For realtime models, use the audio feature ->"))]
     pub realtime_empty_text: String,
 
+    /// Allow attaching files even if the bot doesn't support attachments.
+    ///
+    /// Useful when the app converts documents into text before sending them.
+    #[live(false)]
+    pub allow_document_attachments: bool,
+
     /// If this widget should provoke sending a message or stopping the current response.
     #[rust]
     pub task: Task,
@@ -239,6 +448,39 @@ pub struct PromptInput {
     /// Capabilities of the currently selected bot
     #[rust]
     pub bot_capabilities: Option<BotCapabilities>,
+
+    /// Text of the input before the last change, used to detect pasted URLs.
+    #[rust]
+    last_text: String,
+
+    /// Provides the commands offered when typing a slash.
+    #[rust]
+    slash_command_source: Option<Arc<dyn SlashCommandSource>>,
+
+    /// Commands currently listed in the command menu.
+    #[rust]
+    command_matches: Vec<SlashCommand>,
+
+    /// Command whose arguments are being filled.
+    #[rust]
+    active_command: Option<SlashCommand>,
+
+    /// Provides the resources offered in the resource menu.
+    #[rust]
+    resource_source: Option<Arc<dyn ResourceSource>>,
+
+    /// Resources currently listed in the resource menu.
+    #[rust]
+    resource_matches: Vec<ContextResource>,
+
+    /// Resources among the attachments, shared with the tap handler of the
+    /// attachment list.
+    #[rust]
+    attached_resources: Rc<RefCell<Vec<AttachedResource>>>,
+
+    /// Set when the return key was used by a menu instead of submitting.
+    #[rust]
+    return_consumed: bool,
 }
 
 impl ScriptHook for PromptInput {
@@ -267,21 +509,49 @@ impl Widget for PromptInput {
         self.deref.handle_event(cx, event, scope);
         self.ui_runner().handle(cx, event, scope, self);
 
-        if self.button(cx, ids!(attach)).clicked(event.actions()) {
+        let actions = event.actions();
+        self.return_consumed = false;
+
+        if self.button(cx, ids!(attach)).clicked(actions) {
             let ui = self.ui_runner();
             Attachment::pick_multiple(move |result| match result {
                 Ok(attachments) => {
                     ui.defer_with_redraw(move |me: &mut PromptInput, cx, _| {
-                        let mut list = me.attachment_list_ref(cx);
-                        list.write().attachments.extend(attachments);
-                        list.write().on_tap(move |list, index| {
-                            list.attachments.remove(index);
-                        });
+                        me.add_attachments(cx, attachments);
                     });
                 }
                 Err(_) => {}
             });
         }
+
+        if self.button(cx, ids!(attach_url)).clicked(actions) {
+            self.show_url_bar(cx, "", "Attach the readable content of a web page");
+        }
+
+        if self.button(cx, ids!(url_cancel)).clicked(actions) {
+            self.hide_url_bar(cx);
+        }
+
+        let url_input = self.text_input(cx, ids!(url_input));
+        if self.button(cx, ids!(url_attach)).clicked(actions)
+            || url_input.returned(actions).is_some()
+        {
+            self.attach_url(cx, url_input.text().trim());
+        }
+
+        if let Some(text) = self.text_input_ref(cx).changed(actions) {
+            if self.supports_attachments() {
+                if let Some(url) = detect_pasted_url(&self.last_text, &text) {
+                    self.show_url_bar(cx, url.as_str(), "Attach the content of the pasted link?");
+                }
+            }
+
+            self.update_command_menu(cx, &text);
+            self.last_text = text;
+        }
+
+        self.handle_command_actions(cx, actions);
+        self.handle_resource_actions(cx, actions);
     }
 
     fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
@@ -291,11 +561,7 @@ impl Widget for PromptInput {
             .map(|caps| caps.has_capability(&BotCapability::AudioCall))
             .unwrap_or(false);
 
-        let supports_attachments = self
-            .bot_capabilities
-            .as_ref()
-            .map(|caps| caps.has_capability(&BotCapability::AttachmentInput))
-            .unwrap_or(false);
+        let supports_attachments = self.supports_attachments();
 
         // Attach button: only on supported platforms and if bot supports it
         #[cfg(any(
@@ -315,6 +581,16 @@ impl Widget for PromptInput {
         )))]
         self.button(cx, ids!(attach)).set_visible(cx, false);
 
+        self.button(cx, ids!(attach_url))
+            .set_visible(cx, supports_attachments);
+
+        let has_resources = self
+            .resource_source
+            .as_ref()
+            .is_some_and(|source| !source.resources().is_empty());
+        self.button(cx, ids!(attach_resource))
+            .set_visible(cx, supports_attachments && has_resources);
+
         // Audio button: only on non-wasm and if bot supports realtime
         #[cfg(not(target_arch = "wasm32"))]
         self.button(cx, ids!(audio))
@@ -364,6 +640,423 @@ impl PromptInput {
     pub fn reset(&mut self, cx: &mut Cx) {
         self.text_input_ref(cx).set_text(cx, "");
         self.attachment_list_ref(cx).write().attachments.clear();
+        self.last_text.clear();
+        for attached in self.attached_resources.borrow_mut().drain(..) {
+            attached.source.unwatch(&attached.resource);
+        }
+        self.hide_url_bar(cx);
+        self.hide_command_menu(cx);
+        self.hide_resource_menu(cx);
+    }
+
+    /// Add attachments to the list shown above the text input.
+    pub fn add_attachments(
+        &mut self,
+        cx: &mut Cx,
+        attachments: impl IntoIterator<Item = Attachment>,
+    ) {
+        let mut list = self.attachment_list_ref(cx);
+        list.write().attachments.extend(attachments);
+        let attached_resources = self.attached_resources.clone();
+        list.write().on_tap(move |list, index| {
+            list.attachments.remove(index);
+            detach_resource(&attached_resources, index);
+        });
+        self.redraw(cx);
+    }
+
+    /// If the current bot can receive attachments, natively or as extracted text.
+    fn supports_attachments(&self) -> bool {
+        let Some(caps) = self.bot_capabilities.as_ref() else {
+            return false;
+        };
+
+        caps.has_capability(&BotCapability::AttachmentInput)
+            || (self.allow_document_attachments && !caps.has_capability(&BotCapability::AudioCall))
+    }
+
+    fn show_url_bar(&mut self, cx: &mut Cx, url: &str, status: &str) {
+        self.text_input(cx, ids!(url_input)).set_text(cx, url);
+        self.label(cx, ids!(url_status)).set_text(cx, status);
+        self.view(cx, ids!(url_bar)).set_visible(cx, true);
+        self.redraw(cx);
+    }
+
+    fn hide_url_bar(&mut self, cx: &mut Cx) {
+        self.text_input(cx, ids!(url_input)).set_text(cx, "");
+        self.view(cx, ids!(url_bar)).set_visible(cx, false);
+        self.redraw(cx);
+    }
+
+    /// Download the page at `url` and attach its main content as markdown.
+    fn attach_url(&mut self, cx: &mut Cx, url: &str) {
+        let url = match Url::parse(url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+            _ => {
+                self.label(cx, ids!(url_status))
+                    .set_text(cx, "Please enter a valid http(s) URL");
+                return;
+            }
+        };
+
+        self.label(cx, ids!(url_status))
+            .set_text(cx, &format!("Fetching {}...", url));
+
+        let ui = self.ui_runner();
+        spawn(async move {
+            let result = fetch_readable(&url).await;
+            ui.defer_with_redraw(move |me: &mut PromptInput, cx, _| match result {
+                Ok(page) if !page.markdown.is_empty() => {
+                    me.add_attachments(cx, [page_attachment(&url, &page)]);
+                    me.hide_url_bar(cx);
+                }
+                Ok(_) => {
+                    me.label(cx, ids!(url_status))
+                        .set_text(cx, "No readable content found in this page");
+                }
+                Err(()) => {
+                    me.label(cx, ids!(url_status))
+                        .set_text(cx, "Could not fetch this page");
+                }
+            });
+        });
+    }
+
+    /// Set where the commands offered when typing a slash come from.
+    pub fn set_slash_command_source(&mut self, source: Option<Arc<dyn SlashCommandSource>>) {
+        self.slash_command_source = source;
+    }
+
+    /// Set where the resources offered in the resource menu come from.
+    pub fn set_resource_source(&mut self, source: Option<Arc<dyn ResourceSource>>) {
+        self.resource_source = source;
+    }
+
+    /// Replace the content of an attached resource after it changed.
+    ///
+    /// Does nothing if the resource is no longer attached.
+    pub fn refresh_resource(&mut self, cx: &mut Cx, resource_id: &str, attachment: Attachment) {
+        let mut list = self.attachment_list_ref(cx);
+        let mut list = list.write();
+
+        for attached in self.attached_resources.borrow().iter() {
+            if attached.resource.id != resource_id {
+                continue;
+            }
+
+            if let Some(existing) = list.attachments.get_mut(attached.index) {
+                *existing = attachment.clone();
+            }
+        }
+
+        self.redraw(cx);
+    }
+
+    /// When a slash command was expanded, read the resulting messages from the actions.
+    pub fn command_expanded(&self, actions: &Actions) -> Option<Vec<CommandMessage>> {
+        actions
+            .find_widget_action(self.widget_uid())
+            .map(|wa| wa.cast::<PromptInputAction>())
+            .and_then(|action| match action {
+                PromptInputAction::CommandExpanded(messages) => Some(messages),
+                _ => None,
+            })
+    }
+
+    fn handle_command_actions(&mut self, cx: &mut Cx, actions: &Actions) {
+        let menu = self.view(cx, ids!(command_menu));
+        if menu.visible() {
+            let clicked = (0..MAX_MENU_ITEMS).position(|i| {
+                menu.view(cx, &[slot_id("item", i)])
+                    .finger_up(actions)
+                    .is_some()
+            });
+
+            let returned = self.text_input_ref(cx).returned(actions).is_some();
+            if returned {
+                self.return_consumed = true;
+            }
+
+            let selected = clicked.or(returned.then_some(0));
+            if let Some(command) = selected.and_then(|i| self.command_matches.get(i).cloned()) {
+                self.select_command(cx, command);
+            }
+        }
+
+        if self.button(cx, ids!(command_cancel)).clicked(actions) {
+            self.active_command = None;
+            self.view(cx, ids!(command_form)).set_visible(cx, false);
+            self.redraw(cx);
+        }
+
+        let arg_returned = (0..MAX_COMMAND_ARGUMENTS).any(|i| {
+            self.view(cx, &[slot_id("arg", i)])
+                .text_input(cx, ids!(arg_input))
+                .returned(actions)
+                .is_some()
+        });
+
+        if self.button(cx, ids!(command_run)).clicked(actions) || arg_returned {
+            self.run_active_command(cx);
+        }
+    }
+
+    /// Show the commands matching the text, if it looks like a slash command.
+    fn update_command_menu(&mut self, cx: &mut Cx, text: &str) {
+        let matches = match (
+            &self.slash_command_source,
+            slash_commands::parse_query(text),
+        ) {
+            (Some(source), Some(query)) => {
+                slash_commands::matching_commands(&source.commands(), query)
+            }
+            _ => Vec::new(),
+        };
+
+        if matches.is_empty() {
+            self.hide_command_menu(cx);
+            return;
+        }
+
+        self.command_matches = matches;
+        self.command_matches.truncate(MAX_MENU_ITEMS);
+
+        let entries: Vec<(String, String)> = self
+            .command_matches
+            .iter()
+            .map(|command| (format!("/{}", command.name), command.description.clone()))
+            .collect();
+        self.fill_menu(cx, ids!(command_menu), &entries);
+    }
+
+    fn hide_command_menu(&mut self, cx: &mut Cx) {
+        self.command_matches.clear();
+        self.view(cx, ids!(command_menu)).set_visible(cx, false);
+        self.redraw(cx);
+    }
+
+    /// Show the given entries as title and subtitle in the slots of a menu.
+    fn fill_menu(&mut self, cx: &mut Cx, menu_id: &[LiveId], entries: &[(String, String)]) {
+        let menu = self.view(cx, menu_id);
+
+        for i in 0..MAX_MENU_ITEMS {
+            let item = menu.view(cx, &[slot_id("item", i)]);
+            match entries.get(i) {
+                Some((title, subtitle)) => {
+                    item.label(cx, ids!(item_title)).set_text(cx, title);
+                    item.label(cx, ids!(item_subtitle)).set_text(cx, subtitle);
+                    item.label(cx, ids!(item_subtitle))
+                        .set_visible(cx, !subtitle.is_empty());
+                    item.set_visible(cx, true);
+                }
+                None => item.set_visible(cx, false),
+            }
+        }
+
+        menu.set_visible(cx, true);
+        self.redraw(cx);
+    }
+
+    /// Open the argument form of a command, or run it right away if it takes none.
+    fn select_command(&mut self, cx: &mut Cx, command: SlashCommand) {
+        self.text_input_ref(cx).set_text(cx, "");
+        self.last_text.clear();
+        self.hide_command_menu(cx);
+
+        self.label(cx, ids!(command_title))
+            .set_text(cx, &format!("/{}", command.name));
+        self.label(cx, ids!(command_status))
+            .set_text(cx, &command.description);
+
+        for i in 0..MAX_COMMAND_ARGUMENTS {
+            let row = self.view(cx, &[slot_id("arg", i)]);
+            match command.arguments.get(i) {
+                Some(argument) => {
+                    let label = if argument.required {
+                        format!("{} *", argument.name)
+                    } else {
+                        argument.name.clone()
+                    };
+                    row.label(cx, ids!(arg_label)).set_text(cx, &label);
+
+                    let input = row.text_input(cx, ids!(arg_input));
+                    input.set_text(cx, "");
+                    input.set_empty_text(cx, argument.description.clone());
+                    row.set_visible(cx, true);
+                }
+                None => row.set_visible(cx, false),
+            }
+        }
+
+        self.view(cx, ids!(command_form)).set_visible(cx, true);
+
+        let takes_arguments = !command.arguments.is_empty();
+        self.active_command = Some(command);
+
+        if !takes_arguments {
+            self.run_active_command(cx);
+        }
+
+        self.redraw(cx);
+    }
+
+    /// Expand the active command with the arguments from the form.
+    fn run_active_command(&mut self, cx: &mut Cx) {
+        let (Some(command), Some(source)) = (
+            self.active_command.clone(),
+            self.slash_command_source.clone(),
+        ) else {
+            return;
+        };
+
+        if !self.has_send_task() {
+            self.label(cx, ids!(command_status))
+                .set_text(cx, "Wait for the current response to finish");
+            return;
+        }
+
+        let arguments: BTreeMap<String, String> = command
+            .arguments
+            .iter()
+            .take(MAX_COMMAND_ARGUMENTS)
+            .enumerate()
+            .map(|(i, argument)| {
+                let value = self
+                    .view(cx, &[slot_id("arg", i)])
+                    .text_input(cx, ids!(arg_input))
+                    .text();
+                (argument.name.clone(), value)
+            })
+            .filter(|(_, value)| !value.trim().is_empty())
+            .collect();
+
+        let missing = slash_commands::missing_arguments(&command, &arguments);
+        if !missing.is_empty() {
+            self.label(cx, ids!(command_status))
+                .set_text(cx, &format!("Missing: {}", missing.join(", ")));
+            return;
+        }
+
+        self.label(cx, ids!(command_status))
+            .set_text(cx, &format!("Running /{}...", command.name));
+
+        let future = source.expand(&command, arguments);
+        let ui = self.ui_runner();
+        spawn(async move {
+            let result = future.await;
+            ui.defer_with_redraw(move |me: &mut PromptInput, cx, _| match result {
+                Ok(messages) => {
+                    me.active_command = None;
+                    me.view(cx, ids!(command_form)).set_visible(cx, false);
+                    cx.widget_action(
+                        me.widget_uid(),
+                        PromptInputAction::CommandExpanded(messages),
+                    );
+                }
+                Err(error) => {
+                    me.label(cx, ids!(command_status))
+                        .set_text(cx, &format!("Failed: {}", error));
+                }
+            });
+        });
+    }
+
+    fn handle_resource_actions(&mut self, cx: &mut Cx, actions: &Actions) {
+        if self.button(cx, ids!(attach_resource)).clicked(actions) {
+            if self.view(cx, ids!(resource_menu)).visible() {
+                self.hide_resource_menu(cx);
+            } else {
+                self.text_input(cx, ids!(resource_filter)).set_text(cx, "");
+                self.update_resource_menu(cx, "");
+            }
+        }
+
+        if let Some(filter) = self.text_input(cx, ids!(resource_filter)).changed(actions) {
+            self.update_resource_menu(cx, &filter);
+        }
+
+        let menu = self.view(cx, ids!(resource_menu));
+        if !menu.visible() {
+            return;
+        }
+
+        let clicked = (0..MAX_MENU_ITEMS).position(|i| {
+            menu.view(cx, &[slot_id("item", i)])
+                .finger_up(actions)
+                .is_some()
+        });
+
+        if let Some(resource) = clicked.and_then(|i| self.resource_matches.get(i).cloned()) {
+            self.attach_resource(cx, resource);
+        }
+    }
+
+    /// List the resources whose name or origin contains the filter.
+    fn update_resource_menu(&mut self, cx: &mut Cx, filter: &str) {
+        let Some(source) = &self.resource_source else {
+            return;
+        };
+
+        let filter = filter.to_lowercase();
+        self.resource_matches = source
+            .resources()
+            .into_iter()
+            .filter(|resource| {
+                resource.name.to_lowercase().contains(&filter)
+                    || resource.origin.to_lowercase().contains(&filter)
+            })
+            .take(MAX_MENU_ITEMS)
+            .collect();
+
+        let entries: Vec<(String, String)> = self
+            .resource_matches
+            .iter()
+            .map(|resource| {
+                let subtitle = if resource.description.is_empty() {
+                    resource.origin.clone()
+                } else {
+                    format!("{} · {}", resource.origin, resource.description)
+                };
+                (resource.name.clone(), subtitle)
+            })
+            .collect();
+        self.fill_menu(cx, ids!(resource_menu), &entries);
+    }
+
+    fn hide_resource_menu(&mut self, cx: &mut Cx) {
+        self.resource_matches.clear();
+        self.view(cx, ids!(resource_menu)).set_visible(cx, false);
+        self.redraw(cx);
+    }
+
+    /// Read a resource and attach it, watching it for changes.
+    fn attach_resource(&mut self, cx: &mut Cx, resource: ContextResource) {
+        let Some(source) = self.resource_source.clone() else {
+            return;
+        };
+
+        self.hide_resource_menu(cx);
+
+        let future = source.read(&resource);
+        let ui = self.ui_runner();
+        spawn(async move {
+            let result = future.await;
+            ui.defer_with_redraw(move |me: &mut PromptInput, cx, _| match result {
+                Ok(attachment) => {
+                    let index = me.attachment_list_ref(cx).read().attachments.len();
+                    me.attached_resources.borrow_mut().push(AttachedResource {
+                        index,
+                        resource: resource.clone(),
+                        source: source.clone(),
+                    });
+                    me.add_attachments(cx, [attachment]);
+                    source.watch(&resource);
+                }
+                Err(error) => {
+                    ::log::error!("Failed to read resource {}: {}", resource.name, error);
+                }
+            });
+        });
     }
 
     /// Returns a reference to the inner `TextInput` widget.
@@ -379,7 +1072,8 @@ impl PromptInput {
         let send = self.button(cx, ids!(send));
         let stop = self.button(cx, ids!(stop));
         let input = self.text_input_ref(cx);
-        (send.clicked(actions) || stop.clicked(actions) || input.returned(actions).is_some())
+        let returned = input.returned(actions).is_some() && !self.return_consumed;
+        (send.clicked(actions) || stop.clicked(actions) || returned)
             && self.interactivity == Interactivity::Enabled
     }
 
@@ -472,6 +1166,71 @@ impl PromptInput {
     }
 }
 
+/// Stop watching the resource whose attachment was removed from `index`, if any,
+/// and shift the positions of the ones after it.
+fn detach_resource(attached_resources: &RefCell<Vec<AttachedResource>>, index: usize) {
+    attached_resources.borrow_mut().retain_mut(|attached| {
+        if attached.index == index {
+            attached.source.unwatch(&attached.resource);
+            return false;
+        }
+
+        if attached.index > index {
+            attached.index -= 1;
+        }
+        true
+    });
+}
+
+/// Id of a numbered child slot, like `item_0` or `arg_3`.
+fn slot_id(prefix: &str, index: usize) -> LiveId {
+    LiveId::from_str(&format!("{}_{}", prefix, index))
+}
+
+/// Find a URL that was just pasted, comparing the input text before and after a change.
+///
+/// Single keystrokes are ignored so typing a URL by hand doesn't trigger it halfway.
+fn detect_pasted_url(previous: &str, current: &str) -> Option<Url> {
+    if current.len() < previous.len() + "http://".len() {
+        return None;
+    }
+
+    find_urls(current)
+        .into_iter()
+        .find(|url| !find_urls(previous).contains(url))
+}
+
+/// Build a text attachment holding the readable content of a web page.
+///
+/// The source URL is kept in the content so models can cite it.
+fn page_attachment(url: &Url, page: &ReadablePage) -> Attachment {
+    let title = page
+        .title
+        .clone()
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| url.host_str().unwrap_or("page").to_string());
+
+    let file_name: String = title
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(80)
+        .collect();
+
+    let content = format!("# {}\n\nSource: {}\n\n{}\n", title, url, page.markdown);
+
+    Attachment::from_bytes(
+        format!("{}.md", file_name.trim()),
+        Some("text/markdown".to_string()),
+        content.as_bytes(),
+    )
+}
+
 impl PromptInputRef {
     /// Immutable access to the underlying [[PromptInput]].
     ///
@@ -5,6 +5,9 @@ use crate::aitk::{
 use crate::prelude::*;
 use crate::{
     utils::makepad::events::EventExt,
+    utils::tool_audit::{ToolAuditCall, ToolAuditEvent, ToolAuditHook},
+    utils::tool_calls::ToolCalls,
+    utils::tool_policy::{SharedToolPolicy, ToolApproval, ToolPermission, denial_message},
     widgets::{avatar::*, chat_line::*, slot::*, standard_message_content::*},
 };
 use makepad_widgets::permission::Permission;
@@ -613,6 +616,16 @@ pub struct Realtime {
     #[rust]
     pending_tool_call: Option<(String, String, String)>, // (name, call_id, arguments)
 
+    #[rust]
+    tool_policy: Option<SharedToolPolicy>,
+
+    #[rust]
+    tool_audit: Option<ToolAuditHook>,
+
+    /// Runs approved calls with the timeouts configured for the chat.
+    #[rust]
+    tool_calls: ToolCalls,
+
     #[rust]
     audio_devices: Vec<AudioDeviceDesc>,
 
@@ -824,6 +837,9 @@ impl WidgetMatchEvent for Realtime {
                 ChatLineAction::ToolApprove => {
                     self.approve_tool_call(cx);
                 }
+                ChatLineAction::ToolApproveAlways => {
+                    self.approve_tool_call_always(cx);
+                }
                 ChatLineAction::ToolDeny => {
                     self.deny_tool_call(cx);
                 }
@@ -906,6 +922,18 @@ impl Realtime {
         self.chat_controller = chat_controller;
     }
 
+    pub fn set_tool_policy(&mut self, tool_policy: Option<SharedToolPolicy>) {
+        self.tool_policy = tool_policy;
+    }
+
+    pub fn set_tool_audit(&mut self, tool_audit: Option<ToolAuditHook>) {
+        self.tool_audit = tool_audit;
+    }
+
+    pub fn set_tool_calls(&mut self, tool_calls: ToolCalls) {
+        self.tool_calls = tool_calls;
+    }
+
     fn try_start_pending_conversation(&mut self, cx: &mut Cx) {
         if self.is_connected && !self.conversation_active && self.should_request_connection {
             // We can now start the conversation that was requested
@@ -1219,36 +1247,26 @@ impl Realtime {
                     name,
                     call_id,
                     arguments,
-                } => {
-                    // Check if dangerous mode is enabled to auto-approve function calls
-                    let dangerous_mode_enabled = self
-                        .chat_controller
-                        .as_ref()
-                        .map(|ctx| {
-                            ctx.lock()
-                                .unwrap()
-                                .tool_manager()
-                                .map(|tm| tm.get_dangerous_mode_enabled())
-                                .unwrap_or(false)
-                        })
-                        .unwrap_or(false);
-
-                    if dangerous_mode_enabled {
-                        // Auto-approve function calls in dangerous mode
+                } => match self.tool_permission(&name, &arguments) {
+                    (ToolPermission::Allow, approval) => {
                         let display_name = display_name_from_namespaced(&name);
                         self.label(cx, ids!(status_label))
                             .set_text(cx, &format!("🔧 Auto-executing tool: {}", display_name));
 
-                        // Execute the function call directly
+                        self.audit_decision(&name, &call_id, &arguments, true, approval);
                         self.handle_function_call(cx, name, call_id, arguments);
-                    } else {
-                        // Show permission request as usual
+                    }
+                    (ToolPermission::Deny, approval) => {
+                        self.audit_decision(&name, &call_id, &arguments, false, approval);
+                        self.send_tool_denial(cx, name, call_id, approval);
+                    }
+                    (ToolPermission::Ask, _) => {
                         self.label(cx, ids!(status_label))
                             .set_text(cx, &format!("🔧 Tool permission requested: {}", name));
 
                         self.show_tool_permission_request(cx, name, call_id, arguments);
                     }
-                }
+                },
                 RealtimeEvent::Error(error) => {
                     ::log::error!("Realtime API error: {}", error);
 
@@ -1336,25 +1354,14 @@ impl Realtime {
         call_id: String,
         arguments: String,
     ) {
-        let Some(chat_controller) = self.chat_controller.as_ref().cloned() else {
-            ::log::error!("No chat controller available for function call");
-            if let Some(channel) = &self.realtime_channel {
-                let error_result = serde_json::json!({
-                    "error": "Tool manager not available"
-                })
-                .to_string();
-                let _ = channel.command_sender.unbounded_send(
-                    RealtimeCommand::SendFunctionCallResult {
-                        call_id,
-                        output: error_result,
-                    },
-                );
-            }
-            return;
-        };
-
-        let Some(tool_manager) = chat_controller.lock().unwrap().tool_manager().cloned() else {
+        if self.tool_calls.client().is_none() {
             ::log::error!("No tool manager available for function call");
+            report_tool_finished(
+                &self.tool_audit,
+                &call_id,
+                "Tool manager not available",
+                true,
+            );
             if let Some(channel) = &self.realtime_channel {
                 let error_result = serde_json::json!({
                     "error": "Tool manager not available"
@@ -1368,9 +1375,11 @@ impl Realtime {
                 );
             }
             return;
-        };
+        }
 
         let channel = self.realtime_channel.clone();
+        let tool_audit = self.tool_audit.clone();
+        let tool_calls = self.tool_calls.clone();
 
         let future = async move {
             // Parse the arguments JSON
@@ -1378,6 +1387,7 @@ impl Realtime {
                 Ok(args) => args,
                 Err(e) => {
                     ::log::error!("Failed to parse function call arguments: {}", e);
+                    report_tool_finished(&tool_audit, &call_id, &e.to_string(), true);
                     if let Some(channel) = &channel {
                         let error_result = serde_json::json!({
                             "error": e
@@ -1394,9 +1404,8 @@ impl Realtime {
                 }
             };
 
-            let result = tool_manager
-                .execute_tool_call(&name, &call_id, arguments_map)
-                .await;
+            let result = tool_calls.run(&name, &call_id, arguments_map).await;
+            report_tool_finished(&tool_audit, &call_id, &result.content, result.is_error);
 
             if let Some(channel) = &channel {
                 let output = if result.is_error {
@@ -1429,6 +1438,7 @@ impl Realtime {
                 .set_text(cx, &format!("🔧 Executing tool: {}", display_name));
 
             // Execute the tool
+            self.audit_decision(&name, &call_id, &arguments, true, ToolApproval::Manual);
             self.handle_function_call(cx, name, call_id, arguments);
 
             // Resume recording if conversation is active
@@ -1441,29 +1451,13 @@ impl Realtime {
     }
 
     fn deny_tool_call(&mut self, cx: &mut Cx) {
-        if let Some((name, call_id, _arguments)) = self.pending_tool_call.take() {
+        if let Some((name, call_id, arguments)) = self.pending_tool_call.take() {
             // Hide permission UI
             self.chat_line(cx, ids!(tool_permission_line))
                 .set_visible(cx, false);
 
-            // Send denial response
-            if let Some(channel) = &self.realtime_channel {
-                let denial_result = serde_json::json!({
-                    "error": "Tool execution denied by user"
-                })
-                .to_string();
-                let _ = channel.command_sender.unbounded_send(
-                    RealtimeCommand::SendFunctionCallResult {
-                        call_id,
-                        output: denial_result,
-                    },
-                );
-            }
-
-            // Update status
-            let display_name = display_name_from_namespaced(&name);
-            self.label(cx, ids!(status_label))
-                .set_text(cx, &format!("🚫 Tool '{}' denied", display_name));
+            self.audit_decision(&name, &call_id, &arguments, false, ToolApproval::Manual);
+            self.send_tool_denial(cx, name, call_id, ToolApproval::Manual);
 
             // Resume recording if conversation is active
             if self.conversation_active {
@@ -1474,6 +1468,94 @@ impl Realtime {
         }
     }
 
+    /// Remember to always allow the pending tool and execute it.
+    fn approve_tool_call_always(&mut self, cx: &mut Cx) {
+        if let (Some(policy), Some((name, _, _))) = (&self.tool_policy, &self.pending_tool_call) {
+            policy.update(|policy| policy.set_tool_permission(name, ToolPermission::Allow));
+        }
+
+        self.approve_tool_call(cx);
+    }
+
+    fn send_tool_denial(
+        &mut self,
+        cx: &mut Cx,
+        name: String,
+        call_id: String,
+        approval: ToolApproval,
+    ) {
+        if let Some(channel) = &self.realtime_channel {
+            let denial_result = serde_json::json!({
+                "error": denial_message(&name, approval)
+            })
+            .to_string();
+            let _ =
+                channel
+                    .command_sender
+                    .unbounded_send(RealtimeCommand::SendFunctionCallResult {
+                        call_id,
+                        output: denial_result,
+                    });
+        }
+
+        let display_name = display_name_from_namespaced(&name);
+        self.label(cx, ids!(status_label))
+            .set_text(cx, &format!("🚫 Tool '{}' denied", display_name));
+    }
+
+    fn audit_decision(
+        &self,
+        name: &str,
+        call_id: &str,
+        arguments: &str,
+        approved: bool,
+        approval: ToolApproval,
+    ) {
+        let Some(tool_audit) = &self.tool_audit else {
+            return;
+        };
+
+        let bot_id = match &self.bot_entity_id {
+            Some(EntityId::Bot(bot_id)) => Some(bot_id.clone()),
+            _ => None,
+        };
+        tool_audit.report(ToolAuditEvent::Decided {
+            call: ToolAuditCall {
+                id: call_id.to_string(),
+                name: name.to_string(),
+                arguments: serde_json::from_str(arguments)
+                    .unwrap_or_else(|_| serde_json::Value::String(arguments.to_string())),
+                bot_id,
+                realtime: true,
+            },
+            approved,
+            approval,
+        });
+    }
+
+    /// Decide if a tool call needs to be confirmed by the user, and who decided.
+    ///
+    /// Without a tool policy, falls back to the MCP client's dangerous mode.
+    fn tool_permission(&self, name: &str, arguments: &str) -> (ToolPermission, ToolApproval) {
+        if let Some(policy) = &self.tool_policy {
+            let arguments =
+                serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(arguments)
+                    .unwrap_or_default();
+            return policy.evaluate_with_approval(name, &arguments);
+        }
+
+        let dangerous_mode_enabled = self
+            .tool_calls
+            .client()
+            .is_some_and(|client| client.get_dangerous_mode_enabled());
+
+        if dangerous_mode_enabled {
+            (ToolPermission::Allow, ToolApproval::DangerousMode)
+        } else {
+            (ToolPermission::Ask, ToolApproval::Manual)
+        }
+    }
+
     fn setup_audio(&mut self, cx: &mut Cx) {
         let recorded_audio = self.recorded_audio.clone();
         let should_record = self.should_record.clone();
@@ -1749,4 +1831,37 @@ impl RealtimeRef {
             inner.set_chat_controller(chat_controller);
         }
     }
+
+    pub fn set_tool_policy(&mut self, tool_policy: Option<SharedToolPolicy>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_tool_policy(tool_policy);
+        }
+    }
+
+    pub fn set_tool_audit(&mut self, tool_audit: Option<ToolAuditHook>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_tool_audit(tool_audit);
+        }
+    }
+
+    pub fn set_tool_calls(&mut self, tool_calls: ToolCalls) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_tool_calls(tool_calls);
+        }
+    }
+}
+
+fn report_tool_finished(
+    tool_audit: &Option<ToolAuditHook>,
+    call_id: &str,
+    content: &str,
+    is_error: bool,
+) {
+    if let Some(tool_audit) = tool_audit {
+        tool_audit.report(ToolAuditEvent::Finished {
+            id: call_id.to_string(),
+            content: content.to_string(),
+            is_error,
+        });
+    }
 }
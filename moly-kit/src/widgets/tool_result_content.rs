@@ -0,0 +1,573 @@
+use std::collections::{HashMap, HashSet};
+
+use makepad_widgets::*;
+
+use crate::{
+    aitk::protocol::*,
+    utils::tool_result::{ToolResultPart, decode_base64, json_rows, parse_tool_result},
+    widgets::{
+        attachment_list::AttachmentListWidgetRefExt,
+        attachment_viewer_modal::AttachmentViewerModalWidgetRefExt,
+    },
+};
+
+/// Lines of text shown before the rest is hidden behind "Show all".
+const PREVIEW_LINES: usize = 20;
+
+/// Characters of text shown before the rest is hidden behind "Show all".
+const PREVIEW_CHARS: usize = 2000;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    let RowButton = Button {
+        width: Fill
+        height: Fit
+        padding: Inset { top: 3, right: 6, bottom: 3, left: 6 }
+        margin: 0
+        align: Align { x: 0.0, y: 0.5 }
+        draw_bg +: {
+            pixel: fn() -> vec4 {
+                let sdf = Sdf2d.viewport(self.pos * self.rect_size)
+                let color = mix(#xF2F4F700 #xEAECEF88 self.hover);
+                let color = mix(color #xEAECEFFF self.down);
+
+                sdf.box(0.0 0.0 self.rect_size.x self.rect_size.y 2.5);
+                sdf.fill_keep(color);
+
+                return sdf.result;
+            }
+        }
+        draw_text +: {
+            text_style: theme.font_regular { font_size: 9 },
+            get_color: fn() -> vec4 {
+                return #x1D2939;
+            }
+        }
+    }
+
+    mod.widgets.ToolResultContentBase = #(ToolResultContent::register_widget(vm))
+    mod.widgets.ToolResultContent = set_type_default() do mod.widgets.ToolResultContentBase {
+        width: Fill
+        height: Fit
+        flow: Down
+        spacing: 4
+
+        heading_template := Label {
+            width: Fill
+            draw_text +: {
+                text_style: theme.font_bold { font_size: 10 },
+                color: #000
+            }
+        }
+
+        markdown_template := MessageMarkdown {}
+
+        error_template := Label {
+            width: Fill
+            draw_text +: {
+                text_style: theme.font_regular { font_size: 10 },
+                color: #xB42318
+            }
+        }
+
+        text_template := View {
+            width: Fill
+            height: Fit
+            flow: Down
+            text := Label {
+                width: Fill
+                draw_text +: {
+                    text_style: theme.font_regular { font_size: 9 },
+                    color: #x1D2939
+                }
+            }
+            toggle := RowButton { width: Fit }
+        }
+
+        json_node_template := RowButton {}
+
+        json_leaf_template := Label {
+            width: Fill
+            padding: Inset { top: 3, right: 6, bottom: 3, left: 6 }
+            draw_text +: {
+                text_style: theme.font_regular { font_size: 9 },
+                color: #x1D2939
+            }
+        }
+
+        link_template := View {
+            width: Fill
+            height: Fit
+            spacing: 8
+            align: Align { y: 0.5 }
+            label := Label {
+                width: Fill
+                draw_text +: {
+                    text_style: theme.font_regular { font_size: 9 },
+                    color: #x1a5b9c
+                }
+            }
+            open := RowButton { width: Fit, text: "Open" }
+        }
+
+        attachments_template := View {
+            width: Fill
+            height: Fit
+            flow: Down
+            attachments := AttachmentList {}
+            attachment_viewer_modal := AttachmentViewerModal {}
+        }
+    }
+}
+
+/// What a drawn item shows.
+enum ItemKind {
+    Heading(String),
+    Markdown(String),
+    Error(String),
+    /// Text and, when it's long, the key of its "Show all" toggle.
+    Text(String, Option<(String, bool)>),
+    /// A row of a JSON tree, with the key of its toggle when it can collapse.
+    JsonRow(String, Option<String>),
+    Link(String, String),
+    Attachments,
+}
+
+struct Item {
+    id: LiveId,
+    kind: ItemKind,
+}
+
+/// Renders the results of tool calls by content type.
+///
+/// JSON is shown as a collapsible tree, images and binary resources as
+/// attachments, resource links with a button to open them, and long text is
+/// truncated with a button to show it all. Messages without tool results are
+/// rendered as markdown.
+#[derive(Script, Widget)]
+pub struct ToolResultContent {
+    #[uid]
+    uid: WidgetUid,
+
+    #[redraw]
+    #[rust]
+    area: Area,
+
+    #[walk]
+    walk: Walk,
+
+    #[layout]
+    layout: Layout,
+
+    #[rust]
+    templates: HashMap<LiveId, ScriptObjectRef>,
+
+    #[rust]
+    widgets: ComponentMap<LiveId, WidgetRef>,
+
+    /// Identifies the content currently shown, to avoid parsing it on every draw.
+    #[rust]
+    content_key: Option<Vec<(String, usize, bool)>>,
+
+    #[rust]
+    text: String,
+
+    /// Parts of each result, with the id of its call and whether it failed.
+    #[rust]
+    results: Vec<(String, bool, Vec<ToolResultPart>)>,
+
+    #[rust]
+    attachments: Vec<Attachment>,
+
+    /// Keys of the text and JSON nodes toggled from their initial state.
+    #[rust]
+    toggled: HashSet<String>,
+
+    #[rust]
+    items: Vec<Item>,
+}
+
+impl ScriptHook for ToolResultContent {
+    fn on_before_apply(
+        &mut self,
+        _vm: &mut ScriptVm,
+        apply: &Apply,
+        _scope: &mut Scope,
+        _value: ScriptValue,
+    ) {
+        if apply.is_reload() {
+            self.templates.clear();
+            self.widgets.clear();
+        }
+    }
+
+    fn on_after_apply(
+        &mut self,
+        vm: &mut ScriptVm,
+        apply: &Apply,
+        _scope: &mut Scope,
+        value: ScriptValue,
+    ) {
+        const TEMPLATES: [LiveId; 8] = [
+            live_id!(heading_template),
+            live_id!(markdown_template),
+            live_id!(error_template),
+            live_id!(text_template),
+            live_id!(json_node_template),
+            live_id!(json_leaf_template),
+            live_id!(link_template),
+            live_id!(attachments_template),
+        ];
+
+        if !apply.is_eval() {
+            if let Some(obj) = value.as_object() {
+                vm.vec_with(obj, |vm, vec| {
+                    for kv in vec {
+                        if let Some(id) = kv.key.as_id()
+                            && TEMPLATES.contains(&id)
+                            && let Some(template_obj) = kv.value.as_object()
+                        {
+                            self.templates
+                                .insert(id, vm.bx.heap.new_object_ref(template_obj));
+                        }
+                    }
+                });
+            }
+        }
+    }
+}
+
+impl Widget for ToolResultContent {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.ui_runner().handle(cx, event, scope, self);
+
+        for (_, widget) in self.widgets.iter_mut() {
+            widget.handle_event(cx, event, scope);
+        }
+
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, _scope: &mut Scope, walk: Walk) -> DrawStep {
+        cx.begin_turtle(walk, self.layout);
+
+        for item in &self.items {
+            let template = match &item.kind {
+                ItemKind::Heading(_) => live_id!(heading_template),
+                ItemKind::Markdown(_) => live_id!(markdown_template),
+                ItemKind::Error(_) => live_id!(error_template),
+                ItemKind::Text(..) => live_id!(text_template),
+                ItemKind::JsonRow(_, Some(_)) => live_id!(json_node_template),
+                ItemKind::JsonRow(_, None) => live_id!(json_leaf_template),
+                ItemKind::Link(..) => live_id!(link_template),
+                ItemKind::Attachments => live_id!(attachments_template),
+            };
+
+            let templates = &self.templates;
+            let widget = self
+                .widgets
+                .get_or_insert(cx, item.id, |cx| {
+                    let template_ref = templates.get(&template).expect("template not found");
+                    let template_value: ScriptValue = template_ref.as_object().into();
+                    cx.with_vm(|vm| WidgetRef::script_from_value(vm, template_value))
+                })
+                .clone();
+
+            match &item.kind {
+                ItemKind::Heading(text) | ItemKind::Error(text) => {
+                    widget.as_label().set_text(cx, text);
+                }
+                ItemKind::Markdown(text) => {
+                    widget.as_markdown().set_text(cx, text);
+                }
+                ItemKind::Text(text, toggle) => {
+                    widget.label(cx, ids!(text)).set_text(cx, text);
+                    let toggle_button = widget.button(cx, ids!(toggle));
+                    toggle_button.set_visible(cx, toggle.is_some());
+                    if let Some((_, expanded)) = toggle {
+                        let label = if *expanded { "Show less" } else { "Show all" };
+                        toggle_button.set_text(cx, label);
+                    }
+                }
+                ItemKind::JsonRow(text, _) => {
+                    widget.set_text(cx, text);
+                }
+                ItemKind::Link(label, _) => {
+                    widget.label(cx, ids!(label)).set_text(cx, label);
+                }
+                ItemKind::Attachments => {
+                    let mut list = widget.attachment_list(cx, ids!(attachments));
+                    list.write().attachments = self.attachments.clone();
+                    let ui = self.ui_runner();
+                    let id = item.id;
+                    list.write().on_tap(move |list, index| {
+                        let Some(attachment) = list.attachments.get(index).cloned() else {
+                            return;
+                        };
+
+                        if crate::widgets::attachment_view::can_preview(&attachment) {
+                            ui.defer(move |me, cx, _| {
+                                if let Some(widget) = me.widgets.get(&id) {
+                                    widget
+                                        .attachment_viewer_modal(cx, ids!(attachment_viewer_modal))
+                                        .borrow_mut()
+                                        .unwrap()
+                                        .open(cx, attachment);
+                                }
+                            });
+                        } else {
+                            attachment.save();
+                        }
+                    });
+                }
+            }
+
+            widget.draw_all(cx, &mut Scope::empty());
+        }
+
+        cx.end_turtle_with_area(&mut self.area);
+        DrawStep::done()
+    }
+}
+
+impl WidgetMatchEvent for ToolResultContent {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let mut toggled = None;
+
+        for item in &self.items {
+            let Some(widget) = self.widgets.get(&item.id) else {
+                continue;
+            };
+
+            match &item.kind {
+                ItemKind::Text(_, Some((key, _))) => {
+                    if widget.button(cx, ids!(toggle)).clicked(actions) {
+                        toggled = Some(key.clone());
+                    }
+                }
+                ItemKind::JsonRow(_, Some(key)) => {
+                    if widget.as_button().clicked(actions) {
+                        toggled = Some(key.clone());
+                    }
+                }
+                ItemKind::Link(_, uri) => {
+                    if widget.button(cx, ids!(open)).clicked(actions) {
+                        let _ = robius_open::Uri::new(uri).open();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(key) = toggled {
+            if !self.toggled.remove(&key) {
+                self.toggled.insert(key);
+            }
+            self.rebuild_items();
+            self.redraw(cx);
+        }
+    }
+}
+
+impl ToolResultContent {
+    /// Set a message content to display it.
+    pub fn set_content(&mut self, cx: &mut Cx, content: &MessageContent) {
+        let key = std::iter::once((String::new(), content.text.len(), false))
+            .chain(
+                content
+                    .tool_results
+                    .iter()
+                    .map(|r| (r.tool_call_id.clone(), r.content.len(), r.is_error)),
+            )
+            .collect::<Vec<_>>();
+
+        if self.content_key.as_ref() == Some(&key) {
+            return;
+        }
+
+        self.content_key = Some(key);
+        self.text = content.text.clone();
+        self.results = content
+            .tool_results
+            .iter()
+            .map(|result| {
+                let parts = if result.is_error {
+                    vec![ToolResultPart::Text(result.content.clone())]
+                } else {
+                    parse_tool_result(&result.content)
+                };
+                (result.tool_call_id.clone(), result.is_error, parts)
+            })
+            .collect();
+        self.attachments = self
+            .results
+            .iter()
+            .flat_map(|(_, _, parts)| parts)
+            .filter_map(attachment_from_part)
+            .collect();
+        self.toggled.clear();
+        self.widgets.clear();
+        self.rebuild_items();
+        self.redraw(cx);
+    }
+
+    fn rebuild_items(&mut self) {
+        let mut items = Vec::new();
+        let mut push = |key: String, kind: ItemKind| {
+            items.push(Item {
+                id: LiveId::from_str(&key),
+                kind,
+            });
+        };
+
+        if self.results.is_empty() {
+            push(
+                "markdown".to_string(),
+                ItemKind::Markdown(self.text.clone()),
+            );
+            self.items = items;
+            return;
+        }
+
+        // The text summarizes the results shown below, its first line is enough.
+        if let Some(summary) = self.text.lines().find(|line| !line.trim().is_empty()) {
+            let summary = summary.trim().trim_end_matches(':');
+            push(
+                "heading".to_string(),
+                ItemKind::Heading(summary.to_string()),
+            );
+        }
+
+        for (call_id, is_error, parts) in &self.results {
+            for (index, part) in parts.iter().enumerate() {
+                let key = format!("{}/{}", call_id, index);
+                match part {
+                    ToolResultPart::Text(text) if *is_error => {
+                        push(key, ItemKind::Error(text.clone()));
+                    }
+                    ToolResultPart::Text(text) => {
+                        let expanded = self.toggled.contains(&key);
+                        push(key.clone(), text_item(text, key, expanded));
+                    }
+                    ToolResultPart::Json(value) => {
+                        for row in json_rows(value, &self.toggled_under(&key)) {
+                            let row_key = format!("{}{}", key, row.path);
+                            let text = format!("{}{}", "    ".repeat(row.depth), row.text);
+                            let toggle = row.expandable.then(|| row_key.clone());
+                            push(row_key, ItemKind::JsonRow(text, toggle));
+                        }
+                    }
+                    ToolResultPart::Resource { uri, text, .. } => {
+                        push(
+                            format!("{}/uri", key),
+                            ItemKind::Link(format!("📄 {}", uri), uri.clone()),
+                        );
+                        if let Some(text) = text {
+                            let expanded = self.toggled.contains(&key);
+                            push(key.clone(), text_item(text, key, expanded));
+                        }
+                    }
+                    ToolResultPart::ResourceLink {
+                        uri,
+                        name,
+                        description,
+                    } => {
+                        let mut label = format!("🔗 {}", name.as_deref().unwrap_or(uri));
+                        if let Some(description) = description {
+                            label.push_str(&format!(" · {}", description));
+                        }
+                        push(key, ItemKind::Link(label, uri.clone()));
+                    }
+                    ToolResultPart::Image { .. } | ToolResultPart::Audio { .. } => {}
+                }
+            }
+        }
+
+        if !self.attachments.is_empty() {
+            push("attachments".to_string(), ItemKind::Attachments);
+        }
+
+        self.items = items;
+    }
+
+    /// Toggled JSON paths of the part with the given key, relative to it.
+    fn toggled_under(&self, key: &str) -> HashSet<String> {
+        self.toggled
+            .iter()
+            .filter_map(|toggled| toggled.strip_prefix(key))
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+fn text_item(text: &str, key: String, expanded: bool) -> ItemKind {
+    let end = text
+        .char_indices()
+        .nth(PREVIEW_CHARS)
+        .map(|(i, _)| i)
+        .into_iter()
+        .chain(
+            text.match_indices('\n')
+                .nth(PREVIEW_LINES - 1)
+                .map(|(i, _)| i),
+        )
+        .min();
+
+    match end {
+        Some(end) if !expanded => ItemKind::Text(format!("{}…", &text[..end]), Some((key, false))),
+        Some(_) => ItemKind::Text(text.to_string(), Some((key, true))),
+        None => ItemKind::Text(text.to_string(), None),
+    }
+}
+
+/// Images, audio and binary resources are shown as attachments.
+fn attachment_from_part(part: &ToolResultPart) -> Option<Attachment> {
+    let (name, mime_type, data) = match part {
+        ToolResultPart::Image { mime_type, data } => {
+            let extension = mime_type.rsplit('/').next().unwrap_or("png");
+            (format!("image.{}", extension), mime_type.clone(), data)
+        }
+        ToolResultPart::Audio { mime_type, data } => {
+            let extension = mime_type.rsplit('/').next().unwrap_or("wav");
+            (format!("audio.{}", extension), mime_type.clone(), data)
+        }
+        ToolResultPart::Resource {
+            uri,
+            mime_type,
+            blob: Some(blob),
+            ..
+        } => {
+            let name = uri
+                .rsplit('/')
+                .find(|segment| !segment.is_empty())
+                .unwrap_or("resource")
+                .to_string();
+            let mime_type = mime_type
+                .clone()
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+            (name, mime_type, blob)
+        }
+        _ => return None,
+    };
+
+    match decode_base64(data) {
+        Ok(bytes) => Some(Attachment::from_bytes(name, Some(mime_type), &bytes)),
+        Err(e) => {
+            ::log::warn!("Skipping a tool result attachment: {}", e);
+            None
+        }
+    }
+}
+
+impl ToolResultContentRef {
+    /// See [`ToolResultContent::set_content`].
+    pub fn set_content(&mut self, cx: &mut Cx, content: &MessageContent) {
+        let Some(mut inner) = self.borrow_mut() else {
+            return;
+        };
+
+        inner.set_content(cx, content);
+    }
+}
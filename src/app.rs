@@ -1,7 +1,7 @@
 use crate::data::capture::register_capture_manager;
 use crate::data::downloads::DownloadPendingNotification;
 use crate::data::downloads::download::DownloadFileAction;
-use crate::data::moly_client::MolyClientAction;
+use crate::data::events::{self, DataEvent};
 use crate::data::store::*;
 use crate::landing::model_files_item::ModelFileItemAction;
 use crate::my_models::delete_model_modal::DeleteModelModalAction;
@@ -111,6 +111,8 @@ script_mod! {
                 svg: (ICON_CLOUD)
             }
         }
+
+        connection_status := ConnectionStatusIndicator {}
     }
 
     load_all_resources() do #(App::script_component(vm)) {
@@ -248,6 +250,12 @@ impl AppMain for App {
             // Prevent rendering the ui before the store is initialized.
             self.ui.view(cx, ids!(body)).set_visible(cx, false);
             register_capture_manager();
+            events::set_handler(|event| match event {
+                DataEvent::MolyServerUnreachable => {
+                    Cx::post_action(MolyClientAction::ServerUnreachable)
+                }
+                DataEvent::ProviderModelsFetched(result) => Cx::post_action(result),
+            });
 
             #[cfg(any(target_os = "android", target_os = "ios"))]
             // Initialize filesystem with the data directory if available,
@@ -281,6 +289,11 @@ impl AppMain for App {
 
         self.ui.view(cx, ids!(loading_view)).set_visible(cx, false);
 
+        if let Event::Shutdown = event {
+            // The MolyServer started by Moly doesn't outlive it.
+            store.moly_server_process.stop();
+        }
+
         // It triggers when the timer expires.
         if self.timer.is_event(event).is_some() {
             if let Some(file_id) = &self.file_id {
@@ -378,6 +391,13 @@ impl MatchEvent for App {
                 StoreAction::Sort(criteria) => {
                     store.search.sort_models(criteria);
                 }
+                StoreAction::Filter(filters) => {
+                    store.search.set_filters(filters);
+                }
+                StoreAction::SwitchMolyServer(id) => {
+                    store.switch_moly_server(&id);
+                    self.ui.redraw(cx);
+                }
                 _ => {}
             }
 
@@ -551,6 +571,13 @@ pub enum NavigationAction {
     None,
 }
 
+#[derive(Clone, Debug, Default)]
+pub enum MolyClientAction {
+    #[default]
+    None,
+    ServerUnreachable,
+}
+
 /// Workaround to switch between sync and async code in `Store`.
 pub fn app_runner() -> UiRunner<App> {
     // `0` is reserved for whatever implements `AppMain`.
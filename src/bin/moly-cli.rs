@@ -0,0 +1,22 @@
+//! Command line access to the chats and providers configured in Moly.
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    env_logger::init();
+
+    let args = std::env::args().skip(1).collect();
+    let result = tokio::runtime::Builder::new_multi_thread()
+        .enable_io()
+        .enable_time()
+        .build()
+        .expect("Failed to create Tokio runtime")
+        .block_on(moly::cli::run(args));
+
+    if let Err(error) = result {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    }
+}
@@ -1,16 +1,9 @@
 use makepad_widgets::defer_with_redraw::DeferWithRedraw;
 use makepad_widgets::*;
 use moly_kit::aitk::utils::asynchronous::spawn;
-use moly_kit::prelude::*;
 
-use std::collections::HashMap;
-
-use crate::data::bot_fetcher::should_include_bot;
-use crate::data::deep_inquire_client::DeepInquireClient;
-use crate::data::openclaw_client::OpenClawClient;
-use crate::data::providers::{Provider, ProviderBot, ProviderId, ProviderType};
+use crate::data::bot_clients;
 use crate::data::store::Store;
-use crate::data::supported_providers::{self, SupportedProvider};
 use crate::settings::provider_view::ProviderViewWidgetExt;
 use crate::settings::providers::ConnectionSettingsAction;
 use crate::shared::actions::ChatAction;
@@ -131,67 +124,13 @@ impl ChatScreen {
     fn create_bot_context(&mut self, _cx: &mut Cx, scope: &mut Scope) {
         let store = scope.data.get_mut::<Store>().unwrap();
 
-        let router_client = {
-            let router_client = RouterClient::new();
-            let supported_providers_list = supported_providers::load_supported_providers();
-
-            let available_bots = store.chats.available_bots.clone();
-            let providers = store.chats.providers.clone();
-
-            // Filter enabled providers upfront and check credentials
-            for (key, provider) in store
-                .chats
-                .providers
-                .iter()
-                .filter(|(_, p)| p.enabled && has_valid_credentials(p))
-            {
-                let client: Option<Box<dyn BotClient>> = match provider.provider_type {
-                    ProviderType::OpenAi | ProviderType::MolyServer | ProviderType::MoFa => {
-                        create_openai_client(
-                            provider,
-                            &supported_providers_list,
-                            &available_bots,
-                            &providers,
-                            &store,
-                            ClientFilter::ChatModels,
-                        )
-                    }
-                    ProviderType::OpenAiImage => create_openai_image_client(
-                        provider,
-                        &supported_providers_list,
-                        &available_bots,
-                        &providers,
-                        &store,
-                    ),
-                    ProviderType::OpenAiRealtime => create_openai_realtime_client(provider),
-                    ProviderType::DeepInquire => create_deep_inquire_client(
-                        provider,
-                        &supported_providers_list,
-                        &available_bots,
-                        &providers,
-                        &store,
-                    ),
-                    ProviderType::OpenClaw => create_openclaw_client(
-                        provider,
-                        &supported_providers_list,
-                        &available_bots,
-                        &providers,
-                        &store,
-                    ),
-                };
-
-                if let Some(client) = client {
-                    router_client.insert_client(key, client);
-                }
-            }
-
-            router_client
-        };
-
-        let mut context: BotContext = router_client.into();
+        let client = bot_clients::chat_client(
+            &store.chats,
+            &store.provider_icons,
+            store.disabled_mcp_tools.clone(),
+        );
+        let mut context: BotContext = client.into();
         let tool_manager = store.create_and_load_mcp_tool_manager();
-        tool_manager
-            .set_dangerous_mode_enabled(store.preferences.get_mcp_servers_dangerous_mode_enabled());
         context.set_tool_manager(tool_manager);
 
         store.bot_context = Some(context.clone());
@@ -207,258 +146,3 @@ impl ChatScreen {
         });
     }
 }
-
-type ProviderMap = HashMap<ProviderId, Provider>;
-type BotMap = HashMap<BotId, ProviderBot>;
-
-// Helper types and functions for client creation
-
-#[derive(Clone)]
-enum ClientFilter {
-    // Apply should_include_model filter
-    ChatModels,
-    // Check bot.enabled in available_bots
-    BotEnabled,
-    // No extra filtering beyond provider enabled
-    None,
-}
-
-fn is_localhost(url: &str) -> bool {
-    url.contains("localhost") || url.contains("127.0.0.1")
-}
-
-fn has_valid_credentials(provider: &Provider) -> bool {
-    match provider.provider_type {
-        ProviderType::OpenAi | ProviderType::MolyServer | ProviderType::OpenAiRealtime => {
-            provider.api_key.is_some() || is_localhost(&provider.url)
-        }
-        ProviderType::MoFa
-        | ProviderType::OpenAiImage
-        | ProviderType::DeepInquire
-        | ProviderType::OpenClaw => true,
-    }
-}
-
-fn apply_icon(bots: &mut Vec<Bot>, icon_opt: &Option<String>) {
-    if let Some(icon) = icon_opt {
-        for bot in bots.iter_mut() {
-            bot.avatar = EntityAvatar::Image(icon.clone());
-        }
-    }
-}
-
-fn apply_bot_filters(
-    bots: &mut Vec<Bot>,
-    available_bots: &BotMap,
-    providers: &ProviderMap,
-    filter: &ClientFilter,
-    supported_models: &Option<Vec<String>>,
-) {
-    // Filter by provider/bot enabled status
-    if !available_bots.is_empty() {
-        bots.retain(|bot| {
-            if let Some(provider_bot) = available_bots.get(&bot.id) {
-                let provider_enabled = providers
-                    .get(&provider_bot.provider_id)
-                    .map_or(false, |p| p.enabled);
-
-                match filter {
-                    ClientFilter::BotEnabled => provider_bot.enabled && provider_enabled,
-                    _ => provider_enabled,
-                }
-            } else {
-                // Bot not in available_bots yet, let it through
-                true
-            }
-        });
-    }
-
-    // Apply filter type
-    if matches!(filter, ClientFilter::ChatModels) {
-        bots.retain(|bot| should_include_bot(&bot.id));
-    }
-
-    // Apply supported models whitelist
-    if let Some(models) = supported_models {
-        bots.retain(|bot| models.iter().any(|m| m == bot.id.as_str()));
-    }
-}
-
-fn setup_map_client<C: BotClient + 'static>(
-    map_client: &mut MapClient<C>,
-    provider: &Provider,
-    supported_providers_list: &[SupportedProvider],
-    available_bots: &BotMap,
-    providers: &ProviderMap,
-    store: &Store,
-    filter: ClientFilter,
-) {
-    let supported_models = supported_providers_list
-        .iter()
-        .find(|sp| sp.id == provider.id)
-        .and_then(|sp| sp.supported_models.clone());
-
-    let icon_opt = store.get_provider_icon(&provider.name).map(String::from);
-    let available_bots = available_bots.clone();
-    let providers = providers.clone();
-
-    map_client.set_map_bots(move |mut bots| {
-        apply_bot_filters(
-            &mut bots,
-            &available_bots,
-            &providers,
-            &filter,
-            &supported_models,
-        );
-        apply_icon(&mut bots, &icon_opt);
-        bots
-    });
-}
-
-fn create_openai_client(
-    provider: &Provider,
-    supported_providers_list: &[SupportedProvider],
-    available_bots: &BotMap,
-    providers: &ProviderMap,
-    store: &Store,
-    filter: ClientFilter,
-) -> Option<Box<dyn BotClient>> {
-    let mut client = OpenAiClient::new(provider.url.clone());
-
-    if let Some(key) = provider.api_key.as_ref() {
-        if let Err(e) = client.set_key(key) {
-            eprintln!("Failed to set API key for {}: {}", provider.name, e);
-            return None;
-        }
-    }
-    client.set_tools_enabled(provider.tools_enabled);
-
-    let mut map_client = MapClient::from(client);
-
-    setup_map_client(
-        &mut map_client,
-        provider,
-        supported_providers_list,
-        available_bots,
-        providers,
-        store,
-        filter,
-    );
-
-    Some(Box::new(map_client))
-}
-
-fn create_openai_image_client(
-    provider: &Provider,
-    supported_providers_list: &[SupportedProvider],
-    available_bots: &BotMap,
-    providers: &ProviderMap,
-    store: &Store,
-) -> Option<Box<dyn BotClient>> {
-    let client_url = provider.url.trim_start_matches('#').to_string();
-    let mut client = OpenAiImageClient::new(client_url);
-
-    if let Some(key) = provider.api_key.as_ref() {
-        if let Err(e) = client.set_key(key) {
-            eprintln!("Failed to set API key for {}: {}", provider.name, e);
-            return None;
-        }
-    }
-
-    let mut map_client = MapClient::from(client);
-
-    setup_map_client(
-        &mut map_client,
-        provider,
-        supported_providers_list,
-        available_bots,
-        providers,
-        store,
-        ClientFilter::BotEnabled,
-    );
-
-    Some(Box::new(map_client))
-}
-
-fn create_openai_realtime_client(provider: &Provider) -> Option<Box<dyn BotClient>> {
-    let client_url = provider.url.trim_start_matches('#').to_string();
-    let mut client = OpenAiRealtimeClient::new(client_url);
-
-    if let Some(key) = provider.api_key.as_ref() {
-        if let Err(e) = client.set_key(key) {
-            eprintln!("Failed to set API key for {}: {}", provider.name, e);
-            return None;
-        }
-    }
-    if let Some(prompt) = provider.system_prompt.as_ref() {
-        if let Err(e) = client.set_system_prompt(prompt) {
-            eprintln!("Failed to set system prompt for {}: {}", provider.name, e);
-            return None;
-        }
-    }
-    client.set_tools_enabled(provider.tools_enabled);
-
-    Some(Box::new(client))
-}
-
-fn create_deep_inquire_client(
-    provider: &Provider,
-    supported_providers_list: &[SupportedProvider],
-    available_bots: &BotMap,
-    providers: &ProviderMap,
-    store: &Store,
-) -> Option<Box<dyn BotClient>> {
-    let mut client = DeepInquireClient::new(provider.url.clone());
-
-    if let Some(key) = provider.api_key.as_ref() {
-        if let Err(e) = client.set_key(key) {
-            eprintln!("Failed to set API key for {}: {}", provider.name, e);
-            return None;
-        }
-    }
-
-    let mut map_client = MapClient::from(client);
-
-    setup_map_client(
-        &mut map_client,
-        provider,
-        supported_providers_list,
-        available_bots,
-        providers,
-        store,
-        ClientFilter::None,
-    );
-
-    Some(Box::new(map_client))
-}
-
-fn create_openclaw_client(
-    provider: &Provider,
-    supported_providers_list: &[SupportedProvider],
-    available_bots: &BotMap,
-    providers: &ProviderMap,
-    store: &Store,
-) -> Option<Box<dyn BotClient>> {
-    let mut client = OpenClawClient::new(provider.url.clone());
-
-    if let Some(key) = provider.api_key.as_ref() {
-        if let Err(e) = client.set_key(key) {
-            eprintln!("Failed to set API key for {}: {}", provider.name, e);
-            return None;
-        }
-    }
-
-    let mut map_client = MapClient::from(client);
-
-    setup_map_client(
-        &mut map_client,
-        provider,
-        supported_providers_list,
-        available_bots,
-        providers,
-        store,
-        ClientFilter::None,
-    );
-
-    Some(Box::new(map_client))
-}
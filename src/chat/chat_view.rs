@@ -2,11 +2,15 @@ use makepad_widgets::*;
 
 use moly_kit::aitk::utils::asynchronous::spawn;
 use moly_kit::prelude::*;
+use moly_kit::utils::tool_audit::{ToolAuditEvent, ToolAuditHook};
 use moly_kit::widgets::stt_input::SttInputWidgetExt;
 
+use crate::app::app_runner;
+use crate::data::chats::ProviderSyncingStatus;
 use crate::data::chats::chat::ChatId;
 use crate::data::deep_inquire_client::DeepInquireCustomContent;
-use crate::data::store::{ProviderSyncingStatus, Store};
+use crate::data::mcp_context::McpContextAction;
+use crate::data::store::Store;
 use crate::shared::bot_context::BotContext;
 use crate::shared::utils::attachments::{
     delete_attachment, generate_persistence_key, set_persistence_key_and_reader,
@@ -82,6 +86,7 @@ script_mod! {
 
     let PromptInputWithShadow = PromptInput {
         padding: Inset {left: 15 right: 15 top: 8 bottom: 8}
+        allow_document_attachments: true
         persistent +: {
             clip_x: false clip_y: false
             show_bg: true
@@ -103,10 +108,15 @@ script_mod! {
 
     mod.widgets.ChatView = #(ChatView::register_widget(vm)) {
         width: Fill height: Fill
+        flow: Down
         spacing: 0
 
+        resident_model := ResidentModelBar {}
+
         deep_inquire_content := DeepInquireContent { visible: false }
 
+        mcp_requests := McpRequestPanel {}
+
         chat := Chat {
             messages := PaddedMessages {}
             prompt := PromptInputWithShadow {}
@@ -153,6 +163,10 @@ pub struct ChatView {
     #[rust]
     prev_available_bots_len: usize,
 
+    /// Chat the tool calls are currently reported to the audit log for.
+    #[rust]
+    audited_chat_id: Option<ChatId>,
+
     #[rust]
     message_updated_while_inactive: bool,
 
@@ -237,6 +251,7 @@ impl Widget for ChatView {
 
         self.handle_current_bot(cx, scope);
         self.handle_unread_messages(cx, scope);
+        self.widget_match_event(cx, event, scope);
     }
 
     fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
@@ -294,6 +309,20 @@ impl Widget for ChatView {
     }
 }
 
+impl WidgetMatchEvent for ChatView {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        for action in actions {
+            if let Some(McpContextAction::ResourceUpdated(resource_id, attachment)) =
+                action.downcast_ref::<McpContextAction>()
+            {
+                self.prompt_input(cx, ids!(chat.prompt))
+                    .write()
+                    .refresh_resource(cx, resource_id, attachment.clone());
+            }
+        }
+    }
+}
+
 impl ChatView {
     /// Manages bot selection state synchronization between Store and Controller.
     ///
@@ -334,7 +363,6 @@ impl ChatView {
             &controller_bot_id,
             controller_bot_available,
             is_streaming,
-            store,
         );
     }
 
@@ -342,18 +370,14 @@ impl ChatView {
     ///
     /// Returns false if:
     /// - bot_id is None
-    /// - Provider syncing is not complete (prevents race conditions)
     /// - Bot is not in the enabled bots list (disabled or provider disabled)
+    ///
+    /// While providers sync, the list holds the bots cached from their last listing.
     fn is_bot_available(&self, bot_id: &Option<BotId>, store: &Store) -> bool {
         let Some(bot_id) = bot_id else {
             return false;
         };
 
-        // Don't trust availability checks during provider syncing
-        if store.provider_syncing_status != ProviderSyncingStatus::Synced {
-            return false;
-        }
-
         store
             .chats
             .get_all_bots(true) // true = only enabled bots
@@ -416,7 +440,6 @@ impl ChatView {
     /// The prompt is disabled when:
     /// - No bot is selected
     /// - Selected bot is unavailable (disabled or provider disabled)
-    /// - Provider syncing is in progress
     /// - Exception: Always enabled during streaming (to allow stopping)
     fn update_prompt_input_state(
         &mut self,
@@ -424,7 +447,6 @@ impl ChatView {
         controller_bot_id: &Option<BotId>,
         controller_bot_available: bool,
         is_streaming: bool,
-        store: &Store,
     ) {
         let mut prompt_input = self.prompt_input(cx, ids!(chat.prompt));
 
@@ -434,10 +456,8 @@ impl ChatView {
             return;
         }
 
-        // Disable if no bot selected, bot unavailable, or still syncing
-        let should_disable = controller_bot_id.is_none()
-            || !controller_bot_available
-            || store.provider_syncing_status != ProviderSyncingStatus::Synced;
+        // Disable if no bot selected or bot unavailable
+        let should_disable = controller_bot_id.is_none() || !controller_bot_available;
 
         if should_disable {
             prompt_input.write().disable();
@@ -492,6 +512,28 @@ impl ChatView {
             if let Some(bot_context) = &mut self.bot_context {
                 bot_context.add_chat_controller(self.chat_controller.clone());
             }
+
+            self.chat(cx, ids!(chat))
+                .write()
+                .set_tool_policy(cx, Some(store.tool_policy.clone()));
+            self.chat(cx, ids!(chat))
+                .write()
+                .set_tool_calls(cx, store.tool_calls.clone());
+
+            let mut prompt = self.prompt_input(cx, ids!(chat.prompt));
+            prompt
+                .write()
+                .set_slash_command_source(Some(Arc::new(store.mcp_context.clone())));
+            prompt
+                .write()
+                .set_resource_source(Some(Arc::new(store.mcp_context.clone())));
+        }
+
+        if self.audited_chat_id != Some(self.chat_id) {
+            self.audited_chat_id = Some(self.chat_id);
+            self.chat(cx, ids!(chat))
+                .write()
+                .set_tool_audit(cx, Some(tool_audit_hook(self.chat_id)));
         }
 
         // Only rebuild grouping and filter when bot_context or available_bots changes
@@ -536,6 +578,24 @@ impl ChatView {
                         .unwrap_or_else(|| moly_kit::widgets::model_selector::default_grouping(bot))
                 });
 
+            // Show what the model registry knows about each bot under its name
+            let bot_details: HashMap<BotId, String> = store
+                .chats
+                .available_bots
+                .iter()
+                .filter_map(|(bot_id, provider_bot)| {
+                    let summary = provider_bot.metadata.as_ref()?.summary()?;
+                    Some((bot_id.clone(), summary))
+                })
+                .collect();
+            chat.read()
+                .prompt_input_ref(cx)
+                .widget(cx, ids!(model_selector))
+                .as_model_selector()
+                .set_details(cx, move |bot: &moly_kit::aitk::protocol::Bot| {
+                    bot_details.get(&bot.id).cloned()
+                });
+
             // Update filter when bot_context changes
             let chat = self.chat(cx, ids!(chat));
             if let Some(mut list) = chat
@@ -618,6 +678,30 @@ impl ChatViewRef {
     }
 }
 
+/// A hook reporting the tool calls of a chat to the audit log of the store.
+fn tool_audit_hook(chat_id: ChatId) -> ToolAuditHook {
+    ToolAuditHook::new(move |event| {
+        app_runner().defer(move |app, cx, _| {
+            if let Some(store) = app.store.as_mut() {
+                // Named after the bot when it is known, as bot ids are opaque.
+                let bot = match &event {
+                    ToolAuditEvent::Decided { call, .. } => call.bot_id.as_ref().map(|id| {
+                        store
+                            .chats
+                            .available_bots
+                            .get(id)
+                            .map(|bot| bot.name.clone())
+                            .unwrap_or_else(|| id.as_str().to_string())
+                    }),
+                    ToolAuditEvent::Finished { .. } => None,
+                };
+                store.tool_audit.handle_event(Some(chat_id), bot, event);
+                cx.redraw_all();
+            }
+        });
+    })
+}
+
 /// Glue between Moly and Moly Kit.
 pub struct Glue {
     ui: UiRunner<ChatView>,
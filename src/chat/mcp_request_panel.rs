@@ -0,0 +1,527 @@
+use makepad_widgets::*;
+use moly_kit::prelude::*;
+use std::collections::BTreeMap;
+
+use crate::data::mcp_requests::{
+    ElicitationField, ElicitationFieldKind, McpRequestKind, PendingMcpRequest, SamplingState,
+};
+use crate::data::store::Store;
+
+/// Elicitation forms with more fields than this are truncated.
+const MAX_FIELDS: usize = 6;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    let PanelButton = MolyButton {
+        padding: Inset {
+            top: 6 bottom: 6 left: 12 right: 12
+        }
+        draw_bg +: {
+            color: (TRANSPARENT)
+            border_color_1: #D0D5DD
+            border_size: 1.0
+        }
+        draw_text +: {
+            text_style: REGULAR_FONT { font_size: 10 }
+            color: #344054
+        }
+    }
+
+    let PrimaryPanelButton = PanelButton {
+        draw_bg +: {
+            color: #x155EEF
+            border_size: 0.0
+        }
+        draw_text +: {
+            color: #fff
+        }
+    }
+
+    let SmallLabel = Label {
+        width: Fill
+        draw_text +: {
+            text_style: REGULAR_FONT { font_size: 9 }
+            color: #667085
+        }
+    }
+
+    let FieldRow = View {
+        width: Fill
+        height: Fit
+        flow: Down
+        spacing: 4
+        visible: false
+
+        field_label := Label {
+            draw_text +: {
+                text_style: BOLD_FONT { font_size: 10 }
+                color: #000
+            }
+        }
+        field_description := SmallLabel {}
+        field_text := MolyTextInput {
+            width: Fill
+            height: Fit
+            padding: 8
+            draw_bg +: {
+                border_size: 1.0
+                border_color: #ddd
+            }
+            draw_text +: {
+                text_style: REGULAR_FONT { font_size: 10 }
+                color: #000
+            }
+        }
+        field_switch := MolySwitch {}
+        field_choice := DropDownFlat {
+            width: Fill
+            draw_text +: {
+                text_style: REGULAR_FONT { font_size: 10 }
+            }
+        }
+    }
+
+    mod.widgets.McpRequestPanelBase = #(McpRequestPanel::register_widget(vm))
+    mod.widgets.McpRequestPanel = set_type_default() do mod.widgets.McpRequestPanelBase {
+        width: Fill
+        height: Fit
+
+        card := RoundedView {
+            visible: false
+            width: Fill
+            height: Fit
+            flow: Down
+            spacing: 8
+            margin: Inset { left: 10 right: 10 top: 8 }
+            padding: 12
+            show_bg: true
+            draw_bg +: {
+                color: #F9FAFB
+                border_radius: 6.0
+                border_size: 1.0
+                border_color: #EAECF0
+            }
+
+            View {
+                width: Fill
+                height: Fit
+                align: Align { y: 0.5 }
+
+                title := Label {
+                    draw_text +: {
+                        text_style: BOLD_FONT { font_size: 11 }
+                        color: #000
+                    }
+                }
+                View { width: Fill height: 1 }
+                queue := SmallLabel { width: Fit }
+            }
+
+            message := Label {
+                width: Fill
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 10 }
+                    color: #344054
+                }
+            }
+
+            model_row := View {
+                width: Fill
+                height: Fit
+                spacing: 8
+                align: Align { y: 0.5 }
+
+                SmallLabel { width: Fit text: "Model" }
+                model := DropDownFlat {
+                    width: Fill
+                    draw_text +: {
+                        text_style: REGULAR_FONT { font_size: 10 }
+                    }
+                }
+            }
+
+            transcript := RoundedView {
+                width: Fill
+                height: Fit
+                flow: Down
+                spacing: 6
+                padding: 10
+                show_bg: true
+                draw_bg +: {
+                    color: #F2F4F7
+                    border_radius: 4.0
+                }
+
+                transcript_text := Label {
+                    width: Fill
+                    draw_text +: {
+                        text_style: REGULAR_FONT { font_size: 9 }
+                        color: #1D2939
+                    }
+                }
+                response_text := Label {
+                    width: Fill
+                    draw_text +: {
+                        text_style: BOLD_FONT { font_size: 9 }
+                        color: #1D2939
+                    }
+                }
+            }
+
+            form := View {
+                width: Fill
+                height: Fit
+                flow: Down
+                spacing: 10
+
+                field_0 := FieldRow {}
+                field_1 := FieldRow {}
+                field_2 := FieldRow {}
+                field_3 := FieldRow {}
+                field_4 := FieldRow {}
+                field_5 := FieldRow {}
+            }
+
+            error := Label {
+                width: Fill
+                visible: false
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 9 }
+                    color: #xB42318
+                }
+            }
+
+            View {
+                width: Fill
+                height: Fit
+                spacing: 8
+                align: Align { x: 1.0 }
+
+                secondary := PanelButton {}
+                tertiary := PanelButton {}
+                primary := PrimaryPanelButton {}
+            }
+        }
+    }
+}
+
+/// Sampling and elicitation requests from MCP servers, answered one at a time.
+#[derive(Script, ScriptHook, Widget)]
+pub struct McpRequestPanel {
+    #[deref]
+    view: View,
+
+    /// Request currently shown, to reset the form when it changes.
+    #[rust]
+    shown_request: Option<u64>,
+
+    #[rust]
+    fields: Vec<ElicitationField>,
+
+    /// Bots listed in the model picker of sampling requests.
+    #[rust]
+    sampling_bots: Vec<BotId>,
+
+    /// Index of the bot picked to answer the shown sampling request.
+    #[rust]
+    sampling_bot: Option<usize>,
+}
+
+impl Widget for McpRequestPanel {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let store = scope.data.get::<Store>().unwrap();
+
+        match store.mcp_requests.first() {
+            Some(request) => {
+                self.view(cx, ids!(card)).set_visible(cx, true);
+                let queued = store.mcp_requests.len() - 1;
+                self.label(cx, ids!(queue)).set_text(
+                    cx,
+                    &if queued > 0 {
+                        format!("{} more waiting", queued)
+                    } else {
+                        String::new()
+                    },
+                );
+                self.show_request(cx, store, request);
+            }
+            None => {
+                self.view(cx, ids!(card)).set_visible(cx, false);
+                self.shown_request = None;
+            }
+        }
+
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for McpRequestPanel {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope) {
+        let Some(id) = self.shown_request else {
+            return;
+        };
+        let store = scope.data.get_mut::<Store>().unwrap();
+        let Some(request) = store.mcp_requests.iter().find(|r| r.id == id) else {
+            return;
+        };
+        let sampling_state = match &request.kind {
+            McpRequestKind::Sampling(_, state) => Some(state.clone()),
+            McpRequestKind::Elicitation(_) => None,
+        };
+
+        if let Some(index) = self.drop_down(cx, ids!(model)).selected(actions) {
+            self.sampling_bot = Some(index);
+        }
+        let bot_id = self
+            .sampling_bot
+            .and_then(|index| self.sampling_bots.get(index))
+            .cloned();
+
+        let primary = self.button(cx, ids!(primary)).clicked(actions);
+        let secondary = self.button(cx, ids!(secondary)).clicked(actions);
+        let tertiary = self.button(cx, ids!(tertiary)).clicked(actions);
+
+        match sampling_state {
+            Some(SamplingState::AwaitingApproval) => {
+                if secondary {
+                    store.decline_mcp_request(id);
+                } else if let Some(bot_id) = bot_id.filter(|_| primary || tertiary) {
+                    store.approve_mcp_sampling(id, bot_id, tertiary);
+                }
+            }
+            Some(SamplingState::Running) => {}
+            Some(_) => {
+                if primary {
+                    store.dismiss_mcp_request(id);
+                }
+            }
+            None => {
+                if primary {
+                    let values = self.form_values(cx);
+                    if let Err(error) = store.submit_mcp_elicitation(id, &values) {
+                        let label = self.label(cx, ids!(error));
+                        label.set_text(cx, &error);
+                        label.set_visible(cx, true);
+                    }
+                } else if secondary {
+                    store.decline_mcp_request(id);
+                }
+            }
+        }
+
+        if primary || secondary || tertiary {
+            self.redraw(cx);
+        }
+    }
+}
+
+impl McpRequestPanel {
+    fn show_request(&mut self, cx: &mut Cx, store: &Store, request: &PendingMcpRequest) {
+        let is_new = self.shown_request != Some(request.id);
+        self.shown_request = Some(request.id);
+
+        match &request.kind {
+            McpRequestKind::Sampling(sampling, state) => {
+                self.fields.clear();
+                self.view(cx, ids!(form)).set_visible(cx, false);
+                self.view(cx, ids!(model_row))
+                    .set_visible(cx, *state == SamplingState::AwaitingApproval);
+                if is_new {
+                    self.fill_model_picker(cx, store, request.id);
+                }
+                self.view(cx, ids!(transcript)).set_visible(cx, true);
+                self.label(cx, ids!(title))
+                    .set_text(cx, &format!("{} requests a completion", request.server_id));
+                self.label(cx, ids!(transcript_text))
+                    .set_text(cx, &sampling.transcript());
+
+                let (message, response, error) = match state {
+                    SamplingState::AwaitingApproval if self.sampling_bots.is_empty() => {
+                        ("Enable a model in the settings to answer it.", None, None)
+                    }
+                    SamplingState::AwaitingApproval => {
+                        ("Choose the model that answers it.", None, None)
+                    }
+                    SamplingState::Running => ("Generating...", None, None),
+                    SamplingState::Done(text) => ("Sent back to the server.", Some(text), None),
+                    SamplingState::Failed(error) => ("", None, Some(error)),
+                };
+                self.label(cx, ids!(message)).set_text(cx, message);
+
+                let response_label = self.label(cx, ids!(response_text));
+                response_label.set_visible(cx, response.is_some());
+                if let Some(response) = response {
+                    response_label.set_text(cx, &format!("Assistant: {}", response));
+                }
+
+                let error_label = self.label(cx, ids!(error));
+                error_label.set_visible(cx, error.is_some());
+                if let Some(error) = error {
+                    error_label.set_text(cx, error);
+                }
+
+                match state {
+                    SamplingState::AwaitingApproval if self.sampling_bots.is_empty() => {
+                        self.set_buttons(cx, None, Some("Deny"), None)
+                    }
+                    SamplingState::AwaitingApproval => {
+                        self.set_buttons(cx, Some("Allow once"), Some("Deny"), Some("Always allow"))
+                    }
+                    SamplingState::Running => self.set_buttons(cx, None, None, None),
+                    _ => self.set_buttons(cx, Some("Dismiss"), None, None),
+                }
+            }
+            McpRequestKind::Elicitation(elicitation) => {
+                self.view(cx, ids!(transcript)).set_visible(cx, false);
+                self.view(cx, ids!(model_row)).set_visible(cx, false);
+                self.view(cx, ids!(form)).set_visible(cx, true);
+                self.label(cx, ids!(title))
+                    .set_text(cx, &format!("{} needs more information", request.server_id));
+                self.label(cx, ids!(message))
+                    .set_text(cx, &elicitation.message);
+                self.set_buttons(cx, Some("Submit"), Some("Decline"), None);
+
+                if is_new {
+                    self.label(cx, ids!(error)).set_visible(cx, false);
+                    self.fields = elicitation
+                        .fields
+                        .iter()
+                        .take(MAX_FIELDS)
+                        .cloned()
+                        .collect();
+                    self.fill_form(cx);
+                }
+            }
+        }
+    }
+
+    /// List the bots that can answer the request, selecting the default one.
+    fn fill_model_picker(&mut self, cx: &mut Cx, store: &Store, id: u64) {
+        let bots = store.sampling_bots();
+        let default = store.default_sampling_bot(id);
+
+        self.sampling_bots = bots.iter().map(|bot| bot.id.clone()).collect();
+        self.sampling_bot = default
+            .and_then(|default| self.sampling_bots.iter().position(|id| *id == default))
+            .or((!bots.is_empty()).then_some(0));
+
+        let picker = self.drop_down(cx, ids!(model));
+        picker.set_labels(cx, bots.iter().map(|bot| bot.name.clone()).collect());
+        if let Some(index) = self.sampling_bot {
+            picker.set_selected_item(cx, index);
+        }
+    }
+
+    fn set_buttons(
+        &mut self,
+        cx: &mut Cx,
+        primary: Option<&str>,
+        secondary: Option<&str>,
+        tertiary: Option<&str>,
+    ) {
+        for (id, text) in [
+            (ids!(primary), primary),
+            (ids!(secondary), secondary),
+            (ids!(tertiary), tertiary),
+        ] {
+            let button = self.button(cx, id);
+            button.set_visible(cx, text.is_some());
+            button.set_text(cx, text.unwrap_or_default());
+        }
+    }
+
+    fn fill_form(&mut self, cx: &mut Cx) {
+        for index in 0..MAX_FIELDS {
+            let row = self.view(cx, &[live_id!(form), field_id(index)]);
+            let Some(field) = self.fields.get(index) else {
+                row.set_visible(cx, false);
+                continue;
+            };
+
+            row.set_visible(cx, true);
+            let title = if field.required {
+                format!("{} *", field.title)
+            } else {
+                field.title.clone()
+            };
+            row.label(cx, ids!(field_label)).set_text(cx, &title);
+
+            let description = row.label(cx, ids!(field_description));
+            description.set_visible(cx, !field.description.is_empty());
+            description.set_text(cx, &field.description);
+
+            let default = field.default.clone().unwrap_or_default();
+            let text = row.text_input(cx, ids!(field_text));
+            let switch = row.check_box(cx, ids!(field_switch));
+            let choice = row.drop_down(cx, ids!(field_choice));
+            text.set_visible(cx, false);
+            switch.set_visible(cx, false);
+            choice.set_visible(cx, false);
+
+            match &field.kind {
+                ElicitationFieldKind::Text | ElicitationFieldKind::Number { .. } => {
+                    text.set_visible(cx, true);
+                    text.set_text(cx, &default);
+                }
+                ElicitationFieldKind::Boolean => {
+                    switch.set_visible(cx, true);
+                    switch.set_active(cx, default == "true");
+                }
+                ElicitationFieldKind::Choice(options) => {
+                    choice.set_visible(cx, true);
+                    let mut labels: Vec<String> =
+                        options.iter().map(|(_, name)| name.clone()).collect();
+                    // Optional choices can be left empty.
+                    if !field.required {
+                        labels.insert(0, String::new());
+                    }
+                    choice.set_labels(cx, labels);
+                    let selected = options
+                        .iter()
+                        .find(|(value, _)| *value == default)
+                        .map(|(_, name)| name.as_str())
+                        .unwrap_or_default();
+                    choice.set_selected_by_label(selected, cx);
+                }
+            }
+        }
+    }
+
+    fn form_values(&mut self, cx: &mut Cx) -> BTreeMap<String, String> {
+        let mut values = BTreeMap::new();
+
+        for (index, field) in self.fields.iter().enumerate() {
+            let row = self.view(cx, &[live_id!(form), field_id(index)]);
+            let value = match &field.kind {
+                ElicitationFieldKind::Text | ElicitationFieldKind::Number { .. } => {
+                    row.text_input(cx, ids!(field_text)).text()
+                }
+                ElicitationFieldKind::Boolean => {
+                    row.check_box(cx, ids!(field_switch)).active(cx).to_string()
+                }
+                ElicitationFieldKind::Choice(options) => {
+                    let label = row.drop_down(cx, ids!(field_choice)).selected_label();
+                    options
+                        .iter()
+                        .find(|(_, name)| *name == label)
+                        .map(|(value, _)| value.clone())
+                        .unwrap_or_default()
+                }
+            };
+            values.insert(field.name.clone(), value);
+        }
+
+        values
+    }
+}
+
+fn field_id(index: usize) -> LiveId {
+    LiveId::from_str(&format!("field_{}", index))
+}
@@ -11,8 +11,10 @@ pub mod deep_inquire_content;
 pub mod deep_inquire_stages;
 pub mod delete_chat_modal;
 pub mod entity_button;
+pub mod mcp_request_panel;
 pub mod model_info;
 pub mod moly_bot_filter;
+pub mod resident_model_bar;
 pub mod shared;
 
 use makepad_widgets::ScriptVm;
@@ -28,6 +30,8 @@ pub fn script_mod(vm: &mut ScriptVm) {
     chat_history::script_mod(vm);
     chat_history_panel::script_mod(vm);
     chat_params::script_mod(vm);
+    mcp_request_panel::script_mod(vm);
+    resident_model_bar::script_mod(vm);
     chat_view::script_mod(vm);
     chats_deck::script_mod(vm);
     model_info::script_mod(vm);
@@ -1,5 +1,7 @@
 use makepad_widgets::*;
 
+use crate::data::model_registry::ModelMetadata;
+
 script_mod! {
     use mod.prelude.widgets.*
     use mod.widgets.*
@@ -41,10 +43,25 @@ script_mod! {
             }
         }
 
-        label := Label {
-            draw_text +: {
-                text_style: REGULAR_FONT {font_size: 11}
-                color: #000
+        View {
+            width: Fit
+            height: Fit
+            flow: Down
+            spacing: 2
+
+            label := Label {
+                draw_text +: {
+                    text_style: REGULAR_FONT {font_size: 11}
+                    color: #000
+                }
+            }
+
+            details := Label {
+                visible: false
+                draw_text +: {
+                    text_style: REGULAR_FONT {font_size: 8.5}
+                    color: #667085
+                }
             }
         }
 
@@ -113,3 +130,11 @@ script_mod! {
         }
     }
 }
+
+/// Show what is known about a model under its name in a `ModelInfo`.
+pub fn set_model_metadata(cx: &mut Cx, model_info: &WidgetRef, metadata: Option<&ModelMetadata>) {
+    let summary = metadata.and_then(|m| m.summary());
+    let details = model_info.label(cx, ids!(details));
+    details.set_visible(cx, summary.is_some());
+    details.set_text(cx, summary.as_deref().unwrap_or_default());
+}
@@ -0,0 +1,119 @@
+use makepad_widgets::*;
+
+use crate::data::model_loader::LoadState;
+use crate::data::store::Store;
+use crate::shared::utils::human_readable_name;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.ResidentModelBarBase = #(ResidentModelBar::register_widget(vm))
+    mod.widgets.ResidentModelBar = set_type_default() do mod.widgets.ResidentModelBarBase {
+        width: Fill
+        height: Fit
+
+        bar := RoundedView {
+            visible: false
+            width: Fill
+            height: Fit
+            margin: Inset { left: 10 right: 10 top: 8 }
+            padding: Inset { top: 6 bottom: 6 left: 12 right: 6 }
+            spacing: 10
+            align: Align { y: 0.5 }
+            show_bg: true
+            draw_bg +: {
+                color: #F9FAFB
+                border_radius: 6.0
+                border_size: 1.0
+                border_color: #EAECF0
+            }
+
+            status := Label {
+                width: Fill
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 9 }
+                    color: #344054
+                }
+            }
+
+            eject_button := MolyButton {
+                padding: Inset {
+                    top: 4 bottom: 4 left: 10 right: 10
+                }
+                text: "Eject"
+                draw_bg +: {
+                    color: (TRANSPARENT)
+                    border_color_1: #D0D5DD
+                    border_size: 1.0
+                }
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 9 }
+                    color: #344054
+                }
+            }
+        }
+    }
+}
+
+/// Which downloaded file MolyServer has in memory, with a way to eject it.
+#[derive(Script, ScriptHook, Widget)]
+pub struct ResidentModelBar {
+    #[deref]
+    view: View,
+}
+
+impl Widget for ResidentModelBar {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let store = scope.data.get::<Store>().unwrap();
+        let state = &store.model_loader.state;
+
+        let name = state.file_id().map(|file_id| {
+            store
+                .downloads
+                .downloaded_files
+                .iter()
+                .find(|f| f.file.id == *file_id)
+                .map(|f| human_readable_name(&f.file.name))
+                .unwrap_or_else(|| file_id.clone())
+        });
+
+        let status = match (state, name) {
+            (LoadState::Loading { progress, .. }, Some(name)) => match progress {
+                Some(progress) => format!("Loading {} ({:.0}%)", name, progress * 100.0),
+                None => format!("Loading {}...", name),
+            },
+            (LoadState::Loaded(model), Some(name)) if model.information.is_empty() => {
+                format!("Loaded locally: {}", name)
+            }
+            (LoadState::Loaded(model), Some(name)) => {
+                format!("Loaded locally: {} ({})", name, model.information)
+            }
+            (LoadState::Ejecting(_), Some(name)) => format!("Ejecting {}...", name),
+            (LoadState::Failed { error, .. }, Some(name)) => {
+                format!("Couldn't load {}: {}", name, error)
+            }
+            _ => String::new(),
+        };
+
+        self.view(cx, ids!(bar)).set_visible(cx, !status.is_empty());
+        self.label(cx, ids!(status)).set_text(cx, &status);
+
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for ResidentModelBar {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope) {
+        if self.button(cx, ids!(eject_button)).clicked(actions) {
+            let store = scope.data.get_mut::<Store>().unwrap();
+            store.model_loader.eject();
+            self.redraw(cx);
+        }
+    }
+}
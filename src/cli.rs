@@ -0,0 +1,609 @@
+//! Headless access to the chats, providers and MCP servers of Moly.
+//!
+//! Reads the same preferences, chats and MCP configuration as the app, so a chat
+//! started here can be continued in the app and the other way around.
+
+use futures::StreamExt;
+use moly_kit::aitk::utils::tool::display_name_from_namespaced;
+use moly_kit::prelude::*;
+use moly_kit::utils::mcp::McpClient;
+use moly_kit::utils::tool_audit::{ToolAuditCall, ToolAuditEvent};
+use moly_kit::utils::tool_calls::ToolCalls;
+use moly_kit::utils::tool_policy::{
+    ToolApproval, ToolPermission, ToolPolicy, denial_message, denial_notice,
+};
+use std::collections::HashMap;
+use std::io::{IsTerminal, Read, Write};
+
+use crate::data::bot_clients;
+use crate::data::bot_fetcher::fetch_models;
+use crate::data::chats::chat::ChatId;
+use crate::data::chats::{Chats, ProviderSyncingStatus};
+use crate::data::mcp_oauth;
+use crate::data::mcp_servers::McpServersConfig;
+use crate::data::mcp_supervisor::STARTUP_TIMEOUT;
+use crate::data::moly_client::MolyClient;
+use crate::data::preferences::Preferences;
+use crate::data::tool_audit::ToolAuditLog;
+use crate::data::tool_filter::DisabledTools;
+
+const USAGE: &str = "\
+Usage: moly-cli <command> [options]
+
+Commands:
+  bots                  List the enabled bots of the configured providers
+  chats                 List the saved chats, most recent first
+  send [options] [PROMPT]
+                        Send a prompt and stream the reply to stdout.
+                        The prompt is read from stdin when missing or \"-\".
+
+Options of send:
+  --bot <ID or NAME>    Bot to talk to, defaults to the bot of the chat or
+                        the one last picked in the app
+  --chat <ID>           Continue a saved chat instead of starting a new one
+  --no-tools            Don't offer MCP tools to the bot
+  --yes                 Run the tool calls the policy asks about without asking
+";
+
+enum Command {
+    Help,
+    Bots,
+    Chats,
+    Send(SendOptions),
+}
+
+#[derive(Default)]
+struct SendOptions {
+    bot: Option<String>,
+    chat: Option<ChatId>,
+    tools: bool,
+    yes: bool,
+    prompt: Option<String>,
+}
+
+fn parse_args(args: Vec<String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let command = match args.next().as_deref() {
+        None | Some("help" | "-h" | "--help") => return Ok(Command::Help),
+        Some("bots") => Command::Bots,
+        Some("chats") => Command::Chats,
+        Some("send") => Command::Send(SendOptions {
+            tools: true,
+            ..Default::default()
+        }),
+        Some(other) => return Err(format!("Unknown command '{}'", other)),
+    };
+
+    let Command::Send(mut options) = command else {
+        return match args.next() {
+            Some(arg) => Err(format!("Unexpected argument '{}'", arg)),
+            None => Ok(command),
+        };
+    };
+
+    let mut words = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bot" => options.bot = Some(args.next().ok_or("--bot needs a value")?),
+            "--chat" => {
+                let id = args.next().ok_or("--chat needs a value")?;
+                let id = id
+                    .parse()
+                    .map_err(|_| format!("Invalid chat id '{}'", id))?;
+                options.chat = Some(id);
+            }
+            "--no-tools" => options.tools = false,
+            "--yes" | "-y" => options.yes = true,
+            "--" => words.extend(args.by_ref()),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option '{}'", flag)),
+            _ => words.push(arg),
+        }
+    }
+
+    if !words.is_empty() && words != ["-"] {
+        options.prompt = Some(words.join(" "));
+    }
+
+    Ok(Command::Send(options))
+}
+
+/// Run the command line with the given arguments, without the program name.
+pub async fn run(args: Vec<String>) -> Result<(), String> {
+    moly_kit::utils::extraction::set_extraction_cache(
+        crate::shared::utils::attachments::PersistedExtractionCache,
+    );
+
+    match parse_args(args)? {
+        Command::Help => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        Command::Bots => list_bots().await,
+        Command::Chats => list_chats().await,
+        Command::Send(options) => send(options).await,
+    }
+}
+
+async fn list_bots() -> Result<(), String> {
+    let mut preferences = Preferences::load().await;
+    let chats = load_chats(&mut preferences).await;
+
+    let mut bots = chats.get_all_bots(true);
+    bots.sort_by(|a, b| (&a.provider_id, &a.name).cmp(&(&b.provider_id, &b.name)));
+
+    if bots.is_empty() {
+        eprintln!("No bot is available, enable a provider in the app first.");
+    }
+    for bot in bots {
+        println!("{}\t{}", bot.id.as_str(), bot.name);
+    }
+    Ok(())
+}
+
+async fn list_chats() -> Result<(), String> {
+    let chats = Chats::load(moly_client()).await;
+
+    let mut saved: Vec<_> = chats.saved_chats.iter().map(|c| c.borrow()).collect();
+    saved.sort_by(|a, b| b.accessed_at.cmp(&a.accessed_at));
+
+    for chat in saved {
+        let bot = chat
+            .associated_bot
+            .as_ref()
+            .map(|id| id.as_str())
+            .unwrap_or("-");
+        println!(
+            "{}\t{}\t{}\t{}",
+            chat.id,
+            chat.accessed_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M"),
+            bot,
+            chat.get_title()
+        );
+    }
+    Ok(())
+}
+
+async fn send(options: SendOptions) -> Result<(), String> {
+    let prompt = match options.prompt {
+        Some(prompt) => prompt,
+        None => {
+            let mut prompt = String::new();
+            std::io::stdin()
+                .read_to_string(&mut prompt)
+                .map_err(|e| format!("Could not read the prompt: {}", e))?;
+            prompt
+        }
+    };
+    if prompt.trim().is_empty() {
+        return Err("The prompt is empty".to_string());
+    }
+
+    let mut preferences = Preferences::load().await;
+    let mut chats = load_chats(&mut preferences).await;
+
+    if let Some(id) = options.chat {
+        chats
+            .get_chat_by_id(id)
+            .ok_or_else(|| format!("No chat with id {}", id))?;
+    }
+
+    let bot_id = match &options.bot {
+        Some(bot) => find_bot(&chats, bot)?,
+        None => options
+            .chat
+            .and_then(|id| chats.get_chat_by_id(id)?.borrow().associated_bot.clone())
+            .or_else(|| preferences.current_chat_model.clone())
+            .ok_or("No bot to talk to, pick one with --bot")?,
+    };
+    let bot_name = chats
+        .get_all_bots(true)
+        .into_iter()
+        .find(|bot| bot.id == bot_id)
+        .map(|bot| bot.name)
+        .ok_or_else(|| {
+            format!(
+                "The bot '{}' is not available, see `moly-cli bots`",
+                bot_id.as_str()
+            )
+        })?;
+
+    let chat_id = match options.chat {
+        Some(id) => id,
+        None => chats.create_empty_chat(Some(bot_id.clone())),
+    };
+
+    let config = &preferences.mcp_servers_config;
+    let tool_manager = match options.tools {
+        true => load_mcp_servers(config).await,
+        false => None,
+    };
+    let tools = tool_manager
+        .as_ref()
+        .map(|manager| manager.tools())
+        .unwrap_or_default();
+
+    let disabled_tools = DisabledTools::from_config(config);
+    let mut client = bot_clients::chat_client(&chats, &[], disabled_tools.clone());
+    let tool_calls = ToolCalls::default();
+    tool_calls.set_timeouts(config.tool_timeouts());
+    tool_calls.set_disabled_check(Some(disabled_tools.check()));
+    tool_calls.set_client(tool_manager);
+    let policy = config.effective_tool_policy();
+    let mut tool_audit = ToolAuditLog::load().await;
+
+    let mut messages = chats
+        .get_chat_by_id(chat_id)
+        .map(|chat| chat.borrow().messages.clone())
+        .unwrap_or_default();
+    messages.push(Message {
+        from: EntityId::User,
+        content: MessageContent {
+            text: prompt.trim_end().to_string(),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    let result = loop {
+        let content = match stream_reply(&mut client, &bot_id, &messages, &tools).await {
+            Ok(content) => content,
+            Err(error) => break Err(error),
+        };
+        let has_tool_calls = !content.tool_calls.is_empty();
+        messages.push(Message {
+            from: EntityId::Bot(bot_id.clone()),
+            content,
+            ..Default::default()
+        });
+
+        if !has_tool_calls || tool_calls.client().is_none() {
+            break Ok(());
+        }
+
+        let last = messages.len() - 1;
+        let mut denied_by = HashMap::new();
+        let approved = decide_tool_calls(
+            &mut messages[last].content.tool_calls,
+            &policy,
+            options.yes,
+            &mut |call, approved, approval| {
+                if !approved {
+                    denied_by.insert(call.id.clone(), approval);
+                }
+                let event = ToolAuditEvent::Decided {
+                    call: ToolAuditCall {
+                        id: call.id.clone(),
+                        name: call.name.clone(),
+                        arguments: serde_json::Value::Object(call.arguments.clone()),
+                        bot_id: Some(bot_id.clone()),
+                        realtime: false,
+                    },
+                    approved,
+                    approval,
+                };
+                tool_audit.handle_event(Some(chat_id), Some(bot_name.clone()), event);
+            },
+        );
+
+        let denied: Vec<ToolResult> = messages[last]
+            .content
+            .tool_calls
+            .iter()
+            .filter(|call| call.permission_status == ToolCallPermissionStatus::Denied)
+            .map(|call| ToolResult {
+                tool_call_id: call.id.clone(),
+                content: denial_message(&call.name, denied_by[&call.id]),
+                is_error: true,
+            })
+            .collect();
+        if !denied.is_empty() {
+            let approval = if denied_by.values().any(|a| *a == ToolApproval::Manual) {
+                ToolApproval::Manual
+            } else {
+                ToolApproval::Policy
+            };
+            messages.push(tool_message(&denial_notice(approval), denied));
+        }
+
+        if approved.is_empty() {
+            break Ok(());
+        }
+
+        let mut results = Vec::new();
+        for call in approved {
+            let display_name = display_name_from_namespaced(&call.name);
+            eprintln!("Running {}…", display_name);
+            let result = tool_calls.run(&call.name, &call.id, call.arguments).await;
+            if result.is_error {
+                eprintln!("{} failed: {}", display_name, result.content);
+            }
+            tool_audit.handle_event(
+                Some(chat_id),
+                None,
+                ToolAuditEvent::Finished {
+                    id: result.tool_call_id.clone(),
+                    content: result.content.clone(),
+                    is_error: result.is_error,
+                },
+            );
+            results.push(result);
+        }
+
+        let failed = results.iter().filter(|result| result.is_error).count();
+        let text = if failed == 0 {
+            "\u{1f527} Tool execution finished."
+        } else if failed == results.len() {
+            "\u{26a0}\u{fe0f} Tool execution failed."
+        } else {
+            "\u{26a0}\u{fe0f} Some tools failed."
+        };
+        messages.push(tool_message(text, results));
+    };
+
+    if let Some(chat) = chats.get_chat_by_id(chat_id) {
+        let chat = {
+            let mut chat = chat.borrow_mut();
+            chat.messages = messages;
+            chat.associated_bot = Some(bot_id);
+            chat.update_title_based_on_first_message();
+            chat.update_accessed_at();
+            chat.clone()
+        };
+        chat.save().await;
+    }
+    tool_audit.flush().await;
+
+    eprintln!("Chat {}", chat_id);
+    result
+}
+
+fn moly_client() -> MolyClient {
+    let server_port = std::env::var("MOLY_SERVER_PORT")
+        .ok()
+        .and_then(|p| p.parse::<u16>().ok())
+        .unwrap_or(8765);
+
+    MolyClient::new(format!("http://localhost:{}", server_port))
+}
+
+/// Load the saved chats with the providers of the preferences and their models.
+///
+/// Like the app, only the providers it would connect to on startup are queried.
+async fn load_chats(preferences: &mut Preferences) -> Chats {
+    let mut chats = Chats::load(moly_client()).await;
+
+    for provider in preferences.providers() {
+        chats.providers.insert(provider.id.clone(), provider);
+    }
+
+    let to_fetch: Vec<_> = preferences
+        .providers_preferences
+        .iter()
+        .filter(|pp| pp.can_auto_fetch())
+        .filter_map(|pp| {
+            let mut pp = pp.clone();
+            pp.ensure_id();
+            chats.providers.get(&pp.id).cloned()
+        })
+        .collect();
+
+    let registry = &chats.model_registry;
+    let results =
+        futures::future::join_all(to_fetch.iter().map(|p| fetch_models(p, registry))).await;
+
+    let mut syncing = ProviderSyncingStatus::NotSyncing;
+    for result in results {
+        chats.handle_provider_connection_result(result, preferences, &mut syncing);
+    }
+
+    chats
+}
+
+/// Find an enabled bot by id, or by name when unambiguous.
+fn find_bot(chats: &Chats, bot: &str) -> Result<BotId, String> {
+    let bots = chats.get_all_bots(true);
+    if let Some(found) = bots.iter().find(|b| b.id.as_str() == bot) {
+        return Ok(found.id.clone());
+    }
+
+    let named: Vec<_> = bots
+        .iter()
+        .filter(|b| b.name.eq_ignore_ascii_case(bot))
+        .collect();
+    match named.as_slice() {
+        [found] => Ok(found.id.clone()),
+        [] => Err(format!("No bot named '{}', see `moly-cli bots`", bot)),
+        _ => Err(format!(
+            "Several bots are named '{}', pass one of their ids: {}",
+            bot,
+            named
+                .iter()
+                .map(|b| b.id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Connect to the enabled MCP servers, reporting the ones that fail on stderr.
+async fn load_mcp_servers(config: &McpServersConfig) -> Option<McpClient> {
+    if !config.enabled {
+        return None;
+    }
+
+    let tool_manager = McpClient::new();
+
+    for (server_id, server) in config.list_enabled_servers() {
+        let transport = match mcp_oauth::connection_auth(server_id, server).await {
+            Ok(Some(auth)) => server
+                .to_transport()
+                .map(|transport| transport.map(|transport| transport.with_auth(auth))),
+            Ok(None) => server.to_transport(),
+            Err(error) => Err(error),
+        };
+
+        let error = match transport {
+            Ok(Some(transport)) => {
+                match tokio::time::timeout(
+                    STARTUP_TIMEOUT,
+                    tool_manager.add_server(server_id, transport),
+                )
+                .await
+                {
+                    Ok(Ok(())) => continue,
+                    Ok(Err(e)) => e.to_string(),
+                    Err(_) => format!(
+                        "The server did not start within {} seconds",
+                        STARTUP_TIMEOUT.as_secs()
+                    ),
+                }
+            }
+            Ok(None) => "Missing \"command\" or \"url\"".to_string(),
+            Err(error) => error,
+        };
+        eprintln!("MCP server '{}' is unavailable: {}", server_id, error);
+    }
+
+    Some(tool_manager)
+}
+
+/// Stream the reply of the bot to stdout, returning its final content.
+async fn stream_reply(
+    client: &mut impl BotClient,
+    bot_id: &BotId,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<MessageContent, String> {
+    let mut stream = client.send(bot_id, messages, tools);
+    let mut content = MessageContent::default();
+    let mut stdout = std::io::stdout();
+
+    while let Some(result) = stream.next().await {
+        let next = result.into_result().map_err(|errors| {
+            errors
+                .iter()
+                .map(|e| e.message().to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        })?;
+
+        // Each item holds the whole content so far.
+        match next.text.strip_prefix(content.text.as_str()) {
+            Some(delta) => print!("{}", delta),
+            None => print!("\n{}", next.text),
+        }
+        let _ = stdout.flush();
+        content = next;
+    }
+
+    if !content.text.is_empty() && !content.text.ends_with('\n') {
+        println!();
+    }
+    Ok(content)
+}
+
+/// Decide the tool calls of a reply with the policy, asking on the terminal
+/// about the ones it leaves to the user.
+///
+/// Without a terminal, those are denied unless `yes` is set.
+fn decide_tool_calls(
+    tool_calls: &mut [ToolCall],
+    policy: &ToolPolicy,
+    yes: bool,
+    on_decided: &mut dyn FnMut(&ToolCall, bool, ToolApproval),
+) -> Vec<ToolCall> {
+    let interactive = std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+
+    for call in tool_calls.iter_mut() {
+        let (permission, approval) = policy.evaluate_with_approval(&call.name, &call.arguments);
+        let approved = match permission {
+            ToolPermission::Allow => true,
+            ToolPermission::Deny => false,
+            ToolPermission::Ask if yes => true,
+            ToolPermission::Ask if interactive => ask(call),
+            ToolPermission::Ask => {
+                eprintln!(
+                    "Denied {}, it needs approval: run in a terminal or pass --yes",
+                    display_name_from_namespaced(&call.name)
+                );
+                false
+            }
+        };
+
+        call.permission_status = if approved {
+            ToolCallPermissionStatus::Approved
+        } else {
+            ToolCallPermissionStatus::Denied
+        };
+        on_decided(call, approved, approval);
+    }
+
+    tool_calls
+        .iter()
+        .filter(|call| call.permission_status == ToolCallPermissionStatus::Approved)
+        .cloned()
+        .collect()
+}
+
+fn ask(call: &ToolCall) -> bool {
+    eprint!(
+        "Run {} with {}? [y/N] ",
+        display_name_from_namespaced(&call.name),
+        serde_json::Value::Object(call.arguments.clone())
+    );
+    let _ = std::io::stderr().flush();
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+fn tool_message(text: &str, tool_results: Vec<ToolResult>) -> Message {
+    Message {
+        from: EntityId::Tool,
+        content: MessageContent {
+            text: text.to_string(),
+            tool_results,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_send() {
+        let Ok(Command::Send(options)) =
+            parse_args(args("send --bot gpt --chat 42 --no-tools hello there"))
+        else {
+            panic!("expected a send command");
+        };
+        assert_eq!(options.bot.as_deref(), Some("gpt"));
+        assert_eq!(options.chat, Some(42));
+        assert!(!options.tools);
+        assert!(!options.yes);
+        assert_eq!(options.prompt.as_deref(), Some("hello there"));
+
+        let Ok(Command::Send(options)) = parse_args(args("send -y -")) else {
+            panic!("expected a send command");
+        };
+        assert!(options.yes && options.tools);
+        assert_eq!(options.prompt, None);
+
+        assert!(parse_args(args("send --chat abc")).is_err());
+        assert!(parse_args(args("send --bot")).is_err());
+        assert!(parse_args(args("bots extra")).is_err());
+        assert!(matches!(parse_args(Vec::new()), Ok(Command::Help)));
+    }
+}
@@ -0,0 +1,165 @@
+//! Client wrapper adapting message attachments to what each bot can read.
+//!
+//! Documents sent to bots without native file support are replaced by their
+//! extracted text, inlined into the message. The original attachments are left
+//! untouched in the chat history; only the copy sent to the bot is modified.
+
+use async_stream::stream;
+use futures::StreamExt;
+use moly_kit::prelude::*;
+use moly_kit::utils::extraction::{self, DocumentKind};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// How an attachment is delivered to a bot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentDelivery {
+    /// Sent as a file, letting the underlying client encode it.
+    Raw,
+    /// Replaced by its extracted text.
+    ExtractedText,
+    /// The bot can't read this attachment in any form.
+    Omitted,
+}
+
+/// Decide how an attachment should reach a bot with the given capabilities.
+///
+/// If the capabilities are unknown, the bot is assumed to accept files.
+pub fn attachment_delivery(
+    attachment: &Attachment,
+    capabilities: Option<&BotCapabilities>,
+) -> AttachmentDelivery {
+    let accepts_files = capabilities
+        .map(|c| c.has_capability(&BotCapability::AttachmentInput))
+        .unwrap_or(true);
+
+    match DocumentKind::for_attachment(attachment) {
+        // PDFs are understood natively by most file-capable providers.
+        Some(DocumentKind::Pdf) if accepts_files => AttachmentDelivery::Raw,
+        Some(_) => AttachmentDelivery::ExtractedText,
+        None if accepts_files => AttachmentDelivery::Raw,
+        None => AttachmentDelivery::Omitted,
+    }
+}
+
+/// Wraps a client to convert documents into text before sending them.
+pub struct AttachmentPreprocessor {
+    inner: Box<dyn BotClient>,
+    /// Capabilities of the bots seen in the last `bots()` call.
+    capabilities: Arc<Mutex<HashMap<BotId, BotCapabilities>>>,
+}
+
+impl Clone for AttachmentPreprocessor {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone_box(),
+            capabilities: self.capabilities.clone(),
+        }
+    }
+}
+
+impl AttachmentPreprocessor {
+    pub fn new(inner: impl BotClient + 'static) -> Self {
+        Self {
+            inner: Box::new(inner),
+            capabilities: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl BotClient for AttachmentPreprocessor {
+    fn bots(&mut self) -> BoxPlatformSendFuture<'static, ClientResult<Vec<Bot>>> {
+        let future = self.inner.bots();
+        let capabilities = self.capabilities.clone();
+
+        Box::pin(async move {
+            let result = future.await;
+
+            if let Some(bots) = result.value() {
+                let mut capabilities = capabilities.lock().unwrap();
+                for bot in bots {
+                    capabilities.insert(bot.id.clone(), bot.capabilities.clone());
+                }
+            }
+
+            result
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn BotClient> {
+        Box::new(self.clone())
+    }
+
+    fn send(
+        &mut self,
+        bot_id: &BotId,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> BoxPlatformSendStream<'static, ClientResult<MessageContent>> {
+        if messages.iter().all(|m| m.content.attachments.is_empty()) {
+            return self.inner.send(bot_id, messages, tools);
+        }
+
+        let capabilities = self.capabilities.lock().unwrap().get(bot_id).cloned();
+        let mut inner = self.inner.clone_box();
+        let bot_id = bot_id.clone();
+        let messages = messages.to_vec();
+        let tools = tools.to_vec();
+
+        let stream = stream! {
+            let messages = prepare_messages(messages, capabilities.as_ref()).await;
+            let mut inner_stream = inner.send(&bot_id, &messages, &tools);
+            while let Some(result) = inner_stream.next().await {
+                yield result;
+            }
+        };
+
+        Box::pin(stream)
+    }
+}
+
+/// Replace the attachments of each message according to [`attachment_delivery`].
+async fn prepare_messages(
+    messages: Vec<Message>,
+    capabilities: Option<&BotCapabilities>,
+) -> Vec<Message> {
+    let mut prepared = Vec::with_capacity(messages.len());
+
+    for mut message in messages {
+        let attachments = std::mem::take(&mut message.content.attachments);
+
+        for attachment in attachments {
+            match attachment_delivery(&attachment, capabilities) {
+                AttachmentDelivery::Raw => message.content.attachments.push(attachment),
+                AttachmentDelivery::ExtractedText => {
+                    match extraction::extract_attachment(&attachment).await {
+                        Ok(text) => {
+                            message.content.text.push_str(&format!(
+                                "\n\n<attachment name=\"{}\">\n{}\n</attachment>",
+                                attachment.name,
+                                text.trim()
+                            ));
+                        }
+                        Err(e) => {
+                            log::warn!("Failed to extract text from {}: {}", attachment.name, e);
+                            message.content.text.push_str(&format!(
+                                "\n\n[Attachment \"{}\" could not be read: {}]",
+                                attachment.name, e
+                            ));
+                        }
+                    }
+                }
+                AttachmentDelivery::Omitted => {
+                    message.content.text.push_str(&format!(
+                        "\n\n[Attachment \"{}\" omitted: this model can't read this kind of file]",
+                        attachment.name
+                    ));
+                }
+            }
+        }
+
+        prepared.push(message);
+    }
+
+    prepared
+}
@@ -0,0 +1,366 @@
+//! Construction of the clients used to talk to the configured providers.
+//!
+//! Shared by the chat screen and the command line, so it only depends on the
+//! loaded [`Chats`] and not on the UI.
+
+use moly_kit::prelude::*;
+use std::collections::HashMap;
+
+use super::attachment_preprocessor::AttachmentPreprocessor;
+use super::bot_fetcher::should_include_bot;
+use super::chats::Chats;
+use super::deep_inquire_client::DeepInquireClient;
+use super::model_registry::ModelRegistry;
+use super::openclaw_client::OpenClawClient;
+use super::providers::{Provider, ProviderBot, ProviderId, ProviderType};
+use super::store::provider_icon;
+use super::supported_providers::{self, SupportedProvider};
+use super::tool_filter::{DisabledTools, ToolFilter};
+
+/// A client routing to every enabled provider with valid credentials.
+pub fn router_client(chats: &Chats, provider_icons: &[String]) -> RouterClient {
+    let router_client = RouterClient::new();
+    let supported_providers_list = supported_providers::load_supported_providers();
+
+    let available_bots = chats.available_bots.clone();
+    let providers = chats.providers.clone();
+
+    // Filter enabled providers upfront and check credentials
+    for (key, provider) in chats
+        .providers
+        .iter()
+        .filter(|(_, p)| p.enabled && has_valid_credentials(p))
+    {
+        let client: Option<Box<dyn BotClient>> = match provider.provider_type {
+            ProviderType::OpenAi | ProviderType::MolyServer | ProviderType::MoFa => {
+                create_openai_client(
+                    provider,
+                    &supported_providers_list,
+                    &available_bots,
+                    &providers,
+                    provider_icons,
+                    &chats.model_registry,
+                    ClientFilter::ChatModels,
+                )
+            }
+            ProviderType::OpenAiImage => create_openai_image_client(
+                provider,
+                &supported_providers_list,
+                &available_bots,
+                &providers,
+                provider_icons,
+                &chats.model_registry,
+            ),
+            ProviderType::OpenAiRealtime => create_openai_realtime_client(provider),
+            ProviderType::DeepInquire => create_deep_inquire_client(
+                provider,
+                &supported_providers_list,
+                &available_bots,
+                &providers,
+                provider_icons,
+                &chats.model_registry,
+            ),
+            ProviderType::OpenClaw => create_openclaw_client(
+                provider,
+                &supported_providers_list,
+                &available_bots,
+                &providers,
+                provider_icons,
+                &chats.model_registry,
+            ),
+        };
+
+        if let Some(client) = client {
+            router_client.insert_client(key, client);
+        }
+    }
+
+    router_client
+}
+
+/// The client chats are sent through: the [`router_client`] with attachments
+/// adapted to each bot and without the disabled MCP tools, nor any tool for
+/// models known not to support them.
+pub fn chat_client(
+    chats: &Chats,
+    provider_icons: &[String],
+    disabled_tools: DisabledTools,
+) -> ToolFilter {
+    let client = AttachmentPreprocessor::new(router_client(chats, provider_icons));
+    ToolFilter::new(client, disabled_tools, chats.model_registry.clone())
+}
+
+type ProviderMap = HashMap<ProviderId, Provider>;
+type BotMap = HashMap<BotId, ProviderBot>;
+
+// Helper types and functions for client creation
+
+#[derive(Clone)]
+enum ClientFilter {
+    // Apply should_include_model filter
+    ChatModels,
+    // Check bot.enabled in available_bots
+    BotEnabled,
+    // No extra filtering beyond provider enabled
+    None,
+}
+
+fn is_localhost(url: &str) -> bool {
+    url.contains("localhost") || url.contains("127.0.0.1")
+}
+
+fn has_valid_credentials(provider: &Provider) -> bool {
+    match provider.provider_type {
+        ProviderType::OpenAi | ProviderType::MolyServer | ProviderType::OpenAiRealtime => {
+            provider.api_key.is_some() || is_localhost(&provider.url)
+        }
+        ProviderType::MoFa
+        | ProviderType::OpenAiImage
+        | ProviderType::DeepInquire
+        | ProviderType::OpenClaw => true,
+    }
+}
+
+fn apply_icon(bots: &mut Vec<Bot>, icon_opt: &Option<String>) {
+    if let Some(icon) = icon_opt {
+        for bot in bots.iter_mut() {
+            bot.avatar = EntityAvatar::Image(icon.clone());
+        }
+    }
+}
+
+/// Correct the capabilities reported by the client with the model registry.
+fn apply_metadata(bots: &mut Vec<Bot>, registry: &ModelRegistry) {
+    for bot in bots.iter_mut() {
+        if let Some(metadata) = registry.lookup(bot.id.as_str()) {
+            bot.capabilities = metadata.capabilities(&bot.capabilities);
+        }
+    }
+}
+
+fn apply_bot_filters(
+    bots: &mut Vec<Bot>,
+    available_bots: &BotMap,
+    providers: &ProviderMap,
+    filter: &ClientFilter,
+    supported_models: &Option<Vec<String>>,
+) {
+    // Filter by provider/bot enabled status
+    if !available_bots.is_empty() {
+        bots.retain(|bot| {
+            if let Some(provider_bot) = available_bots.get(&bot.id) {
+                let provider_enabled = providers
+                    .get(&provider_bot.provider_id)
+                    .map_or(false, |p| p.enabled);
+
+                match filter {
+                    ClientFilter::BotEnabled => provider_bot.enabled && provider_enabled,
+                    _ => provider_enabled,
+                }
+            } else {
+                // Bot not in available_bots yet, let it through
+                true
+            }
+        });
+    }
+
+    // Apply filter type
+    if matches!(filter, ClientFilter::ChatModels) {
+        bots.retain(|bot| should_include_bot(&bot.id));
+    }
+
+    // Apply supported models whitelist
+    if let Some(models) = supported_models {
+        bots.retain(|bot| models.iter().any(|m| m == bot.id.as_str()));
+    }
+}
+
+fn setup_map_client<C: BotClient + 'static>(
+    map_client: &mut MapClient<C>,
+    provider: &Provider,
+    supported_providers_list: &[SupportedProvider],
+    available_bots: &BotMap,
+    providers: &ProviderMap,
+    provider_icons: &[String],
+    registry: &ModelRegistry,
+    filter: ClientFilter,
+) {
+    let supported_models = supported_providers_list
+        .iter()
+        .find(|sp| sp.id == provider.id)
+        .and_then(|sp| sp.supported_models.clone());
+
+    let icon_opt = provider_icon(provider_icons, &provider.name).map(String::from);
+    let available_bots = available_bots.clone();
+    let providers = providers.clone();
+    let registry = registry.clone();
+
+    map_client.set_map_bots(move |mut bots| {
+        apply_bot_filters(
+            &mut bots,
+            &available_bots,
+            &providers,
+            &filter,
+            &supported_models,
+        );
+        apply_icon(&mut bots, &icon_opt);
+        apply_metadata(&mut bots, &registry);
+        bots
+    });
+}
+
+fn create_openai_client(
+    provider: &Provider,
+    supported_providers_list: &[SupportedProvider],
+    available_bots: &BotMap,
+    providers: &ProviderMap,
+    provider_icons: &[String],
+    registry: &ModelRegistry,
+    filter: ClientFilter,
+) -> Option<Box<dyn BotClient>> {
+    let mut client = OpenAiClient::new(provider.url.clone());
+
+    if let Some(key) = provider.api_key.as_ref() {
+        if let Err(e) = client.set_key(key) {
+            eprintln!("Failed to set API key for {}: {}", provider.name, e);
+            return None;
+        }
+    }
+    client.set_tools_enabled(provider.tools_enabled);
+
+    let mut map_client = MapClient::from(client);
+
+    setup_map_client(
+        &mut map_client,
+        provider,
+        supported_providers_list,
+        available_bots,
+        providers,
+        provider_icons,
+        registry,
+        filter,
+    );
+
+    Some(Box::new(map_client))
+}
+
+fn create_openai_image_client(
+    provider: &Provider,
+    supported_providers_list: &[SupportedProvider],
+    available_bots: &BotMap,
+    providers: &ProviderMap,
+    provider_icons: &[String],
+    registry: &ModelRegistry,
+) -> Option<Box<dyn BotClient>> {
+    let client_url = provider.url.trim_start_matches('#').to_string();
+    let mut client = OpenAiImageClient::new(client_url);
+
+    if let Some(key) = provider.api_key.as_ref() {
+        if let Err(e) = client.set_key(key) {
+            eprintln!("Failed to set API key for {}: {}", provider.name, e);
+            return None;
+        }
+    }
+
+    let mut map_client = MapClient::from(client);
+
+    setup_map_client(
+        &mut map_client,
+        provider,
+        supported_providers_list,
+        available_bots,
+        providers,
+        provider_icons,
+        registry,
+        ClientFilter::BotEnabled,
+    );
+
+    Some(Box::new(map_client))
+}
+
+fn create_openai_realtime_client(provider: &Provider) -> Option<Box<dyn BotClient>> {
+    let client_url = provider.url.trim_start_matches('#').to_string();
+    let mut client = OpenAiRealtimeClient::new(client_url);
+
+    if let Some(key) = provider.api_key.as_ref() {
+        if let Err(e) = client.set_key(key) {
+            eprintln!("Failed to set API key for {}: {}", provider.name, e);
+            return None;
+        }
+    }
+    if let Some(prompt) = provider.system_prompt.as_ref() {
+        if let Err(e) = client.set_system_prompt(prompt) {
+            eprintln!("Failed to set system prompt for {}: {}", provider.name, e);
+            return None;
+        }
+    }
+    client.set_tools_enabled(provider.tools_enabled);
+
+    Some(Box::new(client))
+}
+
+fn create_deep_inquire_client(
+    provider: &Provider,
+    supported_providers_list: &[SupportedProvider],
+    available_bots: &BotMap,
+    providers: &ProviderMap,
+    provider_icons: &[String],
+    registry: &ModelRegistry,
+) -> Option<Box<dyn BotClient>> {
+    let mut client = DeepInquireClient::new(provider.url.clone());
+
+    if let Some(key) = provider.api_key.as_ref() {
+        if let Err(e) = client.set_key(key) {
+            eprintln!("Failed to set API key for {}: {}", provider.name, e);
+            return None;
+        }
+    }
+
+    let mut map_client = MapClient::from(client);
+
+    setup_map_client(
+        &mut map_client,
+        provider,
+        supported_providers_list,
+        available_bots,
+        providers,
+        provider_icons,
+        registry,
+        ClientFilter::None,
+    );
+
+    Some(Box::new(map_client))
+}
+
+fn create_openclaw_client(
+    provider: &Provider,
+    supported_providers_list: &[SupportedProvider],
+    available_bots: &BotMap,
+    providers: &ProviderMap,
+    provider_icons: &[String],
+    registry: &ModelRegistry,
+) -> Option<Box<dyn BotClient>> {
+    let mut client = OpenClawClient::new(provider.url.clone());
+
+    if let Some(key) = provider.api_key.as_ref() {
+        if let Err(e) = client.set_key(key) {
+            eprintln!("Failed to set API key for {}: {}", provider.name, e);
+            return None;
+        }
+    }
+
+    let mut map_client = MapClient::from(client);
+
+    setup_map_client(
+        &mut map_client,
+        provider,
+        supported_providers_list,
+        available_bots,
+        providers,
+        provider_icons,
+        registry,
+        ClientFilter::None,
+    );
+
+    Some(Box::new(map_client))
+}
@@ -1,13 +1,31 @@
-use makepad_widgets::Cx;
 use moly_kit::aitk::utils::asynchronous::spawn;
 use moly_kit::prelude::*;
 
+use crate::data::events::{DataEvent, report};
+use crate::data::model_registry::ModelRegistry;
 use crate::data::providers::ProviderId;
 
 use super::providers::{Provider, ProviderBot, ProviderFetchModelsResult, ProviderType};
 
-/// Fetches models for a provider using the appropriate MolyKit client
-pub fn fetch_models_for_provider(provider: &Provider) {
+/// Fetches models for a provider using the appropriate MolyKit client,
+/// reporting the result as a [`DataEvent::ProviderModelsFetched`].
+pub fn fetch_models_for_provider(provider: &Provider, registry: &ModelRegistry) {
+    let provider = provider.clone();
+    let registry = registry.clone();
+    spawn(async move {
+        report(DataEvent::ProviderModelsFetched(
+            fetch_models(&provider, &registry).await,
+        ));
+    });
+}
+
+/// Fetches the models of a provider without posting them to the UI.
+///
+/// The listed models get what `registry` knows about them.
+pub async fn fetch_models(
+    provider: &Provider,
+    registry: &ModelRegistry,
+) -> ProviderFetchModelsResult {
     let provider_id = provider.id.clone();
     let url = provider.url.clone();
     let api_key = provider.api_key.clone();
@@ -30,9 +48,12 @@ pub fn fetch_models_for_provider(provider: &Provider) {
                     provider_id: provider_id.clone(),
                     enabled: true,
                     is_recommended: false,
+                    metadata: None,
                 },
                 Some(should_include_bot),
-            );
+                registry,
+            )
+            .await
         }
         ProviderType::OpenAiImage => {
             fetch_models_with_client(
@@ -52,9 +73,12 @@ pub fn fetch_models_for_provider(provider: &Provider) {
                     provider_id: provider_id.clone(),
                     enabled: true,
                     is_recommended: false,
+                    metadata: None,
                 },
                 None,
-            );
+                registry,
+            )
+            .await
         }
         ProviderType::OpenAiRealtime => {
             fetch_models_with_client(
@@ -74,9 +98,12 @@ pub fn fetch_models_for_provider(provider: &Provider) {
                     provider_id: provider_id.clone(),
                     enabled: true,
                     is_recommended: false,
+                    metadata: None,
                 },
                 None,
-            );
+                registry,
+            )
+            .await
         }
         ProviderType::DeepInquire => {
             fetch_models_with_client(
@@ -95,9 +122,12 @@ pub fn fetch_models_for_provider(provider: &Provider) {
                     provider_id: provider_id.clone(),
                     enabled: true,
                     is_recommended: false,
+                    metadata: None,
                 },
                 None,
-            );
+                registry,
+            )
+            .await
         }
         ProviderType::OpenClaw => {
             fetch_models_with_client(
@@ -116,57 +146,66 @@ pub fn fetch_models_for_provider(provider: &Provider) {
                     provider_id: provider_id.clone(),
                     enabled: true,
                     is_recommended: false,
+                    metadata: None,
                 },
                 None,
-            );
+                registry,
+            )
+            .await
         }
     }
 }
 
 /// Generic function to fetch models using any BotClient implementation
-fn fetch_models_with_client<F, M>(
+async fn fetch_models_with_client<F, M>(
     provider_id: ProviderId,
     client_factory: F,
     map_bot: M,
     filter: Option<fn(&BotId) -> bool>,
-) where
-    F: FnOnce() -> Box<dyn BotClient> + Send + 'static,
-    M: Fn(Bot) -> ProviderBot + Send + 'static,
+    registry: &ModelRegistry,
+) -> ProviderFetchModelsResult
+where
+    F: FnOnce() -> Box<dyn BotClient>,
+    M: Fn(Bot) -> ProviderBot,
 {
-    spawn(async move {
-        let mut client = client_factory();
+    let mut client = client_factory();
 
-        match client.bots().await.into_result() {
-            Ok(bots) => {
-                let models: Vec<ProviderBot> = bots
-                    .into_iter()
-                    .filter(|bot| filter.map_or(true, |f| f(&bot.id)))
-                    .map(|bot| Bot {
+    match client.bots().await.into_result() {
+        Ok(bots) => {
+            let models: Vec<ProviderBot> = bots
+                .into_iter()
+                .filter(|bot| filter.map_or(true, |f| f(&bot.id)))
+                .map(|bot| {
+                    let metadata = registry.lookup(bot.id.as_str());
+                    let bot = Bot {
                         // The client Moly interacts with in the `Store` is a `RouterClient`.
                         // This module is creating specific clients to obtain the bots that will
                         // end up becoming `ProviderBot`s as expected by Moly.
                         // So for now, let's ensure here that ids match.
                         id: RouterClient::prefix(&provider_id, &bot.id),
                         ..bot
-                    })
-                    .map(map_bot)
-                    .collect();
+                    };
+                    ProviderBot {
+                        metadata,
+                        ..map_bot(bot)
+                    }
+                })
+                .collect();
 
-                Cx::post_action(ProviderFetchModelsResult::Success(provider_id, models));
-            }
-            Err(errors) => {
-                let error = if errors.is_empty() {
-                    ClientError::new(
-                        ClientErrorKind::Unknown,
-                        "An error occurred, but no details were provided".to_string(),
-                    )
-                } else {
-                    errors[0].clone()
-                };
-                Cx::post_action(ProviderFetchModelsResult::Failure(provider_id, error));
-            }
+            ProviderFetchModelsResult::Success(provider_id, models)
         }
-    });
+        Err(errors) => {
+            let error = if errors.is_empty() {
+                ClientError::new(
+                    ClientErrorKind::Unknown,
+                    "An error occurred, but no details were provided".to_string(),
+                )
+            } else {
+                errors[0].clone()
+            };
+            ProviderFetchModelsResult::Failure(provider_id, error)
+        }
+    }
 }
 
 /// Filter out non-chat models for OpenAI-compatible providers
@@ -1,6 +1,7 @@
 pub mod chat;
 
 use chat::{Chat, ChatId};
+use chrono::{DateTime, Utc};
 use futures::StreamExt;
 use moly_kit::prelude::*;
 use moly_protocol::data::*;
@@ -9,13 +10,27 @@ use std::{cell::RefCell, path::PathBuf};
 
 use crate::shared::utils::filesystem;
 
+use super::model_registry::ModelRegistry;
 use super::moly_client::MolyClient;
 use super::preferences::Preferences;
+use super::provider_models_cache::CachedProviderModels;
 use super::providers::{
     Provider, ProviderBot, ProviderConnectionStatus, ProviderFetchModelsResult, ProviderId,
     ProviderType, fetch_models_for_provider,
 };
-use super::store::{ProviderSyncing, ProviderSyncingStatus};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProviderSyncingStatus {
+    NotSyncing,
+    Syncing(ProviderSyncing),
+    Synced,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProviderSyncing {
+    pub current: u32,
+    pub total: u32,
+}
 
 pub struct Chats {
     pub moly_client: MolyClient,
@@ -23,9 +38,17 @@ pub struct Chats {
 
     pub available_bots: HashMap<BotId, ProviderBot>,
 
+    /// Bots shown from the cache, with when their provider listed them.
+    ///
+    /// A bot leaves this map once its provider lists it again.
+    pub stale_bots: HashMap<BotId, DateTime<Utc>>,
+
     /// Map of providers keyed by their ID
     pub providers: HashMap<ProviderId, Provider>,
 
+    /// What is known about models, merged into the bots as they are listed.
+    pub model_registry: ModelRegistry,
+
     /// Set it thru `set_current_chat` method to trigger side effects.
     current_chat_id: Option<ChatId>,
     chats_dir: PathBuf,
@@ -43,13 +66,16 @@ impl Chats {
             current_chat_id: None,
             chats_dir: PathBuf::from("chats"),
             available_bots: HashMap::new(),
+            stale_bots: HashMap::new(),
             providers: HashMap::new(),
+            model_registry: ModelRegistry::bundled(),
             unknown_bot: ProviderBot::unknown(),
         }
     }
 
     pub async fn load(moly_client: MolyClient) -> Self {
         let mut chats = Chats::new(moly_client);
+        chats.model_registry = ModelRegistry::load().await;
 
         let fs = filesystem::global();
         let paths = fs
@@ -197,7 +223,7 @@ impl Chats {
         }
 
         let provider = self.providers.get(provider_id).unwrap();
-        fetch_models_for_provider(provider);
+        fetch_models_for_provider(provider, &self.model_registry);
     }
 
     /// Handle the result of a provider fetching models operation.
@@ -253,22 +279,13 @@ impl Chats {
                         true
                     };
 
-                    if let Some(pref_entry) = preferences
-                        .providers_preferences
-                        .iter()
-                        .find(|pp| pp.id == provider_id)
+                    if let Some(enabled) =
+                        preferred_enabled(preferences, &provider_id, &provider_bot)
                     {
-                        // if there's a matching "(model_name, enabled)" in preferences, apply it
-                        if let Some((_, enabled_val)) = pref_entry.models.iter().find(|(m, _)| {
-                            *m == provider_bot.name
-                                || RouterClient::unprefix(&provider_bot.id)
-                                    .map(|(_, id)| id.as_str() == *m)
-                                    .unwrap_or(false)
-                        }) {
-                            final_enabled = *enabled_val;
-                        }
+                        final_enabled = enabled;
                     }
                     provider_bot.enabled = final_enabled;
+                    self.stale_bots.remove(&provider_bot.id);
 
                     // Add it to the provider record, only if it's not already in there
                     if !self
@@ -369,9 +386,52 @@ impl Chats {
     pub fn remove_provider(&mut self, provider_id: &ProviderId) {
         self.available_bots
             .retain(|_, model| model.provider_id != *provider_id);
+        let available_bots = &self.available_bots;
+        self.stale_bots
+            .retain(|bot_id, _| available_bots.contains_key(bot_id));
         self.providers.remove(provider_id);
     }
 
+    /// Show the cached models of a provider until it lists its models again.
+    ///
+    /// Bots the provider already listed in this session are left as they are.
+    pub fn insert_cached_models(
+        &mut self,
+        provider_id: &ProviderId,
+        cached: &CachedProviderModels,
+        preferences: &Preferences,
+    ) {
+        let Some(provider) = self.providers.get_mut(provider_id) else {
+            return;
+        };
+
+        for bot in &cached.bots {
+            if self.available_bots.contains_key(&bot.id) {
+                continue;
+            }
+
+            let mut bot = bot.clone();
+            // The registry may have changed since the models were listed.
+            bot.metadata = self.model_registry.lookup_bot(&bot.id);
+            if let Some(enabled) = preferred_enabled(preferences, provider_id, &bot) {
+                bot.enabled = enabled;
+            }
+            if !provider.models.contains(&bot.id) {
+                provider.models.push(bot.id.clone());
+            }
+            self.stale_bots.insert(bot.id.clone(), cached.fetched_at);
+            self.available_bots.insert(bot.id.clone(), bot);
+        }
+    }
+
+    /// The bots of a provider it listed in this session, not those from the cache.
+    pub fn get_listed_provider_models(&self, provider_id: &ProviderId) -> Vec<ProviderBot> {
+        self.get_provider_models(provider_id)
+            .into_iter()
+            .filter(|bot| !self.stale_bots.contains_key(&bot.id))
+            .collect()
+    }
+
     /// Returns a list of remote models for a given server address.
     pub fn get_provider_models(&self, provider_id: &ProviderId) -> Vec<ProviderBot> {
         let provider = self.providers.get(provider_id);
@@ -391,10 +451,8 @@ impl Chats {
     /// if the remote model is not found in the available remote models list.
     ///
     /// This is useful when dealing with historical chat references to remote models that may
-    /// no longer be available (e.g., server disconnected or remote model deleted).
-    ///
-    /// In the future, we'll want a more sophisticated solution, by potentially storing
-    /// remote model information locally and updating it when a server is connected.
+    /// no longer be available (e.g., remote model deleted, or a provider never listed
+    /// and not cached).
     pub fn get_bot_or_placeholder(&self, bot_id: &BotId) -> &ProviderBot {
         self.available_bots.get(bot_id).unwrap_or(&self.unknown_bot)
     }
@@ -498,3 +556,27 @@ impl Chats {
             .map(|m| m.id.clone())
     }
 }
+
+/// Whether the user enabled or disabled `bot` in the preferences of its provider.
+fn preferred_enabled(
+    preferences: &Preferences,
+    provider_id: &str,
+    bot: &ProviderBot,
+) -> Option<bool> {
+    let pref_entry = preferences
+        .providers_preferences
+        .iter()
+        .find(|pp| pp.id == provider_id)?;
+
+    // if there's a matching "(model_name, enabled)" in preferences, apply it
+    pref_entry
+        .models
+        .iter()
+        .find(|(m, _)| {
+            *m == bot.name
+                || RouterClient::unprefix(&bot.id)
+                    .map(|(_, id)| id.as_str() == *m)
+                    .unwrap_or(false)
+        })
+        .map(|(_, enabled)| *enabled)
+}
@@ -1,17 +1,26 @@
-use crate::data::moly_client::MolyClient;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::data::moly_client::{MolyClient, ProgressStreamEnd};
 use makepad_widgets::Cx;
-use moly_kit::aitk::utils::asynchronous::spawn;
-use moly_protocol::{data::*, protocol::FileDownloadResponse};
+use moly_kit::aitk::utils::asynchronous::{sleep, spawn};
+use moly_protocol::data::*;
+
+use super::progress::TransferRate;
 
 #[derive(Debug)]
 pub struct DownloadFileAction {
     pub file_id: FileId,
+    /// Follower that reported it, see [`Download::generation`].
+    generation: u64,
     kind: DownloadFileActionKind,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum DownloadFileActionKind {
     Progress(f64),
+    /// The progress stream dropped, waiting to reach it again.
+    Reconnecting,
     Error,
     StreamingDone,
 }
@@ -20,23 +29,52 @@ enum DownloadFileActionKind {
 pub enum DownloadState {
     Initializing(f64),
     Downloading(f64),
+    Reconnecting(f64),
     Errored(f64),
     Completed,
 }
 
+/// How the progress stream of a download is reached again after it drops.
+#[derive(Clone, Copy, Debug)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Attempts in a row without progress before the download is failed.
+    pub max_attempts: u32,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            max_attempts: 10,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Download {
     pub file: File,
     pub state: DownloadState,
     pub notification_pending: bool,
+    pub rate: TransferRate,
+    /// Identifies the task following the download, so reports of a follower
+    /// left over from a pause or a previous start are ignored.
+    generation: u64,
 }
 
+/// Source of follower generations, unique across downloads.
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
 impl Download {
     pub fn new(file: File, progress: f64, moly_client: MolyClient) -> Self {
         let mut download = Self {
             file,
             state: DownloadState::Initializing(progress),
             notification_pending: false,
+            rate: TransferRate::default(),
+            generation: 0,
         };
 
         download.start(moly_client);
@@ -44,83 +82,53 @@ impl Download {
     }
 
     pub fn start(&mut self, moly_client: MolyClient) {
-        use futures::{StreamExt, channel::mpsc::unbounded};
-
-        let (tx, mut rx) = unbounded();
-        let file_id = self.file.id.clone();
-        let moly_client_clone = moly_client.clone();
+        let file = self.file.clone();
+        let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+        self.generation = generation;
         spawn(async move {
-            if let Some(Ok(())) = rx.next().await {
-                // Download started successfully, now track progress
-                let (progress_tx, mut progress_rx) = unbounded();
-
-                // Create a new task for tracking progress
-                let file_id_clone = file_id.clone();
-
-                spawn(async move {
-                    moly_client_clone
-                        .track_download_progress(file_id_clone, progress_tx)
-                        .await
-                });
-
-                // Wait for progress updates
-                loop {
-                    match progress_rx.next().await {
-                        Some(result) => match result {
-                            Ok(response) => match response {
-                                FileDownloadResponse::Completed(_completed) => {
-                                    Cx::post_action(DownloadFileAction {
-                                        file_id: file_id.clone(),
-                                        kind: DownloadFileActionKind::StreamingDone,
-                                    });
-                                    break;
-                                }
-                                FileDownloadResponse::Progress(_file, value) => {
-                                    Cx::post_action(DownloadFileAction {
-                                        file_id: file_id.clone(),
-                                        kind: DownloadFileActionKind::Progress(value as f64),
-                                    })
-                                }
-                            },
-                            Err(err) => {
-                                Cx::post_action(DownloadFileAction {
-                                    file_id: file_id.clone(),
-                                    kind: DownloadFileActionKind::Error,
-                                });
-                                eprintln!("Error downloading file: {:?}", err);
-                                break;
-                            }
-                        },
-                        None => break,
-                    }
-                }
-            } else {
-                // Initial download request failed
+            let file_id = file.id.clone();
+            let post = |kind| {
                 Cx::post_action(DownloadFileAction {
                     file_id: file_id.clone(),
-                    kind: DownloadFileActionKind::Error,
-                });
+                    generation,
+                    kind,
+                })
+            };
+
+            if let Err(err) = moly_client.download_file(file).await {
+                eprintln!("Error downloading file: {:?}", err);
+                post(DownloadFileActionKind::Error);
+                return;
             }
-        });
 
-        // Start the download
-        let file = self.file.clone();
-        let moly_client_clone = moly_client.clone();
-        spawn(async move {
-            let result = moly_client_clone.download_file(file).await;
-            tx.unbounded_send(result).unwrap();
+            follow_download(
+                &moly_client,
+                file_id.clone(),
+                ReconnectPolicy::default(),
+                post,
+            )
+            .await;
         });
     }
 
     pub fn handle_action(&mut self, action: &DownloadFileAction) {
+        if action.generation != self.generation {
+            return;
+        }
+
         match action.kind {
             DownloadFileActionKind::StreamingDone => {
                 self.state = DownloadState::Completed;
                 self.notification_pending = true;
             }
             DownloadFileActionKind::Progress(value) => {
+                self.rate.record(Cx::time_now(), value);
                 self.state = DownloadState::Downloading(value)
             }
+            DownloadFileActionKind::Reconnecting => {
+                self.rate.reset();
+                self.state = DownloadState::Reconnecting(self.get_progress());
+            }
             DownloadFileActionKind::Error => {
                 let current_progress = self.get_progress();
                 self.state = DownloadState::Errored(current_progress);
@@ -133,6 +141,20 @@ impl Download {
         matches!(self.state, DownloadState::Initializing(..))
     }
 
+    pub fn is_reconnecting(&self) -> bool {
+        matches!(self.state, DownloadState::Reconnecting(..))
+    }
+
+    /// Whether the download takes one of the slots of concurrent downloads.
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self.state,
+            DownloadState::Initializing(..)
+                | DownloadState::Downloading(..)
+                | DownloadState::Reconnecting(..)
+        )
+    }
+
     pub fn is_complete(&self) -> bool {
         matches!(self.state, DownloadState::Completed)
     }
@@ -141,11 +163,22 @@ impl Download {
         match self.state {
             DownloadState::Initializing(progress) => progress,
             DownloadState::Downloading(progress) => progress,
+            DownloadState::Reconnecting(progress) => progress,
             DownloadState::Errored(progress) => progress,
             DownloadState::Completed => 1.0,
         }
     }
 
+    /// Bytes per second and seconds left, once the speed is known.
+    pub fn speed_and_eta(&self) -> Option<(f64, Option<f64>)> {
+        if !matches!(self.state, DownloadState::Downloading(..)) {
+            return None;
+        }
+        let total_bytes = self.file.size.parse::<f64>().ok()?;
+        let speed = self.rate.bytes_per_second(total_bytes)?;
+        Some((speed, self.rate.eta_seconds(total_bytes)))
+    }
+
     pub fn must_show_notification(&mut self) -> bool {
         if self.notification_pending {
             self.notification_pending = false;
@@ -155,3 +188,270 @@ impl Download {
         }
     }
 }
+
+/// What the server says of a download after its progress stream dropped.
+#[derive(Debug)]
+enum Reconciled {
+    Downloading(f64),
+    Downloaded,
+    /// Paused by the user, there is nothing left to follow.
+    Paused,
+    /// Failed, or no longer known to the server.
+    Failed,
+}
+
+/// Follow a started download until it completes or fails, reaching its progress
+/// stream again each time the connection drops.
+///
+/// A dropped stream says nothing of how the download is doing, so the downloads
+/// and files of the server are checked before following it again.
+async fn follow_download(
+    moly_client: &MolyClient,
+    file_id: FileId,
+    policy: ReconnectPolicy,
+    mut report: impl FnMut(DownloadFileActionKind),
+) {
+    let mut attempts = 0;
+    let mut delay = policy.initial_delay;
+
+    loop {
+        let mut received = false;
+        let end = moly_client
+            .track_download_progress(file_id.clone(), |value| {
+                received = true;
+                report(DownloadFileActionKind::Progress(value));
+            })
+            .await;
+
+        match end {
+            ProgressStreamEnd::Completed => {
+                report(DownloadFileActionKind::StreamingDone);
+                return;
+            }
+            ProgressStreamEnd::Failed => {
+                report(DownloadFileActionKind::Error);
+                return;
+            }
+            ProgressStreamEnd::Disconnected => {}
+        }
+
+        if received {
+            attempts = 0;
+            delay = policy.initial_delay;
+        }
+        attempts += 1;
+        if attempts > policy.max_attempts {
+            eprintln!("Gave up following the download of {}", file_id);
+            report(DownloadFileActionKind::Error);
+            return;
+        }
+
+        report(DownloadFileActionKind::Reconnecting);
+        sleep(delay).await;
+        delay = (delay * 2).min(policy.max_delay);
+
+        match reconcile(moly_client, &file_id).await {
+            Some(Reconciled::Downloading(progress)) => {
+                report(DownloadFileActionKind::Progress(progress));
+            }
+            Some(Reconciled::Downloaded) => {
+                report(DownloadFileActionKind::StreamingDone);
+                return;
+            }
+            Some(Reconciled::Failed) => {
+                report(DownloadFileActionKind::Error);
+                return;
+            }
+            // Pausing already updated the download, and resuming it starts
+            // a new follower.
+            Some(Reconciled::Paused) => return,
+            // The server can't be reached, try again later.
+            None => {}
+        }
+    }
+}
+
+async fn reconcile(moly_client: &MolyClient, file_id: &FileId) -> Option<Reconciled> {
+    let pending = moly_client.get_current_downloads().await.ok()?;
+    if let Some(download) = pending.iter().find(|d| d.file.id == *file_id) {
+        return Some(match download.status {
+            PendingDownloadsStatus::Error => Reconciled::Failed,
+            PendingDownloadsStatus::Paused => Reconciled::Paused,
+            _ => Reconciled::Downloading(download.progress),
+        });
+    }
+
+    let downloaded = moly_client.get_downloaded_files().await.ok()?;
+    if downloaded.iter().any(|f| f.file.id == *file_id) {
+        Some(Reconciled::Downloaded)
+    } else {
+        Some(Reconciled::Failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::extract::State;
+    use axum::response::Json;
+    use axum::routing::get;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    const FILE_ID: &str = "author/model/model.Q4_K_M.gguf";
+
+    /// What happened to the download while its progress stream was dropped.
+    #[derive(Clone, Copy)]
+    enum Outcome {
+        StillDownloading,
+        Paused,
+        Downloaded,
+        Lost,
+    }
+
+    struct MockState {
+        outcome: Outcome,
+        progress_requests: usize,
+    }
+
+    type Shared = Arc<Mutex<MockState>>;
+
+    /// A stand-in MolyServer whose first progress stream drops mid-download.
+    async fn start_mock_server(outcome: Outcome) -> String {
+        let state = Arc::new(Mutex::new(MockState {
+            outcome,
+            progress_requests: 0,
+        }));
+
+        let app = Router::new()
+            .route("/downloads", get(downloads))
+            .route("/downloads/:id/progress", get(progress))
+            .route("/files", get(files))
+            .with_state(state);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        base
+    }
+
+    fn file() -> File {
+        File {
+            id: FILE_ID.to_string(),
+            ..Default::default()
+        }
+    }
+
+    async fn progress(State(state): State<Shared>) -> Body {
+        let first = {
+            let mut state = state.lock().unwrap();
+            state.progress_requests += 1;
+            state.progress_requests == 1
+        };
+
+        let chunks: Vec<Result<&'static str, std::io::Error>> = if first {
+            vec![
+                Ok("event: progress\ndata: 20"),
+                Ok(".0\n\n"),
+                Err(std::io::Error::other("connection reset")),
+            ]
+        } else {
+            vec![
+                Ok("event: progress\ndata: 60.0\n\n"),
+                Ok("event: complete\ndata: done\n\n"),
+            ]
+        };
+        Body::from_stream(futures::stream::iter(chunks))
+    }
+
+    async fn downloads(State(state): State<Shared>) -> Json<Vec<PendingDownload>> {
+        let status = match state.lock().unwrap().outcome {
+            Outcome::StillDownloading => PendingDownloadsStatus::Downloading,
+            Outcome::Paused => PendingDownloadsStatus::Paused,
+            Outcome::Downloaded | Outcome::Lost => return Json(vec![]),
+        };
+        let pending = vec![PendingDownload {
+            file: file(),
+            model: Model::default(),
+            progress: 40.0,
+            status,
+        }];
+        Json(pending)
+    }
+
+    async fn files(State(state): State<Shared>) -> Json<Vec<DownloadedFile>> {
+        let files = match state.lock().unwrap().outcome {
+            Outcome::Downloaded => vec![DownloadedFile {
+                file: file(),
+                ..Default::default()
+            }],
+            Outcome::StillDownloading | Outcome::Paused | Outcome::Lost => vec![],
+        };
+        Json(files)
+    }
+
+    fn follow(outcome: Outcome) -> Vec<DownloadFileActionKind> {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let moly_client = MolyClient::new(start_mock_server(outcome).await);
+            let policy = ReconnectPolicy {
+                initial_delay: Duration::from_millis(10),
+                max_delay: Duration::from_millis(50),
+                max_attempts: 3,
+            };
+
+            let mut reported = Vec::new();
+            follow_download(&moly_client, FILE_ID.to_string(), policy, |kind| {
+                reported.push(kind)
+            })
+            .await;
+            reported
+        })
+    }
+
+    #[test]
+    fn test_reconnect_after_drop() {
+        use DownloadFileActionKind::*;
+
+        assert_eq!(
+            follow(Outcome::StillDownloading),
+            vec![
+                Progress(20.0),
+                Reconnecting,
+                Progress(40.0),
+                Progress(60.0),
+                StreamingDone
+            ]
+        );
+        assert_eq!(follow(Outcome::Paused), vec![Progress(20.0), Reconnecting]);
+        assert_eq!(
+            follow(Outcome::Downloaded),
+            vec![Progress(20.0), Reconnecting, StreamingDone]
+        );
+        assert_eq!(
+            follow(Outcome::Lost),
+            vec![Progress(20.0), Reconnecting, Error]
+        );
+    }
+
+    #[test]
+    fn test_stale_follower_is_ignored() {
+        let mut download = Download {
+            file: file(),
+            state: DownloadState::Downloading(0.5),
+            notification_pending: false,
+            rate: TransferRate::default(),
+            generation: 2,
+        };
+
+        download.handle_action(&DownloadFileAction {
+            file_id: FILE_ID.to_string(),
+            generation: 1,
+            kind: DownloadFileActionKind::Error,
+        });
+        assert!(matches!(download.state, DownloadState::Downloading(..)));
+        assert!(!download.notification_pending);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod download;
+pub mod progress;
 
 use download::{Download, DownloadFileAction, DownloadState};
 use makepad_widgets::Action;
@@ -6,33 +7,176 @@ use moly_kit::aitk::utils::asynchronous::spawn;
 use moly_protocol::data::{
     DownloadedFile, File, FileId, Model, PendingDownload, PendingDownloadsStatus,
 };
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 use crate::app::app_runner;
+use crate::shared::utils::filesystem;
 
 use super::moly_client::MolyClient;
+use super::moly_servers::LOCAL_SERVER_ID;
+
+const QUEUE_DIR: &str = "downloads";
+const QUEUE_FILENAME: &str = "queue.json";
+
+/// Downloads running at the same time when not configured.
+pub const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 2;
 
 #[derive(Debug)]
 pub enum DownloadPendingNotification {
     DownloadedFile(File),
     DownloadErrored(File),
 }
+
+/// A download waiting for a free slot, kept across sessions.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueuedDownload {
+    pub model: Model,
+    pub file: File,
+}
+
+/// A download as shown in the list of downloads.
+#[derive(Clone, Debug)]
+pub struct DownloadListing {
+    pub download: PendingDownload,
+    /// Place in the queue, from 1, while waiting for a free slot.
+    pub queue_position: Option<usize>,
+    pub reconnecting: bool,
+    /// Bytes per second and seconds left, once known.
+    pub speed_and_eta: Option<(f64, Option<f64>)>,
+}
+
 pub struct Downloads {
+    /// The MolyServer the downloads are made by, see [`MolyServers`].
+    ///
+    /// [`MolyServers`]: super::moly_servers::MolyServers
+    server_id: String,
     pub moly_client: MolyClient,
     pub downloaded_files: Vec<DownloadedFile>,
     pub pending_downloads: Vec<PendingDownload>,
     pub current_downloads: HashMap<FileId, Download>,
     pub pending_notifications: Vec<DownloadPendingNotification>,
+    /// Downloads started by the user that wait for others to finish, in order.
+    queue: Vec<QueuedDownload>,
+    max_concurrent: usize,
 }
 
 impl Downloads {
-    pub fn new(moly_client: MolyClient) -> Self {
+    pub fn new(
+        server_id: String,
+        moly_client: MolyClient,
+        queue: Vec<QueuedDownload>,
+        max_concurrent: usize,
+    ) -> Self {
         Self {
+            server_id,
             moly_client,
             downloaded_files: Vec::new(),
             pending_downloads: Vec::new(),
             current_downloads: HashMap::new(),
             pending_notifications: Vec::new(),
+            queue,
+            max_concurrent: max_concurrent.max(1),
+        }
+    }
+
+    /// The queue of the server `server_id` saved by the last session.
+    pub async fn load_queue(server_id: &str) -> Vec<QueuedDownload> {
+        filesystem::global()
+            .read_json(&queue_path(server_id))
+            .await
+            .unwrap_or_default()
+    }
+
+    /// Restore the saved queue, then the downloads of the server.
+    ///
+    /// For servers activated after launch, which start with an empty queue.
+    pub fn load_saved_queue(&mut self) {
+        let server_id = self.server_id.clone();
+        let moly_client = self.moly_client.clone();
+        spawn(async move {
+            let queue = Self::load_queue(&server_id).await;
+            app_runner().defer(move |app, _, _| {
+                let Some(me) = app.store.as_mut().unwrap().downloads_of(&moly_client) else {
+                    return;
+                };
+                for queued in queue {
+                    if !me.is_queued(&queued.file.id) {
+                        me.queue.push(queued);
+                    }
+                }
+                me.load_pending_downloads();
+            });
+        });
+    }
+
+    fn save_queue(&self) {
+        let queue = self.queue.clone();
+        let path = queue_path(&self.server_id);
+        spawn(async move {
+            if let Err(e) = filesystem::global().queue_write_json(path, &queue).await {
+                log::error!("Failed to write the download queue: {:?}", e);
+            }
+        });
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    pub fn set_max_concurrent(&mut self, max_concurrent: usize) {
+        // Downloads over a lowered limit keep going, the queue waits for them.
+        self.max_concurrent = max_concurrent.max(1);
+        self.start_queued();
+    }
+
+    pub fn is_queued(&self, file_id: &FileId) -> bool {
+        self.queue.iter().any(|q| q.file.id == *file_id)
+    }
+
+    pub fn queued_count(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Downloads running now, including those still starting.
+    pub fn active_count(&self) -> usize {
+        self.current_downloads
+            .values()
+            .filter(|d| d.is_active())
+            .count()
+    }
+
+    /// Start queued downloads while there are free slots.
+    fn start_queued(&mut self) {
+        // Without the server, they would all fail one after the other.
+        if !self.moly_client.is_connected() {
+            return;
+        }
+
+        let mut started = false;
+        while self.active_count() < self.max_concurrent && !self.queue.is_empty() {
+            let queued = self.queue.remove(0);
+            self.start_download(queued.model, queued.file);
+            started = true;
+        }
+
+        if started {
+            self.save_queue();
+        }
+    }
+
+    pub fn listing(&self, download: &PendingDownload) -> DownloadListing {
+        let current = self.current_downloads.get(&download.file.id);
+        DownloadListing {
+            download: download.clone(),
+            queue_position: self
+                .queue
+                .iter()
+                .position(|q| q.file.id == download.file.id)
+                .map(|index| index + 1),
+            reconnecting: current.is_some_and(|d| d.is_reconnecting()),
+            speed_and_eta: current.and_then(|d| d.speed_and_eta()),
         }
     }
 
@@ -40,8 +184,10 @@ impl Downloads {
         let moly_client = self.moly_client.clone();
         spawn(async move {
             let response = moly_client.get_downloaded_files().await;
-            app_runner().defer(|app, _, _| {
-                let me = &mut app.store.as_mut().unwrap().downloads;
+            app_runner().defer(move |app, _, _| {
+                let Some(me) = app.store.as_mut().unwrap().downloads_of(&moly_client) else {
+                    return;
+                };
                 match response {
                     Ok(files) => {
                         me.downloaded_files = files;
@@ -61,12 +207,30 @@ impl Downloads {
         spawn(async move {
             let response = moly_client.get_current_downloads().await;
 
-            app_runner().defer(|app, _, _| {
-                let me = &mut app.store.as_mut().unwrap().downloads;
+            app_runner().defer(move |app, _, _| {
+                let Some(me) = app.store.as_mut().unwrap().downloads_of(&moly_client) else {
+                    return;
+                };
                 match response {
                     Ok(files) => {
                         me.pending_downloads = files;
 
+                        // Queued downloads that never started are unknown to the server.
+                        for queued in &me.queue {
+                            if !me
+                                .pending_downloads
+                                .iter()
+                                .any(|d| d.file.id == queued.file.id)
+                            {
+                                me.pending_downloads.push(PendingDownload {
+                                    file: queued.file.clone(),
+                                    model: queued.model.clone(),
+                                    progress: 0.0,
+                                    status: PendingDownloadsStatus::Paused,
+                                });
+                            }
+                        }
+
                         me.pending_downloads
                             .sort_by(|a, b| b.file.id.cmp(&a.file.id));
 
@@ -81,6 +245,8 @@ impl Downloads {
                                 }
                             }
                         });
+
+                        me.start_queued();
                     }
                     Err(_err) => {
                         eprintln!(
@@ -92,7 +258,40 @@ impl Downloads {
         });
     }
 
+    /// Start downloading `file`, or queue it when enough downloads are running.
     pub fn download_file(&mut self, model: Model, file: File) {
+        if self.is_queued(&file.id)
+            || self
+                .current_downloads
+                .get(&file.id)
+                .is_some_and(|d| d.is_active())
+        {
+            return;
+        }
+
+        if self.active_count() < self.max_concurrent {
+            self.start_download(model, file);
+            return;
+        }
+
+        match self
+            .pending_downloads
+            .iter_mut()
+            .find(|d| d.file.id == file.id)
+        {
+            Some(pending) => pending.status = PendingDownloadsStatus::Paused,
+            None => self.pending_downloads.push(PendingDownload {
+                file: file.clone(),
+                model: model.clone(),
+                progress: 0.0,
+                status: PendingDownloadsStatus::Paused,
+            }),
+        }
+        self.queue.push(QueuedDownload { model, file });
+        self.save_queue();
+    }
+
+    fn start_download(&mut self, model: Model, file: File) {
         let mut current_progress = 0.0;
 
         if let Some(pending) = self
@@ -152,6 +351,13 @@ impl Downloads {
     }
 
     pub fn pause_download_file(&mut self, file_id: &FileId) {
+        if self.is_queued(file_id) {
+            // Not started yet, leaving the queue is enough.
+            self.queue.retain(|q| q.file.id != *file_id);
+            self.save_queue();
+            return;
+        }
+
         let Some(current_download) = self.current_downloads.get(file_id) else {
             return;
         };
@@ -167,13 +373,17 @@ impl Downloads {
             match response {
                 Ok(()) => {
                     app_runner().defer(move |app, _, _| {
-                        let me = &mut app.store.as_mut().unwrap().downloads;
+                        let Some(me) = app.store.as_mut().unwrap().downloads_of(&moly_client)
+                        else {
+                            return;
+                        };
                         me.current_downloads.remove(&file_id);
                         me.pending_downloads.iter_mut().for_each(|d| {
                             if d.file.id == *file_id {
                                 d.status = PendingDownloadsStatus::Paused;
                             }
                         });
+                        me.start_queued();
                     });
                 }
                 Err(err) => eprintln!("Error pausing download: {:?}", err),
@@ -188,17 +398,28 @@ impl Downloads {
             }
         };
 
+        if self.is_queued(file_id) {
+            // The server only knows it if it was paused before being queued, so
+            // it's removed here whatever the server answers.
+            self.queue.retain(|q| q.file.id != *file_id);
+            self.save_queue();
+            self.pending_downloads.retain(|d| d.file.id != *file_id);
+        }
+
         let file_id = file_id.clone();
         let moly_client = self.moly_client.clone();
 
         spawn(async move {
             let response = moly_client.cancel_download_file(file_id.clone()).await;
             app_runner().defer(move |app, _, _| {
-                let me = &mut app.store.as_mut().unwrap().downloads;
+                let Some(me) = app.store.as_mut().unwrap().downloads_of(&moly_client) else {
+                    return;
+                };
                 match response {
                     Ok(()) => {
                         me.current_downloads.remove(&file_id);
                         me.pending_downloads.retain(|d| d.file.id != *file_id);
+                        me.start_queued();
                     }
                     Err(err) => eprintln!("Error cancelling download: {:?}", err),
                 }
@@ -244,7 +465,7 @@ impl Downloads {
                     DownloadState::Initializing(_) => {
                         pending.status = PendingDownloadsStatus::Initializing;
                     }
-                    DownloadState::Downloading(_) => {
+                    DownloadState::Downloading(_) | DownloadState::Reconnecting(_) => {
                         pending.status = PendingDownloadsStatus::Downloading;
                     }
                     DownloadState::Errored(_) => {
@@ -281,6 +502,18 @@ impl Downloads {
             self.load_pending_downloads();
         }
 
+        // Completed and failed downloads free their slots.
+        self.start_queued();
+
         completed_download_ids
     }
 }
+
+/// Where the queue of the server `server_id` is saved.
+fn queue_path(server_id: &str) -> PathBuf {
+    if server_id == LOCAL_SERVER_ID {
+        Path::new(QUEUE_DIR).join(QUEUE_FILENAME)
+    } else {
+        Path::new(QUEUE_DIR).join(format!("queue-{}.json", server_id))
+    }
+}
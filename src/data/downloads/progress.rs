@@ -0,0 +1,182 @@
+//! Reading the progress streams of MolyServer, and the speed downloads go at.
+
+use std::collections::VecDeque;
+
+/// An event of the progress stream MolyServer sends for a download.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProgressEvent {
+    /// Percentage downloaded, from 0 to 100.
+    Progress(f64),
+    Complete,
+    Error,
+}
+
+/// Parser of server-sent events, fed as chunks arrive. Yields the name and the
+/// data of each event.
+///
+/// Chunks can end anywhere, even inside a character, so lines are only decoded
+/// once they are complete.
+#[derive(Debug, Default)]
+pub struct EventParser {
+    buffer: Vec<u8>,
+    event: String,
+}
+
+impl EventParser {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<(String, String)> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+
+            if line.is_empty() {
+                // A blank line ends the event.
+                self.event.clear();
+            } else if let Some(event) = line.strip_prefix("event:") {
+                self.event = event.trim().to_string();
+            } else if let Some(data) = line.strip_prefix("data:") {
+                events.push((self.event.clone(), data.trim().to_string()));
+            }
+        }
+        events
+    }
+}
+
+/// Parser of the progress stream of a download.
+#[derive(Debug, Default)]
+pub struct ProgressParser {
+    events: EventParser,
+}
+
+impl ProgressParser {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<ProgressEvent> {
+        self.events
+            .push(chunk)
+            .into_iter()
+            .filter_map(|(event, data)| match event.as_str() {
+                "progress" => data.parse().ok().map(ProgressEvent::Progress),
+                "complete" => Some(ProgressEvent::Complete),
+                "error" => Some(ProgressEvent::Error),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Samples older than this are left out of the speed.
+const RATE_WINDOW_SECS: f64 = 10.0;
+/// Speed isn't given until the samples cover this much time.
+const RATE_MIN_SPAN_SECS: f64 = 1.0;
+
+/// Speed of a download, from its progress over the last seconds.
+#[derive(Clone, Debug, Default)]
+pub struct TransferRate {
+    /// Time in seconds and percentage downloaded, oldest first.
+    samples: VecDeque<(f64, f64)>,
+}
+
+impl TransferRate {
+    pub fn record(&mut self, time: f64, progress: f64) {
+        // The download started over, what came before says nothing of its speed.
+        if self
+            .samples
+            .back()
+            .is_some_and(|(_, last)| progress < *last)
+        {
+            self.samples.clear();
+        }
+
+        self.samples.push_back((time, progress));
+        while self.samples.len() > 2
+            && self
+                .samples
+                .front()
+                .is_some_and(|(first, _)| time - first > RATE_WINDOW_SECS)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Forget the samples, for when the download stopped for a while.
+    pub fn reset(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn bytes_per_second(&self, total_bytes: f64) -> Option<f64> {
+        let (first_time, first_progress) = *self.samples.front()?;
+        let (last_time, last_progress) = *self.samples.back()?;
+        let span = last_time - first_time;
+        if span < RATE_MIN_SPAN_SECS {
+            return None;
+        }
+        Some((last_progress - first_progress) / 100.0 * total_bytes / span)
+    }
+
+    /// Seconds left at the current speed.
+    pub fn eta_seconds(&self, total_bytes: f64) -> Option<f64> {
+        let speed = self.bytes_per_second(total_bytes)?;
+        if speed <= 0.0 {
+            return None;
+        }
+        let (_, progress) = *self.samples.back()?;
+        Some((100.0 - progress).max(0.0) / 100.0 * total_bytes / speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_split_chunks() {
+        let mut parser = ProgressParser::default();
+        assert!(parser.push(b"event: progr").is_empty());
+        assert_eq!(
+            parser.push(b"ess\ndata: 12.5\n\nevent: progress\ndata: 1"),
+            vec![ProgressEvent::Progress(12.5)]
+        );
+        assert_eq!(parser.push(b"3\n\n"), vec![ProgressEvent::Progress(13.0)]);
+
+        // A character split between two chunks.
+        let comment = ": d\u{e9}j\u{e0}\n".as_bytes();
+        assert!(parser.push(&comment[..4]).is_empty());
+        assert!(parser.push(&comment[4..]).is_empty());
+
+        assert_eq!(
+            parser.push(b"event: complete\ndata: done\n\n"),
+            vec![ProgressEvent::Complete]
+        );
+        assert_eq!(
+            parser.push(b"data: 50\n\nevent: error\ndata: failed\n\n"),
+            vec![ProgressEvent::Error]
+        );
+    }
+
+    #[test]
+    fn test_transfer_rate() {
+        let total_bytes = 1000.0;
+        let mut rate = TransferRate::default();
+        rate.record(0.0, 10.0);
+        rate.record(0.5, 15.0);
+        assert_eq!(rate.bytes_per_second(total_bytes), None);
+
+        let close = |value: Option<f64>, expected: f64| {
+            value.is_some_and(|value| (value - expected).abs() < 1e-9)
+        };
+
+        rate.record(2.0, 30.0);
+        assert!(close(rate.bytes_per_second(total_bytes), 100.0));
+        assert!(close(rate.eta_seconds(total_bytes), 7.0));
+
+        // Only the last seconds count.
+        rate.record(20.0, 40.0);
+        rate.record(22.0, 60.0);
+        assert!(close(rate.bytes_per_second(total_bytes), 100.0));
+
+        rate.record(23.0, 5.0);
+        assert_eq!(rate.bytes_per_second(total_bytes), None);
+    }
+}
@@ -0,0 +1,33 @@
+//! What the data layer reports from the background, like a server going away.
+//!
+//! The app installs a handler turning these into actions for its widgets.
+//! Without one, like in the command line, they are dropped.
+
+use std::sync::OnceLock;
+
+use super::providers::ProviderFetchModelsResult;
+
+#[derive(Debug)]
+pub enum DataEvent {
+    /// A request to MolyServer could not reach it.
+    MolyServerUnreachable,
+    /// A provider listed its models, or failed to.
+    ProviderModelsFetched(ProviderFetchModelsResult),
+}
+
+type Handler = dyn Fn(DataEvent) + Send + Sync;
+
+static HANDLER: OnceLock<Box<Handler>> = OnceLock::new();
+
+/// Set the handler of every event, once.
+pub fn set_handler(handler: impl Fn(DataEvent) + Send + Sync + 'static) {
+    if HANDLER.set(Box::new(handler)).is_err() {
+        ::log::warn!("The data event handler is already set");
+    }
+}
+
+pub(crate) fn report(event: DataEvent) {
+    if let Some(handler) = HANDLER.get() {
+        handler(event);
+    }
+}
@@ -0,0 +1,286 @@
+//! Local OpenAI-compatible HTTP gateway to the providers configured in Moly.
+//!
+//! Editors and scripts on the same machine can reach the models of Moly through
+//! `/v1/models` and `/v1/chat/completions` without their own copy of the API
+//! keys. The server only listens on localhost, every request must carry the token
+//! generated here, and requests are logged by client.
+
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::app::app_runner;
+#[cfg(not(target_arch = "wasm32"))]
+use futures::channel::oneshot;
+
+/// Next to the port of MolyServer.
+pub const DEFAULT_PORT: u16 = 8766;
+
+/// Requests kept per client, older ones are dropped.
+const MAX_CLIENT_REQUESTS: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GatewayConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Expected as a bearer token in the `Authorization` header.
+    ///
+    /// Generated when first needed, see [`GatewayConfig::ensure_token`].
+    #[serde(default)]
+    pub token: String,
+    /// Offer the tools of the MCP servers to the models and run them in Moly,
+    /// following the tool policy.
+    #[serde(default)]
+    pub mcp_tools: bool,
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+            token: String::new(),
+            mcp_tools: false,
+        }
+    }
+}
+
+impl GatewayConfig {
+    /// Generate the token if there is none yet, returns `true` if it did.
+    pub fn ensure_token(&mut self) -> bool {
+        if !self.token.is_empty() {
+            return false;
+        }
+        self.token = new_token();
+        true
+    }
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+/// A new random access token.
+pub fn new_token() -> String {
+    format!("moly-{}", uuid::Uuid::new_v4().simple())
+}
+
+/// A request served by the gateway.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GatewayRequestLog {
+    pub timestamp: DateTime<Utc>,
+    pub method: String,
+    pub path: String,
+    pub model: Option<String>,
+    pub stream: bool,
+    pub status: u16,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+/// Recent requests of each client, keyed by the name it gives in its `User-Agent`.
+#[derive(Clone, Default)]
+pub struct GatewayLogs(Arc<Mutex<IndexMap<String, VecDeque<GatewayRequestLog>>>>);
+
+impl GatewayLogs {
+    pub fn record(&self, client: &str, request: GatewayRequestLog) {
+        let mut clients = self.0.lock().unwrap();
+        let requests = clients.entry(client.to_string()).or_default();
+        requests.push_back(request);
+        if requests.len() > MAX_CLIENT_REQUESTS {
+            requests.pop_front();
+        }
+    }
+
+    /// Clients with their requests, newest first, the most recently active first.
+    pub fn clients(&self) -> Vec<(String, Vec<GatewayRequestLog>)> {
+        let mut clients: Vec<(String, Vec<GatewayRequestLog>)> = self
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(client, requests)| (client.clone(), requests.iter().rev().cloned().collect()))
+            .collect();
+        clients
+            .sort_by_key(|(_, requests)| std::cmp::Reverse(requests.first().map(|r| r.timestamp)));
+        clients
+    }
+}
+
+/// Name of a client given its `User-Agent`, without the version.
+///
+/// `curl/8.5.0` is reported as `curl`, `OpenAI/Python 1.40.0` as `OpenAI/Python`.
+pub fn client_name(user_agent: Option<&str>) -> String {
+    let Some(product) = user_agent.and_then(|ua| ua.split_whitespace().next()) else {
+        return "Unknown client".to_string();
+    };
+
+    match product.rsplit_once('/') {
+        Some((name, version)) if version.starts_with(|c: char| c.is_ascii_digit()) => {
+            name.to_string()
+        }
+        _ => product.to_string(),
+    }
+}
+
+/// What the server reads from the configuration, shared with the handlers.
+#[derive(Default)]
+pub struct GatewayShared {
+    pub config: Mutex<GatewayConfig>,
+    pub logs: GatewayLogs,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum GatewayStatus {
+    #[default]
+    Stopped,
+    Starting,
+    Running(SocketAddr),
+    Failed(String),
+}
+
+/// The gateway server, started and stopped as the configuration changes.
+#[derive(Default)]
+pub struct Gateway {
+    shared: Arc<GatewayShared>,
+    status: GatewayStatus,
+    /// Port of the server started last, if it wasn't stopped since.
+    port: Option<u16>,
+    #[cfg(not(target_arch = "wasm32"))]
+    shutdown: Option<oneshot::Sender<()>>,
+    /// Bumped on every start and stop so late status updates are ignored.
+    generation: u64,
+}
+
+impl Gateway {
+    pub fn status(&self) -> &GatewayStatus {
+        &self.status
+    }
+
+    pub fn logs(&self) -> &GatewayLogs {
+        &self.shared.logs
+    }
+
+    /// Start, stop or restart the server to match `config`.
+    ///
+    /// Changes to the token and tools apply to the running server.
+    pub fn apply_config(&mut self, config: &GatewayConfig) {
+        *self.shared.config.lock().unwrap() = config.clone();
+
+        let port = config.enabled.then_some(config.port);
+        if port == self.port {
+            return;
+        }
+
+        self.stop();
+        if let Some(port) = port {
+            self.start(port);
+        }
+    }
+
+    fn stop(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        self.port = None;
+        self.status = GatewayStatus::Stopped;
+        self.generation += 1;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start(&mut self, port: u16) {
+        use moly_kit::aitk::utils::asynchronous::spawn;
+
+        self.generation += 1;
+        self.port = Some(port);
+        self.status = GatewayStatus::Starting;
+
+        let (shutdown, shutdown_signal) = oneshot::channel();
+        self.shutdown = Some(shutdown);
+
+        let shared = self.shared.clone();
+        let generation = self.generation;
+        spawn(async move {
+            let status = match tokio::net::TcpListener::bind(("127.0.0.1", port)).await {
+                Ok(listener) => match listener.local_addr() {
+                    Ok(addr) => {
+                        ::log::info!("Gateway listening on {}", addr);
+                        spawn(super::gateway_server::serve(
+                            listener,
+                            shared,
+                            shutdown_signal,
+                        ));
+                        GatewayStatus::Running(addr)
+                    }
+                    Err(e) => GatewayStatus::Failed(e.to_string()),
+                },
+                Err(e) => {
+                    GatewayStatus::Failed(format!("Could not listen on port {}: {}", port, e))
+                }
+            };
+
+            app_runner().defer(move |app, cx, _| {
+                if let Some(store) = app.store.as_mut() {
+                    if store.gateway.generation == generation {
+                        store.gateway.status = status;
+                        cx.redraw_all();
+                    }
+                }
+            });
+        });
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn start(&mut self, port: u16) {
+        self.port = Some(port);
+        self.status = GatewayStatus::Failed("The gateway is not available on the web".to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_name() {
+        assert_eq!(client_name(Some("curl/8.5.0")), "curl");
+        assert_eq!(client_name(Some("OpenAI/Python 1.40.0")), "OpenAI/Python");
+        assert_eq!(client_name(Some("Zed")), "Zed");
+        assert_eq!(client_name(None), "Unknown client");
+    }
+
+    #[test]
+    fn test_logs_keep_recent_requests() {
+        let logs = GatewayLogs::default();
+        let request = |path: &str, seconds: i64| GatewayRequestLog {
+            timestamp: DateTime::from_timestamp(seconds, 0).unwrap(),
+            method: "GET".to_string(),
+            path: path.to_string(),
+            model: None,
+            stream: false,
+            status: 200,
+            duration_ms: 1,
+            error: None,
+        };
+
+        for i in 0..MAX_CLIENT_REQUESTS + 5 {
+            logs.record("curl", request("/v1/models", i as i64));
+        }
+        logs.record("Zed", request("/v1/chat/completions", 1000));
+
+        let clients = logs.clients();
+        assert_eq!(clients[0].0, "Zed");
+        assert_eq!(clients[1].1.len(), MAX_CLIENT_REQUESTS);
+        assert_eq!(
+            clients[1].1[0].timestamp,
+            DateTime::from_timestamp((MAX_CLIENT_REQUESTS + 4) as i64, 0).unwrap()
+        );
+    }
+}
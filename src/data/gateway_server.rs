@@ -0,0 +1,577 @@
+//! HTTP side of the [gateway](super::gateway), speaking the OpenAI chat completions API.
+//!
+//! Requests go through the [`BotContext`] of the app, so the models and clients
+//! are the ones of the chats. Tools declared by the client are ignored; when
+//! enabled, the MCP tools of Moly are offered instead and the calls the tool
+//! policy allows are run before answering. Calls the policy would ask about
+//! are denied, as nobody is there to approve them.
+
+use async_stream::stream;
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Json, Response};
+use axum::routing::{get, post};
+use chrono::Utc;
+use futures::{Stream, StreamExt};
+use moly_kit::aitk::utils::tool::display_name_from_namespaced;
+use moly_kit::prelude::*;
+use moly_kit::utils::tool_audit::{ToolAuditCall, ToolAuditEvent};
+use moly_kit::utils::tool_calls::ToolCalls;
+use moly_kit::utils::tool_policy::{
+    SharedToolPolicy, ToolApproval, ToolPermission, denial_message,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::net::TcpListener;
+
+use super::gateway::{GatewayRequestLog, GatewayShared, client_name};
+use super::tool_filter::DisabledTools;
+use crate::app::app_runner;
+use crate::shared::bot_context::BotContext;
+
+pub(super) async fn serve(
+    listener: TcpListener,
+    shared: Arc<GatewayShared>,
+    shutdown: futures::channel::oneshot::Receiver<()>,
+) {
+    let app = Router::new()
+        .route("/v1/models", get(list_models))
+        .route("/v1/chat/completions", post(chat_completions))
+        .with_state(shared);
+
+    let server = axum::serve(listener, app).with_graceful_shutdown(async {
+        shutdown.await.ok();
+    });
+
+    if let Err(e) = server.await {
+        ::log::error!("Gateway error: {}", e);
+    }
+}
+
+/// A request being served, recorded in the logs once it ends.
+struct RequestLog {
+    shared: Arc<GatewayShared>,
+    client: String,
+    method: &'static str,
+    path: &'static str,
+    model: Option<String>,
+    stream: bool,
+    started: Instant,
+}
+
+impl RequestLog {
+    fn new(
+        shared: &Arc<GatewayShared>,
+        headers: &HeaderMap,
+        method: &'static str,
+        path: &'static str,
+    ) -> Self {
+        let user_agent = headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok());
+
+        Self {
+            shared: shared.clone(),
+            client: client_name(user_agent),
+            method,
+            path,
+            model: None,
+            stream: false,
+            started: Instant::now(),
+        }
+    }
+
+    fn finish(self, status: StatusCode, error: Option<String>) {
+        self.shared.logs.record(
+            &self.client,
+            GatewayRequestLog {
+                timestamp: Utc::now(),
+                method: self.method.to_string(),
+                path: self.path.to_string(),
+                model: self.model,
+                stream: self.stream,
+                status: status.as_u16(),
+                duration_ms: self.started.elapsed().as_millis() as u64,
+                error,
+            },
+        );
+    }
+
+    /// Record the failure and answer it like OpenAI does.
+    fn fail(self, status: StatusCode, message: String) -> Response {
+        let kind = match status {
+            StatusCode::UNAUTHORIZED => "authentication_error",
+            StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND => "invalid_request_error",
+            _ => "api_error",
+        };
+        let body = json!({
+            "error": { "message": message, "type": kind, "code": null }
+        });
+        self.finish(status, Some(message));
+        (status, Json(body)).into_response()
+    }
+}
+
+fn is_authorized(shared: &GatewayShared, headers: &HeaderMap) -> bool {
+    let token = shared.config.lock().unwrap().token.clone();
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| !token.is_empty() && tokens_match(given.trim(), &token))
+}
+
+/// Compare tokens in a time that doesn't depend on where they differ, so the
+/// token can't be guessed one character at a time. Hashing first also hides
+/// its length.
+fn tokens_match(given: &str, token: &str) -> bool {
+    let given = Sha256::digest(given.as_bytes());
+    let token = Sha256::digest(token.as_bytes());
+    given
+        .iter()
+        .zip(token.iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+/// What the handlers need from the store of the app.
+struct AppHandles {
+    bot_context: BotContext,
+    tool_policy: SharedToolPolicy,
+    tool_calls: ToolCalls,
+    disabled_tools: DisabledTools,
+}
+
+async fn app_handles() -> Option<AppHandles> {
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    app_runner().defer(move |app, _cx, _| {
+        let handles = app.store.as_ref().and_then(|store| {
+            Some(AppHandles {
+                bot_context: store.bot_context.clone()?,
+                tool_policy: store.tool_policy.clone(),
+                tool_calls: store.tool_calls.clone(),
+                disabled_tools: store.disabled_mcp_tools.clone(),
+            })
+        });
+        let _ = sender.send(handles);
+    });
+    receiver.await.ok().flatten()
+}
+
+const NOT_READY: &str = "Moly is still loading its providers, try again in a moment";
+
+/// Model turns a completion can take, to stop models calling tools forever.
+const MAX_TOOL_ROUNDS: usize = 10;
+
+async fn list_models(State(shared): State<Arc<GatewayShared>>, headers: HeaderMap) -> Response {
+    let log = RequestLog::new(&shared, &headers, "GET", "/v1/models");
+    if !is_authorized(&shared, &headers) {
+        return log.fail(
+            StatusCode::UNAUTHORIZED,
+            "Invalid gateway token".to_string(),
+        );
+    }
+    let Some(app) = app_handles().await else {
+        return log.fail(StatusCode::SERVICE_UNAVAILABLE, NOT_READY.to_string());
+    };
+
+    let data: Vec<Value> = app
+        .bot_context
+        .bots()
+        .iter()
+        .map(|bot| {
+            let owner = RouterClient::unprefix(&bot.id)
+                .map(|(provider, _)| provider.to_string())
+                .unwrap_or_else(|| "moly".to_string());
+            json!({
+                "id": bot.id.as_str(),
+                "object": "model",
+                "created": 0,
+                "owned_by": owner,
+                "name": bot.name,
+            })
+        })
+        .collect();
+
+    log.finish(StatusCode::OK, None);
+    Json(json!({ "object": "list", "data": data })).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionRequest {
+    model: String,
+    messages: Vec<RequestMessage>,
+    #[serde(default)]
+    stream: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct RequestMessage {
+    role: String,
+    #[serde(default)]
+    content: Value,
+}
+
+/// Convert OpenAI messages to the messages of the app, keeping their text.
+fn to_messages(messages: Vec<RequestMessage>, bot_id: &BotId) -> Result<Vec<Message>, String> {
+    messages
+        .into_iter()
+        .map(|message| {
+            let from = match message.role.as_str() {
+                "system" | "developer" => EntityId::System,
+                "user" => EntityId::User,
+                "assistant" => EntityId::Bot(bot_id.clone()),
+                "tool" => {
+                    return Err(
+                        "Tools of the client are not supported, enable the MCP tools of the \
+                         gateway in Moly instead"
+                            .to_string(),
+                    );
+                }
+                role => return Err(format!("Unsupported message role '{}'", role)),
+            };
+
+            let text = match message.content {
+                Value::Null => String::new(),
+                Value::String(text) => text,
+                Value::Array(parts) => parts
+                    .iter()
+                    .filter(|part| part["type"] == "text")
+                    .filter_map(|part| part["text"].as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                _ => return Err("Unsupported message content".to_string()),
+            };
+
+            Ok(Message {
+                from,
+                content: MessageContent {
+                    text,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+async fn chat_completions(
+    State(shared): State<Arc<GatewayShared>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let mut log = RequestLog::new(&shared, &headers, "POST", "/v1/chat/completions");
+    if !is_authorized(&shared, &headers) {
+        return log.fail(
+            StatusCode::UNAUTHORIZED,
+            "Invalid gateway token".to_string(),
+        );
+    }
+
+    let request: CompletionRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return log.fail(StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)),
+    };
+    log.model = Some(request.model.clone());
+    log.stream = request.stream;
+
+    let Some(app) = app_handles().await else {
+        return log.fail(StatusCode::SERVICE_UNAVAILABLE, NOT_READY.to_string());
+    };
+
+    let bot_id = BotId::new(&request.model);
+    let Some(bot) = app.bot_context.get_bot(&bot_id) else {
+        return log.fail(
+            StatusCode::NOT_FOUND,
+            format!("The model '{}' does not exist", request.model),
+        );
+    };
+
+    let messages = match to_messages(request.messages, &bot_id) {
+        Ok(messages) => messages,
+        Err(error) => return log.fail(StatusCode::BAD_REQUEST, error),
+    };
+
+    let mcp_tools = shared.config.lock().unwrap().mcp_tools;
+    let tools = app
+        .bot_context
+        .tool_manager()
+        .filter(|_| mcp_tools)
+        .map(|tool_manager| ServerTools {
+            tools: tool_manager
+                .tools()
+                .into_iter()
+                .filter(|tool| !app.disabled_tools.is_disabled(&tool.name))
+                .collect(),
+            policy: app.tool_policy,
+            tool_calls: app.tool_calls,
+            audit_name: format!("{} (via {})", bot.name, log.client),
+        });
+
+    let id = format!("chatcmpl-{}", uuid::Uuid::new_v4().simple());
+    let created = Utc::now().timestamp();
+    let completion = complete(app.bot_context.client(), bot_id, messages, tools);
+
+    if request.stream {
+        let model = request.model;
+        let chunk = move |delta: Value, finish_reason: Option<&str>| {
+            let chunk = json!({
+                "id": id,
+                "object": "chat.completion.chunk",
+                "created": created,
+                "model": model,
+                "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+            });
+            Ok::<_, Infallible>(Event::default().data(chunk.to_string()))
+        };
+
+        let events = stream! {
+            yield chunk(json!({ "role": "assistant", "content": "" }), None);
+
+            let mut error = None;
+            let mut completion = Box::pin(completion);
+            while let Some(delta) = completion.next().await {
+                match delta {
+                    Ok(text) => yield chunk(json!({ "content": text }), None),
+                    Err(e) => {
+                        let body = json!({ "error": { "message": e, "type": "api_error" } });
+                        yield Ok(Event::default().data(body.to_string()));
+                        error = Some(e);
+                        break;
+                    }
+                }
+            }
+
+            if error.is_none() {
+                yield chunk(json!({}), Some("stop"));
+            }
+            yield Ok(Event::default().data("[DONE]"));
+            // Logged as succeeded since the status was sent with the first event.
+            log.finish(StatusCode::OK, error);
+        };
+
+        return Sse::new(events)
+            .keep_alive(KeepAlive::default())
+            .into_response();
+    }
+
+    let mut text = String::new();
+    let mut completion = Box::pin(completion);
+    while let Some(delta) = completion.next().await {
+        match delta {
+            Ok(delta) => text.push_str(&delta),
+            Err(error) => return log.fail(StatusCode::BAD_GATEWAY, error),
+        }
+    }
+
+    log.finish(StatusCode::OK, None);
+    Json(json!({
+        "id": id,
+        "object": "chat.completion",
+        "created": created,
+        "model": request.model,
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": text },
+            "finish_reason": "stop",
+        }],
+    }))
+    .into_response()
+}
+
+/// MCP tools offered to the model and run by the gateway.
+struct ServerTools {
+    tools: Vec<Tool>,
+    policy: SharedToolPolicy,
+    tool_calls: ToolCalls,
+    /// Reported as the bot of the calls in the tool audit log.
+    audit_name: String,
+}
+
+impl ServerTools {
+    /// Run the calls the policy allows, denying the others.
+    async fn run(&self, bot_id: &BotId, tool_calls: &[ToolCall]) -> Vec<ToolResult> {
+        let mut results = Vec::new();
+        for call in tool_calls {
+            let (permission, approval) = self
+                .policy
+                .evaluate_with_approval(&call.name, &call.arguments);
+            let approved = permission == ToolPermission::Allow;
+            self.audit(ToolAuditEvent::Decided {
+                call: ToolAuditCall {
+                    id: call.id.clone(),
+                    name: call.name.clone(),
+                    arguments: Value::Object(call.arguments.clone()),
+                    bot_id: Some(bot_id.clone()),
+                    realtime: false,
+                },
+                approved,
+                approval,
+            });
+
+            let display_name = display_name_from_namespaced(&call.name);
+            let result = match permission {
+                ToolPermission::Allow => {
+                    self.tool_calls
+                        .run(&call.name, &call.id, call.arguments.clone())
+                        .await
+                }
+                ToolPermission::Ask => ToolResult {
+                    tool_call_id: call.id.clone(),
+                    content: format!(
+                        "Tool '{}' needs an approval that can't be given through the \
+                         gateway. It was not executed.",
+                        display_name
+                    ),
+                    is_error: true,
+                },
+                ToolPermission::Deny => ToolResult {
+                    tool_call_id: call.id.clone(),
+                    content: denial_message(&call.name, ToolApproval::Policy),
+                    is_error: true,
+                },
+            };
+
+            if approved {
+                self.audit(ToolAuditEvent::Finished {
+                    id: result.tool_call_id.clone(),
+                    content: result.content.clone(),
+                    is_error: result.is_error,
+                });
+            }
+            results.push(result);
+        }
+        results
+    }
+
+    fn audit(&self, event: ToolAuditEvent) {
+        let bot = self.audit_name.clone();
+        app_runner().defer(move |app, cx, _| {
+            if let Some(store) = app.store.as_mut() {
+                store.tool_audit.handle_event(None, Some(bot), event);
+                cx.redraw_all();
+            }
+        });
+    }
+}
+
+/// Text of the reply as it is streamed, running tools between turns.
+fn complete(
+    mut client: Box<dyn BotClient>,
+    bot_id: BotId,
+    mut messages: Vec<Message>,
+    tools: Option<ServerTools>,
+) -> impl Stream<Item = Result<String, String>> + Send {
+    stream! {
+        for round in 0.. {
+            let offered = tools.as_ref().map(|t| t.tools.clone()).unwrap_or_default();
+            let mut replies = client.send(&bot_id, &messages, &offered);
+            let mut content = MessageContent::default();
+
+            while let Some(result) = replies.next().await {
+                match result.into_result() {
+                    Ok(next) => {
+                        // Each item holds the whole content so far.
+                        if let Some(delta) = next.text.strip_prefix(content.text.as_str())
+                            && !delta.is_empty()
+                        {
+                            yield Ok(delta.to_string());
+                        }
+                        content = next;
+                    }
+                    Err(errors) => {
+                        let error = errors
+                            .iter()
+                            .map(|e| e.message().to_string())
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        yield Err(error);
+                        return;
+                    }
+                }
+            }
+
+            let Some(tools) = tools.as_ref().filter(|_| !content.tool_calls.is_empty()) else {
+                return;
+            };
+
+            if round + 1 == MAX_TOOL_ROUNDS {
+                yield Err(format!(
+                    "The model was still calling tools after {} rounds, stopped",
+                    MAX_TOOL_ROUNDS
+                ));
+                return;
+            }
+
+            let tool_results = tools.run(&bot_id, &content.tool_calls).await;
+            messages.push(Message {
+                from: EntityId::Bot(bot_id.clone()),
+                content,
+                ..Default::default()
+            });
+            messages.push(Message {
+                from: EntityId::Tool,
+                content: MessageContent {
+                    tool_results,
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secreT", "secret"));
+        assert!(!tokens_match("secret2", "secret"));
+        assert!(!tokens_match("", "secret"));
+    }
+
+    #[test]
+    fn test_to_messages() {
+        let request: CompletionRequest = serde_json::from_value(json!({
+            "model": "openai/gpt-4o",
+            "messages": [
+                { "role": "system", "content": "Be brief" },
+                {
+                    "role": "user",
+                    "content": [
+                        { "type": "text", "text": "Hello" },
+                        { "type": "image_url", "image_url": { "url": "data:" } },
+                        { "type": "text", "text": "there" }
+                    ]
+                },
+                { "role": "assistant", "content": null }
+            ],
+            "stream": true
+        }))
+        .unwrap();
+        assert!(request.stream);
+
+        let bot_id = BotId::new(&request.model);
+        let messages = to_messages(request.messages, &bot_id).unwrap();
+        assert_eq!(messages[0].from, EntityId::System);
+        assert_eq!(messages[1].content.text, "Hello\nthere");
+        assert_eq!(messages[2].from, EntityId::Bot(bot_id.clone()));
+        assert_eq!(messages[2].content.text, "");
+
+        let tool = vec![RequestMessage {
+            role: "tool".to_string(),
+            content: json!("42"),
+        }];
+        assert!(to_messages(tool, &bot_id).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+//! What this computer can run, to tell which model files fit in memory.
+//!
+//! Memory is read from `/proc/meminfo` and CPU cores from sysfs on Linux. On
+//! other platforms the memory is unknown and files get no fit.
+
+use moly_protocol::data::{File, FileId};
+
+use super::search::filters::{file_size_gb, required_ram_gb};
+
+const BYTES_PER_GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// Memory needed to run a file, on top of its size: context, KV cache and runtime.
+const RUNTIME_OVERHEAD: f64 = 1.2;
+const RUNTIME_BASE_GB: f64 = 0.5;
+
+/// Share of the total memory that can go to a model without starving the system.
+const USABLE_SHARE: f64 = 0.9;
+/// Share of the total memory a model can take and still leave room for other apps.
+const COMFORTABLE_SHARE: f64 = 0.6;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HardwareInfo {
+    pub total_ram_gb: Option<f64>,
+    pub available_ram_gb: Option<f64>,
+    pub cpu_cores: Option<usize>,
+    /// As reported by Rust, like `x86_64` or `aarch64`.
+    pub arch: &'static str,
+}
+
+impl HardwareInfo {
+    pub fn detect() -> Self {
+        #[cfg(target_os = "linux")]
+        let (total_ram_gb, available_ram_gb, cpu_cores) = {
+            let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap_or_default();
+            let cores = std::fs::read_to_string("/sys/devices/system/cpu/online")
+                .ok()
+                .and_then(|online| parse_cpu_list(&online));
+            (
+                meminfo_gb(&meminfo, "MemTotal"),
+                meminfo_gb(&meminfo, "MemAvailable"),
+                cores,
+            )
+        };
+
+        #[cfg(not(target_os = "linux"))]
+        let (total_ram_gb, available_ram_gb, cpu_cores) = (None, None, None);
+
+        Self {
+            total_ram_gb,
+            available_ram_gb,
+            cpu_cores: cpu_cores.or_else(|| {
+                std::thread::available_parallelism()
+                    .ok()
+                    .map(|cores| cores.get())
+            }),
+            arch: std::env::consts::ARCH,
+        }
+    }
+
+    /// Short description, like `16 GB RAM (9.2 GB free), 8 cores, x86_64`.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(total) = self.total_ram_gb {
+            match self.available_ram_gb {
+                Some(available) => {
+                    parts.push(format!("{:.0} GB RAM ({:.1} GB free)", total, available))
+                }
+                None => parts.push(format!("{:.0} GB RAM", total)),
+            }
+        }
+        if let Some(cores) = self.cpu_cores {
+            parts.push(format!("{} cores", cores));
+        }
+        parts.push(self.arch.to_string());
+        parts.join(", ")
+    }
+
+    /// How a file fits in memory, if the memory and the file size are known.
+    ///
+    /// `requires` is the requirement of the model, used when the file size isn't known.
+    pub fn file_fit(&self, file: &File, requires: &str) -> Option<FileFit> {
+        let total = self.total_ram_gb?;
+        let needed = match file_size_gb(file) {
+            Some(size) => size * RUNTIME_OVERHEAD + RUNTIME_BASE_GB,
+            None => required_ram_gb(requires)?,
+        };
+
+        let fit = if needed > total * USABLE_SHARE {
+            FileFit::WontFit
+        } else if needed > total * COMFORTABLE_SHARE
+            || self
+                .available_ram_gb
+                .is_some_and(|available| needed > available)
+        {
+            FileFit::Tight
+        } else {
+            FileFit::Comfortable
+        };
+        Some(fit)
+    }
+
+    /// The file of a model to pick on this computer: the largest that runs
+    /// comfortably, or else the largest that fits at all.
+    pub fn recommended_file<'a>(
+        &self,
+        files: impl IntoIterator<Item = &'a File>,
+        requires: &str,
+    ) -> Option<FileId> {
+        files
+            .into_iter()
+            .filter_map(|file| {
+                let fit = self.file_fit(file, requires)?;
+                (fit != FileFit::WontFit).then(|| (fit, file_size_gb(file).unwrap_or(0.0), file))
+            })
+            .max_by(|(a_fit, a_size, _), (b_fit, b_size, _)| {
+                // Comfortable sorts before tight, so it's reversed here.
+                b_fit.cmp(a_fit).then(a_size.total_cmp(b_size))
+            })
+            .map(|(_, _, file)| file.id.clone())
+    }
+}
+
+/// Whether a file can be loaded on this computer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FileFit {
+    Comfortable,
+    /// Fits, but takes most of the memory or more than is free now.
+    Tight,
+    WontFit,
+}
+
+impl FileFit {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Comfortable => "Runs comfortably",
+            Self::Tight => "Tight fit",
+            Self::WontFit => "Won't fit",
+        }
+    }
+}
+
+/// A value of `/proc/meminfo`, given in kB, in GB.
+fn meminfo_gb(meminfo: &str, key: &str) -> Option<f64> {
+    meminfo.lines().find_map(|line| {
+        let value = line.strip_prefix(key)?.strip_prefix(':')?;
+        let kb: f64 = value.split_whitespace().next()?.parse().ok()?;
+        Some(kb * 1024.0 / BYTES_PER_GB)
+    })
+}
+
+/// Number of CPUs in a sysfs list like `0-3,8-11`.
+fn parse_cpu_list(list: &str) -> Option<usize> {
+    let mut count = 0;
+    for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+        count += match range.split_once('-') {
+            Some((start, end)) => end.parse::<usize>().ok()? - start.parse::<usize>().ok()? + 1,
+            None => {
+                range.parse::<usize>().ok()?;
+                1
+            }
+        };
+    }
+    (count > 0).then_some(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_system_files() {
+        let meminfo = "MemTotal:       16384000 kB\nMemFree:         1024000 kB\nMemAvailable:    8192000 kB\n";
+        let total = meminfo_gb(meminfo, "MemTotal").unwrap();
+        assert!((total - 15.625).abs() < 1e-9);
+        assert!(meminfo_gb(meminfo, "MemAvailable").is_some());
+        assert_eq!(meminfo_gb(meminfo, "SwapTotal"), None);
+
+        assert_eq!(parse_cpu_list("0-7\n"), Some(8));
+        assert_eq!(parse_cpu_list("0-3,8-11"), Some(8));
+        assert_eq!(parse_cpu_list("0"), Some(1));
+        assert_eq!(parse_cpu_list(""), None);
+    }
+
+    #[test]
+    fn test_fit_order() {
+        assert!(FileFit::Comfortable < FileFit::Tight);
+        assert!(FileFit::Tight < FileFit::WontFit);
+    }
+}
@@ -0,0 +1,161 @@
+//! Periodic re-test of the connections to providers and MCP network servers.
+//!
+//! The store ticks the monitor every [`TICK`]. Healthy providers are listed
+//! again and connected MCP network servers are pinged every
+//! [`HEALTHY_INTERVAL`]. Unreachable ones and failed MCP network servers are
+//! retried with a backoff, so local servers started after Moly (Ollama,
+//! MolyServer, a realtime server) are picked up on their own.
+
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+
+use super::providers::ProviderId;
+
+/// How often the store looks for due checks.
+pub const TICK: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Delay between two checks of a connection that works.
+const HEALTHY_INTERVAL: Duration = Duration::minutes(5);
+
+/// Delay before the first retry of a failed connection, doubled on each failure.
+const FIRST_RETRY_DELAY: Duration = Duration::seconds(5);
+
+/// Longest delay between two retries.
+const MAX_RETRY_DELAY: Duration = Duration::minutes(5);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HealthTarget {
+    Provider(ProviderId),
+    McpServer(String),
+}
+
+#[derive(Debug)]
+struct TargetHealth {
+    /// Failed checks in a row.
+    failures: u32,
+    next_check: DateTime<Utc>,
+    /// A check was started and its outcome is not known yet.
+    checking: bool,
+}
+
+/// Health of every connection seen so far.
+#[derive(Debug, Default)]
+pub struct HealthMonitor {
+    targets: HashMap<HealthTarget, TargetHealth>,
+}
+
+impl HealthMonitor {
+    /// Whether `target` should be checked now, in which case the check is
+    /// considered started until its outcome is recorded.
+    ///
+    /// Connections never seen are left to their first connection attempt.
+    pub fn start_check_if_due(&mut self, target: &HealthTarget, now: DateTime<Utc>) -> bool {
+        let Some(health) = self.targets.get_mut(target) else {
+            return false;
+        };
+        if health.checking || health.next_check > now {
+            return false;
+        }
+        health.checking = true;
+        true
+    }
+
+    /// Record the outcome of a check of `target`, scheduling the next one.
+    ///
+    /// Returns `true` if the connection works again after failing.
+    pub fn record(&mut self, target: HealthTarget, healthy: bool, now: DateTime<Utc>) -> bool {
+        let health = self.targets.entry(target).or_insert(TargetHealth {
+            failures: 0,
+            next_check: now,
+            checking: false,
+        });
+        let recovered = healthy && health.failures > 0;
+
+        health.failures = if healthy {
+            0
+        } else {
+            health.failures.saturating_add(1)
+        };
+        health.next_check = now + check_delay(health.failures);
+        health.checking = false;
+        recovered
+    }
+
+    /// Record the status of a connection that is watched rather than checked.
+    ///
+    /// Only the first status, changes and the outcome of started checks count,
+    /// so a connection that keeps failing backs off instead of being retried
+    /// on every tick.
+    pub fn observe(&mut self, target: HealthTarget, healthy: bool, now: DateTime<Utc>) -> bool {
+        let changed = match self.targets.get(&target) {
+            Some(health) => health.checking || healthy != (health.failures == 0),
+            None => true,
+        };
+        changed && self.record(target, healthy, now)
+    }
+
+    pub fn forget(&mut self, target: &HealthTarget) {
+        self.targets.remove(target);
+    }
+}
+
+/// Delay before the next check of a connection after `failures` failed checks in a row.
+fn check_delay(failures: u32) -> Duration {
+    if failures == 0 {
+        return HEALTHY_INTERVAL;
+    }
+    let factor = 1 << (failures - 1).min(16);
+    (FIRST_RETRY_DELAY * factor).min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_delay() {
+        let delays: Vec<_> = (0..9).map(|f| check_delay(f).num_seconds()).collect();
+        assert_eq!(delays, vec![300, 5, 10, 20, 40, 80, 160, 300, 300]);
+        assert_eq!(check_delay(u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_backoff_and_recovery() {
+        let mut monitor = HealthMonitor::default();
+        let target = HealthTarget::Provider("ollama".into());
+        let now = Utc::now();
+
+        assert!(!monitor.start_check_if_due(&target, now));
+        assert!(!monitor.record(target.clone(), false, now));
+
+        assert!(!monitor.start_check_if_due(&target, now));
+        let retry = now + Duration::seconds(5);
+        assert!(monitor.start_check_if_due(&target, retry));
+        // Already checking.
+        assert!(!monitor.start_check_if_due(&target, retry));
+
+        assert!(monitor.record(target.clone(), true, retry));
+        assert!(!monitor.start_check_if_due(&target, retry + Duration::minutes(1)));
+        assert!(monitor.start_check_if_due(&target, retry + Duration::minutes(5)));
+    }
+
+    #[test]
+    fn test_observe() {
+        let mut monitor = HealthMonitor::default();
+        let target = HealthTarget::McpServer("search".into());
+        let now = Utc::now();
+
+        monitor.observe(target.clone(), false, now);
+        // Still failing on later ticks, the retry stays scheduled.
+        monitor.observe(target.clone(), false, now + Duration::seconds(4));
+        assert!(monitor.start_check_if_due(&target, now + Duration::seconds(5)));
+
+        let later = now + Duration::seconds(6);
+        monitor.observe(target.clone(), false, later);
+        assert!(!monitor.start_check_if_due(&target, later + Duration::seconds(9)));
+        assert!(monitor.start_check_if_due(&target, later + Duration::seconds(10)));
+
+        assert!(monitor.observe(target.clone(), true, later + Duration::seconds(11)));
+        assert!(!monitor.observe(target.clone(), true, later + Duration::seconds(12)));
+    }
+}
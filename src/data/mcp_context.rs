@@ -0,0 +1,624 @@
+//! MCP prompts and resources offered in the prompt input.
+//!
+//! Prompts from `prompts/list` become slash commands that expand into messages
+//! through `prompts/get`, and resources from `resources/list` can be attached
+//! to a message through `resources/read`. Attached resources are subscribed to
+//! so their content can be refreshed when the server reports a change, and
+//! unsubscribed from once no longer attached anywhere.
+//!
+//! Commands and resources are identified by `{server_id}/{name}`, so server ids
+//! are assumed not to contain slashes.
+
+use base64::Engine;
+use moly_kit::aitk::utils::asynchronous::spawn;
+use moly_kit::prelude::*;
+use moly_kit::utils::mcp::McpClient;
+use moly_kit::utils::resources::{ContextResource, ResourceSource};
+use moly_kit::utils::slash_commands::{
+    CommandMessage, CommandRole, SlashCommand, SlashCommandArgument, SlashCommandSource,
+};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use super::mcp_supervisor::McpLogSource;
+use crate::app::app_runner;
+
+#[derive(Clone, Debug, Default)]
+pub enum McpContextAction {
+    /// An attached resource changed and was read again.
+    ResourceUpdated(String, Attachment),
+    #[default]
+    None,
+}
+
+#[derive(Default)]
+struct McpContextState {
+    tool_manager: Option<McpClient>,
+    /// Prompts by server, in the order servers finished loading.
+    prompts: Vec<(String, Vec<SlashCommand>)>,
+    resources: Vec<(String, Vec<ContextResource>)>,
+    /// Number of attachments watching each subscribed resource, by id.
+    subscribed: HashMap<String, usize>,
+}
+
+/// Prompts and resources of the connected MCP servers.
+///
+/// Cheap to clone, every clone shares the same state.
+#[derive(Clone, Default)]
+pub struct McpContext(Arc<Mutex<McpContextState>>);
+
+impl McpContext {
+    /// Forget everything loaded from the previous manager and start listening
+    /// to the notifications of the new one.
+    pub fn set_tool_manager(&self, tool_manager: Option<McpClient>) {
+        if let Some(tool_manager) = &tool_manager {
+            let context = self.clone();
+            tool_manager.set_notification_handler(move |server_id, method, params| {
+                context.handle_notification(server_id, method, params);
+            });
+        }
+
+        *self.0.lock().unwrap() = McpContextState {
+            tool_manager,
+            ..Default::default()
+        };
+    }
+
+    /// Fetch the prompts and resources of a server that just connected.
+    ///
+    /// Servers without prompts or resources support simply contribute nothing.
+    pub async fn load_server(&self, server_id: &str) {
+        let Some(tool_manager) = self.tool_manager() else {
+            return;
+        };
+
+        let prompts = tool_manager
+            .send_request(server_id, "prompts/list", json!({}))
+            .await
+            .map(|result| parse_prompts(server_id, &result))
+            .unwrap_or_default();
+
+        let resources = tool_manager
+            .send_request(server_id, "resources/list", json!({}))
+            .await
+            .map(|result| parse_resources(server_id, &result))
+            .unwrap_or_default();
+
+        let mut state = self.0.lock().unwrap();
+        state.prompts.retain(|(id, _)| id != server_id);
+        state.resources.retain(|(id, _)| id != server_id);
+        if !prompts.is_empty() {
+            state.prompts.push((server_id.to_string(), prompts));
+        }
+        if !resources.is_empty() {
+            state.resources.push((server_id.to_string(), resources));
+        }
+    }
+
+    /// Check that a connected server still answers, with an MCP `ping`.
+    pub async fn ping(&self, server_id: &str) -> Result<(), String> {
+        let Some(tool_manager) = self.tool_manager() else {
+            return Err("MCP is disabled".to_string());
+        };
+        tool_manager
+            .send_request(server_id, "ping", json!({}))
+            .await
+            .map(|_| ())
+    }
+
+    fn tool_manager(&self) -> Option<McpClient> {
+        self.0.lock().unwrap().tool_manager.clone()
+    }
+
+    fn handle_notification(&self, server_id: &str, method: &str, params: &Value) {
+        match method {
+            "notifications/prompts/list_changed" | "notifications/resources/list_changed" => {
+                let context = self.clone();
+                let server_id = server_id.to_string();
+                spawn(async move {
+                    context.load_server(&server_id).await;
+                });
+            }
+            "notifications/message" => {
+                let level = params
+                    .get("level")
+                    .and_then(Value::as_str)
+                    .unwrap_or("info");
+                let data = match params.get("data") {
+                    Some(Value::String(text)) => text.clone(),
+                    Some(data) => data.to_string(),
+                    None => String::new(),
+                };
+                let text = format!("{}: {}", level, data);
+                let server_id = server_id.to_string();
+                app_runner().defer(move |app, _cx, _| {
+                    if let Some(store) = app.store.as_ref() {
+                        store
+                            .mcp_supervisor
+                            .log(&server_id, McpLogSource::Protocol, text);
+                    }
+                });
+            }
+            "notifications/progress" => {
+                let params = params.clone();
+                app_runner().defer(move |app, cx, _| {
+                    if let Some(store) = app.store.as_ref()
+                        && store.tool_calls.handle_progress(&params)
+                    {
+                        cx.redraw_all();
+                    }
+                });
+            }
+            "notifications/resources/updated" => {
+                let Some(uri) = params.get("uri").and_then(Value::as_str) else {
+                    return;
+                };
+
+                let id = format!("{}/{}", server_id, uri);
+                if !self.0.lock().unwrap().subscribed.contains_key(&id) {
+                    return;
+                }
+
+                let resource = self.find_resource(&id).unwrap_or_else(|| ContextResource {
+                    id: id.clone(),
+                    name: uri.to_string(),
+                    description: String::new(),
+                    origin: server_id.to_string(),
+                });
+
+                let future = self.read(&resource);
+                spawn(async move {
+                    match future.await {
+                        Ok(attachment) => {
+                            app_runner().defer(move |_, cx, _| {
+                                cx.action(McpContextAction::ResourceUpdated(id, attachment));
+                            });
+                        }
+                        Err(e) => {
+                            ::log::warn!("Failed to refresh MCP resource {}: {}", id, e);
+                        }
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn find_resource(&self, id: &str) -> Option<ContextResource> {
+        self.0
+            .lock()
+            .unwrap()
+            .resources
+            .iter()
+            .flat_map(|(_, resources)| resources)
+            .find(|resource| resource.id == id)
+            .cloned()
+    }
+}
+
+impl SlashCommandSource for McpContext {
+    fn commands(&self) -> Vec<SlashCommand> {
+        let state = self.0.lock().unwrap();
+        let all: Vec<&SlashCommand> = state
+            .prompts
+            .iter()
+            .flat_map(|(_, prompts)| prompts)
+            .collect();
+
+        // Prompts with the same name on several servers are qualified by server.
+        all.iter()
+            .map(|command| {
+                let mut command = (*command).clone();
+                let duplicated = all.iter().filter(|c| c.name == command.name).count() > 1;
+                if duplicated {
+                    let (server_id, _) = split_id(&command.id);
+                    command.name = format!("{}:{}", server_id, command.name);
+                }
+                command
+            })
+            .collect()
+    }
+
+    fn expand(
+        &self,
+        command: &SlashCommand,
+        arguments: BTreeMap<String, String>,
+    ) -> BoxPlatformSendFuture<'static, Result<Vec<CommandMessage>, String>> {
+        let tool_manager = self.tool_manager();
+        let (server_id, name) = split_id(&command.id);
+        let (server_id, name) = (server_id.to_string(), name.to_string());
+
+        Box::pin(async move {
+            let tool_manager = tool_manager.ok_or("MCP servers are not running")?;
+            let params = json!({ "name": name, "arguments": arguments });
+            let result = tool_manager
+                .send_request(&server_id, "prompts/get", params)
+                .await?;
+            parse_prompt_messages(&result)
+        })
+    }
+}
+
+impl ResourceSource for McpContext {
+    fn resources(&self) -> Vec<ContextResource> {
+        self.0
+            .lock()
+            .unwrap()
+            .resources
+            .iter()
+            .flat_map(|(_, resources)| resources.iter().cloned())
+            .collect()
+    }
+
+    fn read(
+        &self,
+        resource: &ContextResource,
+    ) -> BoxPlatformSendFuture<'static, Result<Attachment, String>> {
+        let tool_manager = self.tool_manager();
+        let (server_id, uri) = split_id(&resource.id);
+        let (server_id, uri) = (server_id.to_string(), uri.to_string());
+        let name = resource.name.clone();
+
+        Box::pin(async move {
+            let tool_manager = tool_manager.ok_or("MCP servers are not running")?;
+            let params = json!({ "uri": uri });
+            let result = tool_manager
+                .send_request(&server_id, "resources/read", params)
+                .await?;
+            resource_attachment(&name, &result)
+        })
+    }
+
+    fn watch(&self, resource: &ContextResource) {
+        let Some(tool_manager) = self.tool_manager() else {
+            return;
+        };
+
+        let watchers = {
+            let mut state = self.0.lock().unwrap();
+            let watchers = state.subscribed.entry(resource.id.clone()).or_default();
+            *watchers += 1;
+            *watchers
+        };
+        if watchers > 1 {
+            return;
+        }
+
+        let (server_id, uri) = split_id(&resource.id);
+        let (server_id, uri) = (server_id.to_string(), uri.to_string());
+        spawn(async move {
+            // Servers without subscription support just won't send updates.
+            if let Err(e) = tool_manager
+                .send_request(&server_id, "resources/subscribe", json!({ "uri": uri }))
+                .await
+            {
+                ::log::debug!("Could not subscribe to MCP resource {}: {}", uri, e);
+            }
+        });
+    }
+
+    fn unwatch(&self, resource: &ContextResource) {
+        let Some(tool_manager) = self.tool_manager() else {
+            return;
+        };
+
+        {
+            let mut state = self.0.lock().unwrap();
+            let Some(watchers) = state.subscribed.get_mut(&resource.id) else {
+                return;
+            };
+            *watchers -= 1;
+            if *watchers > 0 {
+                return;
+            }
+            state.subscribed.remove(&resource.id);
+        }
+
+        let (server_id, uri) = split_id(&resource.id);
+        let (server_id, uri) = (server_id.to_string(), uri.to_string());
+        spawn(async move {
+            if let Err(e) = tool_manager
+                .send_request(&server_id, "resources/unsubscribe", json!({ "uri": uri }))
+                .await
+            {
+                ::log::debug!("Could not unsubscribe from MCP resource {}: {}", uri, e);
+            }
+        });
+    }
+}
+
+fn split_id(id: &str) -> (&str, &str) {
+    id.split_once('/').unwrap_or((id, ""))
+}
+
+fn string_field(value: &Value, field: &str) -> String {
+    value
+        .get(field)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Parse the result of `prompts/list` into slash commands.
+fn parse_prompts(server_id: &str, result: &Value) -> Vec<SlashCommand> {
+    let Some(prompts) = result.get("prompts").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+
+    prompts
+        .iter()
+        .filter_map(|prompt| {
+            let name = prompt.get("name").and_then(Value::as_str)?;
+            let arguments = prompt
+                .get("arguments")
+                .and_then(Value::as_array)
+                .map(|arguments| {
+                    arguments
+                        .iter()
+                        .filter_map(|argument| {
+                            Some(SlashCommandArgument {
+                                name: argument.get("name").and_then(Value::as_str)?.to_string(),
+                                description: string_field(argument, "description"),
+                                required: argument
+                                    .get("required")
+                                    .and_then(Value::as_bool)
+                                    .unwrap_or(false),
+                            })
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            Some(SlashCommand {
+                id: format!("{}/{}", server_id, name),
+                name: name.to_string(),
+                description: string_field(prompt, "description"),
+                arguments,
+            })
+        })
+        .collect()
+}
+
+/// Parse the result of `resources/list`.
+fn parse_resources(server_id: &str, result: &Value) -> Vec<ContextResource> {
+    let Some(resources) = result.get("resources").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+
+    resources
+        .iter()
+        .filter_map(|resource| {
+            let uri = resource.get("uri").and_then(Value::as_str)?;
+            let name = resource
+                .get("title")
+                .or_else(|| resource.get("name"))
+                .and_then(Value::as_str)
+                .unwrap_or(uri);
+
+            Some(ContextResource {
+                id: format!("{}/{}", server_id, uri),
+                name: name.to_string(),
+                description: string_field(resource, "description"),
+                origin: server_id.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Parse the result of `prompts/get` into chat messages.
+///
+/// Text content is concatenated, images become attachments and embedded
+/// resources are attached as files.
+fn parse_prompt_messages(result: &Value) -> Result<Vec<CommandMessage>, String> {
+    let messages = result
+        .get("messages")
+        .and_then(Value::as_array)
+        .ok_or("The prompt has no messages")?;
+
+    let mut parsed: Vec<CommandMessage> = Vec::new();
+    for message in messages {
+        let role = match message.get("role").and_then(Value::as_str) {
+            Some("assistant") => CommandRole::Assistant,
+            _ => CommandRole::User,
+        };
+
+        let mut content = MessageContent::default();
+        let parts = match message.get("content") {
+            Some(Value::Array(parts)) => parts.clone(),
+            Some(part) => vec![part.clone()],
+            None => Vec::new(),
+        };
+
+        for part in &parts {
+            match part.get("type").and_then(Value::as_str) {
+                Some("text") => {
+                    if !content.text.is_empty() {
+                        content.text.push_str("\n\n");
+                    }
+                    content.text.push_str(&string_field(part, "text"));
+                }
+                Some("image") | Some("audio") => {
+                    let mime_type = string_field(part, "mimeType");
+                    let extension = mime_type.rsplit('/').next().unwrap_or("bin");
+                    let name = format!("prompt.{}", extension);
+                    content.attachments.push(decode_attachment(
+                        &name,
+                        Some(mime_type),
+                        part,
+                        "data",
+                    )?);
+                }
+                Some("resource") => {
+                    let resource = part.get("resource").cloned().unwrap_or_default();
+                    let name = string_field(&resource, "uri");
+                    content
+                        .attachments
+                        .push(resource_contents_attachment(&name, &[resource])?);
+                }
+                _ => {}
+            }
+        }
+
+        // Consecutive messages from the same role are merged so they read as one turn.
+        match parsed.last_mut() {
+            Some(last) if last.role == role => {
+                if !content.text.is_empty() {
+                    if !last.content.text.is_empty() {
+                        last.content.text.push_str("\n\n");
+                    }
+                    last.content.text.push_str(&content.text);
+                }
+                last.content.attachments.extend(content.attachments);
+            }
+            _ => parsed.push(CommandMessage { role, content }),
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Build an attachment from the result of `resources/read`.
+fn resource_attachment(name: &str, result: &Value) -> Result<Attachment, String> {
+    let contents = result
+        .get("contents")
+        .and_then(Value::as_array)
+        .ok_or("The resource has no content")?;
+
+    resource_contents_attachment(name, contents)
+}
+
+/// Build an attachment from resource contents, joining multiple text parts.
+fn resource_contents_attachment(name: &str, contents: &[Value]) -> Result<Attachment, String> {
+    let name = file_name(name);
+
+    if let [single] = contents {
+        if single.get("blob").is_some() {
+            let mime_type = single
+                .get("mimeType")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            return decode_attachment(&name, mime_type, single, "blob");
+        }
+    }
+
+    let text = contents
+        .iter()
+        .filter_map(|content| content.get("text").and_then(Value::as_str))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let mime_type = contents
+        .first()
+        .and_then(|content| content.get("mimeType"))
+        .and_then(Value::as_str)
+        .unwrap_or("text/plain");
+
+    Ok(Attachment::from_bytes(
+        name,
+        Some(mime_type.to_string()),
+        text.as_bytes(),
+    ))
+}
+
+fn decode_attachment(
+    name: &str,
+    mime_type: Option<String>,
+    value: &Value,
+    field: &str,
+) -> Result<Attachment, String> {
+    let data = value.get(field).and_then(Value::as_str).unwrap_or_default();
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| format!("Invalid base64 content in {}: {}", name, e))?;
+
+    Ok(Attachment::from_bytes(name.to_string(), mime_type, &bytes))
+}
+
+/// Turn a name or URI into something usable as a file name.
+fn file_name(name: &str) -> String {
+    let last = name
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .find(|part| !part.is_empty())
+        .unwrap_or("resource");
+
+    last.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prompts() {
+        let result = json!({
+            "prompts": [
+                {
+                    "name": "code_review",
+                    "description": "Review a diff",
+                    "arguments": [
+                        { "name": "diff", "description": "The diff", "required": true },
+                        { "name": "focus" }
+                    ]
+                },
+                { "description": "Missing a name" }
+            ]
+        });
+
+        let commands = parse_prompts("git", &result);
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].id, "git/code_review");
+        assert_eq!(commands[0].arguments.len(), 2);
+        assert!(commands[0].arguments[0].required);
+        assert!(!commands[0].arguments[1].required);
+    }
+
+    #[test]
+    fn test_parse_prompt_messages() {
+        let result = json!({
+            "messages": [
+                { "role": "user", "content": { "type": "text", "text": "Review this" } },
+                {
+                    "role": "user",
+                    "content": {
+                        "type": "resource",
+                        "resource": { "uri": "file:///repo/main.rs", "text": "fn main() {}" }
+                    }
+                },
+                { "role": "assistant", "content": { "type": "text", "text": "Sure" } }
+            ]
+        });
+
+        let messages = parse_prompt_messages(&result).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, CommandRole::User);
+        assert_eq!(messages[0].content.text, "Review this");
+        assert_eq!(messages[0].content.attachments.len(), 1);
+        assert_eq!(messages[0].content.attachments[0].name, "main.rs");
+        assert_eq!(messages[1].role, CommandRole::Assistant);
+    }
+
+    #[test]
+    fn test_parse_resources() {
+        let result = json!({
+            "resources": [
+                { "uri": "postgres://db/users", "name": "users", "description": "User table" },
+                { "uri": "file:///notes.md" }
+            ]
+        });
+
+        let resources = parse_resources("db", &result);
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[0].id, "db/postgres://db/users");
+        assert_eq!(split_id(&resources[0].id), ("db", "postgres://db/users"));
+        assert_eq!(resources[1].name, "file:///notes.md");
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("file:///repo/src/main.rs"), "main.rs");
+        assert_eq!(file_name("postgres://db/users/"), "users");
+        assert_eq!(file_name("notes"), "notes");
+    }
+}
@@ -0,0 +1,604 @@
+//! Import and export of MCP servers from and to other applications.
+//!
+//! Claude Desktop and Cursor keep their servers under `mcpServers`, VS Code
+//! under `servers` in its `mcp.json`, or under `mcp.servers` in its settings.
+//! All of them are read into Moly's format, and merged into the existing
+//! configuration without touching servers already configured differently
+//! unless asked to.
+
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use std::path::PathBuf;
+
+use super::mcp_servers::{InputConfig, McpServer, McpServersConfig};
+
+/// Applications whose MCP configuration can be imported and exported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum McpConfigApp {
+    ClaudeDesktop,
+    Cursor,
+    VsCode,
+}
+
+impl McpConfigApp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ClaudeDesktop => "Claude Desktop",
+            Self::Cursor => "Cursor",
+            Self::VsCode => "VS Code",
+        }
+    }
+
+    /// Where the application keeps its user wide configuration, most
+    /// preferred first.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn config_paths(&self) -> Vec<PathBuf> {
+        let Some(dirs) = directories::BaseDirs::new() else {
+            return Vec::new();
+        };
+
+        match self {
+            Self::ClaudeDesktop => {
+                vec![dirs.config_dir().join("Claude/claude_desktop_config.json")]
+            }
+            Self::Cursor => vec![dirs.home_dir().join(".cursor/mcp.json")],
+            Self::VsCode => vec![
+                dirs.config_dir().join("Code/User/mcp.json"),
+                dirs.config_dir().join("Code/User/settings.json"),
+            ],
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn config_paths(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    /// The configuration file of the application, if it exists on this machine.
+    pub fn locate(&self) -> Option<PathBuf> {
+        self.config_paths().into_iter().find(|path| path.is_file())
+    }
+}
+
+/// Servers and inputs read from another application's configuration.
+#[derive(Debug, Default)]
+pub struct McpImport {
+    pub servers: IndexMap<String, McpServer>,
+    pub inputs: Vec<InputConfig>,
+    /// Entries that could not be imported, or only partially.
+    pub warnings: Vec<String>,
+}
+
+/// What merging an [`McpImport`] changed.
+#[derive(Debug, Default)]
+pub struct McpMergeReport {
+    pub added: Vec<String>,
+    pub replaced: Vec<String>,
+    /// Servers already configured the same way.
+    pub unchanged: Vec<String>,
+    /// Servers already configured differently, which were kept as they are.
+    pub conflicts: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl McpMergeReport {
+    pub fn changed(&self) -> bool {
+        !self.added.is_empty() || !self.replaced.is_empty()
+    }
+
+    /// A few lines describing the result to the user.
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::new();
+        let groups = [
+            ("Added", &self.added),
+            ("Replaced", &self.replaced),
+            ("Already configured", &self.unchanged),
+            (
+                "Kept your existing configuration, which differs from the imported one, of",
+                &self.conflicts,
+            ),
+        ];
+        for (label, ids) in groups {
+            if !ids.is_empty() {
+                lines.push(format!("{}: {}", label, ids.join(", ")));
+            }
+        }
+        lines.extend(self.warnings.iter().cloned());
+
+        if lines.is_empty() {
+            "No servers found to import".to_string()
+        } else {
+            lines.join("\n")
+        }
+    }
+}
+
+/// Read the MCP servers of a configuration from any supported application.
+///
+/// Comments and trailing commas, allowed by VS Code, are accepted.
+pub fn parse(text: &str) -> Result<McpImport, String> {
+    let value: Value =
+        serde_json::from_str(&strip_jsonc(text)).map_err(|e| format!("Invalid JSON: {}", e))?;
+
+    // VS Code settings nest the configuration under `mcp`.
+    let root = value
+        .get("mcp")
+        .filter(|mcp| mcp.is_object())
+        .unwrap_or(&value);
+    let servers = root
+        .get("mcpServers")
+        .or_else(|| root.get("servers"))
+        .and_then(Value::as_object)
+        .ok_or_else(|| "No \"mcpServers\" or \"servers\" found".to_string())?;
+
+    let mut import = McpImport::default();
+    for (id, server) in servers {
+        match parse_server(server) {
+            Ok((server, warnings)) => {
+                import
+                    .warnings
+                    .extend(warnings.into_iter().map(|w| format!("{}: {}", id, w)));
+                import.servers.insert(id.clone(), server);
+            }
+            Err(error) => import.warnings.push(format!("{}: {}, skipped", id, error)),
+        }
+    }
+
+    for input in root
+        .get("inputs")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let Some(id) = input.get("id").and_then(Value::as_str) else {
+            import
+                .warnings
+                .push("Skipped an input without \"id\"".to_string());
+            continue;
+        };
+        let text = |key: &str| input.get(key).and_then(Value::as_str).map(str::to_string);
+
+        import.inputs.push(InputConfig {
+            id: id.to_string(),
+            input_type: text("type").unwrap_or_else(|| "promptString".to_string()),
+            description: text("description").unwrap_or_default(),
+            password: input
+                .get("password")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+        });
+    }
+
+    Ok(import)
+}
+
+/// Fields read from a server entry of another application.
+///
+/// Anything else, including Moly's own extras like `allow_sampling` or
+/// `sandbox`, is left out so an imported file can't grant itself permissions.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ImportedServer {
+    command: Option<String>,
+    args: Vec<String>,
+    env: IndexMap<String, String>,
+    url: Option<String>,
+    #[serde(rename = "serverUrl")]
+    server_url: Option<String>,
+    #[serde(rename = "type")]
+    transport_type: Option<String>,
+    headers: IndexMap<String, String>,
+    cwd: Option<String>,
+    disabled: bool,
+}
+
+/// Map a single server entry, returning what could not be kept as warnings.
+fn parse_server(value: &Value) -> Result<(McpServer, Vec<String>), String> {
+    let object = value
+        .as_object()
+        .ok_or_else(|| "not an object".to_string())?;
+
+    // Values are usually strings, but numbers and booleans are common enough.
+    let mut normalized = object.clone();
+    if let Some(Value::Object(env)) = normalized.get_mut("env") {
+        for value in env.values_mut() {
+            if !value.is_string() {
+                *value = Value::String(value.to_string());
+            }
+        }
+    }
+    let imported: ImportedServer =
+        serde_json::from_value(Value::Object(normalized)).map_err(|e| e.to_string())?;
+    let mut warnings = Vec::new();
+
+    if object.contains_key("envFile") {
+        warnings.push("\"envFile\" is not supported, set the variables in \"env\"".to_string());
+    }
+
+    let mut server = if let Some(command) = imported.command {
+        McpServer::stdio(command, imported.args)
+    } else if let Some(url) = imported.url.or(imported.server_url) {
+        match imported.transport_type.as_deref() {
+            None | Some("http" | "streamable-http" | "streamableHttp" | "streamable_http") => {
+                McpServer::http(url)
+            }
+            Some("sse") => McpServer::sse(url),
+            Some(other) => return Err(format!("unsupported type \"{}\"", other)),
+        }
+    } else {
+        return Err("missing \"command\" or \"url\"".to_string());
+    };
+
+    server.env = imported.env;
+    server.headers = imported.headers;
+    server.working_directory = imported.cwd;
+    server.enabled = !imported.disabled;
+
+    Ok((server, warnings))
+}
+
+/// Merge imported servers and inputs into `config`.
+///
+/// Servers already configured differently are kept, and reported as conflicts,
+/// unless `replace_conflicts` is set.
+pub fn merge(
+    config: &mut McpServersConfig,
+    import: McpImport,
+    replace_conflicts: bool,
+) -> McpMergeReport {
+    let mut report = McpMergeReport {
+        warnings: import.warnings,
+        ..Default::default()
+    };
+
+    for (id, server) in import.servers {
+        match config.servers.get_mut(&id) {
+            None => {
+                config.servers.insert(id.clone(), server);
+                report.added.push(id);
+            }
+            Some(existing) if *existing == server => report.unchanged.push(id),
+            Some(existing) if replace_conflicts => {
+                *existing = server;
+                report.replaced.push(id);
+            }
+            Some(_) => report.conflicts.push(id),
+        }
+    }
+
+    for input in import.inputs {
+        if config.get_input(&input.id).is_none() {
+            config.add_input(input);
+        }
+    }
+
+    report
+}
+
+/// The enabled servers of `config` in the format of `app`.
+///
+/// Claude Desktop only runs local servers, so remote ones go through the
+/// `mcp-remote` proxy.
+pub fn export(config: &McpServersConfig, app: McpConfigApp) -> String {
+    let mut servers = Map::new();
+    for (id, server) in config.list_enabled_servers() {
+        let entry = match app {
+            McpConfigApp::ClaudeDesktop => export_claude_server(server),
+            McpConfigApp::Cursor => export_cursor_server(server),
+            McpConfigApp::VsCode => export_vscode_server(server),
+        };
+        servers.insert(id.clone(), Value::Object(entry));
+    }
+
+    let value = match app {
+        McpConfigApp::ClaudeDesktop | McpConfigApp::Cursor => json!({ "mcpServers": servers }),
+        McpConfigApp::VsCode if config.inputs.is_empty() => json!({ "servers": servers }),
+        McpConfigApp::VsCode => json!({
+            "servers": servers,
+            "inputs": config.inputs.iter().map(|input| json!({
+                "type": input.input_type,
+                "id": input.id,
+                "description": input.description,
+                "password": input.password,
+            })).collect::<Vec<_>>(),
+        }),
+    };
+
+    serde_json::to_string_pretty(&value).unwrap_or_else(|_| "{}".to_string())
+}
+
+fn export_stdio_server(server: &McpServer, entry: &mut Map<String, Value>) {
+    entry.insert("command".to_string(), json!(server.command));
+    if !server.args.is_empty() {
+        entry.insert("args".to_string(), json!(server.args));
+    }
+    if !server.env.is_empty() {
+        entry.insert("env".to_string(), json!(server.env));
+    }
+}
+
+fn export_claude_server(server: &McpServer) -> Map<String, Value> {
+    let mut entry = Map::new();
+    if server.is_stdio() {
+        export_stdio_server(server, &mut entry);
+        return entry;
+    }
+
+    let mut args = vec!["-y".to_string(), "mcp-remote".to_string()];
+    args.extend(server.url.clone());
+    if server.transport_type.as_deref() == Some("sse") {
+        args.extend(["--transport".to_string(), "sse-only".to_string()]);
+    }
+    for (name, value) in &server.headers {
+        args.extend(["--header".to_string(), format!("{}: {}", name, value)]);
+    }
+
+    entry.insert("command".to_string(), json!("npx"));
+    entry.insert("args".to_string(), json!(args));
+    entry
+}
+
+fn export_cursor_server(server: &McpServer) -> Map<String, Value> {
+    let mut entry = Map::new();
+    if server.is_stdio() {
+        export_stdio_server(server, &mut entry);
+    } else {
+        entry.insert("url".to_string(), json!(server.url));
+        if !server.headers.is_empty() {
+            entry.insert("headers".to_string(), json!(server.headers));
+        }
+    }
+    entry
+}
+
+fn export_vscode_server(server: &McpServer) -> Map<String, Value> {
+    let mut entry = export_cursor_server(server);
+    let transport = server.get_transport_type().unwrap_or("stdio");
+    entry.insert("type".to_string(), json!(transport));
+    if let Some(working_directory) = &server.working_directory {
+        entry.insert("cwd".to_string(), json!(working_directory));
+    }
+    entry
+}
+
+/// Remove comments and trailing commas, outside of strings.
+fn strip_jsonc(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut output = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        i += 1;
+
+        if in_string {
+            output.push(c);
+            match c {
+                '\\' => {
+                    output.extend(next);
+                    i += 1;
+                }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, next) {
+            ('"', _) => {
+                in_string = true;
+                output.push(c);
+            }
+            ('/', Some('/')) => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            ('/', Some('*')) => {
+                i += 2;
+                while i < chars.len() && !(chars[i - 1] == '*' && chars[i] == '/') {
+                    i += 1;
+                }
+                i += 1;
+            }
+            (',', _) if closes_after_comma(&chars[i..]) => {}
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
+
+/// Whether only whitespace and comments come before the next `}` or `]`.
+fn closes_after_comma(rest: &[char]) -> bool {
+    let mut i = 0;
+    while i < rest.len() {
+        match (rest[i], rest.get(i + 1)) {
+            (c, _) if c.is_whitespace() => i += 1,
+            ('/', Some('/')) => {
+                while i < rest.len() && rest[i] != '\n' {
+                    i += 1;
+                }
+            }
+            ('/', Some('*')) => {
+                i += 3;
+                while i < rest.len() && !(rest[i - 1] == '*' && rest[i] == '/') {
+                    i += 1;
+                }
+                i += 1;
+            }
+            (c, _) => return c == '}' || c == ']',
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_claude_desktop() {
+        let json = r#"{
+            "mcpServers": {
+                "filesystem": {
+                    "command": "npx",
+                    "args": ["-y", "@modelcontextprotocol/server-filesystem", "/tmp"],
+                    "env": { "DEBUG": true }
+                },
+                "remote": { "type": "streamable-http", "url": "https://example.com/mcp" },
+                "legacy": { "serverUrl": "https://example.com/sse", "type": "sse", "disabled": true },
+                "broken": { "args": [] }
+            },
+            "globalShortcut": "Ctrl+Space"
+        }"#;
+
+        let import = parse(json).unwrap();
+        assert_eq!(import.servers.len(), 3);
+
+        let filesystem = &import.servers["filesystem"];
+        assert_eq!(filesystem.command.as_deref(), Some("npx"));
+        assert_eq!(filesystem.env["DEBUG"], "true");
+
+        let remote = &import.servers["remote"];
+        assert_eq!(remote.transport_type.as_deref(), Some("http"));
+
+        let legacy = &import.servers["legacy"];
+        assert_eq!(legacy.url.as_deref(), Some("https://example.com/sse"));
+        assert_eq!(legacy.transport_type.as_deref(), Some("sse"));
+        assert!(!legacy.enabled);
+
+        assert_eq!(import.warnings.len(), 1);
+        assert!(import.warnings[0].starts_with("broken:"));
+    }
+
+    #[test]
+    fn test_parse_vscode_settings() {
+        let json = r#"{
+            // Editor settings come first
+            "editor.fontSize": 13,
+            "mcp": {
+                "inputs": [
+                    { "type": "promptString", "id": "token", "description": "API token", "password": true },
+                ],
+                "servers": {
+                    /* Local server */
+                    "github": {
+                        "type": "stdio",
+                        "command": "docker",
+                        "args": ["run", "-i", "ghcr.io/github/github-mcp-server"],
+                        "env": { "GITHUB_TOKEN": "${input:token}" },
+                        "cwd": "/home/user",
+                    },
+                },
+            },
+        }"#;
+
+        let import = parse(json).unwrap();
+        let github = &import.servers["github"];
+        assert_eq!(github.transport_type, None);
+        assert_eq!(github.working_directory.as_deref(), Some("/home/user"));
+        assert_eq!(github.env["GITHUB_TOKEN"], "${input:token}");
+        assert_eq!(import.inputs.len(), 1);
+        assert!(import.inputs[0].password);
+        assert!(import.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parse_ignores_moly_extras() {
+        let json = r#"{
+            "mcpServers": {
+                "shell": {
+                    "command": "sh",
+                    "allow_sampling": true,
+                    "disabled_tools": ["rm"],
+                    "tool_timeout_secs": 1
+                }
+            }
+        }"#;
+
+        let import = parse(json).unwrap();
+        assert_eq!(
+            import.servers["shell"],
+            McpServer::stdio("sh".to_string(), Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_strip_jsonc_keeps_strings() {
+        let text = r#"{ "url": "http://a/b,}", /* c */ "x": "// not a comment", }"#;
+        let value: Value = serde_json::from_str(&strip_jsonc(text)).unwrap();
+        assert_eq!(value["url"], "http://a/b,}");
+        assert_eq!(value["x"], "// not a comment");
+    }
+
+    #[test]
+    fn test_merge_reports_conflicts() {
+        let mut config = McpServersConfig::new();
+        config.add_server(
+            "same".to_string(),
+            McpServer::stdio("node".to_string(), vec!["a.js".to_string()]),
+        );
+        config.add_server(
+            "different".to_string(),
+            McpServer::stdio("node".to_string(), vec!["b.js".to_string()]),
+        );
+
+        let import = || {
+            parse(
+                r#"{ "mcpServers": {
+                    "same": { "command": "node", "args": ["a.js"] },
+                    "different": { "command": "node", "args": ["c.js"] },
+                    "new": { "url": "https://example.com/mcp" }
+                } }"#,
+            )
+            .unwrap()
+        };
+
+        let report = merge(&mut config, import(), false);
+        assert_eq!(report.added, vec!["new"]);
+        assert_eq!(report.unchanged, vec!["same"]);
+        assert_eq!(report.conflicts, vec!["different"]);
+        assert_eq!(config.servers["different"].args, vec!["b.js"]);
+
+        let report = merge(&mut config, import(), true);
+        assert_eq!(report.replaced, vec!["different"]);
+        assert_eq!(config.servers["different"].args, vec!["c.js"]);
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let mut config = McpServersConfig::new();
+        config.add_server(
+            "local".to_string(),
+            McpServer::stdio("uvx".to_string(), vec!["server".to_string()]),
+        );
+        config.add_server(
+            "remote".to_string(),
+            McpServer::sse("https://example.com/sse".to_string()),
+        );
+
+        let cursor = parse(&export(&config, McpConfigApp::Cursor)).unwrap();
+        assert_eq!(cursor.servers["local"], config.servers["local"]);
+
+        let vscode = parse(&export(&config, McpConfigApp::VsCode)).unwrap();
+        assert_eq!(vscode.servers["local"], config.servers["local"]);
+        assert_eq!(vscode.servers["remote"], config.servers["remote"]);
+
+        let claude: Value =
+            serde_json::from_str(&export(&config, McpConfigApp::ClaudeDesktop)).unwrap();
+        assert_eq!(
+            claude["mcpServers"]["remote"]["args"],
+            json!([
+                "-y",
+                "mcp-remote",
+                "https://example.com/sse",
+                "--transport",
+                "sse-only"
+            ])
+        );
+    }
+}
@@ -0,0 +1,804 @@
+//! OAuth authorization for remote MCP servers.
+//!
+//! Implements the flow described by the MCP authorization spec: the
+//! authorization server is discovered from the MCP server, a client is
+//! registered dynamically when none is configured, and the user signs in from
+//! the browser through a PKCE authorization-code flow redirecting to a loopback
+//! listener. Tokens are stored per server, readable by the user only, and
+//! refreshed before they expire or when the server rejects them.
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::Engine;
+use moly_kit::utils::mcp::TokenSource;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use url::Url;
+
+use super::mcp_servers::{McpOAuthConfig, McpServer};
+use crate::shared::utils::filesystem;
+
+const TOKENS_DIR: &str = "preferences";
+const TOKENS_FILENAME: &str = "mcp_oauth_tokens.json";
+
+/// How long the user has to complete the sign in from the browser.
+const AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Tokens are refreshed this long before they expire.
+const EXPIRY_MARGIN_SECS: u64 = 60;
+
+/// Largest request line and headers read by the loopback listener.
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// Largest request body read by the loopback listener.
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// The parts of the authorization server metadata (RFC 8414) used by Moly.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthorizationServerMetadata {
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    #[serde(default)]
+    pub registration_endpoint: Option<String>,
+    #[serde(default)]
+    pub scopes_supported: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProtectedResourceMetadata {
+    #[serde(default)]
+    authorization_servers: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClientRegistration {
+    client_id: String,
+    #[serde(default)]
+    client_secret: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+/// Credentials obtained for a server, with what is needed to refresh them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpOAuthTokens {
+    pub client_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    pub token_endpoint: String,
+    /// The MCP server the tokens were issued for.
+    pub resource: String,
+    pub access_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// Unix time in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+impl McpOAuthTokens {
+    fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| now() + EXPIRY_MARGIN_SECS >= expires_at)
+    }
+
+    /// Whether to refresh before sending the access token again, given the
+    /// token the server rejected last, if any.
+    fn needs_refresh(&self, rejected: Option<&str>) -> bool {
+        self.is_expired() || rejected == Some(self.access_token.as_str())
+    }
+}
+
+/// Find the authorization server protecting an MCP server.
+///
+/// Tries the protected resource metadata first, then falls back to treating
+/// the MCP server origin as the authorization server, and finally to the
+/// default endpoint paths when no metadata is published at all.
+pub async fn discover(
+    http: &reqwest::Client,
+    server_url: &str,
+) -> Result<AuthorizationServerMetadata> {
+    let server_url = Url::parse(server_url).context("Invalid server URL")?;
+    let origin = origin(&server_url);
+
+    let mut resource_metadata_urls = vec![format!(
+        "{}/.well-known/oauth-protected-resource{}",
+        origin,
+        server_url.path().trim_end_matches('/')
+    )];
+    resource_metadata_urls.push(format!("{}/.well-known/oauth-protected-resource", origin));
+    resource_metadata_urls.dedup();
+
+    let mut issuer = None;
+    for url in &resource_metadata_urls {
+        if let Ok(metadata) = get_json::<ProtectedResourceMetadata>(http, url).await {
+            issuer = metadata.authorization_servers.into_iter().next();
+            break;
+        }
+    }
+
+    let issuer = Url::parse(issuer.as_deref().unwrap_or(&origin))
+        .context("Invalid authorization server URL")?;
+    let issuer_origin = self::origin(&issuer);
+    let issuer_path = issuer.path().trim_end_matches('/');
+
+    let candidates = [
+        format!(
+            "{}/.well-known/oauth-authorization-server{}",
+            issuer_origin, issuer_path
+        ),
+        format!(
+            "{}/.well-known/openid-configuration{}",
+            issuer_origin, issuer_path
+        ),
+        format!(
+            "{}{}/.well-known/openid-configuration",
+            issuer_origin, issuer_path
+        ),
+    ];
+
+    for url in &candidates {
+        if let Ok(metadata) = get_json::<AuthorizationServerMetadata>(http, url).await {
+            return Ok(metadata);
+        }
+    }
+
+    Ok(AuthorizationServerMetadata {
+        authorization_endpoint: format!("{}/authorize", issuer_origin),
+        token_endpoint: format!("{}/token", issuer_origin),
+        registration_endpoint: Some(format!("{}/register", issuer_origin)),
+        scopes_supported: Vec::new(),
+    })
+}
+
+/// Run the whole sign in flow for a server.
+///
+/// `open_browser` is called with the URL the user must visit. The future
+/// resolves once the browser is redirected back to Moly, or fails after a few
+/// minutes without an answer.
+pub async fn authorize(
+    server_url: &str,
+    config: &McpOAuthConfig,
+    open_browser: impl FnOnce(&str),
+) -> Result<McpOAuthTokens> {
+    let http = reqwest::Client::new();
+    let metadata = discover(&http, server_url).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .context("Could not start the sign in listener")?;
+    let redirect_uri = format!(
+        "http://127.0.0.1:{}/callback",
+        listener.local_addr()?.port()
+    );
+
+    let (client_id, client_secret) = match &config.client_id {
+        Some(client_id) => (client_id.clone(), None),
+        None => register_client(&http, &metadata, &redirect_uri).await?,
+    };
+
+    let scopes = if config.scopes.is_empty() {
+        metadata.scopes_supported.clone()
+    } else {
+        config.scopes.clone()
+    };
+
+    let (verifier, challenge) = pkce_pair();
+    let state = random_string(32);
+
+    let mut authorization_url =
+        Url::parse(&metadata.authorization_endpoint).context("Invalid authorization endpoint")?;
+    {
+        let mut query = authorization_url.query_pairs_mut();
+        query
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &client_id)
+            .append_pair("redirect_uri", &redirect_uri)
+            .append_pair("code_challenge", &challenge)
+            .append_pair("code_challenge_method", "S256")
+            .append_pair("state", &state)
+            .append_pair("resource", server_url);
+        if !scopes.is_empty() {
+            query.append_pair("scope", &scopes.join(" "));
+        }
+    }
+
+    open_browser(authorization_url.as_str());
+
+    let code = tokio::time::timeout(AUTHORIZATION_TIMEOUT, wait_for_callback(&listener, &state))
+        .await
+        .map_err(|_| anyhow!("Sign in timed out"))??;
+
+    let mut params = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", redirect_uri.as_str()),
+        ("client_id", client_id.as_str()),
+        ("code_verifier", verifier.as_str()),
+        ("resource", server_url),
+    ];
+    if let Some(secret) = &client_secret {
+        params.push(("client_secret", secret.as_str()));
+    }
+
+    let response = request_token(&http, &metadata.token_endpoint, &params).await?;
+
+    Ok(McpOAuthTokens {
+        client_id,
+        client_secret,
+        token_endpoint: metadata.token_endpoint,
+        resource: server_url.to_string(),
+        access_token: response.access_token,
+        refresh_token: response.refresh_token,
+        expires_at: response.expires_in.map(|expires_in| now() + expires_in),
+    })
+}
+
+/// Exchange the refresh token for a new access token.
+pub async fn refresh(tokens: &McpOAuthTokens) -> Result<McpOAuthTokens> {
+    let refresh_token = tokens
+        .refresh_token
+        .as_deref()
+        .ok_or_else(|| anyhow!("The session expired, sign in again"))?;
+
+    let mut params = vec![
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token),
+        ("client_id", tokens.client_id.as_str()),
+        ("resource", tokens.resource.as_str()),
+    ];
+    if let Some(secret) = &tokens.client_secret {
+        params.push(("client_secret", secret.as_str()));
+    }
+
+    let response = request_token(&reqwest::Client::new(), &tokens.token_endpoint, &params).await?;
+
+    Ok(McpOAuthTokens {
+        access_token: response.access_token,
+        // Servers not rotating refresh tokens keep the previous one valid.
+        refresh_token: response
+            .refresh_token
+            .or_else(|| tokens.refresh_token.clone()),
+        expires_at: response.expires_in.map(|expires_in| now() + expires_in),
+        ..tokens.clone()
+    })
+}
+
+/// Whether the user signed in to the server.
+pub async fn has_tokens(server_id: &str) -> bool {
+    load_tokens().await.contains_key(server_id)
+}
+
+/// Access tokens for the requests of the MCP client to a server.
+///
+/// Tokens are kept in memory once loaded, and refreshed then stored again
+/// before they expire or when the server rejects them. Concurrent requests
+/// wait for the same refresh.
+pub fn token_source(server_id: &str) -> Arc<TokenSource> {
+    let server_id = server_id.to_string();
+    let cache = Arc::new(futures::lock::Mutex::new(None::<McpOAuthTokens>));
+
+    Arc::new(move |rejected: Option<String>| {
+        let server_id = server_id.clone();
+        let cache = cache.clone();
+        Box::pin(async move {
+            let mut cache = cache.lock().await;
+            let tokens = match cache.take() {
+                Some(tokens) => tokens,
+                None => load_tokens()
+                    .await
+                    .remove(&server_id)
+                    .ok_or("Sign in required")?,
+            };
+
+            let tokens = if tokens.needs_refresh(rejected.as_deref()) {
+                let refreshed = refresh(&tokens).await.map_err(|e| e.to_string())?;
+                store_tokens(&server_id, refreshed.clone())
+                    .await
+                    .map_err(|e| e.to_string())?;
+                refreshed
+            } else {
+                tokens
+            };
+
+            let access_token = tokens.access_token.clone();
+            *cache = Some(tokens);
+            Ok(access_token)
+        })
+    })
+}
+
+/// Source of the OAuth access tokens of the server, if it uses OAuth.
+///
+/// Fails when the user didn't sign in or the tokens can no longer be refreshed.
+pub async fn connection_auth(
+    server_id: &str,
+    server: &McpServer,
+) -> std::result::Result<Option<Arc<TokenSource>>, String> {
+    if server.oauth.is_none() || !server.is_network() {
+        return Ok(None);
+    }
+
+    if !has_tokens(server_id).await {
+        return Err("Sign in required".to_string());
+    }
+
+    // Refreshes expired tokens now, so failures show before connecting.
+    let source = token_source(server_id);
+    source(None).await?;
+    Ok(Some(source))
+}
+
+pub async fn store_tokens(server_id: &str, tokens: McpOAuthTokens) -> Result<()> {
+    let mut all = load_tokens().await;
+    all.insert(server_id.to_string(), tokens);
+    save_tokens(&all).await
+}
+
+pub async fn forget_tokens(server_id: &str) -> Result<()> {
+    let mut all = load_tokens().await;
+    if all.remove(server_id).is_some() {
+        save_tokens(&all).await?;
+    }
+    Ok(())
+}
+
+async fn load_tokens() -> HashMap<String, McpOAuthTokens> {
+    filesystem::global()
+        .read_json(&tokens_path())
+        .await
+        .unwrap_or_default()
+}
+
+async fn save_tokens(tokens: &HashMap<String, McpOAuthTokens>) -> Result<()> {
+    let content = serde_json::to_vec(tokens)?;
+    filesystem::global()
+        .write_private(&tokens_path(), &content)
+        .await
+}
+
+fn tokens_path() -> PathBuf {
+    Path::new(TOKENS_DIR).join(TOKENS_FILENAME)
+}
+
+async fn register_client(
+    http: &reqwest::Client,
+    metadata: &AuthorizationServerMetadata,
+    redirect_uri: &str,
+) -> Result<(String, Option<String>)> {
+    let endpoint = metadata.registration_endpoint.as_deref().ok_or_else(|| {
+        anyhow!("The server does not support client registration, configure a \"client_id\"")
+    })?;
+
+    let response = http
+        .post(endpoint)
+        .json(&serde_json::json!({
+            "client_name": "Moly",
+            "redirect_uris": [redirect_uri],
+            "grant_types": ["authorization_code", "refresh_token"],
+            "response_types": ["code"],
+            "token_endpoint_auth_method": "none",
+        }))
+        .send()
+        .await
+        .context("Client registration failed")?;
+
+    if !response.status().is_success() {
+        bail!(
+            "Client registration failed with status {}",
+            response.status()
+        );
+    }
+
+    let registration: ClientRegistration = response
+        .json()
+        .await
+        .context("Invalid client registration response")?;
+
+    Ok((registration.client_id, registration.client_secret))
+}
+
+async fn request_token(
+    http: &reqwest::Client,
+    token_endpoint: &str,
+    params: &[(&str, &str)],
+) -> Result<TokenResponse> {
+    let response = http
+        .post(token_endpoint)
+        .form(params)
+        .send()
+        .await
+        .context("Token request failed")?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        bail!("Token request failed with status {}: {}", status, body);
+    }
+
+    response.json().await.context("Invalid token response")
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(http: &reqwest::Client, url: &str) -> Result<T> {
+    let response = http.get(url).send().await?;
+    if !response.status().is_success() {
+        bail!("{} returned {}", url, response.status());
+    }
+    Ok(response.json().await?)
+}
+
+/// Accept connections on the loopback listener until the browser comes back
+/// with the authorization code.
+async fn wait_for_callback(listener: &TcpListener, state: &str) -> Result<String> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let Ok((_, target, _)) = read_http_request(&mut stream).await else {
+            continue;
+        };
+
+        let Ok(url) = Url::parse(&format!("http://127.0.0.1{}", target)) else {
+            continue;
+        };
+        if url.path() != "/callback" {
+            write_http_response(&mut stream, "404 Not Found", "").await;
+            continue;
+        }
+
+        let query: HashMap<_, _> = url.query_pairs().into_owned().collect();
+
+        // Not the answer to this request, which may still come until the timeout.
+        if query.get("state").map(String::as_str) != Some(state) {
+            write_http_response(
+                &mut stream,
+                "400 Bad Request",
+                "Sign in response does not match the request.",
+            )
+            .await;
+            continue;
+        }
+
+        let result = if let Some(error) = query.get("error") {
+            let description = query.get("error_description").unwrap_or(error);
+            Err(anyhow!("Sign in was refused: {}", description))
+        } else {
+            query
+                .get("code")
+                .cloned()
+                .ok_or_else(|| anyhow!("Sign in response without a code"))
+        };
+
+        let page = match &result {
+            Ok(_) => "Signed in. You can close this window and go back to Moly.",
+            Err(_) => "Sign in failed. You can close this window and try again from Moly.",
+        };
+        write_http_response(&mut stream, "200 OK", page).await;
+
+        return result;
+    }
+}
+
+/// Read a request, returning its method, target and body.
+///
+/// Requests with a head over [`MAX_HEAD_SIZE`] or a body over [`MAX_BODY_SIZE`]
+/// are rejected before reading the rest.
+async fn read_http_request(stream: &mut TcpStream) -> Result<(String, String, String)> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    let header_end = loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            bail!("Connection closed");
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+        if buffer.len() > MAX_HEAD_SIZE {
+            bail!("Request too large");
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let content_length = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    if content_length > MAX_BODY_SIZE {
+        bail!("Request body too large");
+    }
+
+    while buffer.len() < header_end + content_length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or_default().to_string();
+    let body_end = buffer.len().min(header_end + content_length);
+    let body = String::from_utf8_lossy(&buffer[header_end..body_end]).to_string();
+
+    Ok((method, target, body))
+}
+
+async fn write_http_response(stream: &mut TcpStream, status: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// A PKCE code verifier and its S256 challenge.
+fn pkce_pair() -> (String, String) {
+    let verifier = random_string(64);
+    (verifier.clone(), pkce_challenge(&verifier))
+}
+
+fn pkce_challenge(verifier: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+fn random_string(len: usize) -> String {
+    rand::rng()
+        .sample_iter(&rand::distr::Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn origin(url: &Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct MockState {
+        challenge: Option<String>,
+        registered_redirect: Option<String>,
+    }
+
+    /// A minimal authorization server protecting an MCP server at `/mcp`.
+    async fn start_mock_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let server_base = base.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let base = server_base.clone();
+                let state = state.clone();
+                tokio::spawn(async move {
+                    let (method, target, body) = read_http_request(&mut stream).await.unwrap();
+                    let url = Url::parse(&format!("{}{}", base, target)).unwrap();
+                    let query: HashMap<_, _> = url.query_pairs().into_owned().collect();
+                    let form: HashMap<String, String> =
+                        url::form_urlencoded::parse(body.as_bytes())
+                            .into_owned()
+                            .collect();
+
+                    let json = |value: serde_json::Value| {
+                        let body = value.to_string();
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                            body.len(),
+                            body
+                        )
+                    };
+
+                    let response = match (method.as_str(), url.path()) {
+                        ("GET", "/.well-known/oauth-protected-resource/mcp") => {
+                            json(serde_json::json!({
+                                "resource": format!("{}/mcp", base),
+                                "authorization_servers": [base],
+                            }))
+                        }
+                        ("GET", "/.well-known/oauth-authorization-server") => {
+                            json(serde_json::json!({
+                                "issuer": base,
+                                "authorization_endpoint": format!("{}/authorize", base),
+                                "token_endpoint": format!("{}/token", base),
+                                "registration_endpoint": format!("{}/register", base),
+                                "scopes_supported": ["tools"],
+                            }))
+                        }
+                        ("POST", "/register") => {
+                            let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+                            state.lock().unwrap().registered_redirect =
+                                request["redirect_uris"][0].as_str().map(str::to_string);
+                            json(serde_json::json!({ "client_id": "mock-client" }))
+                        }
+                        ("GET", "/authorize") => {
+                            assert_eq!(query["client_id"], "mock-client");
+                            assert_eq!(query["code_challenge_method"], "S256");
+                            assert_eq!(query["scope"], "tools");
+                            assert_eq!(
+                                Some(&query["redirect_uri"]),
+                                state.lock().unwrap().registered_redirect.as_ref()
+                            );
+                            state.lock().unwrap().challenge = Some(query["code_challenge"].clone());
+                            format!(
+                                "HTTP/1.1 302 Found\r\nLocation: {}?code=mock-code&state={}\r\nContent-Length: 0\r\n\r\n",
+                                query["redirect_uri"], query["state"]
+                            )
+                        }
+                        ("POST", "/token") => match form["grant_type"].as_str() {
+                            "authorization_code" => {
+                                assert_eq!(form["code"], "mock-code");
+                                let challenge = state.lock().unwrap().challenge.clone();
+                                assert_eq!(Some(pkce_challenge(&form["code_verifier"])), challenge);
+                                json(serde_json::json!({
+                                    "access_token": "access-1",
+                                    "token_type": "Bearer",
+                                    "expires_in": 3600,
+                                    "refresh_token": "refresh-1",
+                                }))
+                            }
+                            "refresh_token" => {
+                                assert_eq!(form["refresh_token"], "refresh-1");
+                                json(serde_json::json!({
+                                    "access_token": "access-2",
+                                    "token_type": "Bearer",
+                                    "expires_in": 3600,
+                                }))
+                            }
+                            _ => {
+                                "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n".to_string()
+                            }
+                        },
+                        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+                    };
+
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+
+        base
+    }
+
+    #[test]
+    fn test_pkce_challenge() {
+        // Example from RFC 7636, appendix B.
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCQaoWM0Ng0DE-Ao82aTb5YU"
+        );
+    }
+
+    #[test]
+    fn test_authorize_and_refresh_against_mock_server() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let base = start_mock_server().await;
+            let server_url = format!("{}/mcp", base);
+
+            let metadata = discover(&reqwest::Client::new(), &server_url)
+                .await
+                .unwrap();
+            assert_eq!(metadata.token_endpoint, format!("{}/token", base));
+
+            // Plays the browser, following the redirect back to the loopback listener.
+            let open_browser = |url: &str| {
+                let url = url.to_string();
+                tokio::spawn(async move {
+                    reqwest::get(url).await.unwrap();
+                });
+            };
+
+            let tokens = authorize(&server_url, &McpOAuthConfig::default(), open_browser)
+                .await
+                .unwrap();
+            assert_eq!(tokens.client_id, "mock-client");
+            assert_eq!(tokens.access_token, "access-1");
+            assert!(!tokens.is_expired());
+            assert!(!tokens.needs_refresh(Some("access-0")));
+            assert!(tokens.needs_refresh(Some("access-1")));
+
+            let refreshed = refresh(&tokens).await.unwrap();
+            assert_eq!(refreshed.access_token, "access-2");
+            assert_eq!(refreshed.refresh_token.as_deref(), Some("refresh-1"));
+        });
+    }
+
+    /// Send `request` to `address`, returning the status line of the answer.
+    async fn send_raw(address: std::net::SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response).await;
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn test_callback_waits_past_other_states() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            let waiting =
+                tokio::spawn(async move { wait_for_callback(&listener, "expected").await });
+
+            let forged = "GET /callback?state=other&error=access_denied HTTP/1.1\r\n\r\n";
+            assert!(send_raw(address, forged).await.contains("400"));
+
+            let answer = "GET /callback?state=expected&code=abc HTTP/1.1\r\n\r\n";
+            assert!(send_raw(address, answer).await.contains("200"));
+            assert_eq!(waiting.await.unwrap().unwrap(), "abc");
+        });
+    }
+
+    #[test]
+    fn test_request_body_is_capped() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            let reading = tokio::spawn(async move {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                read_http_request(&mut stream)
+                    .await
+                    .map(|(_, _, body)| body)
+            });
+
+            let mut client = TcpStream::connect(address).await.unwrap();
+            let head = format!(
+                "POST /token HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+                MAX_BODY_SIZE + 1
+            );
+            client.write_all(head.as_bytes()).await.unwrap();
+            assert!(reading.await.unwrap().is_err());
+
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            let reading = tokio::spawn(async move {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                read_http_request(&mut stream)
+                    .await
+                    .map(|(_, _, body)| body)
+            });
+            let mut client = TcpStream::connect(address).await.unwrap();
+            client
+                .write_all(b"POST /token HTTP/1.1\r\nContent-Length: 4\r\n\r\ncode")
+                .await
+                .unwrap();
+            assert_eq!(reading.await.unwrap().unwrap(), "code");
+        });
+    }
+}
@@ -0,0 +1,607 @@
+//! Requests sent by MCP servers to Moly.
+//!
+//! Servers can ask for an LLM completion (`sampling/createMessage`) or for
+//! structured input from the user (`elicitation/create`). Requests are queued in
+//! the store until the user answers them from the chat.
+
+use futures::channel::oneshot;
+use moly_kit::prelude::*;
+use moly_kit::utils::mcp::McpClient;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+
+use crate::app::app_runner;
+
+/// Rough number of characters per token, used to honour `maxTokens` since bot
+/// clients have no way to limit the length of a reply.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Who a message in a sampling request comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplingRole {
+    User,
+    Assistant,
+}
+
+/// A completion requested by a server through `sampling/createMessage`.
+#[derive(Debug, Clone)]
+pub struct SamplingRequest {
+    pub messages: Vec<(SamplingRole, MessageContent)>,
+    pub system_prompt: Option<String>,
+    pub max_tokens: Option<u32>,
+    /// Names of the models the server would prefer, best first.
+    pub model_hints: Vec<String>,
+}
+
+impl SamplingRequest {
+    pub fn from_params(params: &Value) -> Result<Self, String> {
+        let messages = params
+            .get("messages")
+            .and_then(Value::as_array)
+            .ok_or("Sampling request without messages")?;
+
+        let messages = messages
+            .iter()
+            .map(|message| {
+                let role = match message.get("role").and_then(Value::as_str) {
+                    Some("assistant") => SamplingRole::Assistant,
+                    _ => SamplingRole::User,
+                };
+
+                let mut content = MessageContent::default();
+                let parts = match message.get("content") {
+                    Some(Value::Array(parts)) => parts.clone(),
+                    Some(part) => vec![part.clone()],
+                    None => Vec::new(),
+                };
+
+                for part in &parts {
+                    match part.get("type").and_then(Value::as_str) {
+                        Some("text") => {
+                            if !content.text.is_empty() {
+                                content.text.push_str("\n\n");
+                            }
+                            content
+                                .text
+                                .push_str(part.get("text").and_then(Value::as_str).unwrap_or(""));
+                        }
+                        Some("image") | Some("audio") => {
+                            let data = part.get("data").and_then(Value::as_str).unwrap_or("");
+                            let mime_type = part
+                                .get("mimeType")
+                                .and_then(Value::as_str)
+                                .unwrap_or("application/octet-stream");
+                            let bytes = base64_decode(data)?;
+                            let extension = mime_type.rsplit('/').next().unwrap_or("bin");
+                            content.attachments.push(Attachment::from_bytes(
+                                format!("sampling.{}", extension),
+                                Some(mime_type.to_string()),
+                                &bytes,
+                            ));
+                        }
+                        _ => {}
+                    }
+                }
+
+                Ok((role, content))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let system_prompt = params
+            .get("systemPrompt")
+            .and_then(Value::as_str)
+            .filter(|prompt| !prompt.trim().is_empty())
+            .map(str::to_string);
+
+        let max_tokens = params
+            .get("maxTokens")
+            .and_then(Value::as_u64)
+            .map(|max| max.min(u32::MAX as u64) as u32);
+
+        let model_hints = params
+            .pointer("/modelPreferences/hints")
+            .and_then(Value::as_array)
+            .map(|hints| {
+                hints
+                    .iter()
+                    .filter_map(|hint| hint.get("name").and_then(Value::as_str))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            messages,
+            system_prompt,
+            max_tokens,
+            model_hints,
+        })
+    }
+
+    /// Cut `text` to the length allowed by `maxTokens`, if it's longer.
+    pub fn truncate<'a>(&self, text: &'a str) -> Option<&'a str> {
+        let max_chars = self.max_tokens? as usize * CHARS_PER_TOKEN;
+        let (end, _) = text.char_indices().nth(max_chars)?;
+        Some(&text[..end])
+    }
+
+    /// First of `bots` matching the model hints of the server.
+    ///
+    /// Hints are matched as substrings of the bot ids and names, like the spec
+    /// suggests.
+    pub fn preferred_bot<'a>(&self, bots: &'a [Bot]) -> Option<&'a Bot> {
+        self.model_hints.iter().find_map(|hint| {
+            let hint = hint.to_lowercase();
+            bots.iter().find(|bot| {
+                bot.id.as_str().to_lowercase().contains(&hint)
+                    || bot.name.to_lowercase().contains(&hint)
+            })
+        })
+    }
+
+    /// Messages to send to the bot answering the request.
+    pub fn to_messages(&self, bot_id: &BotId) -> Vec<Message> {
+        let system = self.system_prompt.iter().map(|prompt| Message {
+            from: EntityId::System,
+            content: MessageContent {
+                text: prompt.clone(),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        let conversation = self.messages.iter().map(|(role, content)| Message {
+            from: match role {
+                SamplingRole::User => EntityId::User,
+                SamplingRole::Assistant => EntityId::Bot(bot_id.clone()),
+            },
+            content: content.clone(),
+            ..Default::default()
+        });
+
+        system.chain(conversation).collect()
+    }
+
+    /// Readable version of the request, shown before approving it.
+    pub fn transcript(&self) -> String {
+        let system = self
+            .system_prompt
+            .iter()
+            .map(|prompt| format!("System: {}", prompt));
+
+        let conversation = self.messages.iter().map(|(role, content)| {
+            let role = match role {
+                SamplingRole::User => "User",
+                SamplingRole::Assistant => "Assistant",
+            };
+            let attachments = content
+                .attachments
+                .iter()
+                .map(|attachment| format!(" [{}]", attachment.name))
+                .collect::<String>();
+            format!("{}: {}{}", role, content.text, attachments)
+        });
+
+        system.chain(conversation).collect::<Vec<_>>().join("\n\n")
+    }
+}
+
+/// Result of `sampling/createMessage` for the given completion.
+///
+/// `truncated` tells the server the completion was cut at `maxTokens`.
+pub fn sampling_result(text: &str, model: &str, truncated: bool) -> Value {
+    json!({
+        "role": "assistant",
+        "content": { "type": "text", "text": text },
+        "model": model,
+        "stopReason": if truncated { "maxTokens" } else { "endTurn" },
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElicitationFieldKind {
+    Text,
+    Number {
+        integer: bool,
+    },
+    Boolean,
+    /// Allowed values with their display names.
+    Choice(Vec<(String, String)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElicitationField {
+    pub name: String,
+    pub title: String,
+    pub description: String,
+    pub kind: ElicitationFieldKind,
+    pub required: bool,
+    pub default: Option<String>,
+}
+
+/// Input requested from the user through `elicitation/create`.
+#[derive(Debug, Clone)]
+pub struct ElicitationRequest {
+    pub message: String,
+    pub fields: Vec<ElicitationField>,
+}
+
+impl ElicitationRequest {
+    /// Parse the request, turning the flat object schema it carries into fields.
+    ///
+    /// Fields are sorted by name since the schema properties are unordered.
+    pub fn from_params(params: &Value) -> Result<Self, String> {
+        let message = params
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+
+        let schema = params
+            .get("requestedSchema")
+            .ok_or("Elicitation request without a schema")?;
+
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|required| required.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        let fields = schema
+            .get("properties")
+            .and_then(Value::as_object)
+            .map(|properties| {
+                properties
+                    .iter()
+                    .map(|(name, property)| {
+                        parse_field(name, property, required.contains(&name.as_str()))
+                    })
+                    .collect::<Result<Vec<_>, String>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Self { message, fields })
+    }
+
+    /// Validate the values entered by the user and convert them to the types
+    /// requested by the schema.
+    pub fn content(&self, values: &BTreeMap<String, String>) -> Result<Map<String, Value>, String> {
+        let mut content = Map::new();
+
+        for field in &self.fields {
+            let value = values.get(&field.name).map(|v| v.trim()).unwrap_or("");
+
+            if value.is_empty() && field.kind != ElicitationFieldKind::Boolean {
+                if field.required {
+                    return Err(format!("{} is required", field.title));
+                }
+                continue;
+            }
+
+            let value = match &field.kind {
+                ElicitationFieldKind::Text => Value::String(value.to_string()),
+                ElicitationFieldKind::Boolean => Value::Bool(value == "true"),
+                ElicitationFieldKind::Number { integer: true } => value
+                    .parse::<i64>()
+                    .map(Value::from)
+                    .map_err(|_| format!("{} must be a whole number", field.title))?,
+                ElicitationFieldKind::Number { integer: false } => value
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|number| serde_json::Number::from_f64(number))
+                    .map(Value::Number)
+                    .ok_or_else(|| format!("{} must be a number", field.title))?,
+                ElicitationFieldKind::Choice(options) => {
+                    if !options.iter().any(|(option, _)| option == value) {
+                        return Err(format!("{} has an invalid value", field.title));
+                    }
+                    Value::String(value.to_string())
+                }
+            };
+
+            content.insert(field.name.clone(), value);
+        }
+
+        Ok(content)
+    }
+}
+
+fn parse_field(name: &str, property: &Value, required: bool) -> Result<ElicitationField, String> {
+    let string = |key: &str| {
+        property
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+
+    let kind = if let Some(options) = property.get("enum").and_then(Value::as_array) {
+        let options: Vec<String> = options
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect();
+        let names: Vec<String> = property
+            .get("enumNames")
+            .and_then(Value::as_array)
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        ElicitationFieldKind::Choice(
+            options
+                .iter()
+                .enumerate()
+                .map(|(i, option)| {
+                    let name = names.get(i).unwrap_or(option).clone();
+                    (option.clone(), name)
+                })
+                .collect(),
+        )
+    } else {
+        match property.get("type").and_then(Value::as_str) {
+            Some("string") | None => ElicitationFieldKind::Text,
+            Some("number") => ElicitationFieldKind::Number { integer: false },
+            Some("integer") => ElicitationFieldKind::Number { integer: true },
+            Some("boolean") => ElicitationFieldKind::Boolean,
+            Some(other) => {
+                return Err(format!("Unsupported field type \"{}\" for {}", other, name));
+            }
+        }
+    };
+
+    let default = property.get("default").and_then(|default| match default {
+        Value::String(s) => Some(s.clone()),
+        Value::Null => None,
+        other => Some(other.to_string()),
+    });
+
+    Ok(ElicitationField {
+        name: name.to_string(),
+        title: string("title").unwrap_or_else(|| name.to_string()),
+        description: string("description").unwrap_or_default(),
+        kind,
+        required,
+        default,
+    })
+}
+
+/// Result of `elicitation/create` when the user submits the form.
+pub fn elicitation_accepted(content: Map<String, Value>) -> Value {
+    json!({ "action": "accept", "content": content })
+}
+
+/// Result of `elicitation/create` when the user refuses to answer.
+pub fn elicitation_declined() -> Value {
+    json!({ "action": "decline" })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SamplingState {
+    AwaitingApproval,
+    Running,
+    Done(String),
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum McpRequestKind {
+    Sampling(SamplingRequest, SamplingState),
+    Elicitation(ElicitationRequest),
+}
+
+/// A request waiting for the user, or a finished sampling request kept visible
+/// until dismissed.
+pub struct PendingMcpRequest {
+    pub id: u64,
+    pub server_id: String,
+    pub kind: McpRequestKind,
+    responder: Option<oneshot::Sender<Result<Value, String>>>,
+}
+
+impl PendingMcpRequest {
+    pub fn new(
+        id: u64,
+        server_id: String,
+        kind: McpRequestKind,
+        responder: oneshot::Sender<Result<Value, String>>,
+    ) -> Self {
+        Self {
+            id,
+            server_id,
+            kind,
+            responder: Some(responder),
+        }
+    }
+
+    /// Answer the server. Later calls are ignored.
+    pub fn respond(&mut self, result: Result<Value, String>) {
+        if let Some(responder) = self.responder.take() {
+            let _ = responder.send(result);
+        }
+    }
+}
+
+/// Route sampling and elicitation requests from the servers of `tool_manager`
+/// to the store.
+///
+/// Registering the handler is what makes the client advertise both
+/// capabilities to the servers it connects.
+pub fn install_request_handler(tool_manager: &McpClient) {
+    tool_manager.set_request_handler(|server_id, method, params| {
+        let server_id = server_id.to_string();
+        let method = method.to_string();
+        let params = params.clone();
+
+        Box::pin(async move {
+            let kind = match method.as_str() {
+                "sampling/createMessage" => McpRequestKind::Sampling(
+                    SamplingRequest::from_params(&params)?,
+                    SamplingState::AwaitingApproval,
+                ),
+                "elicitation/create" => {
+                    McpRequestKind::Elicitation(ElicitationRequest::from_params(&params)?)
+                }
+                _ => return Err(format!("Unsupported request: {}", method)),
+            };
+
+            let (sender, receiver) = oneshot::channel();
+            app_runner().defer(move |app, cx, _| {
+                if let Some(store) = app.store.as_mut() {
+                    store.push_mcp_request(server_id, kind, sender);
+                    cx.redraw_all();
+                }
+            });
+
+            receiver
+                .await
+                .unwrap_or_else(|_| Err("The request was dismissed".to_string()))
+        })
+    });
+}
+
+fn base64_decode(data: &str) -> Result<Vec<u8>, String> {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| format!("Invalid base64 content: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling_request() {
+        let params = json!({
+            "messages": [
+                { "role": "user", "content": { "type": "text", "text": "Summarize the log" } },
+                { "role": "assistant", "content": { "type": "text", "text": "Which log?" } },
+                { "role": "user", "content": { "type": "text", "text": "build.log" } }
+            ],
+            "systemPrompt": "Be brief",
+            "maxTokens": 100
+        });
+
+        let request = SamplingRequest::from_params(&params).unwrap();
+        assert_eq!(request.messages.len(), 3);
+        assert_eq!(request.messages[1].0, SamplingRole::Assistant);
+
+        let messages = request.to_messages(&BotId::new("model"));
+        assert_eq!(messages.len(), 4);
+        assert!(matches!(messages[0].from, EntityId::System));
+        assert!(
+            request
+                .transcript()
+                .starts_with("System: Be brief\n\nUser: Summarize")
+        );
+
+        assert!(SamplingRequest::from_params(&json!({})).is_err());
+    }
+
+    #[test]
+    fn test_sampling_limits() {
+        let params = json!({
+            "messages": [],
+            "maxTokens": 2,
+            "modelPreferences": { "hints": [{ "name": "opus" }, { "name": "gpt" }] }
+        });
+
+        let request = SamplingRequest::from_params(&params).unwrap();
+        assert_eq!(request.max_tokens, Some(2));
+        assert_eq!(request.truncate("short"), None);
+        assert_eq!(request.truncate("a longer reply"), Some("a longer"));
+
+        let bot = |id: &str, name: &str| Bot {
+            id: BotId::new(id),
+            name: name.to_string(),
+            avatar: EntityAvatar::Text(name[..1].to_string()),
+            capabilities: BotCapabilities::new(),
+        };
+        let bots = vec![bot("llama", "Llama"), bot("openai/gpt-4o", "GPT-4o")];
+        assert_eq!(
+            request.preferred_bot(&bots).map(|bot| bot.name.as_str()),
+            Some("GPT-4o")
+        );
+        assert!(request.preferred_bot(&bots[..1]).is_none());
+    }
+
+    #[test]
+    fn test_elicitation_fields() {
+        let params = json!({
+            "message": "Configure the deployment",
+            "requestedSchema": {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "title": "Name" },
+                    "replicas": { "type": "integer", "default": 2 },
+                    "confirm": { "type": "boolean" },
+                    "region": { "type": "string", "enum": ["eu", "us"], "enumNames": ["Europe", "America"] }
+                },
+                "required": ["name", "region"]
+            }
+        });
+
+        let request = ElicitationRequest::from_params(&params).unwrap();
+        assert_eq!(request.fields.len(), 4);
+
+        // Properties come out in alphabetical order.
+        let names: Vec<&str> = request.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["confirm", "name", "region", "replicas"]);
+
+        let name = &request.fields[1];
+        assert_eq!(name.title, "Name");
+        assert!(name.required);
+
+        assert_eq!(
+            request.fields[2].kind,
+            ElicitationFieldKind::Choice(vec![
+                ("eu".to_string(), "Europe".to_string()),
+                ("us".to_string(), "America".to_string()),
+            ])
+        );
+
+        let replicas = &request.fields[3];
+        assert_eq!(
+            replicas.kind,
+            ElicitationFieldKind::Number { integer: true }
+        );
+        assert_eq!(replicas.default.as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn test_elicitation_content() {
+        let params = json!({
+            "message": "",
+            "requestedSchema": {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "replicas": { "type": "integer" },
+                    "confirm": { "type": "boolean" }
+                },
+                "required": ["name"]
+            }
+        });
+        let request = ElicitationRequest::from_params(&params).unwrap();
+
+        let mut values = BTreeMap::new();
+        assert!(request.content(&values).is_err());
+
+        values.insert("name".to_string(), "api".to_string());
+        values.insert("replicas".to_string(), "two".to_string());
+        assert!(request.content(&values).is_err());
+
+        values.insert("replicas".to_string(), "3".to_string());
+        values.insert("confirm".to_string(), "true".to_string());
+        let content = request.content(&values).unwrap();
+        assert_eq!(content["name"], json!("api"));
+        assert_eq!(content["replicas"], json!(3));
+        assert_eq!(content["confirm"], json!(true));
+    }
+}
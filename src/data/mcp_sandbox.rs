@@ -0,0 +1,567 @@
+//! OS level restrictions for stdio MCP server processes.
+//!
+//! The environment allowlist is applied when building the command and works
+//! everywhere. The rest is enforced by the kernel and only available on Linux:
+//!
+//! - Paths are restricted with Landlock (Linux 5.13+). Once any path is listed,
+//!   everything outside of the listed paths and a few system directories needed
+//!   to run programs is denied. Unless `no_caches` is set, the temporary
+//!   directory and the caches and toolchains of package runners in the home
+//!   directory stay available too, see [`cache_paths`]. Of `/proc`, only the
+//!   server's own entry is readable, so it can't read the environment of Moly or
+//!   of other processes. Processes the server starts don't get theirs.
+//! - Network access is removed with a seccomp filter refusing to create IP
+//!   sockets. Unix sockets keep working.
+//! - CPU time and memory are limited with rlimits. A process going over its CPU
+//!   time is killed by the kernel. Memory is limited with `RLIMIT_DATA`, so
+//!   allocations over the heap and private mappings limit fail, while address
+//!   space reserved but not used, as V8 does, is not counted. Shared memory is
+//!   not limited.
+//!
+//! The Landlock ruleset and the seccomp filter are prepared in Moly, so the
+//! forked child only has to install them right before running the server.
+
+use super::mcp_servers::McpSandbox;
+#[cfg(target_os = "linux")]
+use std::path::{Path, PathBuf};
+
+/// Restrict `command` as configured in `sandbox`.
+///
+/// Fails if a restriction can not be enforced on this system, as running the
+/// server without it would be worse than not running it.
+#[cfg(target_os = "linux")]
+pub fn apply(sandbox: &McpSandbox, command: &mut tokio::process::Command) -> Result<(), String> {
+    let ruleset = if sandbox.restricts_paths() {
+        let mut read_only = sandbox.read_only_paths.clone();
+        let mut read_write = sandbox.read_write_paths.clone();
+        if !sandbox.no_caches {
+            let home = std::env::var_os("HOME").map(PathBuf::from);
+            let cache_home = std::env::var_os("XDG_CACHE_HOME").map(PathBuf::from);
+            let (cache_read_only, cache_read_write) = cache_paths(
+                home.as_deref(),
+                cache_home.as_deref(),
+                &std::env::temp_dir(),
+            );
+            // Not every runner is installed, so missing paths are skipped.
+            let existing = |paths: Vec<PathBuf>| {
+                paths
+                    .into_iter()
+                    .filter(|path| path.exists())
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .collect::<Vec<_>>()
+            };
+            read_only.extend(existing(cache_read_only));
+            read_write.extend(existing(cache_read_write));
+        }
+        Some(landlock::ruleset(&read_only, &read_write)?)
+    } else {
+        None
+    };
+
+    let filter = if sandbox.no_network {
+        Some(seccomp::network_filter()?)
+    } else {
+        None
+    };
+
+    let mut limits = Vec::new();
+    if let Some(seconds) = sandbox.cpu_seconds {
+        limits.push((libc::RLIMIT_CPU, seconds));
+    }
+    if let Some(megabytes) = sandbox.memory_mb {
+        limits.push((libc::RLIMIT_DATA, megabytes.saturating_mul(1024 * 1024)));
+    }
+
+    if ruleset.is_none() && filter.is_none() && limits.is_empty() {
+        return Ok(());
+    }
+
+    // SAFETY: Runs in the forked child, where only async-signal-safe calls are
+    // allowed. Everything here is a plain syscall on data prepared above.
+    unsafe {
+        command.pre_exec(move || {
+            for (resource, value) in &limits {
+                let limit = libc::rlimit {
+                    rlim_cur: *value as libc::rlim_t,
+                    rlim_max: *value as libc::rlim_t,
+                };
+                if libc::setrlimit(*resource, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+
+            // Required to install both, and keeps setuid programs from
+            // escaping them.
+            if (ruleset.is_some() || filter.is_some())
+                && libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            if let Some(ruleset) = &ruleset {
+                landlock::allow_own_proc(ruleset)?;
+                landlock::restrict_self(ruleset)?;
+            }
+
+            if let Some(filter) = &filter {
+                seccomp::install(filter)?;
+            }
+
+            Ok(())
+        });
+    }
+
+    Ok(())
+}
+
+/// Paths package runners like `npx`, `uvx` and `bunx` need, read only and read
+/// write: the temporary directory, their caches, and the toolchains installed
+/// in the home directory.
+#[cfg(target_os = "linux")]
+fn cache_paths(
+    home: Option<&Path>,
+    cache_home: Option<&Path>,
+    temp_dir: &Path,
+) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut read_only = Vec::new();
+    let mut read_write = vec![temp_dir.to_path_buf()];
+
+    if let Some(home) = home {
+        read_only.extend(
+            [".npmrc", ".nvm", ".volta", ".local/bin", ".bun/bin"]
+                .iter()
+                .map(|path| home.join(path)),
+        );
+        read_write.extend(
+            [".npm", ".local/share/uv", ".bun/install/cache"]
+                .iter()
+                .map(|path| home.join(path)),
+        );
+    }
+
+    match cache_home {
+        Some(cache_home) => read_write.push(cache_home.to_path_buf()),
+        None => read_write.extend(home.map(|home| home.join(".cache"))),
+    }
+
+    (read_only, read_write)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply(sandbox: &McpSandbox, _command: &mut tokio::process::Command) -> Result<(), String> {
+    if sandbox.restricts_paths()
+        || sandbox.no_network
+        || sandbox.cpu_seconds.is_some()
+        || sandbox.memory_mb.is_some()
+    {
+        return Err(
+            "Sandboxing paths, network and resources is only supported on Linux. \
+             Remove these settings to run the server unrestricted."
+                .to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod landlock {
+    use std::fs::OpenOptions;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::fs::OpenOptionsExt;
+
+    const CREATE_RULESET_VERSION: libc::c_uint = 1;
+    const RULE_PATH_BENEATH: libc::c_uint = 1;
+
+    const ACCESS_EXECUTE: u64 = 1 << 0;
+    const ACCESS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_READ_FILE: u64 = 1 << 2;
+    const ACCESS_READ_DIR: u64 = 1 << 3;
+    const ACCESS_TRUNCATE: u64 = 1 << 14;
+
+    /// Rights that apply to files rather than directories.
+    const FILE_ACCESS: u64 =
+        ACCESS_EXECUTE | ACCESS_WRITE_FILE | ACCESS_READ_FILE | ACCESS_TRUNCATE;
+    const READ_ACCESS: u64 = ACCESS_EXECUTE | ACCESS_READ_FILE | ACCESS_READ_DIR;
+
+    /// Needed by almost any program, readable when paths are restricted.
+    /// Missing ones are skipped.
+    const SYSTEM_READ_ONLY: &[&str] = &[
+        "/bin",
+        "/sbin",
+        "/usr",
+        "/lib",
+        "/lib32",
+        "/lib64",
+        "/etc",
+        "/opt",
+        "/nix/store",
+        "/sys",
+        "/dev",
+        "/run",
+    ];
+    const SYSTEM_READ_WRITE: &[&str] = &["/dev/null", "/dev/zero", "/dev/random", "/dev/urandom"];
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    /// Version of the Landlock ABI supported by the kernel, if any.
+    pub fn abi_version() -> Option<u32> {
+        // SAFETY: Querying the version takes no attributes.
+        let version = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                CREATE_RULESET_VERSION,
+            )
+        };
+        (version > 0).then_some(version as u32)
+    }
+
+    /// Every filesystem right known to an ABI version, all denied by default.
+    fn handled_access(abi: u32) -> u64 {
+        match abi {
+            1 => (1 << 13) - 1,
+            // Adds moving files between directories.
+            2 => (1 << 14) - 1,
+            // Adds truncation. Later rights are left unhandled.
+            _ => (1 << 15) - 1,
+        }
+    }
+
+    /// A ruleset allowing the system directories and the given paths.
+    pub fn ruleset(read_only: &[String], read_write: &[String]) -> Result<OwnedFd, String> {
+        let abi = abi_version().ok_or_else(|| {
+            "Restricting paths requires Landlock, which is not available. \
+             It needs Linux 5.13 or newer with Landlock enabled."
+                .to_string()
+        })?;
+        let handled = handled_access(abi);
+
+        let attr = RulesetAttr {
+            handled_access_fs: handled,
+        };
+        // SAFETY: `attr` outlives the call and its size is passed along.
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                std::mem::size_of::<RulesetAttr>(),
+                0,
+            )
+        };
+        if fd < 0 {
+            return Err(format!(
+                "Could not create the sandbox: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        // SAFETY: The descriptor was just created and is owned by nobody else.
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd as i32) };
+
+        let system = SYSTEM_READ_ONLY
+            .iter()
+            .map(|path| (*path, READ_ACCESS))
+            .chain(SYSTEM_READ_WRITE.iter().map(|path| (*path, handled)));
+        for (path, access) in system {
+            if std::path::Path::new(path).exists() {
+                add_rule(&ruleset, path, access & handled)?;
+            }
+        }
+
+        let user = read_only
+            .iter()
+            .map(|path| (path, READ_ACCESS))
+            .chain(read_write.iter().map(|path| (path, handled)));
+        for (path, access) in user {
+            add_rule(&ruleset, path, access & handled)?;
+        }
+
+        Ok(ruleset)
+    }
+
+    fn add_rule(ruleset: &OwnedFd, path: &str, access: u64) -> Result<(), String> {
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_PATH)
+            .open(path)
+            .map_err(|e| format!("Could not open sandbox path {}: {}", path, e))?;
+        let is_dir = file.metadata().map(|m| m.is_dir()).unwrap_or(false);
+
+        let attr = PathBeneathAttr {
+            allowed_access: if is_dir { access } else { access & FILE_ACCESS },
+            parent_fd: file.as_raw_fd(),
+        };
+        // SAFETY: Both descriptors are open and `attr` outlives the call.
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset.as_raw_fd(),
+                RULE_PATH_BENEATH,
+                &attr as *const PathBeneathAttr,
+                0,
+            )
+        };
+        if result < 0 {
+            return Err(format!(
+                "Could not allow sandbox path {}: {}",
+                path,
+                std::io::Error::last_os_error()
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Allow reading the `/proc` entry of the calling process.
+    ///
+    /// Runs in the forked child, since `/proc/self` resolves to the process
+    /// opening it.
+    pub fn allow_own_proc(ruleset: &OwnedFd) -> std::io::Result<()> {
+        // SAFETY: Plain syscall on a static, nul terminated path.
+        let fd = unsafe {
+            libc::open(
+                c"/proc/self".as_ptr(),
+                libc::O_PATH | libc::O_DIRECTORY | libc::O_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // SAFETY: The descriptor was just opened and is owned by nobody else.
+        let proc_self = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let attr = PathBeneathAttr {
+            allowed_access: READ_ACCESS,
+            parent_fd: proc_self.as_raw_fd(),
+        };
+        // SAFETY: Both descriptors are open and `attr` outlives the call.
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset.as_raw_fd(),
+                RULE_PATH_BENEATH,
+                &attr as *const PathBeneathAttr,
+                0,
+            )
+        };
+        if result < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Enforce the ruleset on the calling process and its future children.
+    pub fn restrict_self(ruleset: &OwnedFd) -> std::io::Result<()> {
+        // SAFETY: Plain syscall on an open descriptor.
+        let result =
+            unsafe { libc::syscall(libc::SYS_landlock_restrict_self, ruleset.as_raw_fd(), 0) };
+        if result < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod seccomp {
+    const LD_W_ABS: u16 = 0x20;
+    const JEQ_K: u16 = 0x15;
+    #[cfg(target_arch = "x86_64")]
+    const JGE_K: u16 = 0x35;
+    const RET_K: u16 = 0x06;
+
+    const RET_ALLOW: u32 = 0x7fff_0000;
+    const RET_ERRNO: u32 = 0x0005_0000;
+    const RET_KILL_PROCESS: u32 = 0x8000_0000;
+
+    // Offsets in `struct seccomp_data`.
+    const OFFSET_NR: u32 = 0;
+    const OFFSET_ARCH: u32 = 4;
+    const OFFSET_ARG0: u32 = 16;
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+    /// Set in the numbers of x32 syscalls, which report the x86_64 arch.
+    #[cfg(target_arch = "x86_64")]
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+    fn statement(code: u16, k: u32) -> libc::sock_filter {
+        jump(code, k, 0, 0)
+    }
+
+    fn jump(code: u16, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter { code, jt, jf, k }
+    }
+
+    /// Refuses IPv4 and IPv6 sockets, and io_uring which could open them
+    /// without going through `socket`.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub fn network_filter() -> Result<Vec<libc::sock_filter>, String> {
+        let mut filter = vec![
+            statement(LD_W_ABS, OFFSET_ARCH),
+            jump(JEQ_K, AUDIT_ARCH, 1, 0),
+            // Syscall numbers differ under other ABIs, like i386.
+            statement(RET_K, RET_KILL_PROCESS),
+            statement(LD_W_ABS, OFFSET_NR),
+        ];
+        #[cfg(target_arch = "x86_64")]
+        filter.extend([
+            jump(JGE_K, X32_SYSCALL_BIT, 0, 1),
+            statement(RET_K, RET_KILL_PROCESS),
+        ]);
+        filter.extend([
+            jump(JEQ_K, libc::SYS_io_uring_setup as u32, 0, 1),
+            statement(RET_K, RET_ERRNO | libc::ENOSYS as u32),
+            jump(JEQ_K, libc::SYS_socket as u32, 0, 4),
+            statement(LD_W_ABS, OFFSET_ARG0),
+            jump(JEQ_K, libc::AF_INET as u32, 1, 0),
+            jump(JEQ_K, libc::AF_INET6 as u32, 0, 1),
+            statement(RET_K, RET_ERRNO | libc::EACCES as u32),
+            statement(RET_K, RET_ALLOW),
+        ]);
+        Ok(filter)
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    pub fn network_filter() -> Result<Vec<libc::sock_filter>, String> {
+        Err("Blocking the network is not supported on this architecture".to_string())
+    }
+
+    pub fn install(filter: &[libc::sock_filter]) -> std::io::Result<()> {
+        let program = libc::sock_fprog {
+            len: filter.len() as u16,
+            filter: filter.as_ptr() as *mut libc::sock_filter,
+        };
+        // SAFETY: `program` points into `filter`, which outlives the call. The
+        // kernel copies it.
+        let result = unsafe {
+            libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER,
+                &program as *const libc::sock_fprog,
+            )
+        };
+        if result != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    fn run(sandbox: &McpSandbox, script: &str) -> std::process::Output {
+        let mut command = tokio::process::Command::new("/bin/sh");
+        command.arg("-c").arg(script);
+        apply(sandbox, &mut command).unwrap();
+
+        // The child is spawned right away, which needs the runtime.
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(async { command.output().await })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_restricts_paths() {
+        if landlock::abi_version().is_none() {
+            return;
+        }
+
+        let root = std::env::temp_dir().join(format!("moly-sandbox-{}", std::process::id()));
+        let allowed = root.join("allowed");
+        let denied = root.join("denied");
+        std::fs::create_dir_all(&allowed).unwrap();
+        std::fs::create_dir_all(&denied).unwrap();
+
+        // The denied folder is in the temporary directory.
+        let sandbox = McpSandbox {
+            read_write_paths: vec![allowed.to_string_lossy().into_owned()],
+            no_caches: true,
+            ..Default::default()
+        };
+        run(
+            &sandbox,
+            &format!(
+                "echo yes > {}/file; echo no > {}/file",
+                allowed.display(),
+                denied.display()
+            ),
+        );
+
+        assert!(allowed.join("file").exists());
+        assert!(!denied.join("file").exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_hides_other_processes() {
+        if landlock::abi_version().is_none() {
+            return;
+        }
+
+        let sandbox = McpSandbox {
+            read_only_paths: vec![std::env::temp_dir().to_string_lossy().into_owned()],
+            no_caches: true,
+            ..Default::default()
+        };
+        // `read` is a builtin, so it reads the entry of the sandboxed shell.
+        let output = run(
+            &sandbox,
+            &format!(
+                "read line < /proc/$$/status && echo own; cat /proc/{}/environ || echo denied",
+                std::process::id()
+            ),
+        );
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("own"));
+        assert!(stdout.contains("denied"));
+    }
+
+    #[test]
+    fn test_cache_paths() {
+        let (read_only, read_write) =
+            cache_paths(Some(Path::new("/home/me")), None, Path::new("/tmp"));
+        assert!(read_only.contains(&PathBuf::from("/home/me/.npmrc")));
+        assert!(read_write.contains(&PathBuf::from("/tmp")));
+        assert!(read_write.contains(&PathBuf::from("/home/me/.npm")));
+        assert!(read_write.contains(&PathBuf::from("/home/me/.cache")));
+
+        let (read_only, read_write) = cache_paths(
+            None,
+            Some(Path::new("/var/cache/me")),
+            Path::new("/run/tmp"),
+        );
+        assert!(read_only.is_empty());
+        assert_eq!(
+            read_write,
+            vec![PathBuf::from("/run/tmp"), PathBuf::from("/var/cache/me")]
+        );
+    }
+
+    #[test]
+    fn test_limits_cpu_time() {
+        let sandbox = McpSandbox {
+            cpu_seconds: Some(1),
+            ..Default::default()
+        };
+        let output = run(&sandbox, "ulimit -t");
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "1");
+    }
+}
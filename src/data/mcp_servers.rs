@@ -1,9 +1,14 @@
 use indexmap::IndexMap;
-use moly_kit::prelude::*;
+use moly_kit::utils::tool_calls::ToolTimeouts;
+use moly_kit::utils::tool_policy::{ToolPermission, ToolPolicy};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use moly_kit::utils::mcp::McpServerTransport;
 
 /// Represents an input configuration for MCP servers
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InputConfig {
     pub id: String,
     #[serde(rename = "type")]
@@ -14,7 +19,7 @@ pub struct InputConfig {
 }
 
 /// Represents an MCP server configuration following the standard format
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct McpServer {
     // Stdio transport fields
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -40,6 +45,81 @@ pub struct McpServer {
     pub enabled: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub working_directory: Option<String>,
+    /// Tools from this server that are never offered to models.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_tools: Vec<String>,
+    /// Whether sampling requests from this server run without asking first.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub allow_sampling: bool,
+    /// Id of the bot answering the sampling requests that run without asking.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling_bot: Option<String>,
+    /// Sign in with the MCP OAuth flow. Only used by HTTP/SSE servers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oauth: Option<McpOAuthConfig>,
+    /// Restrictions for the server process. Only used by stdio servers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<McpSandbox>,
+    /// Seconds a tool call can run before it is cancelled and reported to the
+    /// model as failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_timeout_secs: Option<u64>,
+    /// Same as `tool_timeout_secs`, for specific tools.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub tool_timeouts: IndexMap<String, u64>,
+}
+
+/// OAuth options for a remote MCP server.
+///
+/// Everything else is discovered from the server, so `{}` is enough for most.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct McpOAuthConfig {
+    /// Scopes to request. Defaults to those advertised by the authorization server.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+    /// Pre-registered client id, for servers without dynamic client registration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+}
+
+/// Restrictions for a stdio server process, see [`super::mcp_sandbox`].
+///
+/// Everything is unrestricted unless set. Only `env_allowlist` works outside
+/// of Linux, other settings make the server fail to start there.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct McpSandbox {
+    /// Variables inherited from Moly's environment, instead of all of them.
+    /// Variables in `env` are always set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_allowlist: Option<Vec<String>>,
+    /// Paths the process can read. Listing any path, here or in
+    /// `read_write_paths`, denies access to everything else except for the
+    /// system directories needed to run programs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub read_only_paths: Vec<String>,
+    /// Paths the process can read, write and create files in.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub read_write_paths: Vec<String>,
+    /// Deny the temporary directory and the package caches, see
+    /// [`super::mcp_sandbox`]. They are allowed by default when paths are
+    /// restricted, as servers run with `npx` or `uvx` don't start without them.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub no_caches: bool,
+    /// Refuse to open network connections. Local sockets keep working.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub no_network: bool,
+    /// CPU time after which the process is killed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_seconds: Option<u64>,
+    /// Heap and private mappings the process can allocate, in megabytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_mb: Option<u64>,
+}
+
+impl McpSandbox {
+    pub fn restricts_paths(&self) -> bool {
+        !self.read_only_paths.is_empty() || !self.read_write_paths.is_empty()
+    }
 }
 
 fn default_enabled() -> bool {
@@ -50,6 +130,10 @@ fn is_default_enabled(enabled: &bool) -> bool {
     *enabled
 }
 
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
 fn default_mcp_servers_enabled() -> bool {
     true
 }
@@ -70,6 +154,13 @@ impl McpServer {
             headers: IndexMap::new(),
             enabled: true,
             working_directory: None,
+            disabled_tools: Vec::new(),
+            allow_sampling: false,
+            sampling_bot: None,
+            oauth: None,
+            sandbox: None,
+            tool_timeout_secs: None,
+            tool_timeouts: IndexMap::new(),
         }
     }
 
@@ -84,6 +175,13 @@ impl McpServer {
             headers: IndexMap::new(),
             enabled: true,
             working_directory: None,
+            disabled_tools: Vec::new(),
+            allow_sampling: false,
+            sampling_bot: None,
+            oauth: None,
+            sandbox: None,
+            tool_timeout_secs: None,
+            tool_timeouts: IndexMap::new(),
         }
     }
 
@@ -98,6 +196,13 @@ impl McpServer {
             headers: IndexMap::new(),
             enabled: true,
             working_directory: None,
+            disabled_tools: Vec::new(),
+            allow_sampling: false,
+            sampling_bot: None,
+            oauth: None,
+            sandbox: None,
+            tool_timeout_secs: None,
+            tool_timeouts: IndexMap::new(),
         }
     }
 
@@ -145,37 +250,84 @@ impl McpServer {
         self.headers = headers;
         self
     }
+
+    /// Check if a tool from this server can be offered to models
+    pub fn is_tool_enabled(&self, tool: &str) -> bool {
+        !self.disabled_tools.iter().any(|t| t == tool)
+    }
+
+    /// Enable or disable a tool from this server
+    pub fn set_tool_enabled(&mut self, tool: &str, enabled: bool) {
+        self.disabled_tools.retain(|t| t != tool);
+        if !enabled {
+            self.disabled_tools.push(tool.to_string());
+        }
+    }
 }
 
 impl McpServer {
-    /// Convert this server configuration to a transport for the MCP manager
+    /// Convert this server configuration to a transport for the MCP client
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn to_transport(&self) -> Option<McpTransport> {
-        if let Some(command_str) = &self.command {
-            // Stdio transport
-            let mut command = tokio::process::Command::new(command_str);
-            command.args(&self.args);
-
-            // Add environment variables
-            for (key, value) in &self.env {
-                command.env(key, value);
-            }
-
-            // Set working directory if specified
-            if let Some(working_dir) = &self.working_directory {
-                command.current_dir(working_dir);
-            }
-
-            Some(McpTransport::Stdio(command))
+    pub fn to_transport(&self) -> Result<Option<McpServerTransport>, String> {
+        if let Some(command) = self.stdio_command()? {
+            Ok(Some(McpServerTransport::Stdio(command)))
         } else if let Some(url) = &self.url {
             // Network transport - determine if HTTP or SSE
-            match self.transport_type.as_deref() {
-                Some("sse") => Some(McpTransport::Sse(url.clone())),
-                _ => Some(McpTransport::Http(url.clone())),
-            }
+            let url = url.clone();
+            let headers = self.headers.clone().into_iter().collect();
+            let transport = match self.transport_type.as_deref() {
+                Some("sse") => McpServerTransport::Sse {
+                    url,
+                    headers,
+                    auth: None,
+                },
+                _ => McpServerTransport::Http {
+                    url,
+                    headers,
+                    auth: None,
+                },
+            };
+            Ok(Some(transport))
         } else {
-            None
+            Ok(None)
+        }
+    }
+
+    /// Build the process command for a stdio server, sandboxed if configured.
+    ///
+    /// Fails if the sandbox can not be enforced on this system.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn stdio_command(&self) -> Result<Option<tokio::process::Command>, String> {
+        let Some(command_str) = self.command.as_ref() else {
+            return Ok(None);
+        };
+        let mut command = tokio::process::Command::new(command_str);
+        command.args(&self.args);
+
+        if let Some(allowlist) = self.sandbox.as_ref().and_then(|s| s.env_allowlist.as_ref()) {
+            command.env_clear();
+            for name in allowlist {
+                if let Some(value) = std::env::var_os(name) {
+                    command.env(name, value);
+                }
+            }
+        }
+
+        // Add environment variables
+        for (key, value) in &self.env {
+            command.env(key, value);
+        }
+
+        // Set working directory if specified
+        if let Some(working_dir) = &self.working_directory {
+            command.current_dir(working_dir);
+        }
+
+        if let Some(sandbox) = &self.sandbox {
+            super::mcp_sandbox::apply(sandbox, &mut command)?;
         }
+
+        Ok(Some(command))
     }
 
     #[cfg(target_arch = "wasm32")]
@@ -194,6 +346,9 @@ pub struct McpServersConfig {
     pub enabled: bool,
     #[serde(default = "default_dangerous_mode_enabled")]
     pub dangerous_mode_enabled: bool,
+    /// Per server and per tool rules deciding which calls run without asking.
+    #[serde(default)]
+    pub tool_policy: ToolPolicy,
 }
 
 impl Default for McpServersConfig {
@@ -203,6 +358,7 @@ impl Default for McpServersConfig {
             inputs: Vec::new(),
             enabled: true,
             dangerous_mode_enabled: false,
+            tool_policy: ToolPolicy::default(),
         }
     }
 }
@@ -236,6 +392,30 @@ impl McpServersConfig {
         self.inputs.iter().find(|input| input.id == id)
     }
 
+    /// Time limits of the tool calls of every server.
+    pub fn tool_timeouts(&self) -> ToolTimeouts {
+        let mut timeouts = ToolTimeouts::default();
+        for (id, server) in &self.servers {
+            if let Some(secs) = server.tool_timeout_secs {
+                timeouts.set_server_timeout(id, Duration::from_secs(secs));
+            }
+            for (tool, secs) in &server.tool_timeouts {
+                timeouts.set_tool_timeout(id, tool, Duration::from_secs(*secs));
+            }
+        }
+        timeouts
+    }
+
+    /// The tool policy to enforce, where dangerous mode allows anything not
+    /// covered by a rule.
+    pub fn effective_tool_policy(&self) -> ToolPolicy {
+        let mut policy = self.tool_policy.clone();
+        if self.dangerous_mode_enabled {
+            policy.default_permission = ToolPermission::Allow;
+        }
+        policy
+    }
+
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string_pretty(self)
     }
@@ -339,11 +519,66 @@ mod tests {
         assert!(json.contains("\"url\": \"http://localhost:8931\""));
         assert!(json.contains("\"url\": \"http://localhost:8931/sse\""));
 
+        // Servers without a sandbox don't write one
+        assert!(!json.contains("\"sandbox\""));
+
         // Verify transport types
         assert!(json.contains("\"type\": \"http\""));
         assert!(json.contains("\"type\": \"sse\""));
     }
 
+    #[test]
+    fn test_tool_policy() {
+        let json = r#"{
+            "servers": {},
+            "dangerous_mode_enabled": true,
+            "tool_policy": {
+                "rules": [
+                    { "server": "filesystem", "tool": "write_file", "permission": "deny" },
+                    {
+                        "server": "filesystem",
+                        "tool": "read_file",
+                        "arguments": { "path": "~/projects/*" },
+                        "permission": "allow"
+                    }
+                ]
+            }
+        }"#;
+
+        let config = McpServersConfig::from_json(json).unwrap();
+        assert_eq!(config.tool_policy.rules.len(), 2);
+        assert_eq!(config.tool_policy.default_permission, ToolPermission::Ask);
+        assert_eq!(
+            config.effective_tool_policy().default_permission,
+            ToolPermission::Allow
+        );
+
+        let deserialized = McpServersConfig::from_json(&config.to_json().unwrap()).unwrap();
+        assert_eq!(deserialized.tool_policy, config.tool_policy);
+    }
+
+    #[test]
+    fn test_disabled_tools() {
+        let mut server = McpServer::stdio("node".to_string(), vec![]);
+        assert!(server.is_tool_enabled("read_file"));
+
+        server.set_tool_enabled("read_file", false);
+        server.set_tool_enabled("read_file", false);
+        assert_eq!(server.disabled_tools, vec!["read_file"]);
+        assert!(!server.is_tool_enabled("read_file"));
+
+        let json = serde_json::to_string(&server).unwrap();
+        assert!(json.contains("\"disabled_tools\":[\"read_file\"]"));
+
+        server.set_tool_enabled("read_file", true);
+        assert!(server.is_tool_enabled("read_file"));
+        assert!(
+            !serde_json::to_string(&server)
+                .unwrap()
+                .contains("disabled_tools")
+        );
+    }
+
     #[test]
     fn test_stdio_server_creation() {
         let server = McpServer::stdio("node".to_string(), vec!["server.js".to_string()])
@@ -414,4 +649,62 @@ mod tests {
         assert_eq!(imagesorcery_server.command.as_ref().unwrap(), "uvx");
         assert_eq!(imagesorcery_server.args, vec!["imagesorcery-mcp"]);
     }
+
+    #[test]
+    fn test_sandbox_env_allowlist() {
+        let json = r#"{
+            "servers": {
+                "files": {
+                    "command": "node",
+                    "env": { "API_KEY": "secret" },
+                    "sandbox": { "env_allowlist": ["PATH"], "no_network": true }
+                }
+            }
+        }"#;
+
+        let config = McpServersConfig::from_json(json).unwrap();
+        let server = config.get_server("files").unwrap();
+        let sandbox = server.sandbox.as_ref().unwrap();
+        assert!(sandbox.no_network);
+        assert!(!sandbox.restricts_paths());
+
+        let mut server = server.clone();
+        server.sandbox.as_mut().unwrap().no_network = false;
+        let command = server.stdio_command().unwrap().unwrap();
+        let mut names: Vec<_> = command
+            .as_std()
+            .get_envs()
+            .map(|(name, _)| name.to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+
+        let mut expected = vec!["API_KEY".to_string()];
+        if std::env::var_os("PATH").is_some() {
+            expected.push("PATH".to_string());
+        }
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn test_tool_timeouts() {
+        let json = r#"{
+            "servers": {
+                "git": {
+                    "command": "uvx",
+                    "tool_timeout_secs": 30,
+                    "tool_timeouts": { "clone": 300 }
+                },
+                "files": { "command": "node" }
+            }
+        }"#;
+
+        let config = McpServersConfig::from_json(json).unwrap();
+        let mut expected = ToolTimeouts::default();
+        expected.set_server_timeout("git", Duration::from_secs(30));
+        expected.set_tool_timeout("git", "clone", Duration::from_secs(300));
+        assert_eq!(config.tool_timeouts(), expected);
+
+        let json = config.to_json().unwrap();
+        assert_eq!(json.matches("tool_timeout").count(), 2);
+    }
 }
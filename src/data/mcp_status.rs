@@ -0,0 +1,89 @@
+//! Live state of the configured MCP servers, as shown in the MCP screen.
+
+use moly_kit::utils::mcp::{McpClient, split_namespaced};
+
+/// Max number of trailing log lines searched for the stderr of a failed server.
+pub const STDERR_TAIL_LINES: usize = 20;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum McpServerStatus {
+    Connecting,
+    Ready,
+    Failed(McpServerFailure),
+}
+
+impl McpServerStatus {
+    pub fn to_human_readable(&self) -> &'static str {
+        match self {
+            McpServerStatus::Connecting => "Connecting...",
+            McpServerStatus::Ready => "Ready",
+            McpServerStatus::Failed(_) => "Failed",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct McpServerFailure {
+    pub error: String,
+    /// Exit code of the process, for stdio servers that exited on startup.
+    pub exit_code: Option<i32>,
+    /// Last lines the process wrote to stderr, if any.
+    pub stderr: String,
+}
+
+impl McpServerFailure {
+    /// Error, exit code and stderr tail as a single block of text.
+    pub fn details(&self) -> String {
+        let mut details = self.error.clone();
+
+        if let Some(code) = self.exit_code {
+            details.push_str(&format!("\nProcess exited with code {}", code));
+        }
+
+        if !self.stderr.is_empty() {
+            details.push_str("\n\n");
+            details.push_str(&self.stderr);
+        }
+
+        details
+    }
+}
+
+/// A tool exposed by an MCP server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct McpToolInfo {
+    /// Name as exposed by the server.
+    pub name: String,
+    pub description: String,
+    /// Pretty printed JSON schema of the tool input.
+    pub input_schema: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct McpServerState {
+    pub status: McpServerStatus,
+    pub tools: Vec<McpToolInfo>,
+}
+
+impl McpServerState {
+    pub fn connecting() -> Self {
+        Self {
+            status: McpServerStatus::Connecting,
+            tools: Vec::new(),
+        }
+    }
+}
+
+/// Collect the tools the client exposes for the given server.
+pub fn server_tools(tool_manager: &McpClient, server_id: &str) -> Vec<McpToolInfo> {
+    tool_manager
+        .server_tools(server_id)
+        .into_iter()
+        .map(|tool| McpToolInfo {
+            name: split_namespaced(&tool.name).1.to_string(),
+            description: tool.description.clone().unwrap_or_default(),
+            input_schema: serde_json::to_string_pretty(tool.input_schema.as_ref())
+                .unwrap_or_default(),
+        })
+        .collect()
+}
@@ -0,0 +1,297 @@
+//! Supervision of the MCP servers processes.
+//!
+//! Stdio servers are spawned with their stderr captured into a per server log,
+//! and a closed stderr is taken as the process exiting. The store restarts
+//! crashed servers with an exponential backoff until they keep failing.
+//!
+//! Every spawn is an "instance" of the server. Restarting a server or replacing
+//! the MCP client retires the previous instances, so their exit is expected
+//! and not reported as a crash. Processes are stopped by the MCP client once
+//! their connection ends, closing their stdin before signaling them.
+
+use chrono::{DateTime, Local};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::mcp_servers::McpServer;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::app::app_runner;
+#[cfg(not(target_arch = "wasm32"))]
+use moly_kit::utils::mcp::McpServerTransport;
+
+/// Lines kept per server. Older lines are dropped.
+const LOG_CAPACITY: usize = 500;
+
+/// Crashes in a row after which a server is no longer restarted.
+const MAX_RESTARTS: u32 = 5;
+
+/// How long a server has to complete the MCP handshake.
+pub const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum McpLogSource {
+    /// Written by the server process to stderr.
+    Stderr,
+    /// Reported by the server or the client over the protocol.
+    Protocol,
+    /// Events of the supervisor itself, like restarts.
+    Supervisor,
+}
+
+#[derive(Clone, Debug)]
+pub struct McpLogLine {
+    pub time: DateTime<Local>,
+    pub source: McpLogSource,
+    pub text: String,
+}
+
+impl McpLogLine {
+    pub fn to_display(&self) -> String {
+        let source = match self.source {
+            McpLogSource::Stderr => "stderr",
+            McpLogSource::Protocol => "protocol",
+            McpLogSource::Supervisor => "moly",
+        };
+        format!(
+            "{} [{}] {}",
+            self.time.format("%H:%M:%S"),
+            source,
+            self.text
+        )
+    }
+}
+
+/// Fixed capacity log dropping the oldest lines.
+#[derive(Debug, Default)]
+struct RingLog(VecDeque<McpLogLine>);
+
+impl RingLog {
+    fn push(&mut self, line: McpLogLine) {
+        if self.0.len() == LOG_CAPACITY {
+            self.0.pop_front();
+        }
+        self.0.push_back(line);
+    }
+}
+
+#[derive(Default)]
+struct SupervisorState {
+    logs: HashMap<String, RingLog>,
+    /// Current instance of each server.
+    instances: HashMap<String, u64>,
+    next_instance: u64,
+    /// Crashes in a row, reset when the server connects.
+    restarts: HashMap<String, u32>,
+}
+
+/// Logs and process instances of the MCP servers.
+///
+/// Cheap to clone, every clone shares the same state.
+#[derive(Clone, Default)]
+pub struct McpSupervisor(Arc<Mutex<SupervisorState>>);
+
+impl McpSupervisor {
+    pub fn log(&self, server_id: &str, source: McpLogSource, text: impl Into<String>) {
+        self.0
+            .lock()
+            .unwrap()
+            .logs
+            .entry(server_id.to_string())
+            .or_default()
+            .push(McpLogLine {
+                time: Local::now(),
+                source,
+                text: text.into(),
+            });
+    }
+
+    /// The last `max_lines` lines logged for a server, oldest first.
+    pub fn log_tail(&self, server_id: &str, max_lines: usize) -> Vec<McpLogLine> {
+        let state = self.0.lock().unwrap();
+        let Some(log) = state.logs.get(server_id) else {
+            return Vec::new();
+        };
+
+        let start = log.0.len().saturating_sub(max_lines);
+        log.0.iter().skip(start).cloned().collect()
+    }
+
+    /// What a server wrote to stderr among the last `max_lines` lines logged.
+    pub fn stderr_tail(&self, server_id: &str, max_lines: usize) -> String {
+        self.log_tail(server_id, max_lines)
+            .into_iter()
+            .filter(|line| line.source == McpLogSource::Stderr)
+            .map(|line| line.text)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Start a new instance of a server, retiring the previous one.
+    pub fn start_instance(&self, server_id: &str) -> u64 {
+        let mut state = self.0.lock().unwrap();
+        state.next_instance += 1;
+        let instance = state.next_instance;
+        state.instances.insert(server_id.to_string(), instance);
+        instance
+    }
+
+    pub fn is_current(&self, server_id: &str, instance: u64) -> bool {
+        self.0.lock().unwrap().instances.get(server_id) == Some(&instance)
+    }
+
+    /// Retire every instance, for when the MCP client is replaced.
+    pub fn retire_all(&self) {
+        let mut state = self.0.lock().unwrap();
+        state.instances.clear();
+        state.restarts.clear();
+    }
+
+    /// The server connected, so previous crashes no longer count.
+    pub fn mark_ready(&self, server_id: &str) {
+        self.0.lock().unwrap().restarts.remove(server_id);
+    }
+
+    /// Delay before restarting a crashed server, or `None` to give up.
+    pub fn next_restart_delay(&self, server_id: &str) -> Option<Duration> {
+        let mut state = self.0.lock().unwrap();
+        let attempts = state.restarts.entry(server_id.to_string()).or_default();
+        let delay = restart_delay(*attempts)?;
+        *attempts += 1;
+        Some(delay)
+    }
+
+    /// Transport for a server, capturing the stderr of stdio processes.
+    ///
+    /// Fails if the sandbox of a stdio server can not be enforced.
+    ///
+    /// Stdio processes are watched under `instance`, see [`Store::handle_mcp_server_exit`].
+    ///
+    /// [`Store::handle_mcp_server_exit`]: super::store::Store::handle_mcp_server_exit
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn transport(
+        &self,
+        server_id: &str,
+        server: &McpServer,
+        instance: u64,
+    ) -> Result<Option<McpServerTransport>, String> {
+        let Some(mut command) = server.stdio_command()? else {
+            return server.to_transport();
+        };
+
+        match std::io::pipe() {
+            Ok((reader, writer)) => {
+                command.stderr(writer);
+                self.watch_stderr(server_id.to_string(), instance, reader);
+            }
+            Err(e) => self.log(
+                server_id,
+                McpLogSource::Supervisor,
+                format!("Could not capture stderr: {}", e),
+            ),
+        }
+
+        Ok(Some(McpServerTransport::Stdio(command)))
+    }
+
+    /// Forward the stderr of a process to its log until it closes.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn watch_stderr(&self, server_id: String, instance: u64, reader: std::io::PipeReader) {
+        use std::io::BufRead;
+
+        let supervisor = self.clone();
+        std::thread::spawn(move || {
+            // Batches redraws while the process writes a lot.
+            let mut last_redraw = std::time::Instant::now();
+
+            for line in std::io::BufReader::new(reader).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                supervisor.log(&server_id, McpLogSource::Stderr, line);
+
+                if last_redraw.elapsed() > Duration::from_millis(250) {
+                    last_redraw = std::time::Instant::now();
+                    app_runner().defer(|_, cx, _| cx.redraw_all());
+                }
+            }
+
+            // The write end is only closed once the process and its children exit.
+            if supervisor.is_current(&server_id, instance) {
+                app_runner().defer(move |app, cx, _| {
+                    if let Some(store) = app.store.as_mut() {
+                        store.handle_mcp_server_exit(&server_id, instance);
+                        cx.redraw_all();
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// Exponential backoff starting at one second, or `None` once `attempts`
+/// reaches [`MAX_RESTARTS`].
+pub(super) fn restart_delay(attempts: u32) -> Option<Duration> {
+    (attempts < MAX_RESTARTS).then(|| Duration::from_secs(1 << attempts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_drops_oldest_lines() {
+        let supervisor = McpSupervisor::default();
+        for i in 0..LOG_CAPACITY + 10 {
+            supervisor.log("server", McpLogSource::Stderr, i.to_string());
+        }
+
+        let tail = supervisor.log_tail("server", LOG_CAPACITY * 2);
+        assert_eq!(tail.len(), LOG_CAPACITY);
+        assert_eq!(tail[0].text, "10");
+
+        let tail = supervisor.log_tail("server", 2);
+        assert_eq!(tail.len(), 2);
+        assert_eq!(tail[1].text, (LOG_CAPACITY + 9).to_string());
+        assert!(supervisor.log_tail("other", 10).is_empty());
+    }
+
+    #[test]
+    fn test_stderr_tail() {
+        let supervisor = McpSupervisor::default();
+        supervisor.log("server", McpLogSource::Stderr, "first");
+        supervisor.log("server", McpLogSource::Supervisor, "Starting");
+        supervisor.log("server", McpLogSource::Stderr, "second");
+
+        assert_eq!(supervisor.stderr_tail("server", 10), "first\nsecond");
+        assert_eq!(supervisor.stderr_tail("server", 1), "second");
+    }
+
+    #[test]
+    fn test_instances() {
+        let supervisor = McpSupervisor::default();
+        let first = supervisor.start_instance("server");
+        let second = supervisor.start_instance("server");
+        assert!(!supervisor.is_current("server", first));
+        assert!(supervisor.is_current("server", second));
+
+        supervisor.retire_all();
+        assert!(!supervisor.is_current("server", second));
+    }
+
+    #[test]
+    fn test_restart_backoff() {
+        let supervisor = McpSupervisor::default();
+        let delays: Vec<_> = std::iter::from_fn(|| supervisor.next_restart_delay("server"))
+            .map(|delay| delay.as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16]);
+
+        supervisor.mark_ready("server");
+        assert_eq!(
+            supervisor.next_restart_delay("server"),
+            Some(Duration::from_secs(1))
+        );
+    }
+}
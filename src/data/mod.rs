@@ -1,13 +1,38 @@
+pub mod attachment_preprocessor;
+pub mod bot_clients;
 pub mod bot_fetcher;
 pub mod capture;
 pub mod chats;
 pub mod deep_inquire_client;
 pub mod downloads;
+pub mod events;
+pub mod gateway;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod gateway_server;
+pub mod hardware;
+pub mod health_monitor;
+pub mod mcp_context;
+pub mod mcp_import;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mcp_oauth;
+pub mod mcp_requests;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mcp_sandbox;
 pub mod mcp_servers;
+pub mod mcp_status;
+pub mod mcp_supervisor;
+pub mod model_loader;
+pub mod model_registry;
 pub mod moly_client;
+pub mod moly_server_process;
+pub mod moly_servers;
 pub mod openclaw_client;
 pub mod preferences;
+pub mod provider_models_cache;
 pub mod providers;
 pub mod search;
+pub mod storage;
 pub mod store;
 pub mod supported_providers;
+pub mod tool_audit;
+pub mod tool_filter;
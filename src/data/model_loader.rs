@@ -0,0 +1,254 @@
+//! Loading downloaded files into MolyServer with chosen runtime options, and
+//! knowing which one is resident.
+//!
+//! Without it, MolyServer loads a file with its defaults when it's first chatted
+//! with, and nothing tells how it's going. Servers that answer a load request
+//! with not found keep doing so, and loading isn't offered anymore.
+//!
+//! The resident file is the one loaded here last. Files loaded by chatting
+//! with them aren't known.
+
+use moly_kit::aitk::utils::asynchronous::spawn;
+use moly_protocol::data::FileId;
+use moly_protocol::protocol::{
+    ContextOverflowPolicy, GPULayers, LoadModelOptions, LoadedModelInfo,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::app::app_runner;
+
+use super::moly_client::MolyClient;
+
+/// Runtime options of a file, remembered for the next time it's loaded.
+///
+/// Unset options are left to MolyServer, which picks them for the model.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeOptions {
+    /// Tokens of context.
+    #[serde(default)]
+    pub context_size: Option<u32>,
+    #[serde(default)]
+    pub batch_size: Option<u32>,
+    /// Layers offloaded to the GPU, all of them when unset.
+    #[serde(default)]
+    pub gpu_layers: Option<u32>,
+    /// Keep the model in memory, so the system can't swap it out.
+    #[serde(default)]
+    pub use_mlock: bool,
+}
+
+impl RuntimeOptions {
+    pub fn to_load_options(&self) -> LoadModelOptions {
+        LoadModelOptions {
+            prompt_template: None,
+            gpu_layers: match self.gpu_layers {
+                Some(layers) => GPULayers::Specific(layers),
+                None => GPULayers::Max,
+            },
+            use_mlock: self.use_mlock,
+            // Zero has llama.cpp take the RoPE settings from the model file.
+            rope_freq_scale: 0.0,
+            rope_freq_base: 0.0,
+            context_overflow_policy: ContextOverflowPolicy::StopAtLimit,
+            n_batch: self.batch_size,
+            n_ctx: self.context_size,
+        }
+    }
+}
+
+/// The file MolyServer has in memory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadedModel {
+    pub file_id: FileId,
+    pub model_id: String,
+    /// What the server says of the loaded model, like its context size.
+    pub information: String,
+}
+
+impl From<LoadedModelInfo> for LoadedModel {
+    fn from(info: LoadedModelInfo) -> Self {
+        Self {
+            file_id: info.file_id,
+            model_id: info.model_id,
+            information: info.information,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum LoadState {
+    #[default]
+    Idle,
+    /// Progress from 0 to 1, when the server reports it.
+    Loading {
+        file_id: FileId,
+        progress: Option<f64>,
+    },
+    Loaded(LoadedModel),
+    Ejecting(FileId),
+    Failed {
+        file_id: FileId,
+        error: String,
+    },
+}
+
+impl LoadState {
+    /// The file being loaded, loaded or ejected.
+    pub fn file_id(&self) -> Option<&FileId> {
+        match self {
+            Self::Idle => None,
+            Self::Loading { file_id, .. }
+            | Self::Ejecting(file_id)
+            | Self::Failed { file_id, .. } => Some(file_id),
+            Self::Loaded(model) => Some(&model.file_id),
+        }
+    }
+}
+
+pub struct ModelLoader {
+    moly_client: MolyClient,
+    pub state: LoadState,
+    /// Whether the server can load files on request, until it says otherwise.
+    pub supported: bool,
+    /// Bumped on each request, so answers to earlier ones are ignored.
+    generation: u64,
+}
+
+impl ModelLoader {
+    pub fn new(moly_client: MolyClient) -> Self {
+        Self {
+            moly_client,
+            state: LoadState::Idle,
+            supported: true,
+            generation: 0,
+        }
+    }
+
+    /// Follow another server, forgetting what the previous one had loaded.
+    pub fn set_client(&mut self, moly_client: MolyClient) {
+        self.moly_client = moly_client;
+        self.generation += 1;
+        self.state = LoadState::Idle;
+        self.supported = true;
+    }
+
+    pub fn is_busy(&self) -> bool {
+        matches!(
+            self.state,
+            LoadState::Loading { .. } | LoadState::Ejecting(_)
+        )
+    }
+
+    /// Forget the resident file when the server dropped it on its own, like
+    /// when ejecting before deleting a file or after restarting.
+    pub fn forget_resident(&mut self) {
+        if self.is_busy() {
+            return;
+        }
+        self.generation += 1;
+        self.state = LoadState::Idle;
+    }
+
+    pub fn load(&mut self, file_id: FileId, options: &RuntimeOptions) {
+        if !self.supported {
+            return;
+        }
+
+        self.generation += 1;
+        let generation = self.generation;
+        self.state = LoadState::Loading {
+            file_id: file_id.clone(),
+            progress: None,
+        };
+
+        let moly_client = self.moly_client.clone();
+        let options = options.to_load_options();
+        spawn(async move {
+            let progress_file_id = file_id.clone();
+            let result = moly_client
+                .load_model(file_id.clone(), options, move |progress| {
+                    let file_id = progress_file_id.clone();
+                    app_runner().defer(move |app, _, _| {
+                        let me = &mut app.store.as_mut().unwrap().model_loader;
+                        if me.generation == generation {
+                            me.state = LoadState::Loading {
+                                file_id,
+                                progress: Some(progress),
+                            };
+                        }
+                    });
+                })
+                .await;
+
+            app_runner().defer(move |app, _, _| {
+                let me = &mut app.store.as_mut().unwrap().model_loader;
+                if me.generation != generation {
+                    return;
+                }
+                me.state = match result {
+                    Ok(Some(model)) => LoadState::Loaded(model),
+                    Ok(None) => {
+                        me.supported = false;
+                        LoadState::Idle
+                    }
+                    Err(e) => LoadState::Failed {
+                        file_id,
+                        error: e.to_string(),
+                    },
+                };
+            });
+        });
+    }
+
+    pub fn eject(&mut self) {
+        let Some(file_id) = self.state.file_id().cloned() else {
+            return;
+        };
+
+        self.generation += 1;
+        let generation = self.generation;
+        self.state = LoadState::Ejecting(file_id.clone());
+
+        let moly_client = self.moly_client.clone();
+        spawn(async move {
+            let result = moly_client.eject_model().await;
+            app_runner().defer(move |app, _, _| {
+                let me = &mut app.store.as_mut().unwrap().model_loader;
+                if me.generation != generation {
+                    return;
+                }
+                me.state = match result {
+                    Ok(()) => LoadState::Idle,
+                    Err(e) => LoadState::Failed {
+                        file_id,
+                        error: format!("Couldn't eject: {}", e),
+                    },
+                };
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_options() {
+        let defaults = RuntimeOptions::default().to_load_options();
+        assert!(matches!(defaults.gpu_layers, GPULayers::Max));
+        assert_eq!(defaults.n_ctx, None);
+
+        let options = RuntimeOptions {
+            context_size: Some(8192),
+            batch_size: Some(512),
+            gpu_layers: Some(20),
+            use_mlock: true,
+        }
+        .to_load_options();
+        assert!(matches!(options.gpu_layers, GPULayers::Specific(20)));
+        assert_eq!(options.n_ctx, Some(8192));
+        assert_eq!(options.n_batch, Some(512));
+        assert!(options.use_mlock);
+    }
+}
@@ -0,0 +1,320 @@
+//! What is known about models beyond what their providers list.
+//!
+//! Providers usually only give an id and a name, so the context window,
+//! supported inputs, tool support and pricing come from a registry keyed by
+//! model id patterns. The bundled registry can be extended or corrected with a
+//! `model_registry.json` file in the data directory, in the same format, whose
+//! entries take precedence.
+
+use moly_kit::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::shared::utils::filesystem;
+
+const OVERRIDES_FILENAME: &str = "model_registry.json";
+
+/// Price in USD per million tokens.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+}
+
+/// Known facts about a model, `None` when unknown.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ModelMetadata {
+    /// Tokens the model can take in, prompt and response included.
+    pub context_window: Option<u32>,
+    /// Tokens the model can produce in one response.
+    pub max_output: Option<u32>,
+    /// Whether the model understands images.
+    pub vision: Option<bool>,
+    /// Whether the model understands audio.
+    pub audio: Option<bool>,
+    /// Whether the model can call tools.
+    pub tools: Option<bool>,
+    /// Whether the model reasons before answering.
+    pub reasoning: Option<bool>,
+    /// Like "2024-06".
+    pub knowledge_cutoff: Option<String>,
+    pub pricing: Option<ModelPricing>,
+}
+
+impl ModelMetadata {
+    /// Fill the fields still unknown with those of `other`.
+    fn merge(&mut self, other: &ModelMetadata) {
+        self.context_window = self.context_window.or(other.context_window);
+        self.max_output = self.max_output.or(other.max_output);
+        self.vision = self.vision.or(other.vision);
+        self.audio = self.audio.or(other.audio);
+        self.tools = self.tools.or(other.tools);
+        self.reasoning = self.reasoning.or(other.reasoning);
+        if self.knowledge_cutoff.is_none() {
+            self.knowledge_cutoff = other.knowledge_cutoff.clone();
+        }
+        self.pricing = self.pricing.or(other.pricing);
+    }
+
+    /// The capabilities a client reported for a model, corrected with what is
+    /// known about it.
+    ///
+    /// Image attachments are only offered to models known to see images, and
+    /// always to those that do.
+    pub fn capabilities(&self, reported: &BotCapabilities) -> BotCapabilities {
+        let attachments = self
+            .vision
+            .unwrap_or_else(|| reported.has_capability(&BotCapability::AttachmentInput));
+
+        let capabilities = [
+            BotCapability::TextInput,
+            BotCapability::AudioCall,
+            BotCapability::AttachmentInput,
+        ]
+        .into_iter()
+        .filter(|capability| match capability {
+            BotCapability::AttachmentInput => attachments,
+            capability => reported.has_capability(capability),
+        });
+
+        BotCapabilities::new().with_capabilities(capabilities)
+    }
+
+    /// A one line description, like "128K context · vision · tools · $2.50/$10.00 per 1M".
+    pub fn summary(&self) -> Option<String> {
+        let mut parts = Vec::new();
+
+        if let Some(context_window) = self.context_window {
+            parts.push(format!("{} context", format_tokens(context_window)));
+        }
+        for (supported, name) in [
+            (self.vision, "vision"),
+            (self.audio, "audio"),
+            (self.tools, "tools"),
+            (self.reasoning, "reasoning"),
+        ] {
+            if supported == Some(true) {
+                parts.push(name.to_string());
+            }
+        }
+        if let Some(cutoff) = &self.knowledge_cutoff {
+            parts.push(format!("knows up to {}", cutoff));
+        }
+        if let Some(pricing) = self.pricing {
+            parts.push(format!(
+                "${:.2}/${:.2} per 1M",
+                pricing.input, pricing.output
+            ));
+        }
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" · "))
+        }
+    }
+}
+
+/// Metadata of the models whose id matches `pattern`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RegistryEntry {
+    /// Model id with `*` wildcards, matched without case against the whole id
+    /// or the part after its last `/`, like `gpt-4o*` for `openai/gpt-4o-2024-08-06`.
+    pub pattern: String,
+    #[serde(flatten)]
+    pub metadata: ModelMetadata,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct RegistryFile {
+    models: Vec<RegistryEntry>,
+}
+
+/// The bundled registry with the user overrides, cheap to clone.
+#[derive(Debug, Clone, Default)]
+pub struct ModelRegistry(Arc<Vec<RegistryEntry>>);
+
+impl ModelRegistry {
+    pub fn bundled() -> Self {
+        Self(Arc::new(bundled_entries()))
+    }
+
+    /// The bundled registry with the overrides from the data directory first.
+    pub async fn load() -> Self {
+        let fs = filesystem::global();
+        let path = PathBuf::from(OVERRIDES_FILENAME);
+
+        let mut entries = Vec::new();
+        if fs.exists(&path).await.unwrap_or(false) {
+            match fs.read_json::<RegistryFile>(&path).await {
+                Ok(file) => entries = file.models,
+                Err(e) => log::error!("Failed to read the model registry overrides: {:?}", e),
+            }
+        }
+        entries.extend(bundled_entries());
+
+        Self(Arc::new(entries))
+    }
+
+    /// What is known about the model `model_id`, as given by its provider.
+    ///
+    /// Every matching entry contributes the fields the entries before it left
+    /// unknown, so put the most specific patterns first.
+    pub fn lookup(&self, model_id: &str) -> Option<ModelMetadata> {
+        let model_id = model_id.to_lowercase();
+        let short_id = model_id.rsplit('/').next().unwrap_or(&model_id);
+
+        let mut found: Option<ModelMetadata> = None;
+        for entry in self.0.iter() {
+            let pattern = entry.pattern.to_lowercase();
+            if !glob_match(&pattern, &model_id) && !glob_match(&pattern, short_id) {
+                continue;
+            }
+            match &mut found {
+                Some(metadata) => metadata.merge(&entry.metadata),
+                None => found = Some(entry.metadata.clone()),
+            }
+        }
+        found
+    }
+
+    /// Like [`Self::lookup`] for a bot routed through the app `RouterClient`,
+    /// whose id is prefixed with its provider.
+    pub fn lookup_bot(&self, bot_id: &BotId) -> Option<ModelMetadata> {
+        match RouterClient::unprefix(bot_id) {
+            Some((_, raw_id)) => self.lookup(raw_id.as_str()),
+            None => self.lookup(bot_id.as_str()),
+        }
+    }
+}
+
+fn bundled_entries() -> Vec<RegistryEntry> {
+    let data = include_str!("./model_registry.json");
+    let parsed: RegistryFile =
+        serde_json::from_str(data).expect("Failed to parse model_registry.json");
+    parsed.models
+}
+
+/// Whether `text` matches `pattern`, where `*` stands for any run of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No wildcard at all.
+        return rest.is_empty();
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Like "128K" or "1M".
+fn format_tokens(tokens: u32) -> String {
+    if tokens >= 1_000_000 {
+        format!("{}M", tokens / 1_000_000)
+    } else if tokens >= 1_000 {
+        format!("{}K", tokens / 1_000)
+    } else {
+        tokens.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pattern: &str, metadata: ModelMetadata) -> RegistryEntry {
+        RegistryEntry {
+            pattern: pattern.to_string(),
+            metadata,
+        }
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("gpt-4o", "gpt-4o"));
+        assert!(!glob_match("gpt-4o", "gpt-4o-mini"));
+        assert!(glob_match("gpt-4o*", "gpt-4o-mini"));
+        assert!(!glob_match("gpt-4o*", "chatgpt-4o"));
+        assert!(glob_match("*realtime*", "gpt-4o-realtime-preview"));
+        assert!(glob_match("qwen*-vl*", "qwen2.5-vl:7b"));
+        assert!(!glob_match("qwen*-vl*", "qwen3:8b"));
+        assert!(glob_match("*", ""));
+        // The end can't overlap with a middle part.
+        assert!(!glob_match("a*bc*c", "abc"));
+    }
+
+    #[test]
+    fn test_lookup_merges_matching_entries() {
+        let registry = ModelRegistry(Arc::new(vec![
+            entry(
+                "llama3.2-vision*",
+                ModelMetadata {
+                    vision: Some(true),
+                    tools: Some(false),
+                    ..Default::default()
+                },
+            ),
+            entry(
+                "llama3*",
+                ModelMetadata {
+                    context_window: Some(128_000),
+                    vision: Some(false),
+                    tools: Some(true),
+                    ..Default::default()
+                },
+            ),
+        ]));
+
+        let vision = registry.lookup("meta/Llama3.2-Vision:11b").unwrap();
+        assert_eq!(vision.vision, Some(true));
+        assert_eq!(vision.tools, Some(false));
+        assert_eq!(vision.context_window, Some(128_000));
+
+        let text = registry.lookup("llama3.1:8b").unwrap();
+        assert_eq!(text.vision, Some(false));
+        assert_eq!(text.tools, Some(true));
+
+        assert_eq!(registry.lookup("mistral"), None);
+    }
+
+    #[test]
+    fn test_bundled_registry() {
+        let registry = ModelRegistry::bundled();
+        let mini = registry.lookup("gpt-4o-mini-2024-07-18").unwrap();
+        assert_eq!(mini.pricing.unwrap().input, 0.15);
+        assert_eq!(registry.lookup("openai/gpt-4o").unwrap().vision, Some(true));
+    }
+
+    #[test]
+    fn test_summary() {
+        let metadata = ModelMetadata {
+            context_window: Some(128_000),
+            vision: Some(true),
+            tools: Some(true),
+            reasoning: Some(false),
+            pricing: Some(ModelPricing {
+                input: 2.5,
+                output: 10.0,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            metadata.summary().as_deref(),
+            Some("128K context · vision · tools · $2.50/$10.00 per 1M")
+        );
+        assert_eq!(ModelMetadata::default().summary(), None);
+    }
+}
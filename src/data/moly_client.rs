@@ -1,12 +1,13 @@
 use anyhow::{Result, anyhow};
-use makepad_widgets::*;
-use moly_protocol::{
-    data::{DownloadedFile, File, FileId, Model, PendingDownload},
-    protocol::FileDownloadResponse,
-};
+use moly_protocol::data::{DownloadedFile, File, FileId, Model, PendingDownload};
+use moly_protocol::protocol::{LoadModelOptions, LoadModelResponse};
 use std::sync::{Arc, Mutex};
 use url::Url;
 
+use super::downloads::progress::{EventParser, ProgressEvent, ProgressParser};
+use super::events::{DataEvent, report};
+use super::model_loader::LoadedModel;
+
 #[derive(Debug)]
 struct Inner {
     address: String,
@@ -22,11 +23,30 @@ pub struct MolyClient {
 #[allow(dead_code)]
 impl MolyClient {
     pub fn new(address: String) -> Self {
-        let client = reqwest::Client::builder();
+        Self::with_api_key(address, None)
+    }
+
+    /// A client sending `api_key` as a bearer token with every request.
+    pub fn with_api_key(address: String, api_key: Option<String>) -> Self {
+        let mut client = reqwest::Client::builder();
 
         // web doesn't support these
         #[cfg(not(target_arch = "wasm32"))]
-        let client = client.no_proxy();
+        {
+            client = client.no_proxy();
+        }
+
+        if let Some(api_key) = api_key {
+            let mut headers = reqwest::header::HeaderMap::new();
+            match reqwest::header::HeaderValue::from_str(&format!("Bearer {}", api_key)) {
+                Ok(mut value) => {
+                    value.set_sensitive(true);
+                    headers.insert(reqwest::header::AUTHORIZATION, value);
+                }
+                Err(_) => ::log::error!("Ignoring an API key that can't be sent in a header"),
+            }
+            client = client.default_headers(headers);
+        }
 
         let client = client.build().expect("Failed to build reqwest client");
 
@@ -43,6 +63,20 @@ impl MolyClient {
         self.inner.lock().unwrap().address.clone()
     }
 
+    /// Whether both are clones of the same client.
+    pub fn same_server(&self, other: &MolyClient) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// Point every clone of the client to another server.
+    pub fn set_address(&self, address: String) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.address != address {
+            inner.address = address;
+            inner.is_connected = false;
+        }
+    }
+
     pub fn is_connected(&self) -> bool {
         self.inner.lock().unwrap().is_connected
     }
@@ -64,7 +98,7 @@ impl MolyClient {
                     Ok(())
                 } else {
                     self.set_is_connected(false);
-                    Cx::post_action(MolyClientAction::ServerUnreachable);
+                    report(DataEvent::MolyServerUnreachable);
                     Err(anyhow!("Server error: {}", r.status()))
                 }
             }
@@ -91,7 +125,7 @@ impl MolyClient {
             }
             Err(e) => {
                 self.set_is_connected(false);
-                Cx::post_action(MolyClientAction::ServerUnreachable);
+                report(DataEvent::MolyServerUnreachable);
                 Err(anyhow!("Request failed: {}", e))
             }
         }
@@ -113,7 +147,7 @@ impl MolyClient {
             }
             Err(e) => {
                 self.set_is_connected(false);
-                Cx::post_action(MolyClientAction::ServerUnreachable);
+                report(DataEvent::MolyServerUnreachable);
                 Err(anyhow!("Request failed: {}", e))
             }
         }
@@ -138,7 +172,7 @@ impl MolyClient {
             }
             Err(e) => {
                 self.set_is_connected(false);
-                Cx::post_action(MolyClientAction::ServerUnreachable);
+                report(DataEvent::MolyServerUnreachable);
                 Err(anyhow!("Request failed: {}", e))
             }
         }
@@ -160,7 +194,7 @@ impl MolyClient {
             }
             Err(e) => {
                 self.set_is_connected(false);
-                Cx::post_action(MolyClientAction::ServerUnreachable);
+                report(DataEvent::MolyServerUnreachable);
                 Err(anyhow!("Request failed: {}", e))
             }
         }
@@ -188,20 +222,20 @@ impl MolyClient {
             }
             Err(e) => {
                 self.set_is_connected(false);
-                Cx::post_action(MolyClientAction::ServerUnreachable);
+                report(DataEvent::MolyServerUnreachable);
                 Err(anyhow!("Request failed: {}", e))
             }
         }
     }
 
+    /// Follow the progress stream of a download, until it completes, fails or
+    /// the connection drops.
     pub async fn track_download_progress(
         &self,
         file_id: FileId,
-        mut tx: futures::channel::mpsc::UnboundedSender<
-            Result<FileDownloadResponse, anyhow::Error>,
-        >,
-    ) {
-        use futures::{SinkExt, stream::TryStreamExt};
+        mut on_progress: impl FnMut(f64),
+    ) -> ProgressStreamEnd {
+        use futures::stream::TryStreamExt;
 
         let mut url =
             Url::parse(&format!("{}/downloads", self.address())).expect("Invalid Moly server URL");
@@ -211,72 +245,33 @@ impl MolyClient {
             .push(&file_id)
             .push("progress");
 
-        match self.client().get(url).send().await {
+        let res = match self.client().get(url).send().await {
+            Ok(res) if res.status().is_success() => res,
             Ok(res) => {
-                let mut bytes = res.bytes_stream();
-                let mut buffer = String::new();
-                let mut current_event = String::new();
-
-                while let Ok(Some(chunk)) = bytes.try_next().await {
-                    if let Ok(text) = String::from_utf8(chunk.to_vec()) {
-                        buffer.push_str(&text);
-
-                        while let Some(pos) = buffer.find('\n') {
-                            let line = buffer[..pos].trim().to_string();
-                            buffer = buffer[pos + 1..].to_string();
-
-                            if line.starts_with("event: ") {
-                                current_event =
-                                    line.trim_start_matches("event: ").trim().to_string();
-                            } else if line.starts_with("data: ") {
-                                let event_data = line.trim_start_matches("data: ").trim();
-                                match current_event.as_str() {
-                                    "complete" => {
-                                        if let Err(e) = tx
-                                            .send(Ok(FileDownloadResponse::Completed(
-                                                moly_protocol::data::DownloadedFile::default(),
-                                            )))
-                                            .await
-                                        {
-                                            eprintln!("Failed to send completion message: {}", e);
-                                        }
-                                        break;
-                                    }
-                                    "error" => {
-                                        if let Err(e) =
-                                            tx.send(Err(anyhow!("Download failed"))).await
-                                        {
-                                            eprintln!("Failed to send error message: {}", e);
-                                        }
-                                        break;
-                                    }
-                                    "progress" => {
-                                        if let Ok(value) = event_data.parse::<f32>() {
-                                            let _ = tx
-                                                .send(Ok(FileDownloadResponse::Progress(
-                                                    file_id.clone(),
-                                                    value,
-                                                )))
-                                                .await;
-                                        }
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }
-
-                        if current_event == "complete" || current_event == "error" {
-                            break;
-                        }
-                    }
-                }
+                eprintln!("Error tracking download progress: {}", res.status());
+                return ProgressStreamEnd::Disconnected;
             }
             Err(e) => {
-                let _ = tx.send(Err(anyhow!("Request failed: {}", e))).await;
+                eprintln!("Error tracking download progress: {}", e);
                 self.set_is_connected(false);
-                Cx::post_action(MolyClientAction::ServerUnreachable);
+                report(DataEvent::MolyServerUnreachable);
+                return ProgressStreamEnd::Disconnected;
+            }
+        };
+
+        let mut parser = ProgressParser::default();
+        let mut bytes = res.bytes_stream();
+        while let Ok(Some(chunk)) = bytes.try_next().await {
+            for event in parser.push(&chunk) {
+                match event {
+                    ProgressEvent::Progress(value) => on_progress(value),
+                    ProgressEvent::Complete => return ProgressStreamEnd::Completed,
+                    ProgressEvent::Error => return ProgressStreamEnd::Failed,
+                }
             }
         }
+
+        ProgressStreamEnd::Disconnected
     }
 
     pub async fn pause_download_file(&self, file_id: FileId) -> Result<()> {
@@ -308,7 +303,7 @@ impl MolyClient {
             }
             Err(e) => {
                 self.set_is_connected(false);
-                Cx::post_action(MolyClientAction::ServerUnreachable);
+                report(DataEvent::MolyServerUnreachable);
                 Err(anyhow!("Request failed: {}", e))
             }
         }
@@ -343,7 +338,7 @@ impl MolyClient {
             }
             Err(e) => {
                 self.set_is_connected(false);
-                Cx::post_action(MolyClientAction::ServerUnreachable);
+                report(DataEvent::MolyServerUnreachable);
                 Err(anyhow!("Request failed: {}", e))
             }
         }
@@ -369,12 +364,98 @@ impl MolyClient {
             }
             Err(e) => {
                 self.set_is_connected(false);
-                Cx::post_action(MolyClientAction::ServerUnreachable);
+                report(DataEvent::MolyServerUnreachable);
                 Err(anyhow!("Request failed: {}", e))
             }
         }
     }
 
+    /// Load a downloaded file into memory, reporting the progress from 0 to 1
+    /// when the server streams it, or `None` if the server only loads files
+    /// when they're chatted with.
+    ///
+    /// The server answers with the [`LoadModelResponse`] of moly-protocol, or
+    /// streams them as events while loading.
+    pub async fn load_model(
+        &self,
+        file_id: FileId,
+        options: LoadModelOptions,
+        mut on_progress: impl FnMut(f64),
+    ) -> Result<Option<LoadedModel>> {
+        use futures::stream::TryStreamExt;
+
+        let url = format!("{}/models/load", self.address());
+
+        let resp = self
+            .client()
+            .post(&url)
+            .json(&serde_json::json!({
+                "file_id": file_id,
+                "options": options,
+            }))
+            .send()
+            .await;
+
+        let r = match resp {
+            Ok(r) => r,
+            Err(e) => {
+                self.set_is_connected(false);
+                report(DataEvent::MolyServerUnreachable);
+                return Err(anyhow!("Request failed: {}", e));
+            }
+        };
+
+        if r.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !r.status().is_success() {
+            return Err(anyhow!("Server error: {}", r.status()));
+        }
+
+        let is_stream = r
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+        if !is_stream {
+            let response = r
+                .json::<LoadModelResponse>()
+                .await
+                .map_err(|e| anyhow!("Failed to parse the load response: {}", e))?;
+            return match response {
+                LoadModelResponse::Completed(info) | LoadModelResponse::ModelResumed(info) => {
+                    Ok(Some(LoadedModel::from(info)))
+                }
+                LoadModelResponse::Progress(..) => {
+                    Err(anyhow!("The server stopped before the model was loaded"))
+                }
+            };
+        }
+
+        let mut parser = EventParser::default();
+        let mut bytes = r.bytes_stream();
+        while let Some(chunk) = bytes
+            .try_next()
+            .await
+            .map_err(|e| anyhow!("Loading stream failed: {}", e))?
+        {
+            for (event, data) in parser.push(&chunk) {
+                if event == "error" {
+                    return Err(anyhow!("{}", data));
+                }
+                match serde_json::from_str::<LoadModelResponse>(&data) {
+                    Ok(LoadModelResponse::Progress(_, progress)) => on_progress(progress as f64),
+                    Ok(
+                        LoadModelResponse::Completed(info) | LoadModelResponse::ModelResumed(info),
+                    ) => return Ok(Some(LoadedModel::from(info))),
+                    Err(e) => return Err(anyhow!("Failed to parse the load response: {}", e)),
+                }
+            }
+        }
+
+        Err(anyhow!("The server stopped before the model was loaded"))
+    }
+
     pub async fn eject_model(&self) -> Result<()> {
         let url = format!("{}/models/eject", self.address());
 
@@ -388,16 +469,18 @@ impl MolyClient {
             }
             Err(e) => {
                 self.set_is_connected(false);
-                Cx::post_action(MolyClientAction::ServerUnreachable);
+                report(DataEvent::MolyServerUnreachable);
                 Err(anyhow!("Request failed: {}", e))
             }
         }
     }
 }
 
-#[derive(Clone, Debug, Default)]
-pub enum MolyClientAction {
-    #[default]
-    None,
-    ServerUnreachable,
+/// How the progress stream of a download ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressStreamEnd {
+    Completed,
+    Failed,
+    /// The connection dropped, or the server didn't know the download.
+    Disconnected,
 }
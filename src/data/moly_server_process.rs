@@ -0,0 +1,630 @@
+//! Running MolyServer as a child process of Moly.
+//!
+//! On launch, a MolyServer already answering on the configured port is used as
+//! is. Otherwise the `moly-server` binary is located and started, on the next
+//! free port when another program listens on the configured one. Its output is
+//! kept in a log, it's restarted with a backoff when it exits on its own, and
+//! it's stopped together with Moly, giving it a moment to exit cleanly first.
+//!
+//! Every start bumps a generation, so the exit of a process Moly stopped or
+//! replaced is not taken as a crash.
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::mcp_supervisor::restart_delay;
+use super::moly_client::MolyClient;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::app::app_runner;
+#[cfg(not(target_arch = "wasm32"))]
+use moly_kit::aitk::utils::asynchronous::{sleep, spawn};
+
+#[cfg(windows)]
+const BINARY_NAME: &str = "moly-server.exe";
+#[cfg(not(windows))]
+const BINARY_NAME: &str = "moly-server";
+
+/// Lines kept from the output of the process. Older lines are dropped.
+const LOG_CAPACITY: usize = 1000;
+
+/// How long a started server has to answer before it's stopped and reported
+/// as failed.
+#[cfg(not(target_arch = "wasm32"))]
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long a stopped server has to exit on its own before it's killed.
+#[cfg(not(target_arch = "wasm32"))]
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Ports tried after the configured one when it's taken.
+const PORT_ATTEMPTS: u16 = 20;
+
+const LOCAL_URL_PREFIX: &str = "http://localhost:";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MolyServerProcessConfig {
+    /// Start MolyServer with Moly when it isn't already running.
+    #[serde(default = "default_managed")]
+    pub managed: bool,
+    /// The `moly-server` binary, searched next to Moly and in the `PATH` when unset.
+    #[serde(default)]
+    pub binary_path: Option<PathBuf>,
+}
+
+impl Default for MolyServerProcessConfig {
+    fn default() -> Self {
+        Self {
+            managed: default_managed(),
+            binary_path: None,
+        }
+    }
+}
+
+fn default_managed() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum MolyServerProcessStatus {
+    /// Not started by Moly, as configured.
+    #[default]
+    Unmanaged,
+    /// Checking whether a MolyServer is already running.
+    Probing,
+    /// A MolyServer started outside of Moly answers on the port.
+    External,
+    /// No `moly-server` binary could be found.
+    NotFound,
+    Starting,
+    Running,
+    /// Exited on its own, started again after the delay.
+    Restarting(Duration),
+    Stopped,
+    Failed(String),
+}
+
+impl MolyServerProcessStatus {
+    pub fn to_human_readable(&self) -> String {
+        match self {
+            Self::Unmanaged => "Not started by Moly".to_string(),
+            Self::Probing => "Looking for a running MolyServer...".to_string(),
+            Self::External => "Running, started outside of Moly".to_string(),
+            Self::NotFound => format!("The {} binary was not found", BINARY_NAME),
+            Self::Starting => "Starting...".to_string(),
+            Self::Running => "Running".to_string(),
+            Self::Restarting(delay) => {
+                format!("Exited, restarting in {}s", delay.as_secs())
+            }
+            Self::Stopped => "Stopped".to_string(),
+            Self::Failed(error) => error.clone(),
+        }
+    }
+}
+
+/// Output of the process and events of Moly about it, shared with the threads
+/// reading the output.
+#[derive(Clone, Default)]
+struct ProcessLog(Arc<Mutex<VecDeque<String>>>);
+
+impl ProcessLog {
+    fn push(&self, text: impl AsRef<str>) {
+        let mut lines = self.0.lock().unwrap();
+        if lines.len() == LOG_CAPACITY {
+            lines.pop_front();
+        }
+        lines.push_back(format!(
+            "{} {}",
+            Local::now().format("%H:%M:%S"),
+            text.as_ref()
+        ));
+    }
+
+    fn tail(&self, max_lines: usize) -> Vec<String> {
+        let lines = self.0.lock().unwrap();
+        let start = lines.len().saturating_sub(max_lines);
+        lines.iter().skip(start).cloned().collect()
+    }
+}
+
+/// The MolyServer process started by Moly, if any.
+#[derive(Default)]
+pub struct MolyServerProcess {
+    status: MolyServerProcessStatus,
+    log: ProcessLog,
+    binary: Option<PathBuf>,
+    /// Port the process was started on last.
+    port: Option<u16>,
+    #[cfg(not(target_arch = "wasm32"))]
+    child: Arc<Mutex<Option<std::process::Child>>>,
+    /// Thread waiting for the process stopped last to exit.
+    #[cfg(not(target_arch = "wasm32"))]
+    stopping: Option<std::thread::JoinHandle<()>>,
+    /// Exits in a row without the server answering in between.
+    restarts: u32,
+    /// Bumped on every start and stop so late updates are ignored.
+    generation: u64,
+}
+
+impl MolyServerProcess {
+    pub fn status(&self) -> &MolyServerProcessStatus {
+        &self.status
+    }
+
+    /// The binary found on the last start.
+    pub fn binary(&self) -> Option<&Path> {
+        self.binary.as_deref()
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    /// The last `max_lines` lines of the log, oldest first.
+    pub fn log_tail(&self, max_lines: usize) -> Vec<String> {
+        self.log.tail(max_lines)
+    }
+
+    /// Whether Moly started a process that wasn't stopped since.
+    pub fn is_started(&self) -> bool {
+        matches!(
+            self.status,
+            MolyServerProcessStatus::Starting
+                | MolyServerProcessStatus::Running
+                | MolyServerProcessStatus::Restarting(_)
+        )
+    }
+
+    /// Start MolyServer unless one already answers through `moly_client`,
+    /// stopping the process started before.
+    ///
+    /// The binary is only needed when no MolyServer is running.
+    ///
+    /// Once a port is picked, the store points the client to it, see
+    /// [`Store::use_moly_server_port`].
+    ///
+    /// [`Store::use_moly_server_port`]: super::store::Store::use_moly_server_port
+    pub fn start(
+        &mut self,
+        moly_client: MolyClient,
+        config: &MolyServerProcessConfig,
+        reserved_ports: Vec<u16>,
+    ) {
+        self.stop();
+        self.restarts = 0;
+
+        if !config.managed {
+            self.status = MolyServerProcessStatus::Unmanaged;
+            return;
+        }
+
+        self.binary = locate_binary(
+            config.binary_path.as_deref(),
+            std::env::current_exe()
+                .ok()
+                .as_deref()
+                .and_then(Path::parent),
+            std::env::var_os("PATH").as_deref(),
+        );
+        self.probe(moly_client, reserved_ports);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn probe(&mut self, moly_client: MolyClient, reserved_ports: Vec<u16>) {
+        self.status = MolyServerProcessStatus::Probing;
+        let generation = self.generation;
+        let stopping = self.stopping.take();
+        spawn(async move {
+            // The process stopped before would still answer while it exits.
+            while stopping.as_ref().is_some_and(|s| !s.is_finished()) {
+                sleep(Duration::from_millis(50)).await;
+            }
+
+            let running = moly_client.test_connection().await.is_ok();
+            let preferred = local_port(&moly_client.address());
+            let port = match preferred {
+                Some(preferred) if !running => pick_port(preferred, &reserved_ports, port_is_free),
+                _ => None,
+            };
+
+            app_runner().defer(move |app, cx, _| {
+                let Some(store) = app.store.as_mut() else {
+                    return;
+                };
+                let me = &mut store.moly_server_process;
+                if me.generation != generation {
+                    return;
+                }
+
+                if running {
+                    me.status = MolyServerProcessStatus::External;
+                    me.log.push("A MolyServer is already running, using it");
+                } else if let Some(port) = port {
+                    if Some(port) != preferred {
+                        me.log.push(format!(
+                            "Port {} is used by another program, using {} instead",
+                            preferred.unwrap_or_default(),
+                            port
+                        ));
+                    }
+                    store.use_moly_server_port(port);
+                    let moly_client = store.moly_servers.local_client();
+                    store.moly_server_process.launch(port, moly_client);
+                } else {
+                    me.status = MolyServerProcessStatus::Failed(format!(
+                        "No free port found from {}",
+                        preferred.unwrap_or_default()
+                    ));
+                }
+                cx.redraw_all();
+            });
+        });
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn probe(&mut self, _moly_client: MolyClient, _reserved_ports: Vec<u16>) {
+        self.status =
+            MolyServerProcessStatus::Failed("MolyServer can't be started from the web".to_string());
+    }
+
+    /// Spawn the binary listening on `port`, following its output and exit.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn launch(&mut self, port: u16, moly_client: MolyClient) {
+        use std::process::{Command, Stdio};
+
+        let Some(binary) = self.binary.clone() else {
+            self.status = MolyServerProcessStatus::NotFound;
+            self.log.push(format!(
+                "Could not find {} next to Moly or in the PATH",
+                BINARY_NAME
+            ));
+            return;
+        };
+
+        self.generation += 1;
+        let generation = self.generation;
+        self.port = Some(port);
+        self.log
+            .push(format!("Starting {} on port {}", binary.display(), port));
+
+        let spawned = Command::new(&binary)
+            .env("MOLY_SERVER_PORT", port.to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) => {
+                let error = format!("Could not start {}: {}", binary.display(), e);
+                self.log.push(&error);
+                self.status = MolyServerProcessStatus::Failed(error);
+                return;
+            }
+        };
+
+        if let Some(stdout) = child.stdout.take() {
+            forward_output(stdout, self.log.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_output(stderr, self.log.clone());
+        }
+
+        *self.child.lock().unwrap() = Some(child);
+        self.status = MolyServerProcessStatus::Starting;
+        watch_exit(self.child.clone(), generation);
+
+        let log = self.log.clone();
+        spawn(async move {
+            let started = std::time::Instant::now();
+            while started.elapsed() < STARTUP_TIMEOUT {
+                sleep(Duration::from_millis(500)).await;
+                if moly_client.test_connection().await.is_ok() {
+                    app_runner().defer(move |app, cx, _| {
+                        if let Some(store) = app.store.as_mut() {
+                            if store.moly_server_process.generation == generation {
+                                store.moly_server_process.status = MolyServerProcessStatus::Running;
+                                store.moly_server_process.restarts = 0;
+                                store.handle_moly_server_ready();
+                                cx.redraw_all();
+                            }
+                        }
+                    });
+                    return;
+                }
+            }
+            log.push(format!(
+                "Not answering after {}s, stopping it",
+                STARTUP_TIMEOUT.as_secs()
+            ));
+            app_runner().defer(move |app, cx, _| {
+                if let Some(store) = app.store.as_mut() {
+                    let me = &mut store.moly_server_process;
+                    // An exit meanwhile is already handled as such.
+                    if me.generation == generation && me.status == MolyServerProcessStatus::Starting
+                    {
+                        me.stop();
+                        me.status = MolyServerProcessStatus::Failed(format!(
+                            "MolyServer did not answer within {}s, see its logs",
+                            STARTUP_TIMEOUT.as_secs()
+                        ));
+                        cx.redraw_all();
+                    }
+                }
+            });
+        });
+    }
+
+    /// The process started under `generation` exited on its own.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn handle_exit(&mut self, generation: u64, exit_status: String) {
+        if generation != self.generation {
+            return;
+        }
+
+        self.log
+            .push(format!("MolyServer exited ({})", exit_status));
+
+        let Some(delay) = restart_delay(self.restarts) else {
+            self.log
+                .push("Exited too many times in a row, not restarting it");
+            self.status =
+                MolyServerProcessStatus::Failed("MolyServer keeps exiting, see its logs".into());
+            return;
+        };
+        self.restarts += 1;
+        self.status = MolyServerProcessStatus::Restarting(delay);
+
+        spawn(async move {
+            sleep(delay).await;
+            app_runner().defer(move |app, cx, _| {
+                let Some(store) = app.store.as_mut() else {
+                    return;
+                };
+                let moly_client = store.moly_servers.local_client();
+                let me = &mut store.moly_server_process;
+                // Skip if the server was started again or stopped meanwhile.
+                if me.generation == generation {
+                    if let Some(port) = me.port {
+                        me.launch(port, moly_client);
+                        cx.redraw_all();
+                    }
+                }
+            });
+        });
+    }
+
+    /// Stop the process started by Moly, if any.
+    ///
+    /// The process is given its time to exit in the background, so the UI
+    /// doesn't freeze meanwhile.
+    pub fn stop(&mut self) {
+        self.generation += 1;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(mut child) = self.child.lock().unwrap().take() {
+            let log = self.log.clone();
+            self.stopping = Some(std::thread::spawn(move || {
+                terminate(&mut child, STOP_TIMEOUT);
+                log.push("Stopped");
+            }));
+        }
+
+        if self.is_started() || self.status == MolyServerProcessStatus::Probing {
+            self.status = MolyServerProcessStatus::Stopped;
+        }
+    }
+}
+
+impl Drop for MolyServerProcess {
+    fn drop(&mut self) {
+        self.stop();
+
+        // Moly is exiting, so the process must be gone before it does.
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(stopping) = self.stopping.take() {
+            let _ = stopping.join();
+        }
+    }
+}
+
+/// Forward the lines written by the process to the log until it closes them.
+#[cfg(not(target_arch = "wasm32"))]
+fn forward_output(output: impl std::io::Read + Send + 'static, log: ProcessLog) {
+    use std::io::BufRead;
+
+    std::thread::spawn(move || {
+        // Batches redraws while the process writes a lot.
+        let mut last_redraw = std::time::Instant::now();
+
+        for line in std::io::BufReader::new(output).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            log.push(line);
+
+            if last_redraw.elapsed() > Duration::from_millis(250) {
+                last_redraw = std::time::Instant::now();
+                app_runner().defer(|_, cx, _| cx.redraw_all());
+            }
+        }
+    });
+}
+
+/// Ask the process to exit so it can shut down cleanly, and kill it if it's
+/// still running after `timeout`.
+#[cfg(not(target_arch = "wasm32"))]
+fn terminate(child: &mut std::process::Child, timeout: Duration) {
+    #[cfg(unix)]
+    {
+        // SAFETY: The child wasn't waited for yet, so its pid wasn't reused.
+        unsafe {
+            libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+        }
+
+        let start = std::time::Instant::now();
+        while start.elapsed() < timeout {
+            match child.try_wait() {
+                Ok(Some(_)) => return,
+                Ok(None) => std::thread::sleep(Duration::from_millis(50)),
+                Err(_) => break,
+            }
+        }
+    }
+
+    // Windows has no signal to ask for it, so the process is ended right away.
+    #[cfg(not(unix))]
+    let _ = timeout;
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Wait for the process to exit, unless it's taken out to be stopped.
+#[cfg(not(target_arch = "wasm32"))]
+fn watch_exit(child: Arc<Mutex<Option<std::process::Child>>>, generation: u64) {
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(Duration::from_millis(500));
+
+            let mut child = child.lock().unwrap();
+            let exit_status = match child.as_mut().map(|c| c.try_wait()) {
+                Some(Ok(Some(exit_status))) => exit_status.to_string(),
+                Some(Ok(None)) => continue,
+                Some(Err(e)) => e.to_string(),
+                None => return,
+            };
+            child.take();
+
+            app_runner().defer(move |app, cx, _| {
+                if let Some(store) = app.store.as_mut() {
+                    store
+                        .moly_server_process
+                        .handle_exit(generation, exit_status);
+                    cx.redraw_all();
+                }
+            });
+            return;
+        }
+    });
+}
+
+/// The binary to start: the configured one, else the one next to Moly, else
+/// the first one in `path_var`.
+fn locate_binary(
+    configured: Option<&Path>,
+    exe_dir: Option<&Path>,
+    path_var: Option<&std::ffi::OsStr>,
+) -> Option<PathBuf> {
+    if let Some(configured) = configured {
+        return configured.is_file().then(|| configured.to_path_buf());
+    }
+
+    exe_dir
+        .map(|dir| dir.to_path_buf())
+        .into_iter()
+        .chain(path_var.into_iter().flat_map(std::env::split_paths))
+        .map(|dir| dir.join(BINARY_NAME))
+        .find(|candidate| candidate.is_file())
+}
+
+fn port_is_free(port: u16) -> bool {
+    std::net::TcpListener::bind(("127.0.0.1", port)).is_ok()
+}
+
+/// `preferred` if it's free, else the next free port not in `reserved`.
+fn pick_port(preferred: u16, reserved: &[u16], is_free: impl Fn(u16) -> bool) -> Option<u16> {
+    (0..=PORT_ATTEMPTS)
+        .filter_map(|offset| preferred.checked_add(offset))
+        .find(|port| !reserved.contains(port) && is_free(*port))
+}
+
+/// Port of a local URL like `http://localhost:8765/api/v1`.
+pub fn local_port(url: &str) -> Option<u16> {
+    let rest = url.strip_prefix(LOCAL_URL_PREFIX)?;
+    rest.split('/').next()?.parse().ok()
+}
+
+/// `url` pointing to `port`, if it's a local URL like `http://localhost:8765/api/v1`.
+pub fn local_url_with_port(url: &str, port: u16) -> Option<String> {
+    let rest = url.strip_prefix(LOCAL_URL_PREFIX)?;
+    let path = rest.find('/').map(|i| &rest[i..]).unwrap_or("");
+    Some(format!("{}{}{}", LOCAL_URL_PREFIX, port, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_port() {
+        assert_eq!(pick_port(8765, &[], |_| true), Some(8765));
+        assert_eq!(pick_port(8765, &[8766], |port| port != 8765), Some(8767));
+        assert_eq!(pick_port(8765, &[], |_| false), None);
+    }
+
+    #[test]
+    fn test_local_url_with_port() {
+        assert_eq!(
+            local_url_with_port("http://localhost:8765/api/v1", 8770).as_deref(),
+            Some("http://localhost:8770/api/v1")
+        );
+        assert_eq!(
+            local_url_with_port("http://localhost:8765", 8770).as_deref(),
+            Some("http://localhost:8770")
+        );
+        assert_eq!(local_url_with_port("https://example.com/v1", 8770), None);
+        assert_eq!(local_port("http://localhost:8765"), Some(8765));
+        assert_eq!(local_port("http://localhost:8770/api/v1"), Some(8770));
+        assert_eq!(local_port("https://example.com:8765/v1"), None);
+    }
+
+    #[test]
+    fn test_locate_binary() {
+        let dir = std::env::temp_dir().join(format!("moly-locate-{}", std::process::id()));
+        let exe_dir = dir.join("app");
+        let path_dir = dir.join("bin");
+        std::fs::create_dir_all(&exe_dir).unwrap();
+        std::fs::create_dir_all(&path_dir).unwrap();
+        std::fs::write(path_dir.join(BINARY_NAME), b"").unwrap();
+        let path_var = std::env::join_paths([&path_dir]).unwrap();
+
+        assert_eq!(
+            locate_binary(None, Some(&exe_dir), Some(&path_var)),
+            Some(path_dir.join(BINARY_NAME))
+        );
+
+        std::fs::write(exe_dir.join(BINARY_NAME), b"").unwrap();
+        assert_eq!(
+            locate_binary(None, Some(&exe_dir), Some(&path_var)),
+            Some(exe_dir.join(BINARY_NAME))
+        );
+
+        let missing = dir.join("missing");
+        assert_eq!(
+            locate_binary(Some(&missing), Some(&exe_dir), Some(&path_var)),
+            None
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_lets_the_process_exit() {
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", "trap 'exit 3' TERM; while true; do sleep 0.1; done"])
+            .spawn()
+            .unwrap();
+        // Gives the shell time to install the trap.
+        std::thread::sleep(Duration::from_millis(200));
+
+        terminate(&mut child, Duration::from_secs(5));
+        assert_eq!(child.wait().unwrap().code(), Some(3));
+    }
+}
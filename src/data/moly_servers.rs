@@ -0,0 +1,214 @@
+//! MolyServer endpoints Moly can search, download and run models with.
+//!
+//! Besides the local server, users can add remote ones, like a GPU box on the
+//! LAN. One server is active at a time: its client, downloads and search live in
+//! the store, where every screen reads them. The others are parked here with
+//! their downloads and search, so switching back finds them as they were, and
+//! their downloads keep going meanwhile.
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use super::downloads::Downloads;
+use super::moly_client::MolyClient;
+use super::search::Search;
+
+/// Id of the server on this computer, always present.
+pub const LOCAL_SERVER_ID: &str = "local";
+
+/// A remote MolyServer, as configured by the user.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MolyServerEndpoint {
+    pub id: String,
+    pub name: String,
+    /// Address of the server, like `http://192.168.1.20:8765`.
+    pub url: String,
+    /// Sent as a bearer token, for servers behind an authenticating proxy.
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+impl MolyServerEndpoint {
+    /// An endpoint named `name`, with an id derived from it.
+    ///
+    /// Fails if the URL isn't an HTTP one.
+    pub fn new(name: &str, url: &str, api_key: Option<String>) -> Result<Self, String> {
+        let url = url.trim().trim_end_matches('/').to_string();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err("The address must start with http:// or https://".to_string());
+        }
+
+        let name = match name.trim() {
+            "" => url.clone(),
+            name => name.to_string(),
+        };
+        let id = name
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+
+        Ok(Self {
+            id,
+            name,
+            url,
+            api_key: api_key.filter(|key| !key.trim().is_empty()),
+        })
+    }
+}
+
+struct MolyServerEntry {
+    name: String,
+    moly_client: MolyClient,
+    /// Downloads and search of the server while another one is active.
+    parked: Option<(Downloads, Search)>,
+}
+
+/// Every known MolyServer, with the state of the inactive ones.
+pub struct MolyServers {
+    entries: IndexMap<String, MolyServerEntry>,
+    active: String,
+}
+
+impl MolyServers {
+    /// The local server, active, and the remote `endpoints`.
+    pub fn new(local_client: MolyClient, endpoints: &[MolyServerEndpoint]) -> Self {
+        let mut servers = Self {
+            entries: IndexMap::new(),
+            active: LOCAL_SERVER_ID.to_string(),
+        };
+        servers.entries.insert(
+            LOCAL_SERVER_ID.to_string(),
+            MolyServerEntry {
+                name: "This computer".to_string(),
+                moly_client: local_client,
+                parked: None,
+            },
+        );
+        for endpoint in endpoints {
+            servers.add(endpoint);
+        }
+        servers
+    }
+
+    /// Add a remote server, returns `false` if its id is already used.
+    pub fn add(&mut self, endpoint: &MolyServerEndpoint) -> bool {
+        if self.entries.contains_key(&endpoint.id) {
+            return false;
+        }
+
+        self.entries.insert(
+            endpoint.id.clone(),
+            MolyServerEntry {
+                name: endpoint.name.clone(),
+                moly_client: MolyClient::with_api_key(
+                    endpoint.url.clone(),
+                    endpoint.api_key.clone(),
+                ),
+                parked: None,
+            },
+        );
+        true
+    }
+
+    /// Remove a remote server that isn't active.
+    pub fn remove(&mut self, id: &str) {
+        if id != LOCAL_SERVER_ID && id != self.active {
+            self.entries.shift_remove(id);
+        }
+    }
+
+    pub fn active_id(&self) -> &str {
+        &self.active
+    }
+
+    pub fn is_local_active(&self) -> bool {
+        self.active == LOCAL_SERVER_ID
+    }
+
+    /// Ids and names, the local server first.
+    pub fn list(&self) -> Vec<(String, String)> {
+        self.entries
+            .iter()
+            .map(|(id, entry)| (id.clone(), entry.name.clone()))
+            .collect()
+    }
+
+    pub fn client(&self, id: &str) -> Option<MolyClient> {
+        self.entries.get(id).map(|entry| entry.moly_client.clone())
+    }
+
+    pub fn local_client(&self) -> MolyClient {
+        self.entries[LOCAL_SERVER_ID].moly_client.clone()
+    }
+
+    /// Make `id` active, parking the downloads and search of the active server.
+    ///
+    /// Returns those of `id`, or `None` if it was never active.
+    pub fn activate(
+        &mut self,
+        id: &str,
+        downloads: Downloads,
+        search: Search,
+    ) -> Option<(Downloads, Search)> {
+        if let Some(entry) = self.entries.get_mut(&self.active) {
+            entry.parked = Some((downloads, search));
+        }
+        self.active = id.to_string();
+        self.entries.get_mut(id)?.parked.take()
+    }
+
+    /// Downloads and search of the servers that aren't active.
+    pub fn parked_mut(&mut self) -> impl Iterator<Item = &mut (Downloads, Search)> {
+        self.entries
+            .values_mut()
+            .filter_map(|entry| entry.parked.as_mut())
+    }
+
+    /// Downloads of the inactive server reached through `moly_client`.
+    pub fn parked_downloads_mut(&mut self, moly_client: &MolyClient) -> Option<&mut Downloads> {
+        self.entries
+            .values_mut()
+            .find(|entry| entry.moly_client.same_server(moly_client))
+            .and_then(|entry| entry.parked.as_mut())
+            .map(|(downloads, _)| downloads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint() {
+        let endpoint =
+            MolyServerEndpoint::new("GPU box", "http://192.168.1.20:8765/", Some(" ".into()))
+                .unwrap();
+        assert_eq!(endpoint.id, "gpu_box");
+        assert_eq!(endpoint.url, "http://192.168.1.20:8765");
+        assert_eq!(endpoint.api_key, None);
+
+        let unnamed = MolyServerEndpoint::new("", "https://moly.example.com", None).unwrap();
+        assert_eq!(unnamed.name, "https://moly.example.com");
+        assert!(MolyServerEndpoint::new("LAN", "192.168.1.20:8765", None).is_err());
+    }
+
+    #[test]
+    fn test_add_and_remove() {
+        let local = MolyClient::new("http://localhost:8765".into());
+        let remote = MolyServerEndpoint::new("GPU box", "http://gpu:8765", None).unwrap();
+        let mut servers = MolyServers::new(local.clone(), &[remote.clone()]);
+
+        assert!(!servers.add(&remote));
+        assert_eq!(servers.list().len(), 2);
+        assert!(servers.local_client().same_server(&local));
+        assert!(!servers.client("gpu_box").unwrap().same_server(&local));
+
+        servers.remove(LOCAL_SERVER_ID);
+        servers.remove("gpu_box");
+        assert_eq!(
+            servers.list(),
+            vec![("local".to_string(), "This computer".to_string())]
+        );
+    }
+}
@@ -1,14 +1,26 @@
+use chrono::Utc;
 use moly_kit::aitk::utils::asynchronous::spawn;
 use moly_kit::prelude::*;
+use moly_kit::utils::tool_policy::ToolPolicyRule;
+use moly_protocol::data::FileId;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use crate::data::providers::ProviderId;
 use crate::shared::utils::filesystem;
 use crate::shared::utils::version::Versioned;
 
+use super::downloads::DEFAULT_MAX_CONCURRENT_DOWNLOADS;
+use super::gateway::GatewayConfig;
+use super::mcp_import::{self, McpImport, McpMergeReport};
 use super::mcp_servers::McpServersConfig;
-use super::providers::{Provider, ProviderType};
+use super::model_loader::RuntimeOptions;
+use super::moly_server_process::MolyServerProcessConfig;
+use super::moly_servers::MolyServerEndpoint;
+use super::provider_models_cache::CachedProviderModels;
+use super::providers::{Provider, ProviderBot, ProviderConnectionStatus, ProviderType};
+use super::supported_providers;
 
 const PREFERENCES_DIR: &str = "preferences";
 const PREFERENCES_FILENAME: &str = "preferences.json";
@@ -24,6 +36,21 @@ pub struct Preferences {
     pub mcp_servers_config: McpServersConfig,
     #[serde(default)]
     stt_config: Versioned<SttConfig>,
+    #[serde(default)]
+    pub gateway: GatewayConfig,
+    #[serde(default = "default_max_concurrent_downloads")]
+    pub max_concurrent_downloads: usize,
+    /// Runtime options last used to load each downloaded file.
+    #[serde(default)]
+    pub runtime_options: HashMap<FileId, RuntimeOptions>,
+    #[serde(default)]
+    pub moly_server_process: MolyServerProcessConfig,
+    /// MolyServers on other computers.
+    #[serde(default)]
+    pub moly_servers: Vec<MolyServerEndpoint>,
+    /// The server searched and downloaded with, the local one when unset.
+    #[serde(default)]
+    pub active_moly_server: Option<String>,
 }
 
 impl Default for Preferences {
@@ -34,6 +61,12 @@ impl Default for Preferences {
             providers_preferences: vec![],
             mcp_servers_config: McpServersConfig::new(),
             stt_config: Versioned::default(),
+            gateway: GatewayConfig::default(),
+            max_concurrent_downloads: default_max_concurrent_downloads(),
+            runtime_options: HashMap::new(),
+            moly_server_process: MolyServerProcessConfig::default(),
+            moly_servers: vec![],
+            active_moly_server: None,
         }
     }
 }
@@ -45,7 +78,9 @@ impl Preferences {
         match fs.read_json::<Preferences>(&preferences_path).await {
             Ok(mut preferences) => {
                 // Migrate providers without IDs
-                preferences.migrate_provider_ids();
+                if preferences.migrate_provider_ids() {
+                    preferences.save_and_wait().await;
+                }
                 preferences
             }
             Err(_e) => {
@@ -58,16 +93,22 @@ impl Preferences {
     pub fn save(&self) {
         let self_clone = self.clone();
         spawn(async move {
-            match filesystem::global()
-                .queue_write_json(preferences_path(), &self_clone)
-                .await
-            {
-                Ok(()) => (),
-                Err(e) => log::error!("Failed to write preferences file: {:?}", e),
-            }
+            self_clone.save_and_wait().await;
         });
     }
 
+    /// Like `save`, but finishing the write before returning, for callers
+    /// that may exit right after, like the command line.
+    pub async fn save_and_wait(&self) {
+        match filesystem::global()
+            .queue_write_json(preferences_path(), self)
+            .await
+        {
+            Ok(()) => (),
+            Err(e) => log::error!("Failed to write preferences file: {:?}", e),
+        }
+    }
+
     pub fn stt_config(&self) -> &Versioned<SttConfig> {
         &self.stt_config
     }
@@ -80,22 +121,150 @@ impl Preferences {
         self.save();
     }
 
+    pub fn update_gateway_config<F>(&mut self, update_fn: F)
+    where
+        F: FnOnce(&mut GatewayConfig),
+    {
+        update_fn(&mut self.gateway);
+        self.save();
+    }
+
+    pub fn update_moly_server_process_config<F>(&mut self, update_fn: F)
+    where
+        F: FnOnce(&mut MolyServerProcessConfig),
+    {
+        update_fn(&mut self.moly_server_process);
+        self.save();
+    }
+
+    pub fn add_moly_server(&mut self, endpoint: MolyServerEndpoint) {
+        self.moly_servers.push(endpoint);
+        self.save();
+    }
+
+    pub fn remove_moly_server(&mut self, id: &str) {
+        self.moly_servers.retain(|endpoint| endpoint.id != id);
+        self.save();
+    }
+
+    pub fn set_active_moly_server(&mut self, id: Option<String>) {
+        self.active_moly_server = id;
+        self.save();
+    }
+
+    pub fn set_max_concurrent_downloads(&mut self, max_concurrent_downloads: usize) {
+        self.max_concurrent_downloads = max_concurrent_downloads;
+        self.save();
+    }
+
+    pub fn runtime_options(&self, file_id: &FileId) -> RuntimeOptions {
+        self.runtime_options
+            .get(file_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn set_runtime_options(&mut self, file_id: FileId, options: RuntimeOptions) {
+        self.runtime_options.insert(file_id, options);
+        self.save();
+    }
+
     pub fn set_current_chat_model(&mut self, bot_id: Option<BotId>) {
         self.current_chat_model = bot_id;
         self.save();
     }
 
-    pub fn _set_downloaded_files_dir(&mut self, path: PathBuf) {
+    pub fn set_downloaded_files_dir(&mut self, path: PathBuf) {
         self.downloaded_files_dir = path;
         self.save();
     }
 
+    /// The known and custom providers, configured with the saved preferences.
+    pub fn providers(&self) -> Vec<Provider> {
+        let supported = supported_providers::load_supported_providers();
+        let mut final_list = Vec::new();
+
+        for s in &supported {
+            let maybe_prefs = self
+                .providers_preferences
+                .iter()
+                .find(|pp| pp.id == s.id || (pp.id.is_empty() && pp.url == s.url));
+
+            if let Some(prefs) = maybe_prefs {
+                final_list.push(Provider {
+                    id: if !prefs.id.is_empty() {
+                        prefs.id.clone()
+                    } else {
+                        s.id.clone()
+                    },
+                    name: s.name.clone(),
+                    url: prefs.url.clone(),
+                    api_key: prefs.api_key.clone(),
+                    provider_type: s.provider_type.clone(),
+                    connection_status: ProviderConnectionStatus::Disconnected,
+                    enabled: prefs.enabled,
+                    models: vec![],
+                    was_customly_added: prefs.was_customly_added,
+                    system_prompt: prefs.system_prompt.clone(),
+                    tools_enabled: prefs.tools_enabled,
+                });
+            } else {
+                // Known from supported_providers.json but user has no preferences
+                final_list.push(Provider {
+                    id: s.id.clone(),
+                    name: s.name.clone(),
+                    url: s.url.clone(),
+                    api_key: None,
+                    provider_type: s.provider_type.clone(),
+                    connection_status: ProviderConnectionStatus::Disconnected,
+                    enabled: false,
+                    models: vec![],
+                    was_customly_added: false,
+                    system_prompt: None,
+                    tools_enabled: true,
+                });
+            }
+        }
+
+        // Custom providers from preferences (not in the supported_providers.json)
+        for pp in &self.providers_preferences {
+            let is_custom = !supported
+                .iter()
+                .any(|sp| sp.id == pp.id || (pp.id.is_empty() && sp.url == pp.url));
+            if is_custom {
+                // Ensure provider has an ID
+                let mut pp_clone = pp.clone();
+                pp_clone.ensure_id();
+
+                final_list.push(Provider {
+                    id: pp_clone.id.clone(),
+                    name: pp_clone.name.clone(),
+                    url: pp_clone.url.clone(),
+                    api_key: pp_clone.api_key.clone(),
+                    provider_type: pp_clone.provider_type.clone(),
+                    connection_status: ProviderConnectionStatus::Disconnected,
+                    enabled: pp_clone.enabled,
+                    models: vec![],
+                    was_customly_added: pp_clone.was_customly_added,
+                    system_prompt: pp_clone.system_prompt.clone(),
+                    tools_enabled: pp_clone.tools_enabled,
+                });
+            }
+        }
+
+        final_list
+    }
+
     pub fn insert_or_update_provider(&mut self, provider: &Provider) {
         if let Some(existing_provider) = self
             .providers_preferences
             .iter_mut()
             .find(|p| p.id == provider.id || (p.id.is_empty() && p.url == provider.url))
         {
+            if existing_provider.url != provider.url {
+                // Listed by another server.
+                existing_provider.listed_models = None;
+            }
             existing_provider.id = provider.id.clone();
             existing_provider.url = provider.url.clone();
             existing_provider.api_key = provider.api_key.clone();
@@ -111,6 +280,7 @@ impl Preferences {
                 enabled: provider.enabled,
                 provider_type: provider.provider_type.clone(),
                 models: vec![],
+                listed_models: None,
                 was_customly_added: provider.was_customly_added,
                 system_prompt: provider.system_prompt.clone(),
                 tools_enabled: provider.tools_enabled,
@@ -124,6 +294,31 @@ impl Preferences {
         self.save();
     }
 
+    /// The models `provider_id` listed last, if it has preferences.
+    pub fn listed_models(&self, provider_id: &ProviderId) -> Option<&CachedProviderModels> {
+        self.providers_preferences
+            .iter()
+            .find(|p| &p.id == provider_id)?
+            .listed_models
+            .as_ref()
+    }
+
+    /// Remember `bots` as the models `provider_id` listed just now.
+    pub fn set_listed_models(&mut self, provider_id: &ProviderId, bots: Vec<ProviderBot>) {
+        let Some(provider) = self
+            .providers_preferences
+            .iter_mut()
+            .find(|p| &p.id == provider_id)
+        else {
+            return;
+        };
+        provider.listed_models = Some(CachedProviderModels {
+            fetched_at: Utc::now(),
+            bots,
+        });
+        self.save();
+    }
+
     /// Update the enabled/disabled status of a model for a specific server
     pub fn update_model_status(
         &mut self,
@@ -191,6 +386,19 @@ impl Preferences {
         Ok(())
     }
 
+    /// Merge servers imported from another application, see [`mcp_import::merge`].
+    pub fn import_mcp_servers(
+        &mut self,
+        import: McpImport,
+        replace_conflicts: bool,
+    ) -> McpMergeReport {
+        let report = mcp_import::merge(&mut self.mcp_servers_config, import, replace_conflicts);
+        if report.changed() {
+            self.save();
+        }
+        report
+    }
+
     pub fn set_mcp_servers_enabled(&mut self, enabled: bool) {
         self.mcp_servers_config.enabled = enabled;
         self.save();
@@ -210,8 +418,31 @@ impl Preferences {
         self.mcp_servers_config.dangerous_mode_enabled
     }
 
-    /// Migrate providers without IDs by generating them from URLs
-    fn migrate_provider_ids(&mut self) {
+    pub fn set_mcp_tool_policy_rules(&mut self, rules: Vec<ToolPolicyRule>) {
+        self.mcp_servers_config.tool_policy.rules = rules;
+        self.save();
+    }
+
+    pub fn set_mcp_tool_enabled(&mut self, server_id: &str, tool: &str, enabled: bool) {
+        if let Some(server) = self.mcp_servers_config.servers.get_mut(server_id) {
+            server.set_tool_enabled(tool, enabled);
+            self.save();
+        }
+    }
+
+    /// Let a server sample without asking, answered by `bot_id`.
+    pub fn set_mcp_sampling_allowed(&mut self, server_id: &str, bot_id: Option<&BotId>) {
+        if let Some(server) = self.mcp_servers_config.servers.get_mut(server_id) {
+            server.allow_sampling = bot_id.is_some();
+            server.sampling_bot = bot_id.map(|bot_id| bot_id.as_str().to_string());
+            self.save();
+        }
+    }
+
+    /// Migrate providers without IDs by generating them from URLs.
+    ///
+    /// Returns whether any provider changed and the preferences need saving.
+    fn migrate_provider_ids(&mut self) -> bool {
         let mut needs_save = false;
         for provider in &mut self.providers_preferences {
             if provider.id.is_empty() {
@@ -219,9 +450,7 @@ impl Preferences {
                 needs_save = true;
             }
         }
-        if needs_save {
-            self.save();
-        }
+        needs_save
     }
 }
 
@@ -229,6 +458,10 @@ fn preferences_path() -> PathBuf {
     Path::new(PREFERENCES_DIR).join(PREFERENCES_FILENAME)
 }
 
+fn default_max_concurrent_downloads() -> usize {
+    DEFAULT_MAX_CONCURRENT_DOWNLOADS
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct ProviderPreferences {
     /// Unique identifier for the provider
@@ -241,6 +474,9 @@ pub struct ProviderPreferences {
     pub provider_type: ProviderType,
     // (model_name, enabled)
     pub models: Vec<(String, bool)>,
+    /// The models the provider listed last, shown until it lists them again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listed_models: Option<CachedProviderModels>,
     pub was_customly_added: bool,
     /// Custom system prompt for the provider (currently used by Realtime providers)
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -255,6 +491,18 @@ fn default_tools_enabled() -> bool {
 }
 
 impl ProviderPreferences {
+    /// Whether the models of this provider can be fetched without asking the user,
+    /// because it is enabled and has credentials or doesn't need them.
+    pub fn can_auto_fetch(&self) -> bool {
+        // TODO: If the provider requires an API key, we should fetch only if the API key is set
+        self.enabled
+            && (self.api_key.is_some()
+                || self.provider_type == ProviderType::MoFa
+                || self.provider_type == ProviderType::DeepInquire
+                || self.provider_type == ProviderType::OpenAiRealtime
+                || self.url.starts_with("http://localhost"))
+    }
+
     /// Ensure this provider has an ID, generating one if needed
     pub fn ensure_id(&mut self) {
         if self.id.is_empty() {
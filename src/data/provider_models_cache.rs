@@ -0,0 +1,53 @@
+//! Models listed by each provider, kept in its preferences across launches.
+//!
+//! At startup the kept lists are shown right away, while providers are asked
+//! for their models in the background. Bots that come from them are tracked as
+//! stale in [`Chats`] until their provider lists them again, so a provider that
+//! can't be reached still shows the models it had.
+//!
+//! [`Chats`]: super::chats::Chats
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::providers::ProviderBot;
+
+/// The models of a provider as of its last successful listing.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CachedProviderModels {
+    pub fetched_at: DateTime<Utc>,
+    pub bots: Vec<ProviderBot>,
+}
+
+/// How long ago a cached listing was fetched, like "3 hours ago".
+pub fn fetched_ago(fetched_at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (now - fetched_at).num_minutes().max(0);
+    let (count, unit) = match minutes {
+        0 => return "just now".to_string(),
+        1..=59 => (minutes, "minute"),
+        60..=1439 => (minutes / 60, "hour"),
+        _ => (minutes / 1440, "day"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{} {}{} ago", count, unit, plural)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_fetched_ago() {
+        let now = Utc::now();
+        assert_eq!(fetched_ago(now, now), "just now");
+        assert_eq!(fetched_ago(now - Duration::minutes(1), now), "1 minute ago");
+        assert_eq!(
+            fetched_ago(now - Duration::minutes(150), now),
+            "2 hours ago"
+        );
+        assert_eq!(fetched_ago(now - Duration::days(3), now), "3 days ago");
+        // A clock set back doesn't make a listing from the future.
+        assert_eq!(fetched_ago(now + Duration::hours(1), now), "just now");
+    }
+}
@@ -1,4 +1,5 @@
 use crate::data::bot_fetcher;
+use crate::data::model_registry::{ModelMetadata, ModelRegistry};
 use makepad_widgets::*;
 use moly_kit::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -35,8 +36,8 @@ fn default_tools_enabled() -> bool {
 }
 
 /// Fetch models for a provider using MolyKit clients
-pub fn fetch_models_for_provider(provider: &Provider) {
-    bot_fetcher::fetch_models_for_provider(provider);
+pub fn fetch_models_for_provider(provider: &Provider, registry: &ModelRegistry) {
+    bot_fetcher::fetch_models_for_provider(provider, registry);
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -48,6 +49,9 @@ pub struct ProviderBot {
     pub enabled: bool,
     #[serde(default)]
     pub is_recommended: bool,
+    /// What the model registry knows about this model.
+    #[serde(default)]
+    pub metadata: Option<ModelMetadata>,
 }
 
 impl ProviderBot {
@@ -62,6 +66,7 @@ impl ProviderBot {
             provider_id: "unknown".to_string(),
             enabled: true,
             is_recommended: false,
+            metadata: None,
         }
     }
 
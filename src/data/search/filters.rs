@@ -0,0 +1,355 @@
+//! Facets to narrow down the models of a search, from the metadata MolyServer returns.
+//!
+//! Quantization and file size are facets of the files: a model matches when one
+//! of its files matches both.
+
+use chrono::{DateTime, Duration, Utc};
+use moly_protocol::data::{File, Model};
+
+use crate::shared::utils::BYTES_PER_MB;
+
+const BYTES_PER_GB: f64 = BYTES_PER_MB * 1024.0;
+
+/// Number of parameters, in billions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParameterSize {
+    Tiny,
+    Small,
+    Medium,
+    Large,
+    Huge,
+}
+
+impl ParameterSize {
+    pub const ALL: [Self; 5] = [
+        Self::Tiny,
+        Self::Small,
+        Self::Medium,
+        Self::Large,
+        Self::Huge,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Tiny => "Under 3B",
+            Self::Small => "3B to 9B",
+            Self::Medium => "9B to 20B",
+            Self::Large => "20B to 70B",
+            Self::Huge => "70B and more",
+        }
+    }
+
+    fn contains(&self, billions: f64) -> bool {
+        let (min, max) = match self {
+            Self::Tiny => (0.0, 3.0),
+            Self::Small => (3.0, 9.0),
+            Self::Medium => (9.0, 20.0),
+            Self::Large => (20.0, 70.0),
+            Self::Huge => (70.0, f64::INFINITY),
+        };
+        billions >= min && billions < max
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileSizeRange {
+    UpTo2Gb,
+    UpTo5Gb,
+    UpTo10Gb,
+    Over10Gb,
+}
+
+impl FileSizeRange {
+    pub const ALL: [Self; 4] = [Self::UpTo2Gb, Self::UpTo5Gb, Self::UpTo10Gb, Self::Over10Gb];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::UpTo2Gb => "Up to 2 GB",
+            Self::UpTo5Gb => "2 GB to 5 GB",
+            Self::UpTo10Gb => "5 GB to 10 GB",
+            Self::Over10Gb => "Over 10 GB",
+        }
+    }
+
+    fn contains(&self, gb: f64) -> bool {
+        match self {
+            Self::UpTo2Gb => gb <= 2.0,
+            Self::UpTo5Gb => gb > 2.0 && gb <= 5.0,
+            Self::UpTo10Gb => gb > 5.0 && gb <= 10.0,
+            Self::Over10Gb => gb > 10.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReleasedWithin {
+    Month,
+    SixMonths,
+    Year,
+}
+
+impl ReleasedWithin {
+    pub const ALL: [Self; 3] = [Self::Month, Self::SixMonths, Self::Year];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Month => "Last month",
+            Self::SixMonths => "Last 6 months",
+            Self::Year => "Last year",
+        }
+    }
+
+    fn duration(&self) -> Duration {
+        match self {
+            Self::Month => Duration::days(30),
+            Self::SixMonths => Duration::days(182),
+            Self::Year => Duration::days(365),
+        }
+    }
+}
+
+/// Limits of the RAM a model requires, in GB.
+pub const RAM_LIMITS_GB: [u32; 4] = [8, 16, 32, 64];
+
+/// One of the dimensions models can be filtered by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Facet {
+    Architecture,
+    ParameterSize,
+    Quantization,
+    FileSize,
+    Ram,
+    Author,
+    Released,
+}
+
+impl Facet {
+    pub const ALL: [Self; 7] = [
+        Self::Architecture,
+        Self::ParameterSize,
+        Self::Quantization,
+        Self::FileSize,
+        Self::Ram,
+        Self::Author,
+        Self::Released,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Architecture => "Architecture",
+            Self::ParameterSize => "Parameters",
+            Self::Quantization => "Quantization",
+            Self::FileSize => "File size",
+            Self::Ram => "RAM",
+            Self::Author => "Author",
+            Self::Released => "Released",
+        }
+    }
+}
+
+/// The facets selected for the search, kept from one search to the next.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchFilters {
+    pub architecture: Option<String>,
+    pub parameter_size: Option<ParameterSize>,
+    pub quantization: Option<String>,
+    pub file_size: Option<FileSizeRange>,
+    /// Only models requiring at most this much RAM, in GB.
+    pub max_ram_gb: Option<u32>,
+    pub author: Option<String>,
+    pub released_within: Option<ReleasedWithin>,
+}
+
+impl SearchFilters {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The selected value of `facet`, as shown to the user.
+    pub fn value_label(&self, facet: Facet) -> Option<String> {
+        match facet {
+            Facet::Architecture => self.architecture.clone(),
+            Facet::ParameterSize => self.parameter_size.map(|s| s.label().to_string()),
+            Facet::Quantization => self.quantization.clone(),
+            Facet::FileSize => self.file_size.map(|s| s.label().to_string()),
+            Facet::Ram => self.max_ram_gb.map(|gb| format!("Up to {} GB", gb)),
+            Facet::Author => self.author.clone(),
+            Facet::Released => self.released_within.map(|r| r.label().to_string()),
+        }
+    }
+
+    pub fn clear(&mut self, facet: Facet) {
+        match facet {
+            Facet::Architecture => self.architecture = None,
+            Facet::ParameterSize => self.parameter_size = None,
+            Facet::Quantization => self.quantization = None,
+            Facet::FileSize => self.file_size = None,
+            Facet::Ram => self.max_ram_gb = None,
+            Facet::Author => self.author = None,
+            Facet::Released => self.released_within = None,
+        }
+    }
+
+    pub fn matches(&self, model: &Model, now: DateTime<Utc>) -> bool {
+        if let Some(architecture) = &self.architecture
+            && !model.architecture.eq_ignore_ascii_case(architecture)
+        {
+            return false;
+        }
+
+        if let Some(author) = &self.author
+            && model.author.name != *author
+        {
+            return false;
+        }
+
+        // Models with unknown sizes or requirements are left out once filtered on.
+        if let Some(size) = self.parameter_size
+            && !parameter_count(&model.size).is_some_and(|billions| size.contains(billions))
+        {
+            return false;
+        }
+
+        if let Some(max_ram_gb) = self.max_ram_gb
+            && !required_ram_gb(&model.requires).is_some_and(|gb| gb <= max_ram_gb as f64)
+        {
+            return false;
+        }
+
+        if let Some(within) = self.released_within
+            && now - model.released_at > within.duration()
+        {
+            return false;
+        }
+
+        if self.quantization.is_none() && self.file_size.is_none() {
+            return true;
+        }
+        model.files.iter().any(|file| self.matches_file(file))
+    }
+
+    fn matches_file(&self, file: &File) -> bool {
+        if let Some(quantization) = &self.quantization
+            && !file.quantization.eq_ignore_ascii_case(quantization)
+        {
+            return false;
+        }
+
+        match self.file_size {
+            Some(range) => file_size_gb(file).is_some_and(|gb| range.contains(gb)),
+            None => true,
+        }
+    }
+}
+
+/// Values of the facets found in the results, the most common first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Facets {
+    pub architectures: Vec<String>,
+    pub quantizations: Vec<String>,
+    pub authors: Vec<String>,
+}
+
+impl Facets {
+    pub fn from_models(models: &[Model]) -> Self {
+        Self {
+            architectures: by_count(models.iter().map(|m| m.architecture.as_str())),
+            quantizations: by_count(
+                models
+                    .iter()
+                    .flat_map(|m| m.files.iter().map(|f| f.quantization.as_str())),
+            ),
+            authors: by_count(models.iter().map(|m| m.author.name.as_str())),
+        }
+    }
+}
+
+fn by_count<'a>(values: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for value in values.filter(|v| !v.trim().is_empty()) {
+        match counts.iter_mut().find(|(v, _)| *v == value) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((value, 1)),
+        }
+    }
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    counts.into_iter().map(|(v, _)| v.to_string()).collect()
+}
+
+/// Parameters of a model in billions, from sizes like `7B`, `1.5B` or `350M`.
+pub fn parameter_count(size: &str) -> Option<f64> {
+    let size = size.trim().to_ascii_uppercase();
+    let (number, scale) = if let Some(number) = size.strip_suffix('B') {
+        (number, 1.0)
+    } else if let Some(number) = size.strip_suffix('M') {
+        (number, 0.001)
+    } else {
+        (size.as_str(), 1.0)
+    };
+    number.trim().parse::<f64>().ok().map(|n| n * scale)
+}
+
+/// RAM a model requires in GB, from requirements like `8GB+ RAM`.
+pub fn required_ram_gb(requires: &str) -> Option<f64> {
+    let number: String = requires
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    number.parse().ok()
+}
+
+/// Size of a file in GB, if known.
+pub fn file_size_gb(file: &File) -> Option<f64> {
+    file.size
+        .parse::<f64>()
+        .ok()
+        .map(|bytes| bytes / BYTES_PER_GB)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_metadata() {
+        assert_eq!(parameter_count("7B"), Some(7.0));
+        assert_eq!(parameter_count("1.5b"), Some(1.5));
+        assert_eq!(parameter_count("350M"), Some(0.35));
+        assert_eq!(parameter_count("unknown"), None);
+
+        assert_eq!(required_ram_gb("8GB+ RAM"), Some(8.0));
+        assert_eq!(required_ram_gb(" 4.5 GB"), Some(4.5));
+        assert_eq!(required_ram_gb("-"), None);
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert!(ParameterSize::Small.contains(7.0));
+        assert!(ParameterSize::Medium.contains(9.0));
+        assert!(ParameterSize::Huge.contains(405.0));
+        assert!(FileSizeRange::UpTo2Gb.contains(2.0));
+        assert!(FileSizeRange::UpTo5Gb.contains(4.1));
+        assert!(!FileSizeRange::Over10Gb.contains(10.0));
+    }
+
+    #[test]
+    fn test_facets_by_count() {
+        let values =
+            by_count(["Q4_K_M", "Q8_0", "Q4_K_M", "", "F16", "Q8_0", "Q4_K_M"].into_iter());
+        assert_eq!(values, vec!["Q4_K_M", "Q8_0", "F16"]);
+
+        let mut filters = SearchFilters {
+            quantization: Some("Q4_K_M".to_string()),
+            max_ram_gb: Some(16),
+            ..Default::default()
+        };
+        assert_eq!(
+            filters.value_label(Facet::Ram).as_deref(),
+            Some("Up to 16 GB")
+        );
+        filters.clear(Facet::Ram);
+        filters.clear(Facet::Quantization);
+        assert!(filters.is_empty());
+    }
+}
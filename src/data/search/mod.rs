@@ -1,22 +1,32 @@
+pub mod filters;
+
+use chrono::Utc;
 use makepad_widgets::{Action, Cx};
 use moly_kit::aitk::utils::asynchronous::spawn;
 use moly_protocol::data::*;
+use std::cmp::Ordering;
 
+use self::filters::{Facets, SearchFilters, file_size_gb};
 use super::moly_client::MolyClient;
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum SortCriteria {
     #[default]
     MostDownloads,
     LeastDownloads,
     MostLikes,
     LeastLikes,
+    /// Most recently released first.
+    Newest,
+    /// Models with the smallest file first.
+    SmallestFile,
 }
 
+/// Outcome of a search, for the search of the server it was made with.
 #[derive(Debug)]
 pub enum SearchAction {
-    Results(Vec<Model>),
-    Error,
+    Results(MolyClient, Vec<Model>),
+    Error(MolyClient),
 }
 
 #[derive(Clone)]
@@ -34,8 +44,13 @@ pub enum SearchState {
 }
 pub struct Search {
     pub moly_client: MolyClient,
+    /// Results of the last search, as returned by MolyServer.
+    results: Vec<Model>,
+    /// Results matching the filters, sorted.
     pub models: Vec<Model>,
     pub sorted_by: SortCriteria,
+    pub filters: SearchFilters,
+    facets: Facets,
     pub keyword: Option<String>,
     pub state: SearchState,
 }
@@ -44,8 +59,11 @@ impl Search {
     pub fn new(moly_client: MolyClient) -> Self {
         let search = Self {
             moly_client,
+            results: Vec::new(),
             models: Vec::new(),
             sorted_by: SortCriteria::MostDownloads,
+            filters: SearchFilters::default(),
+            facets: Facets::default(),
             keyword: None,
             state: SearchState::Idle,
         };
@@ -68,10 +86,10 @@ impl Search {
         spawn(async move {
             match moly_client.get_featured_models().await {
                 Ok(models) => {
-                    Cx::post_action(SearchAction::Results(models));
+                    Cx::post_action(SearchAction::Results(moly_client, models));
                 }
                 Err(_err) => {
-                    Cx::post_action(SearchAction::Error);
+                    Cx::post_action(SearchAction::Error(moly_client));
                 }
             }
         });
@@ -96,11 +114,11 @@ impl Search {
         spawn(async move {
             match moly_client.search_models(keyword.clone()).await {
                 Ok(models) => {
-                    Cx::post_action(SearchAction::Results(models));
+                    Cx::post_action(SearchAction::Results(moly_client, models));
                 }
                 Err(err) => {
                     eprintln!("Error fetching models: {:?}", err);
-                    Cx::post_action(SearchAction::Error);
+                    Cx::post_action(SearchAction::Error(moly_client));
                 }
             }
         });
@@ -122,19 +140,58 @@ impl Search {
             SortCriteria::LeastLikes => {
                 self.models.sort_by(|a, b| a.like_count.cmp(&b.like_count));
             }
+            SortCriteria::Newest => {
+                self.models
+                    .sort_by(|a, b| b.released_at.cmp(&a.released_at));
+            }
+            SortCriteria::SmallestFile => {
+                // Models without a known file size go last.
+                self.models
+                    .sort_by(|a, b| match (smallest_file_gb(a), smallest_file_gb(b)) {
+                        (Some(a), Some(b)) => a.total_cmp(&b),
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => Ordering::Equal,
+                    });
+            }
         }
         self.sorted_by = criteria;
     }
 
     pub fn set_models(&mut self, models: Vec<Model>) {
-        self.models = models;
+        self.facets = Facets::from_models(&models);
+        self.results = models;
+        self.apply_filters();
+    }
+
+    /// Filter the results of this and the next searches.
+    pub fn set_filters(&mut self, filters: SearchFilters) {
+        self.filters = filters;
+        self.apply_filters();
+    }
+
+    /// Values of the facets found in the results, to filter by.
+    pub fn facets(&self) -> &Facets {
+        &self.facets
+    }
+
+    fn apply_filters(&mut self) {
+        let now = Utc::now();
+        self.models = self
+            .results
+            .iter()
+            .filter(|model| self.filters.matches(model, now))
+            .cloned()
+            .collect();
         self.sort_models(self.sorted_by);
     }
 
     pub fn handle_action(&mut self, action: &Action) {
         if let Some(msg) = action.downcast_ref::<SearchAction>() {
             match msg {
-                SearchAction::Results(models) => {
+                SearchAction::Results(moly_client, _) | SearchAction::Error(moly_client)
+                    if !moly_client.same_server(&self.moly_client) => {}
+                SearchAction::Results(_, models) => {
                     let previous_state = self.state.to_owned();
                     self.state = SearchState::Idle;
 
@@ -158,7 +215,7 @@ impl Search {
                         eprintln!("Client was not expecting to receive results");
                     }
                 }
-                SearchAction::Error => {
+                SearchAction::Error(_) => {
                     self.state = SearchState::Errored;
                     self.set_models(vec![]);
                 }
@@ -175,18 +232,19 @@ impl Search {
     }
 
     pub fn update_downloaded_file_in_search_results(&mut self, file_id: &FileId, downloaded: bool) {
-        let model = self
-            .models
+        let files = self
+            .results
             .iter_mut()
-            .find(|m| m.files.iter().any(|f| f.id == *file_id));
-        if let Some(model) = model {
-            let file = model.files.iter_mut().find(|f| f.id == *file_id).unwrap();
+            .chain(self.models.iter_mut())
+            .flat_map(|m| m.files.iter_mut())
+            .filter(|f| f.id == *file_id);
+        for file in files {
             file.downloaded = downloaded;
         }
     }
 
     pub fn get_model_and_file_from_search_results(&self, file_id: &str) -> Option<(Model, File)> {
-        self.models.iter().find_map(|m| {
+        self.results.iter().find_map(|m| {
             m.files
                 .iter()
                 .find(|f| f.id == file_id)
@@ -194,3 +252,11 @@ impl Search {
         })
     }
 }
+
+fn smallest_file_gb(model: &Model) -> Option<f64> {
+    model
+        .files
+        .iter()
+        .filter_map(file_size_gb)
+        .min_by(f64::total_cmp)
+}
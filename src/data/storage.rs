@@ -0,0 +1,695 @@
+//! Disk space taken by the downloaded models, moving the folder they are kept
+//! in, and adding GGUF files that are already on disk.
+//!
+//! MolyServer keeps each file at `{folder}/{model id}/{file name}`, which is
+//! how the folder is found from the paths of the downloaded files. Moving it
+//! moves the files and leaves a link to the new folder in place of the old
+//! one, so the server keeps finding them where it put them. Importing places
+//! the file where the server would download it, then asks the server to
+//! download it: like when resuming a paused download, the server only fetches
+//! the bytes the file doesn't have yet, here none.
+
+use moly_kit::aitk::utils::asynchronous::spawn;
+use moly_protocol::data::{DownloadedFile, File, Model};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::app::app_runner;
+
+use super::moly_client::MolyClient;
+
+/// Size of the chunks files are copied in, and so how often progress is told.
+const COPY_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Space taken by the files of one model.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelUsage {
+    pub name: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Usage of each model, the largest first.
+pub fn usage_by_model(files: &[DownloadedFile]) -> Vec<ModelUsage> {
+    let mut usage: Vec<ModelUsage> = Vec::new();
+    for file in files {
+        let bytes = file.file.size.parse::<f64>().unwrap_or(0.0) as u64;
+        match usage.iter_mut().find(|u| u.name == file.model.name) {
+            Some(model) => {
+                model.files += 1;
+                model.bytes += bytes;
+            }
+            None => usage.push(ModelUsage {
+                name: file.model.name.clone(),
+                files: 1,
+                bytes,
+            }),
+        }
+    }
+    usage.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.name.cmp(&b.name)));
+    usage
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum StorageTask {
+    #[default]
+    Idle,
+    /// The models are being moved to their new folder.
+    Moving {
+        done: u64,
+        total: u64,
+    },
+    /// The file is being copied to the models folder.
+    Importing {
+        done: u64,
+        total: u64,
+    },
+    Done(String),
+    Failed(String),
+}
+
+pub struct Storage {
+    moly_client: MolyClient,
+    /// Folder MolyServer keeps the models in, once found from its files.
+    server_dir: Option<PathBuf>,
+    /// Folder the models really are in, the target of the link left by a move.
+    pub models_dir: PathBuf,
+    /// Space left on the volume of the models folder, when known.
+    pub free_bytes: Option<u64>,
+    /// Whether the folder of MolyServer was found, so it can be moved and
+    /// files imported into it.
+    pub can_manage_files: bool,
+    pub task: StorageTask,
+}
+
+impl Storage {
+    pub fn new(moly_client: MolyClient, models_dir: PathBuf) -> Self {
+        let free_bytes = free_space(&models_dir);
+        Self {
+            moly_client,
+            server_dir: None,
+            models_dir,
+            free_bytes,
+            can_manage_files: false,
+            task: StorageTask::Idle,
+        }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        matches!(
+            self.task,
+            StorageTask::Moving { .. } | StorageTask::Importing { .. }
+        )
+    }
+
+    /// Find the folder of MolyServer from its files, and check the space left there.
+    pub fn refresh(&mut self) {
+        self.free_bytes = free_space(&self.models_dir);
+
+        let moly_client = self.moly_client.clone();
+        spawn(async move {
+            let Ok(files) = moly_client.get_downloaded_files().await else {
+                return;
+            };
+            let server_dir = server_models_dir(&files).filter(|dir| dir.is_dir());
+            app_runner().defer(move |app, _, _| {
+                let me = &mut app.store.as_mut().unwrap().storage;
+                me.can_manage_files = server_dir.is_some();
+                if let Some(server_dir) = server_dir {
+                    me.models_dir =
+                        fs::canonicalize(&server_dir).unwrap_or_else(|_| server_dir.clone());
+                    me.free_bytes = free_space(&me.models_dir);
+                    me.server_dir = Some(server_dir);
+                }
+            });
+        });
+    }
+
+    /// Move the models to `target`, leaving a link to it where MolyServer
+    /// expects them.
+    ///
+    /// Downloads must not be running, as their files would be left behind.
+    pub fn move_models(&mut self, target: PathBuf, downloads_running: bool) {
+        if self.is_busy() {
+            return;
+        }
+        let Some(server_dir) = self.server_dir.clone().filter(|_| self.can_manage_files) else {
+            self.task = StorageTask::Failed("The folder of MolyServer isn't known yet".into());
+            return;
+        };
+        if downloads_running {
+            self.task = StorageTask::Failed("Pause the downloads before moving the models".into());
+            return;
+        }
+        let source = self.models_dir.clone();
+        if let Err(e) = check_target(&source, &target).and(check_target(&server_dir, &target)) {
+            self.task = StorageTask::Failed(e);
+            return;
+        }
+
+        self.task = StorageTask::Moving {
+            done: 0,
+            total: dir_size(&source),
+        };
+        let moly_client = self.moly_client.clone();
+        spawn(async move {
+            // The loaded model keeps its file open.
+            let _ = moly_client.eject_model().await;
+
+            std::thread::spawn(move || {
+                let mut progress = |done| {
+                    app_runner().defer(move |app, cx, _| {
+                        let task = &mut app.store.as_mut().unwrap().storage.task;
+                        if let StorageTask::Moving { done: moved, .. } = task {
+                            *moved = done;
+                        }
+                        cx.redraw_all();
+                    });
+                };
+                // Linking may not be allowed, which is better known before moving.
+                let result = prepare_link(&server_dir, &target).and_then(|link| {
+                    move_dir(&source, &target, &mut progress)
+                        .and_then(|()| swap_link(&server_dir, &source, &link))
+                        .inspect_err(|_| {
+                            let _ = fs::remove_file(&link);
+                        })
+                });
+
+                app_runner().defer(move |app, cx, _| {
+                    let store = app.store.as_mut().unwrap();
+                    match result {
+                        Ok(()) => {
+                            store.storage.task =
+                                StorageTask::Done(format!("Models moved to {}", target.display()));
+                            store.storage.free_bytes = free_space(&target);
+                            store.storage.models_dir = target.clone();
+                            store.preferences.set_downloaded_files_dir(target);
+                        }
+                        Err(e) => {
+                            store.storage.task = StorageTask::Failed(format!(
+                                "Couldn't move the models, some may be left in {}: {}",
+                                source.display(),
+                                e
+                            ));
+                        }
+                    }
+                    store.downloads.load_downloaded_files();
+                    store.model_loader.forget_resident();
+                    cx.redraw_all();
+                });
+            });
+        });
+    }
+
+    /// Have MolyServer list the GGUF file at `path` as downloaded, when it is
+    /// a file of a model in its catalog.
+    pub fn import_file(&mut self, path: PathBuf) {
+        if self.is_busy() {
+            return;
+        }
+        let Some(server_dir) = self.server_dir.clone().filter(|_| self.can_manage_files) else {
+            self.task = StorageTask::Failed("The folder of MolyServer isn't known yet".into());
+            return;
+        };
+        if let Err(e) = check_gguf(&path) {
+            self.task = StorageTask::Failed(e);
+            return;
+        }
+        let Ok(total) = fs::metadata(&path).map(|m| m.len()) else {
+            self.task = StorageTask::Failed(format!("Couldn't read {}", path.display()));
+            return;
+        };
+
+        self.task = StorageTask::Importing { done: 0, total };
+        let moly_client = self.moly_client.clone();
+        spawn(async move {
+            let found = find_in_catalog(&moly_client, &path, total).await;
+            let (model, file) = match found {
+                Ok(found) => found,
+                Err(e) => {
+                    app_runner().defer(move |app, cx, _| {
+                        app.store.as_mut().unwrap().storage.task = StorageTask::Failed(e);
+                        cx.redraw_all();
+                    });
+                    return;
+                }
+            };
+
+            std::thread::spawn(move || {
+                let destination = server_dir.join(&model.id).join(&file.name);
+                let result = place_file(&path, &destination, &mut |done| {
+                    app_runner().defer(move |app, cx, _| {
+                        let task = &mut app.store.as_mut().unwrap().storage.task;
+                        if let StorageTask::Importing { done: copied, .. } = task {
+                            *copied = done;
+                        }
+                        cx.redraw_all();
+                    });
+                });
+
+                app_runner().defer(move |app, cx, _| {
+                    let store = app.store.as_mut().unwrap();
+                    match result {
+                        Ok(()) => {
+                            store.storage.task =
+                                StorageTask::Done(format!("Imported {}", file.name));
+                            store.downloads.download_file(model, file);
+                            store.storage.refresh();
+                        }
+                        Err(e) => {
+                            store.storage.task =
+                                StorageTask::Failed(format!("Couldn't import the file: {}", e));
+                        }
+                    }
+                    cx.redraw_all();
+                });
+            });
+        });
+    }
+}
+
+/// The folder MolyServer keeps its files in, when the paths of all the
+/// downloaded files end with their model id and file name under it.
+fn server_models_dir(files: &[DownloadedFile]) -> Option<PathBuf> {
+    let mut found: Option<PathBuf> = None;
+    for file in files {
+        let Some(path) = &file.file.downloaded_path else {
+            continue;
+        };
+        let path = Path::new(path);
+        let relative = Path::new(&file.model.id).join(&file.file.name);
+        if !path.ends_with(&relative) {
+            return None;
+        }
+        let dir = path.ancestors().nth(relative.components().count())?;
+        match &found {
+            Some(found) if found != dir => return None,
+            _ => found = Some(dir.to_path_buf()),
+        }
+    }
+    found.filter(|dir| dir.is_absolute())
+}
+
+/// The file of a model in the catalog of MolyServer named and sized like the
+/// one at `path`.
+async fn find_in_catalog(
+    moly_client: &MolyClient,
+    path: &Path,
+    size: u64,
+) -> Result<(Model, File), String> {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or("The file name isn't valid")?;
+    let models = moly_client
+        .search_models(search_query(name))
+        .await
+        .map_err(|e| format!("Couldn't search the catalog: {}", e))?;
+
+    models
+        .into_iter()
+        .find_map(|model| {
+            let file = model.files.iter().find(|file| {
+                file.name.eq_ignore_ascii_case(name)
+                    && file.size.parse::<f64>().ok().map(|s| s as u64) == Some(size)
+            })?;
+            let file = file.clone();
+            Some((model, file))
+        })
+        .ok_or_else(|| format!("No model in the catalog has a file like {}", name))
+}
+
+/// What to search the catalog for to find the model of a file, its name
+/// without the extension and the quantization, like `llama-2-7b` for
+/// `llama-2-7b.Q4_K_M.gguf` or `Llama-3-8B-Instruct-Q4_K_M.gguf`.
+fn search_query(file_name: &str) -> String {
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    let is_quantization = |part: &str| {
+        let part = part.trim_start_matches(['I', 'i']);
+        let mut chars = part.chars();
+        matches!(chars.next(), Some('Q' | 'q' | 'F' | 'f'))
+            && chars.next().is_some_and(|c| c.is_ascii_digit())
+    };
+    match stem.rsplit_once('-') {
+        Some((model, quantization)) if is_quantization(quantization) => model.to_string(),
+        _ => stem.to_string(),
+    }
+}
+
+/// Bytes taken by the files under `path`.
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+            _ => entry.metadata().map(|m| m.len()).unwrap_or(0),
+        })
+        .sum()
+}
+
+/// Move what `source` contains into `target`, telling the bytes moved so far.
+///
+/// Entries are renamed when they can be, and copied then removed across volumes.
+fn move_dir(source: &Path, target: &Path, progress: &mut dyn FnMut(u64)) -> io::Result<()> {
+    fs::create_dir_all(target)?;
+    let mut done = 0;
+    move_entries(source, target, &mut done, progress)
+}
+
+fn move_entries(
+    source: &Path,
+    target: &Path,
+    done: &mut u64,
+    progress: &mut dyn FnMut(u64),
+) -> io::Result<()> {
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let from = entry.path();
+        let to = target.join(entry.file_name());
+        let size = if entry.file_type()?.is_dir() {
+            dir_size(&from)
+        } else {
+            entry.metadata()?.len()
+        };
+
+        if fs::rename(&from, &to).is_ok() {
+            *done += size;
+            progress(*done);
+        } else if entry.file_type()?.is_dir() {
+            fs::create_dir_all(&to)?;
+            move_entries(&from, &to, done, progress)?;
+            fs::remove_dir(&from)?;
+        } else {
+            copy_file(&from, &to, done, progress)?;
+            fs::remove_file(&from)?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_file(
+    from: &Path,
+    to: &Path,
+    done: &mut u64,
+    progress: &mut dyn FnMut(u64),
+) -> io::Result<()> {
+    let mut reader = fs::File::open(from)?;
+    let mut writer = fs::File::create(to)?;
+    let mut buffer = vec![0; COPY_CHUNK_SIZE];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read])?;
+        *done += read as u64;
+        progress(*done);
+    }
+    writer.sync_all()
+}
+
+/// Create `target` and a link to it next to `server_dir`, for [`swap_link`].
+fn prepare_link(server_dir: &Path, target: &Path) -> io::Result<PathBuf> {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(server_dir.file_name().unwrap_or_default());
+    name.push(".link");
+    let link = server_dir.with_file_name(name);
+    let _ = fs::remove_file(&link);
+
+    fs::create_dir_all(target)?;
+    symlink_dir(target, &link)?;
+    Ok(link)
+}
+
+#[cfg(unix)]
+fn symlink_dir(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink_dir(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, link)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn symlink_dir(_target: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "links aren't supported here",
+    ))
+}
+
+/// Put `link` where MolyServer looks for its files, in place of the emptied
+/// `source` or of the link to it.
+fn swap_link(server_dir: &Path, source: &Path, link: &Path) -> io::Result<()> {
+    if server_dir.is_symlink() {
+        fs::remove_file(server_dir)?;
+        fs::remove_dir(source)?;
+    } else {
+        fs::remove_dir(server_dir)?;
+    }
+    fs::rename(link, server_dir)
+}
+
+/// Put the file at `from` in `to`, linked when on the same volume, copied
+/// otherwise.
+fn place_file(from: &Path, to: &Path, progress: &mut dyn FnMut(u64)) -> io::Result<()> {
+    if to.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "MolyServer already has this file",
+        ));
+    }
+    fs::create_dir_all(to.parent().unwrap())?;
+    if fs::hard_link(from, to).is_ok() {
+        progress(fs::metadata(to)?.len());
+        return Ok(());
+    }
+
+    let mut done = 0;
+    let result = copy_file(from, to, &mut done, progress);
+    if result.is_err() {
+        let _ = fs::remove_file(to);
+    }
+    result
+}
+
+/// Whether the models can be moved from `source` to `target`.
+fn check_target(source: &Path, target: &Path) -> Result<(), String> {
+    if !target.is_absolute() {
+        return Err("The new folder must be a full path".into());
+    }
+    if target == source {
+        return Err("The models are already there".into());
+    }
+    if target.starts_with(source) {
+        return Err("The new folder can't be inside the current one".into());
+    }
+    if target.is_file() {
+        return Err("The new folder is a file".into());
+    }
+    if target.is_dir()
+        && fs::read_dir(target)
+            .map(|mut entries| entries.next().is_some())
+            .unwrap_or(true)
+    {
+        return Err("The new folder must be empty".into());
+    }
+    Ok(())
+}
+
+/// Whether `path` is a GGUF file, from its extension and magic number.
+fn check_gguf(path: &Path) -> Result<(), String> {
+    let is_gguf_name = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gguf"));
+    if !is_gguf_name {
+        return Err("Only .gguf files can be imported".into());
+    }
+
+    let mut magic = [0u8; 4];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+    if &magic != b"GGUF" {
+        return Err(format!("{} isn't a GGUF file", path.display()));
+    }
+    Ok(())
+}
+
+/// Space left on the volume of `path`, or of its closest existing parent.
+#[cfg(target_os = "linux")]
+pub fn free_space(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let existing = path.ancestors().find(|p| p.exists())?;
+    let c_path = CString::new(existing.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `statvfs` only writes to the zeroed struct it's given, and the
+    // path is a valid C string.
+    let stat = unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(c_path.as_ptr(), &mut stat) != 0 {
+            return None;
+        }
+        stat
+    };
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn free_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("moly-storage-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_usage_by_model() {
+        let file = |model: &str, size: &str| DownloadedFile {
+            file: File {
+                size: size.to_string(),
+                ..Default::default()
+            },
+            model: Model {
+                name: model.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let usage = usage_by_model(&[
+            file("Small", "100"),
+            file("Large", "1000"),
+            file("Small", "300"),
+            file("Unknown", "-"),
+        ]);
+
+        let summary: Vec<_> = usage
+            .iter()
+            .map(|u| (u.name.as_str(), u.files, u.bytes))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("Large", 1, 1000), ("Small", 2, 400), ("Unknown", 1, 0)]
+        );
+    }
+
+    #[test]
+    fn test_check_target_and_gguf() {
+        let dir = temp_dir("check");
+        let source = dir.join("models");
+        fs::create_dir_all(&source).unwrap();
+
+        assert!(check_target(&source, Path::new("relative")).is_err());
+        assert!(check_target(&source, &source).is_err());
+        assert!(check_target(&source, &source.join("inner")).is_err());
+        assert!(check_target(&source, &dir.join("elsewhere")).is_ok());
+
+        fs::write(dir.join("model.gguf"), b"GGUF\x03\x00\x00\x00").unwrap();
+        fs::write(dir.join("fake.gguf"), b"<html>").unwrap();
+        fs::write(dir.join("model.bin"), b"GGUF").unwrap();
+        assert!(check_gguf(&dir.join("model.gguf")).is_ok());
+        assert!(check_gguf(&dir.join("fake.gguf")).is_err());
+        assert!(check_gguf(&dir.join("model.bin")).is_err());
+        assert!(check_gguf(&dir.join("missing.gguf")).is_err());
+
+        // Not empty anymore.
+        assert!(check_target(&source, &dir).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_server_models_dir() {
+        let file = |model: &str, name: &str, path: Option<&str>| DownloadedFile {
+            file: File {
+                name: name.to_string(),
+                downloaded_path: path.map(String::from),
+                ..Default::default()
+            },
+            model: Model {
+                id: model.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let a = file("org/a", "a.gguf", Some("/models/org/a/a.gguf"));
+        let b = file("org/b", "b.gguf", Some("/models/org/b/b.gguf"));
+        let pending = file("org/c", "c.gguf", None);
+        assert_eq!(
+            server_models_dir(&[a.clone(), b, pending]),
+            Some(PathBuf::from("/models"))
+        );
+
+        let elsewhere = file("org/b", "b.gguf", Some("/other/org/b/b.gguf"));
+        assert_eq!(server_models_dir(&[a.clone(), elsewhere]), None);
+        let unexpected = file("org/b", "b.gguf", Some("/models/b.gguf"));
+        assert_eq!(server_models_dir(&[a, unexpected]), None);
+        assert_eq!(server_models_dir(&[]), None);
+    }
+
+    #[test]
+    fn test_search_query() {
+        assert_eq!(search_query("llama-2-7b.Q4_K_M.gguf"), "llama-2-7b");
+        assert_eq!(
+            search_query("Llama-3-8B-Instruct-Q4_K_M.gguf"),
+            "Llama-3-8B-Instruct"
+        );
+        assert_eq!(search_query("phi-2-IQ3_XS.gguf"), "phi-2");
+        assert_eq!(search_query("mistral-7b-f16.gguf"), "mistral-7b");
+        assert_eq!(search_query("qwen2-7b.gguf"), "qwen2-7b");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_move_leaves_a_link() {
+        let dir = temp_dir("move");
+        let server_dir = dir.join("models");
+        let target = dir.join("elsewhere");
+        fs::create_dir_all(server_dir.join("org/a")).unwrap();
+        fs::write(server_dir.join("org/a/a.gguf"), vec![1; 1000]).unwrap();
+        fs::write(server_dir.join("b.gguf"), vec![2; 24]).unwrap();
+
+        let mut reported = Vec::new();
+        let link = prepare_link(&server_dir, &target).unwrap();
+        move_dir(&server_dir, &target, &mut |done| reported.push(done)).unwrap();
+        swap_link(&server_dir, &server_dir, &link).unwrap();
+
+        assert_eq!(reported.last(), Some(&1024));
+        assert!(server_dir.is_symlink());
+        assert_eq!(
+            fs::read(server_dir.join("org/a/a.gguf")).unwrap().len(),
+            1000
+        );
+        assert_eq!(fs::read(target.join("b.gguf")).unwrap().len(), 24);
+
+        // Moving again replaces the link and removes the emptied folder.
+        let again = dir.join("again");
+        let link = prepare_link(&server_dir, &again).unwrap();
+        move_dir(&target, &again, &mut |_| {}).unwrap();
+        swap_link(&server_dir, &target, &link).unwrap();
+        assert!(!target.exists());
+        assert_eq!(
+            fs::canonicalize(&server_dir).unwrap(),
+            fs::canonicalize(&again).unwrap()
+        );
+        assert!(server_dir.join("b.gguf").is_file());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
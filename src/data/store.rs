@@ -3,21 +3,51 @@ use crate::data::providers::ProviderId;
 use crate::shared::actions::ChatAction;
 use crate::shared::bot_context::BotContext;
 
+use super::chats::ProviderSyncingStatus;
 use super::chats::chat::ChatId;
 use super::downloads::download::DownloadFileAction;
+use super::gateway::{Gateway, GatewayConfig};
+use super::hardware::{FileFit, HardwareInfo};
+use super::health_monitor::{self, HealthMonitor, HealthTarget};
+use super::mcp_context::McpContext;
+use super::mcp_import::{McpImport, McpMergeReport};
+#[cfg(not(target_arch = "wasm32"))]
+use super::mcp_oauth;
+use super::mcp_requests::{self, McpRequestKind, PendingMcpRequest, SamplingState};
+#[cfg(not(target_arch = "wasm32"))]
+use super::mcp_servers::McpServer;
 use super::mcp_servers::McpServersConfig;
+use super::mcp_status::McpServerState;
+#[cfg(not(target_arch = "wasm32"))]
+use super::mcp_status::{self, McpServerFailure, McpServerStatus};
+use super::mcp_supervisor::McpSupervisor;
+#[cfg(not(target_arch = "wasm32"))]
+use super::mcp_supervisor::{self, McpLogSource};
+use super::model_loader::{ModelLoader, RuntimeOptions};
 use super::moly_client::MolyClient;
+use super::moly_server_process::{self, MolyServerProcess, MolyServerProcessConfig};
+use super::moly_servers::{LOCAL_SERVER_ID, MolyServerEndpoint, MolyServers};
 use super::preferences::Preferences;
-use super::providers::{ProviderFetchModelsResult, ProviderType};
-use super::search::SortCriteria;
-use super::supported_providers;
-use super::{chats::Chats, downloads::Downloads, search::Search};
+use super::providers::{
+    Provider, ProviderFetchModelsResult, ProviderType, fetch_models_for_provider,
+};
+use super::search::filters::SearchFilters;
+use super::search::{SearchAction, SortCriteria};
+use super::tool_audit::ToolAuditLog;
+use super::tool_filter::DisabledTools;
+use super::{chats::Chats, downloads::Downloads, search::Search, storage::Storage};
 use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
 use makepad_widgets::Action;
-use moly_kit::aitk::utils::asynchronous::spawn;
+use moly_kit::aitk::utils::asynchronous::{sleep, spawn};
 use moly_kit::prelude::*;
+use moly_kit::utils::mcp::McpClient;
+#[cfg(not(target_arch = "wasm32"))]
+use moly_kit::utils::mcp::McpConnectError;
+use moly_kit::utils::tool_calls::ToolCalls;
+use moly_kit::utils::tool_policy::SharedToolPolicy;
 
-use super::providers::{Provider, ProviderConnectionStatus};
+use super::providers::Provider;
 use moly_protocol::data::{Author, File, FileId, Model, ModelId, PendingDownload};
 
 use makepad_widgets::*;
@@ -30,6 +60,9 @@ pub enum StoreAction {
     Search(String),
     ResetSearch,
     Sort(SortCriteria),
+    Filter(SearchFilters),
+    /// Make the MolyServer with this id the active one.
+    SwitchMolyServer(String),
     #[default]
     None,
 }
@@ -38,6 +71,10 @@ pub enum StoreAction {
 pub struct FileWithDownloadInfo {
     pub file: File,
     pub download: Option<PendingDownload>,
+    /// How the file fits in the memory of this computer, if known.
+    pub fit: Option<FileFit>,
+    /// The file of the model to pick on this computer.
+    pub recommended: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -55,55 +92,140 @@ pub struct ModelWithDownloadInfo {
     pub files: Vec<FileWithDownloadInfo>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub enum ProviderSyncingStatus {
-    NotSyncing,
-    Syncing(ProviderSyncing),
-    Synced,
-}
-
-#[derive(Clone, Debug, PartialEq)]
-pub struct ProviderSyncing {
-    pub current: u32,
-    pub total: u32,
-}
-
 pub struct Store {
     pub search: Search,
     pub downloads: Downloads,
+    /// Disk usage and location of the downloaded models.
+    pub storage: Storage,
+    /// The file MolyServer has in memory, and loading new ones.
+    pub model_loader: ModelLoader,
+    /// Memory and CPUs of this computer, refreshed with the search results.
+    pub hardware: HardwareInfo,
     pub chats: Chats,
     pub preferences: Preferences,
     pub bot_context: Option<BotContext>,
+    /// Tool approval rules shared by every chat.
+    pub tool_policy: SharedToolPolicy,
+    /// MCP tools turned off in the settings, hidden from every chat.
+    pub disabled_mcp_tools: DisabledTools,
+    /// Status and tools of each enabled MCP server, in configuration order.
+    pub mcp_servers_state: IndexMap<String, McpServerState>,
+    /// Prompts and resources of the connected MCP servers.
+    pub mcp_context: McpContext,
+    /// Logs and process instances of the MCP servers.
+    pub mcp_supervisor: McpSupervisor,
+    /// Sampling and elicitation requests from MCP servers, oldest first.
+    pub mcp_requests: Vec<PendingMcpRequest>,
+    /// Every tool call decided in the chats, kept across sessions.
+    pub tool_audit: ToolAuditLog,
+    /// Tool calls running in the chats, limited by the configured timeouts.
+    pub tool_calls: ToolCalls,
+    /// Local OpenAI-compatible server to the configured providers.
+    pub gateway: Gateway,
+    /// The MolyServer process, when Moly starts it.
+    pub moly_server_process: MolyServerProcess,
+    next_mcp_request_id: u64,
+    /// Bumped when the tool manager is recreated so late updates from the
+    /// previous one are ignored.
+    mcp_servers_generation: u64,
+    /// Client of the active MolyServer.
     moly_client: MolyClient,
+    /// Every known MolyServer, with the downloads and search of the inactive ones.
+    pub moly_servers: MolyServers,
     pub provider_syncing_status: ProviderSyncingStatus,
+    /// When the providers and MCP network servers are checked again.
+    health_monitor: HealthMonitor,
 
     pub provider_icons: Vec<String>,
 }
 
 const MOLY_SERVER_VERSION_EXTENSION: &str = "/api/v1";
 
+/// How long a connected MCP network server has to answer a health check ping.
+#[cfg(not(target_arch = "wasm32"))]
+const MCP_PING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
 impl Store {
     pub fn load_into_app() {
+        moly_kit::utils::extraction::set_extraction_cache(
+            crate::shared::utils::attachments::PersistedExtractionCache,
+        );
+
         spawn(async move {
-            let preferences = Preferences::load().await;
+            let mut preferences = Preferences::load().await;
+            if preferences.gateway.ensure_token() {
+                preferences.save();
+            }
 
+            // Moly may have moved the server to another port on a previous run.
             let server_port = std::env::var("MOLY_SERVER_PORT")
                 .ok()
                 .and_then(|p| p.parse::<u16>().ok())
+                .or_else(|| {
+                    preferences
+                        .providers_preferences
+                        .iter()
+                        .filter(|p| p.provider_type == ProviderType::MolyServer && p.enabled)
+                        .find_map(|p| moly_server_process::local_port(&p.url))
+                })
                 .unwrap_or(8765);
 
             let moly_client = MolyClient::new(format!("http://localhost:{}", server_port));
 
             let chats = Chats::load(moly_client.clone()).await;
+            let download_queue = Downloads::load_queue(LOCAL_SERVER_ID).await;
+            let tool_audit = ToolAuditLog::load().await;
+
+            let tool_policy =
+                SharedToolPolicy::new(preferences.mcp_servers_config.effective_tool_policy());
+            tool_policy.set_on_change(|policy| {
+                let rules = policy.rules.clone();
+                app_runner().defer(move |app, _cx, _| {
+                    if let Some(store) = app.store.as_mut() {
+                        store.preferences.set_mcp_tool_policy_rules(rules);
+                    }
+                });
+            });
+
+            let disabled_mcp_tools = DisabledTools::from_config(&preferences.mcp_servers_config);
+
+            let tool_calls = ToolCalls::default();
+            tool_calls.set_timeouts(preferences.mcp_servers_config.tool_timeouts());
+            tool_calls.set_disabled_check(Some(disabled_mcp_tools.check()));
 
             let mut store = Self {
                 search: Search::new(moly_client.clone()),
-                downloads: Downloads::new(moly_client.clone()),
+                downloads: Downloads::new(
+                    LOCAL_SERVER_ID.to_string(),
+                    moly_client.clone(),
+                    download_queue,
+                    preferences.max_concurrent_downloads,
+                ),
+                storage: Storage::new(
+                    moly_client.clone(),
+                    preferences.downloaded_files_dir.clone(),
+                ),
+                model_loader: ModelLoader::new(moly_client.clone()),
+                hardware: HardwareInfo::detect(),
                 chats,
+                moly_servers: MolyServers::new(moly_client.clone(), &preferences.moly_servers),
                 moly_client,
                 preferences,
                 bot_context: None,
+                tool_policy,
+                disabled_mcp_tools,
+                mcp_servers_state: IndexMap::new(),
+                mcp_context: McpContext::default(),
+                mcp_supervisor: McpSupervisor::default(),
+                mcp_requests: Vec::new(),
+                tool_audit,
+                tool_calls,
+                gateway: Gateway::default(),
+                moly_server_process: MolyServerProcess::default(),
+                next_mcp_request_id: 0,
+                mcp_servers_generation: 0,
                 provider_syncing_status: ProviderSyncingStatus::NotSyncing,
+                health_monitor: HealthMonitor::default(),
                 provider_icons: vec![],
             };
 
@@ -112,7 +234,14 @@ impl Store {
             store.load_preference_connections();
 
             app_runner().defer(move |app, cx, _| {
-                app.store = Some(store);
+                // Started once the store is in place, where its status is reported.
+                let store = app.store.insert(store);
+                store.gateway.apply_config(&store.preferences.gateway);
+                store.start_moly_server();
+                store.start_health_monitor();
+                if let Some(id) = store.preferences.active_moly_server.clone() {
+                    store.switch_moly_server(&id);
+                }
                 app.ui.view(cx, ids!(body)).set_visible(cx, true);
                 cx.redraw_all(); // app.ui.redraw(cx) doesn't work as expected on web.
             });
@@ -120,11 +249,16 @@ impl Store {
     }
 
     /// Check if the main moly server provider is enabled in settings.
+    ///
+    /// Remote servers are always enabled, as they were added on purpose.
     pub fn is_moly_server_enabled(&self) -> bool {
+        !self.moly_servers.is_local_active() || self.is_local_moly_server_enabled()
+    }
+
+    fn is_local_moly_server_enabled(&self) -> bool {
+        let address = self.moly_servers.local_client().address();
         self.preferences.providers_preferences.iter().any(|p| {
-            p.provider_type == ProviderType::MolyServer
-                && p.enabled
-                && p.url.starts_with(&self.moly_client.address())
+            p.provider_type == ProviderType::MolyServer && p.enabled && p.url.starts_with(&address)
         })
     }
 
@@ -149,6 +283,8 @@ impl Store {
                 let store = app.store.as_mut().unwrap();
                 store.downloads.load_downloaded_files();
                 store.downloads.load_pending_downloads();
+                store.storage.refresh();
+                store.model_loader.forget_resident();
                 store.search.load_featured_models();
             });
         });
@@ -163,6 +299,10 @@ impl Store {
     /// This function combines the search results information for a given model
     /// with the download information for the files of that model.
     pub fn add_download_info_to_model(&self, model: &Model) -> ModelWithDownloadInfo {
+        let recommended = self
+            .hardware
+            .recommended_file(&model.files, &model.requires);
+
         let files = model
             .files
             .iter()
@@ -177,6 +317,8 @@ impl Store {
                 FileWithDownloadInfo {
                     file: file.clone(),
                     download,
+                    fit: self.hardware.file_fit(file, &model.requires),
+                    recommended: recommended.as_ref() == Some(&file.id),
                 }
             })
             .collect();
@@ -209,6 +351,18 @@ impl Store {
         }
     }
 
+    pub fn set_max_concurrent_downloads(&mut self, max_concurrent_downloads: usize) {
+        self.preferences
+            .set_max_concurrent_downloads(max_concurrent_downloads);
+        self.downloads.set_max_concurrent(max_concurrent_downloads);
+    }
+
+    /// Load a downloaded file with `options`, remembered for the next time.
+    pub fn load_model(&mut self, file_id: FileId, options: RuntimeOptions) {
+        self.model_loader.load(file_id.clone(), &options);
+        self.preferences.set_runtime_options(file_id, options);
+    }
+
     pub fn delete_file(&mut self, file_id: FileId) {
         let moly_client = self.moly_client.clone();
         spawn(async move {
@@ -226,6 +380,7 @@ impl Store {
                 let store = app.store.as_mut().unwrap();
                 store.downloads.load_downloaded_files();
                 store.downloads.load_pending_downloads();
+                store.model_loader.forget_resident();
                 store
                     .search
                     .update_downloaded_file_in_search_results(&file_id, false);
@@ -234,37 +389,100 @@ impl Store {
     }
 
     pub fn handle_action(&mut self, action: &Action) {
+        if action.downcast_ref::<SearchAction>().is_some() {
+            // Free memory changes, fits are checked against what's free now.
+            self.hardware = HardwareInfo::detect();
+        }
         self.search.handle_action(action);
         self.downloads.handle_action(action);
+        // Inactive servers keep searching and downloading.
+        for (downloads, search) in self.moly_servers.parked_mut() {
+            search.handle_action(action);
+            downloads.handle_action(action);
+        }
 
         if let Some(_) = action.downcast_ref::<DownloadFileAction>() {
             self.update_downloads();
         }
     }
 
-    fn update_downloads(&mut self) {
-        let completed_download_ids = self.downloads.refresh_downloads_data();
+    /// Downloads made through `moly_client`, whether its server is active or not.
+    pub fn downloads_of(&mut self, moly_client: &MolyClient) -> Option<&mut Downloads> {
+        if self.moly_client.same_server(moly_client) {
+            Some(&mut self.downloads)
+        } else {
+            self.moly_servers.parked_downloads_mut(moly_client)
+        }
+    }
 
-        let mut address = self.moly_client.address().clone();
-        address.push_str(MOLY_SERVER_VERSION_EXTENSION);
+    /// Search, download and run models with the server `id` from now on.
+    pub fn switch_moly_server(&mut self, id: &str) {
+        if id == self.moly_servers.active_id() {
+            return;
+        }
+        let Some(moly_client) = self.moly_servers.client(id) else {
+            return;
+        };
 
-        if !completed_download_ids.is_empty() {
-            // Find MolyServer provider
-            let provider = self
-                .chats
-                .providers
-                .values()
-                .find(|p| {
-                    p.url == address && p.provider_type == ProviderType::MolyServer && p.enabled
-                })
-                .cloned();
+        let placeholder_downloads = Downloads::new(
+            id.to_string(),
+            moly_client.clone(),
+            Vec::new(),
+            self.preferences.max_concurrent_downloads,
+        );
+        let downloads = std::mem::replace(&mut self.downloads, placeholder_downloads);
+        let search = std::mem::replace(&mut self.search, Search::new(moly_client.clone()));
 
-            if let Some(provider) = provider {
-                self.chats.test_provider_and_fetch_models(
-                    &provider.id,
-                    &mut self.provider_syncing_status,
-                );
+        match self.moly_servers.activate(id, downloads, search) {
+            Some((downloads, search)) => {
+                self.downloads = downloads;
+                self.search = search;
             }
+            // Never active before, its queue is restored and search starts over.
+            None => self.downloads.load_saved_queue(),
+        }
+        self.downloads
+            .set_max_concurrent(self.preferences.max_concurrent_downloads);
+
+        self.moly_client = moly_client.clone();
+        self.model_loader.set_client(moly_client);
+        self.preferences
+            .set_active_moly_server((id != LOCAL_SERVER_ID).then(|| id.to_string()));
+        self.sync_with_moly_server();
+    }
+
+    /// Add a remote MolyServer and switch to it.
+    pub fn add_moly_server(&mut self, endpoint: MolyServerEndpoint) -> Result<(), String> {
+        if !self.moly_servers.add(&endpoint) {
+            return Err(format!("There is already a server named {}", endpoint.name));
+        }
+        let id = endpoint.id.clone();
+        self.preferences.add_moly_server(endpoint);
+        self.switch_moly_server(&id);
+        Ok(())
+    }
+
+    /// Forget a remote MolyServer, switching back to the local one if it was active.
+    pub fn remove_moly_server(&mut self, id: &str) {
+        if id == LOCAL_SERVER_ID {
+            return;
+        }
+        if id == self.moly_servers.active_id() {
+            self.switch_moly_server(LOCAL_SERVER_ID);
+        }
+        self.moly_servers.remove(id);
+        self.preferences.remove_moly_server(id);
+    }
+
+    fn update_downloads(&mut self) {
+        for (downloads, _) in self.moly_servers.parked_mut() {
+            downloads.refresh_downloads_data();
+        }
+
+        let completed_download_ids = self.downloads.refresh_downloads_data();
+
+        if !completed_download_ids.is_empty() {
+            self.fetch_moly_server_models();
         }
 
         // For search results let's trust on our local cache, but updating
@@ -300,11 +518,34 @@ impl Store {
         if let ProviderFetchModelsResult::None = result {
             return;
         }
+        let listed_provider_id = match &result {
+            ProviderFetchModelsResult::Success(provider_id, _) => Some(provider_id.clone()),
+            _ => None,
+        };
+        let checked_provider_id = match &result {
+            ProviderFetchModelsResult::Success(provider_id, _)
+            | ProviderFetchModelsResult::Failure(provider_id, _) => provider_id.clone(),
+            ProviderFetchModelsResult::None => unreachable!(),
+        };
         let fetched_from_moly_server = self.chats.handle_provider_connection_result(
             result,
             &mut self.preferences,
             &mut self.provider_syncing_status,
         );
+        if let Some(provider_id) = &listed_provider_id {
+            let bots = self.chats.get_listed_provider_models(provider_id);
+            self.preferences.set_listed_models(provider_id, bots);
+        }
+
+        let recovered = self.health_monitor.record(
+            HealthTarget::Provider(checked_provider_id),
+            listed_provider_id.is_some(),
+            Utc::now(),
+        );
+        if recovered {
+            // Chats pick the bots of a provider that came up after Moly.
+            self.reload_bot_context();
+        }
         if fetched_from_moly_server && !self.moly_client.is_connected() {
             self.sync_with_moly_server();
         }
@@ -312,82 +553,19 @@ impl Store {
 
     /// Loads the preference connections from the preferences and registers them in the chats.
     pub fn load_preference_connections(&mut self) {
-        let supported = supported_providers::load_supported_providers();
-        let mut final_list = Vec::new();
-
-        for s in &supported {
-            let maybe_prefs = self
-                .preferences
-                .providers_preferences
-                .iter()
-                .find(|pp| pp.id == s.id || (pp.id.is_empty() && pp.url == s.url));
-
-            if let Some(prefs) = maybe_prefs {
-                final_list.push(Provider {
-                    id: if !prefs.id.is_empty() {
-                        prefs.id.clone()
-                    } else {
-                        s.id.clone()
-                    },
-                    name: s.name.clone(),
-                    url: prefs.url.clone(),
-                    api_key: prefs.api_key.clone(),
-                    provider_type: s.provider_type.clone(),
-                    connection_status: ProviderConnectionStatus::Disconnected,
-                    enabled: prefs.enabled,
-                    models: vec![],
-                    was_customly_added: prefs.was_customly_added,
-                    system_prompt: prefs.system_prompt.clone(),
-                    tools_enabled: prefs.tools_enabled,
-                });
-            } else {
-                // Known from supported_providers.json but user has no preferences
-                final_list.push(Provider {
-                    id: s.id.clone(),
-                    name: s.name.clone(),
-                    url: s.url.clone(),
-                    api_key: None,
-                    provider_type: s.provider_type.clone(),
-                    connection_status: ProviderConnectionStatus::Disconnected,
-                    enabled: false,
-                    models: vec![],
-                    was_customly_added: false,
-                    system_prompt: None,
-                    tools_enabled: true,
-                });
-            }
+        for provider in self.preferences.providers() {
+            self.chats.providers.insert(provider.id.clone(), provider);
         }
 
-        // Custom providers from preferences (not in the supported_providers.json)
-        for pp in &self.preferences.providers_preferences {
-            let is_custom = !supported
-                .iter()
-                .any(|sp| sp.id == pp.id || (pp.id.is_empty() && sp.url == pp.url));
-            if is_custom {
-                // Ensure provider has an ID
-                let mut pp_clone = pp.clone();
-                pp_clone.ensure_id();
-
-                final_list.push(Provider {
-                    id: pp_clone.id.clone(),
-                    name: pp_clone.name.clone(),
-                    url: pp_clone.url.clone(),
-                    api_key: pp_clone.api_key.clone(),
-                    provider_type: pp_clone.provider_type.clone(),
-                    connection_status: ProviderConnectionStatus::Disconnected,
-                    enabled: pp_clone.enabled,
-                    models: vec![],
-                    was_customly_added: pp_clone.was_customly_added,
-                    system_prompt: pp_clone.system_prompt.clone(),
-                    tools_enabled: pp_clone.tools_enabled,
-                });
+        // Shown right away, the providers are asked for their models below.
+        let provider_ids: Vec<ProviderId> = self.chats.providers.keys().cloned().collect();
+        for provider_id in provider_ids {
+            if let Some(cached) = self.preferences.listed_models(&provider_id) {
+                self.chats
+                    .insert_cached_models(&provider_id, cached, &self.preferences);
             }
         }
 
-        for provider in final_list {
-            self.chats.providers.insert(provider.id.clone(), provider);
-        }
-
         self.auto_fetch_for_enabled_providers();
     }
 
@@ -397,15 +575,7 @@ impl Store {
             .preferences
             .providers_preferences
             .iter()
-            // TODO: If the provider requires an API key, we should fetch only if the API key is set
-            .filter(|pp| {
-                pp.enabled
-                    && (pp.api_key.is_some()
-                        || pp.provider_type == ProviderType::MoFa
-                        || pp.provider_type == ProviderType::DeepInquire
-                        || pp.provider_type == ProviderType::OpenAiRealtime
-                        || pp.url.starts_with("http://localhost"))
-            })
+            .filter(|pp| pp.can_auto_fetch())
             .map(|pp| {
                 // Ensure we have an ID to use
                 if !pp.id.is_empty() {
@@ -448,15 +618,155 @@ impl Store {
     pub fn remove_provider(&mut self, provider_id: &ProviderId) {
         self.chats.remove_provider(provider_id);
         self.preferences.remove_provider(provider_id);
+        self.health_monitor
+            .forget(&HealthTarget::Provider(provider_id.clone()));
     }
 
-    pub fn get_provider_icon(&self, provider_name: &str) -> Option<&str> {
-        let base_name = normalize_provider_name(provider_name);
+    /// Check the connections due for it every [`health_monitor::TICK`], for
+    /// as long as the app runs.
+    fn start_health_monitor(&self) {
+        spawn(async move {
+            loop {
+                sleep(health_monitor::TICK).await;
+                app_runner().defer(|app, cx, _| {
+                    if let Some(store) = app.store.as_mut() {
+                        if store.check_health() {
+                            cx.redraw_all();
+                        }
+                    }
+                });
+            }
+        });
+    }
 
-        self.provider_icons
+    /// Re-test the enabled providers and retry the failed MCP network servers
+    /// whose check is due.
+    ///
+    /// Returns `true` if a connection status changed.
+    fn check_health(&mut self) -> bool {
+        let now = Utc::now();
+
+        let due_providers: Vec<Provider> = self
+            .preferences
+            .providers_preferences
             .iter()
-            .find(|icon| icon.to_lowercase().contains(&base_name.to_lowercase()))
-            .map(|s| s.as_str())
+            .filter(|pp| pp.can_auto_fetch())
+            .filter_map(|pp| self.chats.providers.get(&pp.id))
+            .filter(|provider| {
+                self.health_monitor
+                    .start_check_if_due(&HealthTarget::Provider(provider.id.clone()), now)
+            })
+            .cloned()
+            .collect();
+        // Results come back as `ProviderFetchModelsResult` actions.
+        for provider in &due_providers {
+            fetch_models_for_provider(provider, &self.chats.model_registry);
+        }
+
+        let mut changed = false;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut reconnect = Vec::new();
+            let mut ping = Vec::new();
+            for (server_id, state) in &self.mcp_servers_state {
+                let is_network = self
+                    .preferences
+                    .mcp_servers_config
+                    .get_server(server_id)
+                    .map_or(false, |server| server.is_network());
+                let healthy = match &state.status {
+                    McpServerStatus::Connecting => continue,
+                    McpServerStatus::Ready => true,
+                    McpServerStatus::Failed(_) => false,
+                };
+                if !is_network {
+                    continue;
+                }
+
+                let target = HealthTarget::McpServer(server_id.clone());
+                self.health_monitor.observe(target.clone(), healthy, now);
+                if self.health_monitor.start_check_if_due(&target, now) {
+                    if healthy {
+                        ping.push(server_id.clone());
+                    } else {
+                        reconnect.push(server_id.clone());
+                    }
+                }
+            }
+
+            for server_id in ping {
+                let context = self.mcp_context.clone();
+                let generation = self.mcp_servers_generation;
+                spawn(async move {
+                    let result = match tokio::time::timeout(
+                        MCP_PING_TIMEOUT,
+                        context.ping(&server_id),
+                    )
+                    .await
+                    {
+                        Ok(result) => result,
+                        Err(_) => Err(format!(
+                            "The server did not answer a ping within {} seconds",
+                            MCP_PING_TIMEOUT.as_secs()
+                        )),
+                    };
+                    app_runner().defer(move |app, cx, _| {
+                        if let Some(store) = app.store.as_mut() {
+                            if store.mcp_servers_generation == generation {
+                                store.handle_mcp_ping(server_id, result);
+                                cx.redraw_all();
+                            }
+                        }
+                    });
+                });
+            }
+
+            for server_id in reconnect {
+                self.mcp_supervisor
+                    .log(&server_id, McpLogSource::Supervisor, "Reconnecting");
+                self.restart_mcp_server(&server_id);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    /// Record the answer of a connected MCP server to a ping.
+    ///
+    /// A server that stopped answering is marked as failed, so the next
+    /// health check reconnects it with a backoff.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn handle_mcp_ping(&mut self, server_id: String, result: Result<(), String>) {
+        let Some(state) = self.mcp_servers_state.get_mut(&server_id) else {
+            return;
+        };
+        if !matches!(state.status, McpServerStatus::Ready) {
+            return;
+        }
+
+        let target = HealthTarget::McpServer(server_id.clone());
+        match result {
+            Ok(()) => {
+                self.health_monitor.record(target, true, Utc::now());
+            }
+            Err(error) => {
+                self.mcp_supervisor
+                    .log(&server_id, McpLogSource::Supervisor, error.clone());
+                *state = McpServerState {
+                    status: McpServerStatus::Failed(McpServerFailure {
+                        error,
+                        ..Default::default()
+                    }),
+                    tools: Vec::new(),
+                };
+            }
+        }
+    }
+
+    pub fn get_provider_icon(&self, provider_name: &str) -> Option<&str> {
+        provider_icon(&self.provider_icons, provider_name)
     }
 
     pub fn get_mcp_servers_config(&self) -> &McpServersConfig {
@@ -467,41 +777,467 @@ impl Store {
         self.preferences.get_mcp_servers_config_json()
     }
 
-    /// Creates a new MCP tool manager and loads servers asynchronously
-    /// Returns the manager immediately, loading happens in the background
-    pub fn create_and_load_mcp_tool_manager(&self) -> McpManagerClient {
-        let tool_manager = McpManagerClient::new();
+    /// Creates a new MCP client and loads servers asynchronously
+    /// Returns the client immediately, loading happens in the background
+    pub fn create_and_load_mcp_tool_manager(&mut self) -> McpClient {
+        let tool_manager = McpClient::new();
+
+        self.mcp_servers_generation += 1;
+        self.mcp_servers_state.clear();
+        // Processes of the previous client end with it, which is not a crash.
+        self.mcp_supervisor.retire_all();
 
         // Check if MCP servers are globally enabled
         if !self.preferences.get_mcp_servers_enabled() {
-            // Return empty tool manager if globally disabled
+            // Return empty client if globally disabled
+            self.mcp_context.set_tool_manager(None);
+            self.tool_calls.set_client(None);
             return tool_manager;
         }
 
+        self.mcp_context
+            .set_tool_manager(Some(tool_manager.clone()));
+        self.tool_calls.set_client(Some(tool_manager.clone()));
+
         #[cfg(not(target_arch = "wasm32"))]
         {
-            let mcp_config = self.get_mcp_servers_config().clone();
-            tool_manager.set_dangerous_mode_enabled(mcp_config.dangerous_mode_enabled);
+            let servers: Vec<(String, McpServer, u64)> = self
+                .get_mcp_servers_config()
+                .list_enabled_servers()
+                .map(|(id, server)| {
+                    (
+                        id.clone(),
+                        server.clone(),
+                        self.mcp_supervisor.start_instance(id),
+                    )
+                })
+                .collect();
+
+            for (server_id, _, _) in &servers {
+                self.mcp_servers_state
+                    .insert(server_id.clone(), McpServerState::connecting());
+            }
+
+            mcp_requests::install_request_handler(&tool_manager);
             let tool_manager_clone = tool_manager.clone();
+            let context = self.mcp_context.clone();
+            let supervisor = self.mcp_supervisor.clone();
+            let generation = self.mcp_servers_generation;
 
             spawn(async move {
                 // Load MCP servers from configuration
-                for (server_id, server_config) in mcp_config.list_enabled_servers() {
-                    if let Some(transport) = server_config.to_transport() {
-                        match tool_manager_clone.add_server(server_id, transport).await {
-                            Ok(()) => {
-                                ::log::debug!("Successfully added MCP server: {}", server_id);
-                            }
-                            Err(e) => {
-                                ::log::error!("Failed to add MCP server '{}': {}", server_id, e);
-                            }
+                for (server_id, server_config, instance) in servers {
+                    load_mcp_server(
+                        &tool_manager_clone,
+                        &context,
+                        &supervisor,
+                        server_id,
+                        server_config,
+                        instance,
+                        generation,
+                    )
+                    .await;
+                }
+            });
+        }
+
+        tool_manager
+    }
+
+    /// Reconnects a single MCP server on the current client.
+    pub fn restart_mcp_server(&mut self, server_id: &str) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let Some(tool_manager) = self.bot_context.as_ref().and_then(|c| c.tool_manager())
+            else {
+                return;
+            };
+            let Some(server) = self.get_mcp_servers_config().get_server(server_id).cloned() else {
+                return;
+            };
+
+            self.mcp_servers_state
+                .insert(server_id.to_string(), McpServerState::connecting());
+
+            let instance = self.mcp_supervisor.start_instance(server_id);
+            let server_id = server_id.to_string();
+            let context = self.mcp_context.clone();
+            let supervisor = self.mcp_supervisor.clone();
+            let generation = self.mcp_servers_generation;
+            spawn(async move {
+                load_mcp_server(
+                    &tool_manager,
+                    &context,
+                    &supervisor,
+                    server_id,
+                    server,
+                    instance,
+                    generation,
+                )
+                .await;
+            });
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        let _ = server_id;
+    }
+
+    /// Reports a stdio server whose process exited on its own, restarting it
+    /// after a delay growing with each crash in a row.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn handle_mcp_server_exit(&mut self, server_id: &str, instance: u64) {
+        if !self.mcp_supervisor.is_current(server_id, instance) {
+            return;
+        }
+
+        let stderr = self
+            .mcp_supervisor
+            .stderr_tail(server_id, mcp_status::STDERR_TAIL_LINES);
+
+        self.mcp_servers_state.insert(
+            server_id.to_string(),
+            McpServerState {
+                status: McpServerStatus::Failed(McpServerFailure {
+                    error: "The server process exited".to_string(),
+                    stderr,
+                    ..Default::default()
+                }),
+                tools: Vec::new(),
+            },
+        );
+
+        let Some(delay) = self.mcp_supervisor.next_restart_delay(server_id) else {
+            self.mcp_supervisor.log(
+                server_id,
+                McpLogSource::Supervisor,
+                "Process exited too many times in a row, not restarting it",
+            );
+            return;
+        };
+
+        self.mcp_supervisor.log(
+            server_id,
+            McpLogSource::Supervisor,
+            format!("Process exited, restarting in {}s", delay.as_secs()),
+        );
+
+        let server_id = server_id.to_string();
+        spawn(async move {
+            tokio::time::sleep(delay).await;
+            app_runner().defer(move |app, cx, _| {
+                if let Some(store) = app.store.as_mut() {
+                    // Skip if the server was restarted or removed meanwhile.
+                    if store.mcp_supervisor.is_current(&server_id, instance) {
+                        store.restart_mcp_server(&server_id);
+                        cx.redraw_all();
+                    }
+                }
+            });
+        });
+    }
+
+    /// Opens the browser to sign in to an OAuth protected server, reconnecting
+    /// it once the tokens are stored.
+    pub fn sign_in_mcp_server(&mut self, server_id: &str) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let Some(server) = self.get_mcp_servers_config().get_server(server_id).cloned() else {
+                return;
+            };
+            let (Some(url), Some(config)) = (server.url.clone(), server.oauth.clone()) else {
+                return;
+            };
+
+            self.mcp_servers_state
+                .insert(server_id.to_string(), McpServerState::connecting());
+
+            let server_id = server_id.to_string();
+            let generation = self.mcp_servers_generation;
+            spawn(async move {
+                let result = match mcp_oauth::authorize(&url, &config, |authorization_url| {
+                    let _ = robius_open::Uri::new(authorization_url).open();
+                })
+                .await
+                {
+                    Ok(tokens) => mcp_oauth::store_tokens(&server_id, tokens).await,
+                    Err(e) => Err(e),
+                };
+
+                match result {
+                    Ok(()) => app_runner().defer(move |app, cx, _| {
+                        if let Some(store) = app.store.as_mut() {
+                            store.restart_mcp_server(&server_id);
+                            cx.redraw_all();
                         }
+                    }),
+                    Err(e) => {
+                        ::log::error!("Failed to sign in to MCP server '{}': {}", server_id, e);
+                        let state = McpServerState {
+                            status: McpServerStatus::Failed(McpServerFailure {
+                                error: e.to_string(),
+                                ..Default::default()
+                            }),
+                            tools: Vec::new(),
+                        };
+                        report_mcp_server_state(server_id, state, generation);
                     }
                 }
             });
         }
 
-        tool_manager
+        #[cfg(target_arch = "wasm32")]
+        let _ = server_id;
+    }
+
+    /// Forgets the OAuth tokens of a server and reconnects it without them.
+    pub fn sign_out_mcp_server(&mut self, server_id: &str) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let server_id = server_id.to_string();
+            spawn(async move {
+                if let Err(e) = mcp_oauth::forget_tokens(&server_id).await {
+                    ::log::error!("Failed to sign out of MCP server '{}': {}", server_id, e);
+                }
+                app_runner().defer(move |app, cx, _| {
+                    if let Some(store) = app.store.as_mut() {
+                        store.restart_mcp_server(&server_id);
+                        cx.redraw_all();
+                    }
+                });
+            });
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        let _ = server_id;
+    }
+
+    pub fn set_mcp_tool_enabled(&mut self, server_id: &str, tool: &str, enabled: bool) {
+        self.preferences
+            .set_mcp_tool_enabled(server_id, tool, enabled);
+        self.disabled_mcp_tools
+            .update_from_config(&self.preferences.mcp_servers_config);
+    }
+
+    /// Queues a request from an MCP server. Sampling requests from servers
+    /// allowed to sample run right away.
+    pub fn push_mcp_request(
+        &mut self,
+        server_id: String,
+        kind: McpRequestKind,
+        responder: futures::channel::oneshot::Sender<Result<serde_json::Value, String>>,
+    ) {
+        self.next_mcp_request_id += 1;
+        let id = self.next_mcp_request_id;
+
+        let auto_approve = matches!(kind, McpRequestKind::Sampling(..))
+            && self
+                .get_mcp_servers_config()
+                .get_server(&server_id)
+                .is_some_and(|server| server.allow_sampling);
+
+        self.mcp_requests
+            .push(PendingMcpRequest::new(id, server_id, kind, responder));
+
+        if auto_approve {
+            self.run_mcp_sampling(id, self.default_sampling_bot(id));
+        }
+    }
+
+    /// Enabled bots that can answer sampling requests.
+    pub fn sampling_bots(&self) -> Vec<Bot> {
+        let Some(bot_context) = &self.bot_context else {
+            return Vec::new();
+        };
+
+        bot_context
+            .bots()
+            .into_iter()
+            .filter(|bot| {
+                self.chats
+                    .available_bots
+                    .get(&bot.id)
+                    .is_none_or(|provider_bot| provider_bot.enabled)
+            })
+            .collect()
+    }
+
+    /// Bot proposed to answer a sampling request: the one chosen when the
+    /// server was always allowed, then the first matching the hints of the
+    /// server, then the bot of the current chat.
+    pub fn default_sampling_bot(&self, id: u64) -> Option<BotId> {
+        let request = self.mcp_requests.iter().find(|r| r.id == id)?;
+        let McpRequestKind::Sampling(sampling, _) = &request.kind else {
+            return None;
+        };
+
+        let bots = self.sampling_bots();
+        let available = |bot_id: &BotId| bots.iter().any(|bot| bot.id == *bot_id);
+
+        let saved = self
+            .get_mcp_servers_config()
+            .get_server(&request.server_id)
+            .and_then(|server| server.sampling_bot.as_deref())
+            .map(BotId::new)
+            .filter(|bot_id| available(bot_id));
+        let hinted = || sampling.preferred_bot(&bots).map(|bot| bot.id.clone());
+        let current = || {
+            self.chats
+                .get_current_chat()
+                .and_then(|chat| chat.borrow().associated_bot.clone())
+                .filter(|bot_id| available(bot_id))
+        };
+
+        saved
+            .or_else(hinted)
+            .or_else(current)
+            .or_else(|| bots.first().map(|bot| bot.id.clone()))
+    }
+
+    /// Runs an approved sampling request with `bot_id`, optionally letting the
+    /// server send future requests to the same bot without asking.
+    pub fn approve_mcp_sampling(&mut self, id: u64, bot_id: BotId, always: bool) {
+        if always {
+            if let Some(request) = self.mcp_requests.iter().find(|r| r.id == id) {
+                let server_id = request.server_id.clone();
+                self.preferences
+                    .set_mcp_sampling_allowed(&server_id, Some(&bot_id));
+            }
+        }
+
+        self.run_mcp_sampling(id, Some(bot_id));
+    }
+
+    /// Refuses a request and removes it from the queue.
+    pub fn decline_mcp_request(&mut self, id: u64) {
+        let Some(index) = self.mcp_requests.iter().position(|r| r.id == id) else {
+            return;
+        };
+
+        let mut request = self.mcp_requests.remove(index);
+        match request.kind {
+            McpRequestKind::Sampling(..) => {
+                request.respond(Err("The user rejected the sampling request".to_string()))
+            }
+            McpRequestKind::Elicitation(_) => {
+                request.respond(Ok(mcp_requests::elicitation_declined()))
+            }
+        }
+    }
+
+    /// Answers an elicitation request with the values entered in its form.
+    ///
+    /// The request stays queued if the values don't match the schema.
+    pub fn submit_mcp_elicitation(
+        &mut self,
+        id: u64,
+        values: &std::collections::BTreeMap<String, String>,
+    ) -> Result<(), String> {
+        let Some(index) = self.mcp_requests.iter().position(|r| r.id == id) else {
+            return Ok(());
+        };
+
+        let McpRequestKind::Elicitation(elicitation) = &self.mcp_requests[index].kind else {
+            return Ok(());
+        };
+
+        let content = elicitation.content(values)?;
+        let mut request = self.mcp_requests.remove(index);
+        request.respond(Ok(mcp_requests::elicitation_accepted(content)));
+        Ok(())
+    }
+
+    /// Removes a finished sampling request from view.
+    pub fn dismiss_mcp_request(&mut self, id: u64) {
+        self.mcp_requests.retain(|r| r.id != id);
+    }
+
+    /// Sends a sampling request to `bot_id`, stopping the completion once it
+    /// reaches the `maxTokens` of the request.
+    fn run_mcp_sampling(&mut self, id: u64, bot_id: Option<BotId>) {
+        let client = self.bot_context.as_ref().map(|context| context.client());
+
+        let Some(request) = self.mcp_requests.iter_mut().find(|r| r.id == id) else {
+            return;
+        };
+        let McpRequestKind::Sampling(sampling, state) = &mut request.kind else {
+            return;
+        };
+
+        let (Some(bot_id), Some(mut client)) = (bot_id, client) else {
+            let error = "No model is available to answer sampling requests".to_string();
+            *state = SamplingState::Failed(error.clone());
+            request.respond(Err(error));
+            return;
+        };
+
+        *state = SamplingState::Running;
+        let messages = sampling.to_messages(&bot_id);
+        let sampling = sampling.clone();
+
+        spawn(async move {
+            use futures::StreamExt;
+
+            // Each item carries the whole content generated so far.
+            let mut stream = client.send(&bot_id, &messages, &[]);
+            let mut text = String::new();
+            let mut truncated = false;
+            let mut errors = Vec::new();
+            while let Some(result) = stream.next().await {
+                let (content, new_errors) = result.into_value_and_errors();
+                errors.extend(new_errors);
+                let Some(content) = content else {
+                    continue;
+                };
+
+                // Dropping the stream stops the generation.
+                if let Some(cut) = sampling.truncate(&content.text) {
+                    text = cut.to_string();
+                    truncated = true;
+                    break;
+                }
+                text = content.text;
+            }
+
+            let result = if !errors.is_empty() {
+                Err(errors
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            } else if text.trim().is_empty() {
+                Err("The model returned an empty response".to_string())
+            } else {
+                Ok(text)
+            };
+
+            app_runner().defer(move |app, cx, _| {
+                let Some(store) = app.store.as_mut() else {
+                    return;
+                };
+                let Some(request) = store.mcp_requests.iter_mut().find(|r| r.id == id) else {
+                    return;
+                };
+
+                let new_state = match result {
+                    Ok(text) => {
+                        request.respond(Ok(mcp_requests::sampling_result(
+                            &text,
+                            bot_id.id(),
+                            truncated,
+                        )));
+                        SamplingState::Done(text)
+                    }
+                    Err(error) => {
+                        request.respond(Err(error.clone()));
+                        SamplingState::Failed(error)
+                    }
+                };
+                if let McpRequestKind::Sampling(_, state) = &mut request.kind {
+                    *state = new_state;
+                }
+                cx.redraw_all();
+            });
+        });
     }
 
     pub fn update_mcp_servers_from_json(&mut self, json: &str) -> Result<(), serde_json::Error> {
@@ -511,7 +1247,30 @@ impl Store {
         Ok(())
     }
 
+    /// Merge servers imported from another application, restarting the servers
+    /// if any was added or replaced.
+    pub fn import_mcp_servers(
+        &mut self,
+        import: McpImport,
+        replace_conflicts: bool,
+    ) -> McpMergeReport {
+        let report = self
+            .preferences
+            .import_mcp_servers(import, replace_conflicts);
+        if report.changed() {
+            self.update_mcp_tool_manager();
+        }
+        report
+    }
+
     pub fn update_mcp_tool_manager(&mut self) {
+        self.tool_policy
+            .set(self.preferences.mcp_servers_config.effective_tool_policy());
+        self.disabled_mcp_tools
+            .update_from_config(&self.preferences.mcp_servers_config);
+        self.tool_calls
+            .set_timeouts(self.preferences.mcp_servers_config.tool_timeouts());
+
         let new_tool_manager = self.create_and_load_mcp_tool_manager();
         if let Some(ref mut bot_context_mut) = self.bot_context {
             bot_context_mut.set_tool_manager(new_tool_manager);
@@ -530,6 +1289,99 @@ impl Store {
         self.update_mcp_tool_manager();
     }
 
+    /// Save the gateway configuration and start, stop or restart the server.
+    pub fn update_gateway_config<F>(&mut self, update_fn: F)
+    where
+        F: FnOnce(&mut GatewayConfig),
+    {
+        self.preferences.update_gateway_config(update_fn);
+        self.gateway.apply_config(&self.preferences.gateway);
+    }
+
+    /// Fetch the models of the enabled MolyServer provider, as its files changed.
+    fn fetch_moly_server_models(&mut self) {
+        let mut address = self.moly_client.address().clone();
+        address.push_str(MOLY_SERVER_VERSION_EXTENSION);
+
+        let provider = self
+            .chats
+            .providers
+            .values()
+            .find(|p| p.url == address && p.provider_type == ProviderType::MolyServer && p.enabled)
+            .cloned();
+
+        if let Some(provider) = provider {
+            self.chats
+                .test_provider_and_fetch_models(&provider.id, &mut self.provider_syncing_status);
+        }
+    }
+
+    /// Start MolyServer if it isn't running and the MolyServer provider is enabled.
+    pub fn start_moly_server(&mut self) {
+        if !self.is_local_moly_server_enabled() {
+            self.moly_server_process.stop();
+            return;
+        }
+
+        let reserved_ports = self
+            .preferences
+            .gateway
+            .enabled
+            .then_some(self.preferences.gateway.port)
+            .into_iter()
+            .collect();
+        self.moly_server_process.start(
+            self.moly_servers.local_client(),
+            &self.preferences.moly_server_process,
+            reserved_ports,
+        );
+    }
+
+    /// Save the configuration of the MolyServer process and start it again.
+    pub fn update_moly_server_process_config<F>(&mut self, update_fn: F)
+    where
+        F: FnOnce(&mut MolyServerProcessConfig),
+    {
+        self.preferences
+            .update_moly_server_process_config(update_fn);
+        self.start_moly_server();
+    }
+
+    /// Reach MolyServer on `port`, along with the local MolyServer providers.
+    pub fn use_moly_server_port(&mut self, port: u16) {
+        let local_client = self.moly_servers.local_client();
+        let Some(address) = moly_server_process::local_url_with_port(&local_client.address(), port)
+        else {
+            return;
+        };
+        local_client.set_address(address);
+
+        let providers: Vec<Provider> = self
+            .chats
+            .providers
+            .values()
+            .filter(|p| p.provider_type == ProviderType::MolyServer)
+            .cloned()
+            .collect();
+        for mut provider in providers {
+            match moly_server_process::local_url_with_port(&provider.url, port) {
+                Some(url) if url != provider.url => {
+                    provider.url = url;
+                    self.insert_or_update_provider(&provider);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The MolyServer started by Moly answers, so its data can be pulled.
+    pub fn handle_moly_server_ready(&mut self) {
+        if self.moly_servers.is_local_active() {
+            self.sync_with_moly_server();
+            self.fetch_moly_server_models();
+        }
+    }
+
     /// Triggers a bot context reload by clearing it.
     /// The ChatScreen will automatically recreate it on the next event,
     /// applying updated filters (like enabled status changes).
@@ -540,6 +1392,118 @@ impl Store {
     }
 }
 
+/// Connects a server to the MCP client and reports the result to the store.
+///
+/// Adding a server under an id already in use replaces the previous connection.
+/// Once connected, the prompts and resources of the server are loaded into `context`.
+#[cfg(not(target_arch = "wasm32"))]
+async fn load_mcp_server(
+    tool_manager: &McpClient,
+    context: &McpContext,
+    supervisor: &McpSupervisor,
+    server_id: String,
+    server: McpServer,
+    instance: u64,
+    generation: u64,
+) {
+    let auth = match mcp_oauth::connection_auth(&server_id, &server).await {
+        Ok(auth) => auth,
+        Err(error) => {
+            supervisor.log(&server_id, McpLogSource::Supervisor, error.clone());
+            let state = McpServerState {
+                status: McpServerStatus::Failed(McpServerFailure {
+                    error,
+                    ..Default::default()
+                }),
+                tools: Vec::new(),
+            };
+            report_mcp_server_state(server_id, state, generation);
+            return;
+        }
+    };
+
+    supervisor.log(&server_id, McpLogSource::Supervisor, "Starting");
+    let state = match supervisor.transport(&server_id, &server, instance) {
+        Ok(Some(transport)) => {
+            let transport = match auth {
+                Some(auth) => transport.with_auth(auth),
+                None => transport,
+            };
+            let added = tokio::time::timeout(
+                mcp_supervisor::STARTUP_TIMEOUT,
+                tool_manager.add_server(&server_id, transport),
+            )
+            .await;
+
+            let error = match added {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some(e),
+                Err(_) => Some(McpConnectError::from(format!(
+                    "The server did not start within {} seconds",
+                    mcp_supervisor::STARTUP_TIMEOUT.as_secs()
+                ))),
+            };
+
+            match error {
+                None => {
+                    ::log::debug!("Successfully added MCP server: {}", server_id);
+                    supervisor.mark_ready(&server_id);
+                    supervisor.log(&server_id, McpLogSource::Supervisor, "Connected");
+                    context.load_server(&server_id).await;
+                    McpServerState {
+                        status: McpServerStatus::Ready,
+                        tools: mcp_status::server_tools(tool_manager, &server_id),
+                    }
+                }
+                Some(error) => {
+                    ::log::error!("Failed to add MCP server '{}': {}", server_id, error);
+                    supervisor.log(&server_id, McpLogSource::Protocol, error.message.clone());
+                    McpServerState {
+                        status: McpServerStatus::Failed(McpServerFailure {
+                            error: error.message,
+                            exit_code: error.exit_code,
+                            stderr: supervisor
+                                .stderr_tail(&server_id, mcp_status::STDERR_TAIL_LINES),
+                        }),
+                        tools: Vec::new(),
+                    }
+                }
+            }
+        }
+        Ok(None) => McpServerState {
+            status: McpServerStatus::Failed(McpServerFailure {
+                error: "Missing \"command\" or \"url\"".to_string(),
+                ..Default::default()
+            }),
+            tools: Vec::new(),
+        },
+        Err(error) => {
+            supervisor.log(&server_id, McpLogSource::Supervisor, error.clone());
+            McpServerState {
+                status: McpServerStatus::Failed(McpServerFailure {
+                    error,
+                    ..Default::default()
+                }),
+                tools: Vec::new(),
+            }
+        }
+    };
+
+    report_mcp_server_state(server_id, state, generation);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn report_mcp_server_state(server_id: String, state: McpServerState, generation: u64) {
+    app_runner().defer(move |app, cx, _| {
+        if let Some(store) = app.store.as_mut() {
+            if store.mcp_servers_generation == generation {
+                store.mcp_servers_state.insert(server_id, state);
+                cx.redraw_all();
+            }
+        }
+    });
+}
+
 /// Extracts the base provider name from provider variants for icon matching.
 ///
 /// This allows provider variants like "OpenAI Realtime" and "OpenAI Image"
@@ -555,3 +1519,13 @@ pub fn normalize_provider_name(name: &str) -> String {
         .unwrap_or(name)
         .to_lowercase()
 }
+
+/// The icon among `icons` named after the provider, if any.
+pub fn provider_icon<'a>(icons: &'a [String], provider_name: &str) -> Option<&'a str> {
+    let base_name = normalize_provider_name(provider_name);
+
+    icons
+        .iter()
+        .find(|icon| icon.to_lowercase().contains(&base_name.to_lowercase()))
+        .map(|s| s.as_str())
+}
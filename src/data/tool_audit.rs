@@ -0,0 +1,585 @@
+//! Persistent, append-only log of the tool calls made by models.
+//!
+//! Records are kept as JSON lines in numbered segments under `tool_audit/` in the
+//! app data directory. Each process appends to segments of its own, named after
+//! their number and the process, so instances running at the same time never
+//! write to the same file. A new segment is started every [`SEGMENT_RECORDS`]
+//! lines and only the last [`MAX_SEGMENTS`] are kept. Records are never
+//! modified once written, and don't depend on the chats, so deleting a chat
+//! leaves them intact.
+//!
+//! A call is recorded as soon as it is decided. The outcome of an approved call
+//! is appended as a separate line once it finishes, so calls interrupted by a
+//! crash or an exit stay in the log, without an outcome. Segments are only read
+//! when the log is viewed or exported.
+
+use chrono::{DateTime, Local, Utc};
+use futures::StreamExt;
+use futures::channel::{mpsc, oneshot};
+use moly_kit::aitk::utils::asynchronous::{BoxPlatformSendFuture, spawn};
+use moly_kit::utils::mcp::split_namespaced;
+use moly_kit::utils::tool_audit::ToolAuditEvent;
+use moly_kit::utils::tool_policy::ToolApproval;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+use super::chats::chat::ChatId;
+use crate::shared::utils::filesystem;
+
+const AUDIT_DIR: &str = "tool_audit";
+
+/// Records per segment file.
+const SEGMENT_RECORDS: usize = 1000;
+
+/// Segments kept, older ones are deleted.
+const MAX_SEGMENTS: usize = 10;
+
+/// Results longer than this are cut, the full result stays in the chat.
+const MAX_RESULT_CHARS: usize = 4000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolAuditRecord {
+    /// RFC 3339 time at which the call was decided.
+    pub timestamp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<ChatId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    pub tool: String,
+    pub arguments: Value,
+    pub approved: bool,
+    pub approval: ToolApproval,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub realtime: bool,
+    /// Set on approved calls, whose outcome is appended later.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Outcome of an approved call, appended once it finishes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ToolAuditOutcome {
+    /// RFC 3339 time at which the call finished.
+    timestamp: String,
+    call_id: String,
+    duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// A line of a segment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum AuditLine {
+    Record(ToolAuditRecord),
+    Outcome(ToolAuditOutcome),
+}
+
+impl ToolAuditRecord {
+    /// The time of the record in the local timezone, as `YYYY-MM-DD HH:MM:SS`.
+    pub fn local_time(&self) -> String {
+        DateTime::parse_from_rfc3339(&self.timestamp)
+            .map(|time| {
+                time.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|_| self.timestamp.clone())
+    }
+
+    /// How the call was decided, like "Allowed by policy".
+    pub fn decision(&self) -> &'static str {
+        match (self.approved, self.approval) {
+            (true, ToolApproval::Manual) => "Allowed by the user",
+            (true, ToolApproval::Policy) => "Allowed by policy",
+            (true, ToolApproval::DangerousMode) => "Allowed by dangerous mode",
+            (false, ToolApproval::Manual) => "Denied by the user",
+            (false, _) => "Denied by policy",
+        }
+    }
+
+    /// Whether this is an approved call without a recorded outcome.
+    pub fn lacks_outcome(&self) -> bool {
+        self.call_id.is_some()
+            && self.duration_ms.is_none()
+            && self.result.is_none()
+            && self.error.is_none()
+    }
+
+    /// Whether `query` appears in the server, tool, bot or chat of the record.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        let chat_id = self.chat_id.map(|id| id.to_string());
+        [
+            self.server.as_deref(),
+            Some(self.tool.as_str()),
+            self.bot.as_deref(),
+            chat_id.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|field| field.to_lowercase().contains(&query))
+    }
+}
+
+enum WriteOp {
+    Append(PathBuf, String),
+    Remove(PathBuf),
+    /// Answered once the previous operations are done.
+    Flush(oneshot::Sender<()>),
+}
+
+/// The segments of the log, and the calls still running.
+pub struct ToolAuditLog {
+    /// Segments written before this one, oldest first.
+    segments: VecDeque<Segment>,
+    /// Segment this process appends to, once it recorded something.
+    current: Option<Segment>,
+    /// Lines appended to the current segment.
+    current_lines: usize,
+    /// Approved calls waiting for their outcome, with the time they started.
+    running: HashMap<String, DateTime<Utc>>,
+    /// Bumped on every append, so views know to read the log again.
+    revision: u64,
+    /// Writes go through a single task so they land in order.
+    writer: mpsc::UnboundedSender<WriteOp>,
+}
+
+impl ToolAuditLog {
+    pub async fn load() -> Self {
+        let fs = filesystem::global();
+        let mut names: Vec<(u64, String)> = fs
+            .list(Path::new(AUDIT_DIR))
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|name| Some((segment_number(&name)?, name)))
+            .collect();
+        names.sort();
+
+        let (writer, mut queue) = mpsc::unbounded::<WriteOp>();
+        spawn(async move {
+            let fs = filesystem::global();
+            while let Some(op) = queue.next().await {
+                let result = match op {
+                    WriteOp::Append(path, content) => fs.append(&path, content.as_bytes()).await,
+                    WriteOp::Remove(path) => fs.remove(&path).await,
+                    WriteOp::Flush(done) => {
+                        let _ = done.send(());
+                        Ok(())
+                    }
+                };
+                if let Err(e) = result {
+                    ::log::error!("Failed to update the tool audit log: {:?}", e);
+                }
+            }
+        });
+
+        let mut log = Self {
+            segments: VecDeque::new(),
+            current: None,
+            current_lines: 0,
+            running: HashMap::new(),
+            revision: 0,
+            writer,
+        };
+
+        let excess = names.len().saturating_sub(MAX_SEGMENTS);
+        for (_, name) in names.drain(..excess) {
+            log.send(WriteOp::Remove(Path::new(AUDIT_DIR).join(name)));
+        }
+
+        log.segments = names
+            .into_iter()
+            .map(|(number, name)| Segment {
+                number,
+                path: Path::new(AUDIT_DIR).join(name),
+            })
+            .collect();
+
+        log
+    }
+
+    pub fn handle_event(
+        &mut self,
+        chat_id: Option<ChatId>,
+        bot: Option<String>,
+        event: ToolAuditEvent,
+    ) {
+        match event {
+            ToolAuditEvent::Decided {
+                call,
+                approved,
+                approval,
+            } => {
+                let (server, tool) = split_namespaced(&call.name);
+                let now = Utc::now();
+                let record = ToolAuditRecord {
+                    timestamp: now.to_rfc3339(),
+                    chat_id,
+                    bot,
+                    server: server.map(str::to_string),
+                    tool: tool.to_string(),
+                    arguments: call.arguments,
+                    approved,
+                    approval,
+                    realtime: call.realtime,
+                    call_id: approved.then(|| call.id.clone()),
+                    duration_ms: None,
+                    result: None,
+                    error: None,
+                };
+
+                if approved {
+                    self.running.insert(call.id, now);
+                }
+                self.append(AuditLine::Record(record));
+            }
+            ToolAuditEvent::Finished {
+                id,
+                content,
+                is_error,
+            } => {
+                let Some(started) = self.running.remove(&id) else {
+                    return;
+                };
+
+                let now = Utc::now();
+                let content = truncate(content);
+                let (result, error) = if is_error {
+                    (None, Some(content))
+                } else {
+                    (Some(content), None)
+                };
+                self.append(AuditLine::Outcome(ToolAuditOutcome {
+                    timestamp: now.to_rfc3339(),
+                    call_id: id,
+                    duration_ms: (now - started).num_milliseconds().max(0) as u64,
+                    result,
+                    error,
+                }));
+            }
+        }
+    }
+
+    /// Changes every time something is recorded.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn is_running(&self, call_id: &str) -> bool {
+        self.running.contains_key(call_id)
+    }
+
+    /// Records matching `query` (see [`ToolAuditRecord::matches`]), newest first,
+    /// with the outcome of approved calls.
+    ///
+    /// Segments are read newest first until `limit` records are found.
+    pub fn recent(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> BoxPlatformSendFuture<'static, Vec<ToolAuditRecord>> {
+        let paths = self.paths();
+        let written = self.written();
+        let query = query.to_string();
+
+        Box::pin(async move {
+            let _ = written.await;
+            let mut outcomes = HashMap::new();
+            let mut records = Vec::new();
+            for path in paths.iter().rev() {
+                let content = read_segment(path).await;
+                collect_recent(&content, &query, &mut outcomes, &mut records);
+                if records.len() >= limit {
+                    break;
+                }
+            }
+            records.truncate(limit);
+            records
+        })
+    }
+
+    /// Every line of the log as written, oldest first.
+    pub fn export(&self) -> BoxPlatformSendFuture<'static, String> {
+        let paths = self.paths();
+        let written = self.written();
+
+        Box::pin(async move {
+            let _ = written.await;
+            let mut content = String::new();
+            for path in &paths {
+                content.push_str(&read_segment(path).await);
+            }
+            content
+        })
+    }
+
+    /// Wait for the records appended so far to be written, like before exiting.
+    pub async fn flush(&self) {
+        let _ = self.written().await;
+    }
+
+    /// Resolves once the records appended so far are written.
+    fn written(&self) -> oneshot::Receiver<()> {
+        let (done, written) = oneshot::channel();
+        self.send(WriteOp::Flush(done));
+        written
+    }
+
+    /// Paths of the segments, oldest first.
+    fn paths(&self) -> Vec<PathBuf> {
+        self.segments
+            .iter()
+            .chain(&self.current)
+            .map(|segment| segment.path.clone())
+            .collect()
+    }
+
+    fn append(&mut self, line: AuditLine) {
+        let full = self.current.is_none() || self.current_lines >= SEGMENT_RECORDS;
+        if full {
+            let number = self
+                .segments
+                .iter()
+                .chain(&self.current)
+                .map(|segment| segment.number)
+                .max()
+                .map_or(1, |number| number + 1);
+            let segment = Segment {
+                number,
+                path: segment_path(number),
+            };
+            self.segments.extend(self.current.replace(segment));
+            self.current_lines = 0;
+
+            while self.segments.len() >= MAX_SEGMENTS {
+                if let Some(oldest) = self.segments.pop_front() {
+                    self.send(WriteOp::Remove(oldest.path));
+                }
+            }
+        }
+
+        let Ok(line) = serde_json::to_string(&line) else {
+            return;
+        };
+        let path = self.current.as_ref().unwrap().path.clone();
+        self.current_lines += 1;
+        self.revision += 1;
+        self.send(WriteOp::Append(path, line + "\n"));
+    }
+
+    fn send(&self, op: WriteOp) {
+        let _ = self.writer.unbounded_send(op);
+    }
+}
+
+/// A segment file.
+struct Segment {
+    number: u64,
+    path: PathBuf,
+}
+
+/// Path of a new segment of this process.
+fn segment_path(number: u64) -> PathBuf {
+    Path::new(AUDIT_DIR).join(format!("{:06}-{}.jsonl", number, process_tag()))
+}
+
+/// Number of a segment given its file name, like `000012-4242.jsonl`.
+fn segment_number(name: &str) -> Option<u64> {
+    let stem = name.strip_suffix(".jsonl")?;
+    let number = stem.split_once('-').map_or(stem, |(number, _)| number);
+    number.parse().ok()
+}
+
+/// Tells apart the segments of processes running at the same time.
+#[cfg(not(target_arch = "wasm32"))]
+fn process_tag() -> String {
+    std::process::id().to_string()
+}
+
+/// Only one instance runs per browser profile.
+#[cfg(target_arch = "wasm32")]
+fn process_tag() -> String {
+    "web".to_string()
+}
+
+async fn read_segment(path: &Path) -> String {
+    filesystem::global()
+        .read_string(path)
+        .await
+        .unwrap_or_else(|e| {
+            ::log::error!("Failed to read tool audit segment {:?}: {:?}", path, e);
+            String::new()
+        })
+}
+
+/// Parse JSON lines, skipping lines that can't be read, like a line cut short
+/// by a crash.
+fn parse_lines(content: &str) -> impl DoubleEndedIterator<Item = AuditLine> + '_ {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+}
+
+/// Add the records of a segment matching `query` to `records`, newest first.
+///
+/// Outcomes come after their call, so reading backwards, `outcomes` holds the
+/// outcomes of this and newer segments by the time their call is read.
+fn collect_recent(
+    content: &str,
+    query: &str,
+    outcomes: &mut HashMap<String, ToolAuditOutcome>,
+    records: &mut Vec<ToolAuditRecord>,
+) {
+    for line in parse_lines(content).rev() {
+        match line {
+            AuditLine::Outcome(outcome) => {
+                outcomes.insert(outcome.call_id.clone(), outcome);
+            }
+            AuditLine::Record(mut record) => {
+                if !query.is_empty() && !record.matches(query) {
+                    continue;
+                }
+                if let Some(outcome) = record.call_id.as_ref().and_then(|id| outcomes.remove(id)) {
+                    record.duration_ms = Some(outcome.duration_ms);
+                    record.result = outcome.result;
+                    record.error = outcome.error;
+                }
+                records.push(record);
+            }
+        }
+    }
+}
+
+fn truncate(text: String) -> String {
+    match text.char_indices().nth(MAX_RESULT_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_records_skips_broken_lines() {
+        let record = ToolAuditRecord {
+            timestamp: "2025-01-01T10:00:00+00:00".to_string(),
+            chat_id: Some(42),
+            bot: Some("Model".to_string()),
+            server: Some("filesystem".to_string()),
+            tool: "read_file".to_string(),
+            arguments: serde_json::json!({ "path": "/tmp/a" }),
+            approved: true,
+            approval: ToolApproval::Policy,
+            realtime: false,
+            call_id: None,
+            duration_ms: Some(12),
+            result: Some("content".to_string()),
+            error: None,
+        };
+        let line = serde_json::to_string(&record).unwrap();
+        let content = format!("{}\n{}\n\n{{\"timestamp\": \"cut", line, line);
+
+        let mut records = Vec::new();
+        collect_recent(&content, "", &mut HashMap::new(), &mut records);
+        assert_eq!(records, vec![record.clone(), record.clone()]);
+        assert!(records[0].matches("FILESYSTEM"));
+        assert!(records[0].matches("42"));
+        assert!(!records[0].matches("git"));
+        assert_eq!(records[0].decision(), "Allowed by policy");
+    }
+
+    #[test]
+    fn test_outcomes_join_their_call() {
+        let call = |id: &str, tool: &str| {
+            AuditLine::Record(ToolAuditRecord {
+                timestamp: "2025-01-01T10:00:00+00:00".to_string(),
+                chat_id: None,
+                bot: None,
+                server: Some("git".to_string()),
+                tool: tool.to_string(),
+                arguments: serde_json::json!({}),
+                approved: true,
+                approval: ToolApproval::Manual,
+                realtime: false,
+                call_id: Some(id.to_string()),
+                duration_ms: None,
+                result: None,
+                error: None,
+            })
+        };
+        let outcome = AuditLine::Outcome(ToolAuditOutcome {
+            timestamp: "2025-01-01T10:00:01+00:00".to_string(),
+            call_id: "call_1".to_string(),
+            duration_ms: 1000,
+            result: None,
+            error: Some("failed".to_string()),
+        });
+        let lines = |lines: &[&AuditLine]| {
+            lines
+                .iter()
+                .map(|line| serde_json::to_string(line).unwrap() + "\n")
+                .collect::<String>()
+        };
+
+        // The call in an older segment than its outcome, and a call that was
+        // interrupted.
+        let older = lines(&[&call("call_1", "clone")]);
+        let newer = lines(&[&call("call_2", "push"), &outcome]);
+
+        let mut outcomes = HashMap::new();
+        let mut records = Vec::new();
+        collect_recent(&newer, "", &mut outcomes, &mut records);
+        collect_recent(&older, "", &mut outcomes, &mut records);
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].tool, "push");
+        assert!(records[0].lacks_outcome());
+        assert_eq!(records[1].tool, "clone");
+        assert_eq!(records[1].duration_ms, Some(1000));
+        assert_eq!(records[1].error.as_deref(), Some("failed"));
+        assert!(!records[1].lacks_outcome());
+
+        let mut records = Vec::new();
+        collect_recent(&newer, "clone", &mut HashMap::new(), &mut records);
+        assert!(records.is_empty());
+    }
+
+    #[test]
+    fn test_segment_number() {
+        assert_eq!(segment_number("000012.jsonl"), Some(12));
+        assert_eq!(segment_number("000013-4242.jsonl"), Some(13));
+        assert_eq!(segment_number("notes.txt"), None);
+        assert_eq!(
+            segment_number(&segment_path(7).file_name().unwrap().to_string_lossy()),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short".to_string()), "short");
+        let long = "é".repeat(MAX_RESULT_CHARS + 5);
+        assert_eq!(truncate(long).chars().count(), MAX_RESULT_CHARS + 1);
+    }
+}
@@ -0,0 +1,117 @@
+//! Client wrapper hiding the MCP tools disabled in the settings, and all of
+//! them from models known not to support tools.
+
+use moly_kit::prelude::*;
+use moly_kit::utils::mcp::split_namespaced;
+use moly_kit::utils::tool_calls::DisabledCheck;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use super::mcp_servers::McpServersConfig;
+use super::model_registry::ModelRegistry;
+
+/// Disabled tool names by server id, shared between the store and the clients.
+#[derive(Clone, Default)]
+pub struct DisabledTools(Arc<Mutex<HashMap<String, HashSet<String>>>>);
+
+impl DisabledTools {
+    pub fn from_config(config: &McpServersConfig) -> Self {
+        let disabled = Self::default();
+        disabled.update_from_config(config);
+        disabled
+    }
+
+    pub fn update_from_config(&self, config: &McpServersConfig) {
+        *self.0.lock().unwrap() = config
+            .servers
+            .iter()
+            .filter(|(_, server)| !server.disabled_tools.is_empty())
+            .map(|(id, server)| (id.clone(), server.disabled_tools.iter().cloned().collect()))
+            .collect();
+    }
+
+    /// A check for [`ToolCalls`](moly_kit::utils::tool_calls::ToolCalls),
+    /// following the changes of these disabled tools.
+    pub fn check(&self) -> Arc<DisabledCheck> {
+        let disabled = self.clone();
+        Arc::new(move |namespaced_name| disabled.is_disabled(namespaced_name))
+    }
+
+    pub fn is_disabled(&self, namespaced_name: &str) -> bool {
+        let (server, tool) = split_namespaced(namespaced_name);
+        let Some(server) = server else {
+            return false;
+        };
+
+        self.0
+            .lock()
+            .unwrap()
+            .get(server)
+            .is_some_and(|tools| tools.contains(tool))
+    }
+}
+
+/// Wraps a client to remove disabled tools from every request.
+pub struct ToolFilter {
+    inner: Box<dyn BotClient>,
+    disabled: DisabledTools,
+    registry: ModelRegistry,
+}
+
+impl Clone for ToolFilter {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone_box(),
+            disabled: self.disabled.clone(),
+            registry: self.registry.clone(),
+        }
+    }
+}
+
+impl ToolFilter {
+    pub fn new(
+        inner: impl BotClient + 'static,
+        disabled: DisabledTools,
+        registry: ModelRegistry,
+    ) -> Self {
+        Self {
+            inner: Box::new(inner),
+            disabled,
+            registry,
+        }
+    }
+}
+
+impl BotClient for ToolFilter {
+    fn bots(&mut self) -> BoxPlatformSendFuture<'static, ClientResult<Vec<Bot>>> {
+        self.inner.bots()
+    }
+
+    fn clone_box(&self) -> Box<dyn BotClient> {
+        Box::new(self.clone())
+    }
+
+    fn send(
+        &mut self,
+        bot_id: &BotId,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> BoxPlatformSendStream<'static, ClientResult<MessageContent>> {
+        let supports_tools = self
+            .registry
+            .lookup_bot(bot_id)
+            .and_then(|metadata| metadata.tools)
+            .unwrap_or(true);
+        if !supports_tools {
+            return self.inner.send(bot_id, messages, &[]);
+        }
+
+        let tools: Vec<Tool> = tools
+            .iter()
+            .filter(|tool| !self.disabled.is_disabled(&tool.name))
+            .cloned()
+            .collect();
+
+        self.inner.send(bot_id, messages, &tools)
+    }
+}
@@ -1,12 +1,15 @@
 use crate::{
-    data::downloads::download::DownloadFileAction,
+    data::downloads::{DownloadListing, download::DownloadFileAction},
     shared::{
         actions::DownloadAction,
-        utils::{format_model_downloaded_size, format_model_size},
+        utils::{
+            format_model_downloaded_size, format_model_size, format_time_left,
+            format_transfer_speed,
+        },
     },
 };
 use makepad_widgets::*;
-use moly_protocol::data::{FileId, PendingDownload, PendingDownloadsStatus};
+use moly_protocol::data::{FileId, PendingDownloadsStatus};
 
 script_mod! {
     use mod.prelude.widgets.*
@@ -223,7 +226,8 @@ impl Widget for DownloadItem {
     }
 
     fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
-        let download = scope.data.get::<PendingDownload>().unwrap();
+        let listing = scope.data.get::<DownloadListing>().unwrap();
+        let download = &listing.download;
         self.file_id = Some(download.file.id.clone());
 
         self.label(cx, ids!(filename))
@@ -268,7 +272,12 @@ impl Widget for DownloadItem {
             PendingDownloadsStatus::Downloading => {
                 let downloading_color = vec4(0.035, 0.572, 0.314, 1.0); //#099250
 
-                label.set_text(cx, &format!("Downloading {:.1}%", download.progress));
+                let status = if listing.reconnecting {
+                    "Reconnecting"
+                } else {
+                    "Downloading"
+                };
+                label.set_text(cx, &format!("{} {:.1}%", status, download.progress));
                 script_apply_eval!(cx, label, {
                     draw_text +: { color: #(downloading_color) }
                 });
@@ -287,7 +296,11 @@ impl Widget for DownloadItem {
             PendingDownloadsStatus::Paused => {
                 let paused_color = vec4(0.4, 0.44, 0.52, 1.0); //#667085
 
-                label.set_text(cx, &format!("Paused {:.1}%", download.progress));
+                let status = match listing.queue_position {
+                    Some(position) => format!("Queued #{}", position),
+                    None => "Paused".to_string(),
+                };
+                label.set_text(cx, &format!("{} {:.1}%", status, download.progress));
                 script_apply_eval!(cx, label, {
                     draw_text +: { color: #(paused_color) }
                 });
@@ -298,8 +311,10 @@ impl Widget for DownloadItem {
                     draw_bg +: { color: #(paused_color) }
                 });
 
-                self.button(cx, ids!(pause_button)).set_visible(cx, false);
-                self.button(cx, ids!(play_button)).set_visible(cx, true);
+                // A queued download starts by itself, it can only be taken out of the queue.
+                let queued = listing.queue_position.is_some();
+                self.button(cx, ids!(pause_button)).set_visible(cx, queued);
+                self.button(cx, ids!(play_button)).set_visible(cx, !queued);
                 self.button(cx, ids!(retry_button)).set_visible(cx, false);
                 self.button(cx, ids!(cancel_button)).set_visible(cx, true);
             }
@@ -328,8 +343,14 @@ impl Widget for DownloadItem {
         let downloaded_size = format_model_downloaded_size(&download.file.size, download.progress)
             .unwrap_or("-".to_string());
 
-        self.label(cx, ids!(downloaded_size))
-            .set_text(cx, &format!("{} / {}", downloaded_size, total_size));
+        let mut sizes = format!("{} / {}", downloaded_size, total_size);
+        if let Some((speed, eta)) = listing.speed_and_eta {
+            sizes.push_str(&format!(" | {}", format_transfer_speed(speed)));
+            if let Some(eta) = eta {
+                sizes.push_str(&format!(" | {} left", format_time_left(eta)));
+            }
+        }
+        self.label(cx, ids!(downloaded_size)).set_text(cx, &sizes);
 
         self.view.draw_walk(cx, scope, walk)
     }
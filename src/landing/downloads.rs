@@ -49,6 +49,14 @@ script_mod! {
             text: "1 paused"
         }
 
+        queued_count := Label {
+            draw_text +: {
+                text_style: theme.font_regular {font_size: 9}
+                color: #667085
+            }
+            text: ""
+        }
+
         failed_count := Label {
             draw_text +: {
                 text_style: theme.font_regular {font_size: 9}
@@ -59,6 +67,34 @@ script_mod! {
 
         VerticalFiller {}
 
+        View {
+            width: Fit
+            height: Fit
+            spacing: 8
+            align: Align {y: 0.5}
+
+            Label {
+                draw_text +: {
+                    text_style: theme.font_regular {font_size: 9}
+                    color: #667085
+                }
+                text: "At the same time"
+            }
+
+            max_concurrent := DropDownFlat {
+                width: Fit
+                height: Fit
+                padding: Inset {top: 4 bottom: 4 left: 8 right: 20}
+                draw_text +: {
+                    text_style: theme.font_regular {font_size: 9}
+                    get_color: fn() -> vec4 {
+                        return #344054
+                    }
+                }
+                labels: ["1", "2", "3", "4", "5", "6"]
+            }
+        }
+
         collapse := CollapseButton {
             draw_icon +: { rotation_angle: 180.0 }
         }
@@ -134,6 +170,9 @@ pub struct Downloads {
     animator: Animator,
 }
 
+/// Choices of the number of downloads running at the same time.
+const MAX_CONCURRENT_CHOICES: usize = 6;
+
 impl Widget for Downloads {
     fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
         self.view.handle_event(cx, event, scope);
@@ -174,13 +213,28 @@ impl Widget for Downloads {
         self.label(cx, ids!(downloading_count))
             .set_text(cx, &format!("{} downloading", download_count));
 
+        let queued_count = store.downloads.queued_count();
         let paused_count = pending_downloads
             .iter()
-            .filter(|d| matches!(d.status, PendingDownloadsStatus::Paused))
+            .filter(|d| {
+                matches!(d.status, PendingDownloadsStatus::Paused)
+                    && !store.downloads.is_queued(&d.file.id)
+            })
             .count();
         self.label(cx, ids!(paused_count))
             .set_text(cx, &format!("{} paused", paused_count));
 
+        if queued_count > 0 {
+            self.label(cx, ids!(queued_count))
+                .set_text(cx, &format!("{} queued", queued_count));
+        } else {
+            self.label(cx, ids!(queued_count)).set_text(cx, "");
+        }
+
+        let max_concurrent = store.downloads.max_concurrent().min(MAX_CONCURRENT_CHOICES);
+        self.drop_down(cx, ids!(max_concurrent))
+            .set_selected_item(cx, max_concurrent - 1);
+
         let failed_count = pending_downloads
             .iter()
             .filter(|d| matches!(d.status, PendingDownloadsStatus::Error))
@@ -200,8 +254,8 @@ impl Widget for Downloads {
                     let item = list.item(cx, item_id, id!(DownloadItem));
 
                     if item_id < downloads_count {
-                        let download = &pending_downloads[item_id];
-                        item.draw_all(cx, &mut Scope::with_data(&mut download.clone()));
+                        let mut listing = store.downloads.listing(&pending_downloads[item_id]);
+                        item.draw_all(cx, &mut Scope::with_data(&mut listing));
                     }
                 }
             }
@@ -212,10 +266,16 @@ impl Widget for Downloads {
 }
 
 impl WidgetMatchEvent for Downloads {
-    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope) {
         if self.button(cx, ids!(collapse)).clicked(&actions) {
             self.toggle_collapse(cx);
         }
+
+        if let Some(index) = self.drop_down(cx, ids!(max_concurrent)).selected(actions) {
+            let store = scope.data.get_mut::<Store>().unwrap();
+            store.set_max_concurrent_downloads(index + 1);
+            self.redraw(cx);
+        }
     }
 }
 
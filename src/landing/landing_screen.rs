@@ -23,6 +23,10 @@ script_mod! {
             spacing: 30
             padding: Inset {left: 30 right: 30}
 
+            MolyServerSwitcher {
+                margin: Inset {top: 20}
+            }
+
             heading_with_filters := View {
                 width: Fit
                 height: 50
@@ -46,6 +50,15 @@ script_mod! {
                 }
             }
 
+            hardware := Label {
+                visible: false
+                margin: Inset {top: 10}
+                draw_text +: {
+                    text_style: theme.font_regular {font_size: 9}
+                    color: #667085
+                }
+            }
+
             ModelList {}
         }
         downloads := mod.widgets.Downloads {}
@@ -77,6 +90,17 @@ impl Widget for LandingScreen {
     }
 
     fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let hardware = &scope.data.get::<Store>().unwrap().hardware;
+        let hardware_label = self.label(cx, ids!(models.hardware));
+        hardware_label.set_visible(cx, hardware.total_ram_gb.is_some());
+        hardware_label.set_text(
+            cx,
+            &format!(
+                "Files are checked against this computer: {}",
+                hardware.summary()
+            ),
+        );
+
         let search = &scope.data.get::<Store>().unwrap().search;
         if search.is_pending() || search.was_error() {
             self.view(cx, ids!(heading_with_filters))
@@ -8,6 +8,7 @@ pub mod model_files_list;
 pub mod model_files_tags;
 pub mod model_list;
 pub mod search_bar;
+pub mod search_facets;
 pub mod search_loading;
 pub mod shared;
 pub mod sorting;
@@ -26,6 +27,7 @@ pub fn script_mod(vm: &mut ScriptVm) {
     downloads::script_mod(vm);
     model_card::script_mod(vm);
     model_list::script_mod(vm);
+    search_facets::script_mod(vm);
     search_bar::script_mod(vm);
     landing_screen::script_mod(vm);
 }
@@ -1,7 +1,7 @@
 use crate::data::store::{ModelWithDownloadInfo, StoreAction};
 use makepad_widgets::*;
 
-use super::model_files_list::ModelFilesListWidgetExt;
+use super::model_files_list::{ModelFilesListWidgetExt, model_featured_files};
 
 script_mod! {
     use mod.prelude.widgets.*
@@ -214,7 +214,7 @@ impl Widget for ModelFiles {
     fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
         let model = &scope.data.get::<ModelWithDownloadInfo>().unwrap();
         let files_count = model.files.len();
-        let featured_count = model.files.iter().filter(|f| f.file.featured).count();
+        let featured_count = model_featured_files(model).len();
 
         let show_all_button = self.radio_button(cx, ids!(tab_buttons.show_all_button));
         show_all_button.set_text(&format!("All Files ({})", files_count));
@@ -9,6 +9,7 @@ use crate::{
     },
     shared::{
         actions::{ChatAction, DownloadAction},
+        file_fit_tag::FileFitTagWidgetExt,
         utils::format_model_size,
     },
 };
@@ -195,6 +196,7 @@ script_mod! {
                     }
                 }
             }
+            fit_tag := FileFitTag {}
             tags := ModelFilesTags {}
         }
 
@@ -248,6 +250,12 @@ impl Widget for ModelFilesItem {
             visible: #(show_quantization)
         });
 
+        self.file_fit_tag(cx, ids!(cell3.fit_tag)).set_fit(
+            cx,
+            files_info.fit,
+            files_info.recommended,
+        );
+
         if let Some(download) = &files_info.download {
             let progress = format!("{:.1}%", download.progress);
             let progress_fill_max = 74.0;
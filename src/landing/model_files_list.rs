@@ -118,20 +118,21 @@ impl ModelFilesListRef {
     }
 }
 
-fn model_featured_files(model: &ModelWithDownloadInfo) -> Vec<FileWithDownloadInfo> {
-    model
+/// The featured files, and the file recommended for this computer first.
+pub fn model_featured_files(model: &ModelWithDownloadInfo) -> Vec<FileWithDownloadInfo> {
+    let recommended = model.files.iter().filter(|f| f.recommended);
+    let featured = model
         .files
         .iter()
-        .filter(|f| f.file.featured)
-        .cloned()
-        .collect()
+        .filter(|f| f.file.featured && !f.recommended);
+    recommended.chain(featured).cloned().collect()
 }
 
 fn model_other_files(model: &ModelWithDownloadInfo) -> Vec<FileWithDownloadInfo> {
     model
         .files
         .iter()
-        .filter(|f| !f.file.featured)
+        .filter(|f| !f.file.featured && !f.recommended)
         .cloned()
         .collect()
 }
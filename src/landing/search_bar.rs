@@ -13,7 +13,7 @@ script_mod! {
     mod.widgets.SearchBarBase = #(SearchBar::register_widget(vm))
     mod.widgets.SearchBar = set_type_default() do mod.widgets.SearchBarBase {
         width: Fill
-        height: 200
+        height: 260
 
         flow: Down
         spacing: 30
@@ -104,6 +104,13 @@ script_mod! {
             }
         }
 
+        facets := View {
+            width: 800
+            height: Fit
+            margin: Inset {left: 30 right: 30}
+            mod.widgets.SearchFacets {}
+        }
+
         search_sorting := View {
             visible: false
             width: 300
@@ -125,7 +132,7 @@ script_mod! {
                     redraw: true
                     from: {all: Forward {duration: 0.3}}
                     ease: ExpDecay {d1: 0.80 d2: 0.97}
-                    apply: { height: 200 }
+                    apply: { height: 260 }
                 }
             }
         }
@@ -235,6 +242,7 @@ impl SearchBarRef {
         });
 
         inner.view(cx, ids!(title)).set_visible(cx, false);
+        inner.view(cx, ids!(facets)).set_visible(cx, false);
 
         let mut input_container = inner.view(cx, ids!(input_container));
         script_apply_eval!(cx, input_container, { width: #(fill) });
@@ -267,6 +275,7 @@ impl SearchBarRef {
         });
 
         inner.view(cx, ids!(title)).set_visible(cx, true);
+        inner.view(cx, ids!(facets)).set_visible(cx, true);
 
         let mut input_container = inner.view(cx, ids!(input_container));
         script_apply_eval!(cx, input_container, { width: 800 });
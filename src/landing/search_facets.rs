@@ -0,0 +1,261 @@
+use crate::data::search::filters::{
+    Facet, Facets, FileSizeRange, ParameterSize, RAM_LIMITS_GB, ReleasedWithin, SearchFilters,
+};
+use crate::data::store::{Store, StoreAction};
+use makepad_widgets::*;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    let FacetDropDown = DropDownFlat {
+        width: Fit
+        height: Fit
+        padding: Inset {top: 6 bottom: 6 left: 10 right: 24}
+        draw_text +: {
+            text_style: theme.font_regular {font_size: 9}
+            get_color: fn() -> vec4 {
+                return #344054
+            }
+        }
+    }
+
+    let FilterChip = MolyButton {
+        visible: false
+        width: Fit
+        height: Fit
+        padding: Inset {top: 5 bottom: 5 left: 10 right: 10}
+        spacing: 6
+        draw_bg +: {
+            color: #EEF4FF
+            border_color_1: #C7D7FE
+            border_size: 1.0
+            border_radius: 10.0
+        }
+        draw_text +: {
+            text_style: theme.font_regular {font_size: 9}
+            color: #3538CD
+        }
+        draw_icon +: {
+            svg: ICON_CLOSE
+            get_color: fn() -> vec4 {
+                return #3538CD
+            }
+        }
+        icon_walk +: {width: 7 height: 7}
+    }
+
+    mod.widgets.SearchFacetsBase = #(SearchFacets::register_widget(vm))
+    mod.widgets.SearchFacets = set_type_default() do mod.widgets.SearchFacetsBase {
+        width: Fill
+        height: Fit
+        flow: Down
+        spacing: 8
+
+        pickers := View {
+            width: Fill
+            height: Fit
+            flow: Right
+            spacing: 6
+            align: Align {y: 0.5}
+
+            architecture := FacetDropDown {}
+            parameter_size := FacetDropDown {}
+            quantization := FacetDropDown {}
+            file_size := FacetDropDown {}
+            ram := FacetDropDown {}
+            author := FacetDropDown {}
+            released := FacetDropDown {}
+        }
+
+        chips := View {
+            visible: false
+            width: Fill
+            height: Fit
+            flow: Right
+            spacing: 6
+            align: Align {y: 0.5}
+
+            architecture_chip := FilterChip {}
+            parameter_size_chip := FilterChip {}
+            quantization_chip := FilterChip {}
+            file_size_chip := FilterChip {}
+            ram_chip := FilterChip {}
+            author_chip := FilterChip {}
+            released_chip := FilterChip {}
+
+            clear_filters := MolyButton {
+                width: Fit
+                height: Fit
+                padding: Inset {top: 5 bottom: 5 left: 6 right: 6}
+                draw_bg +: {
+                    color: (TRANSPARENT)
+                    border_size: 0.0
+                }
+                draw_text +: {
+                    text_style: theme.font_regular {font_size: 9}
+                    color: #667085
+                }
+                text: "Clear all"
+            }
+        }
+    }
+}
+
+/// Pickers for the facets of the search, and chips for the selected ones.
+#[derive(Script, ScriptHook, Widget)]
+pub struct SearchFacets {
+    #[deref]
+    view: View,
+
+    /// What the pickers and chips show, refreshed when the search changes.
+    #[rust]
+    shown: Option<(Facets, SearchFilters)>,
+}
+
+impl Widget for SearchFacets {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let search = &scope.data.get::<Store>().unwrap().search;
+        let current = (search.facets().clone(), search.filters.clone());
+        if self.shown.as_ref() != Some(&current) {
+            self.show(cx, &current.0, &current.1);
+            self.shown = Some(current);
+        }
+
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for SearchFacets {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let Some((facets, filters)) = self.shown.clone() else {
+            return;
+        };
+        let mut updated = filters.clone();
+
+        for facet in Facet::ALL {
+            let (picker, chip) = facet_ids(facet);
+
+            if let Some(index) = self.drop_down(cx, &[picker]).selected(actions) {
+                let options = options(facet, &facets, &filters);
+                select(&mut updated, facet, index.checked_sub(1), &options);
+            }
+
+            if self.button(cx, &[chip]).clicked(actions) {
+                updated.clear(facet);
+            }
+        }
+
+        if self.button(cx, ids!(clear_filters)).clicked(actions) {
+            updated = SearchFilters::default();
+        }
+
+        if updated != filters {
+            cx.action(StoreAction::Filter(updated));
+        }
+    }
+}
+
+impl SearchFacets {
+    fn show(&mut self, cx: &mut Cx, facets: &Facets, filters: &SearchFilters) {
+        for facet in Facet::ALL {
+            let (picker, chip) = facet_ids(facet);
+
+            let options = options(facet, facets, filters);
+            let selected = filters
+                .value_label(facet)
+                .and_then(|value| options.iter().position(|o| *o == value))
+                .map_or(0, |index| index + 1);
+
+            let mut labels = vec![format!("Any {}", facet.name().to_lowercase())];
+            labels.extend(options);
+            let drop_down = self.drop_down(cx, &[picker]);
+            drop_down.set_labels(cx, labels);
+            drop_down.set_selected_item(cx, selected);
+
+            let value = filters.value_label(facet);
+            let chip = self.button(cx, &[chip]);
+            chip.set_visible(cx, value.is_some());
+            if let Some(value) = value {
+                chip.set_text(cx, &format!("{}: {}", facet.name(), value));
+            }
+        }
+
+        self.view(cx, ids!(chips))
+            .set_visible(cx, !filters.is_empty());
+        self.redraw(cx);
+    }
+}
+
+fn facet_ids(facet: Facet) -> (LiveId, LiveId) {
+    match facet {
+        Facet::Architecture => (live_id!(architecture), live_id!(architecture_chip)),
+        Facet::ParameterSize => (live_id!(parameter_size), live_id!(parameter_size_chip)),
+        Facet::Quantization => (live_id!(quantization), live_id!(quantization_chip)),
+        Facet::FileSize => (live_id!(file_size), live_id!(file_size_chip)),
+        Facet::Ram => (live_id!(ram), live_id!(ram_chip)),
+        Facet::Author => (live_id!(author), live_id!(author_chip)),
+        Facet::Released => (live_id!(released), live_id!(released_chip)),
+    }
+}
+
+/// Values a facet can be set to, as shown in its picker.
+///
+/// A selected value missing from the results is kept, so it can still be seen
+/// and changed.
+fn options(facet: Facet, facets: &Facets, filters: &SearchFilters) -> Vec<String> {
+    let found = |values: &[String], selected: &Option<String>| {
+        let mut values = values.to_vec();
+        if let Some(selected) = selected
+            && !values.contains(selected)
+        {
+            values.insert(0, selected.clone());
+        }
+        values
+    };
+
+    match facet {
+        Facet::Architecture => found(&facets.architectures, &filters.architecture),
+        Facet::Quantization => found(&facets.quantizations, &filters.quantization),
+        Facet::Author => found(&facets.authors, &filters.author),
+        Facet::ParameterSize => ParameterSize::ALL
+            .iter()
+            .map(|s| s.label().to_string())
+            .collect(),
+        Facet::FileSize => FileSizeRange::ALL
+            .iter()
+            .map(|s| s.label().to_string())
+            .collect(),
+        Facet::Ram => RAM_LIMITS_GB
+            .iter()
+            .map(|gb| format!("Up to {} GB", gb))
+            .collect(),
+        Facet::Released => ReleasedWithin::ALL
+            .iter()
+            .map(|r| r.label().to_string())
+            .collect(),
+    }
+}
+
+/// Set `facet` to the option at `index` of its picker, or clear it.
+fn select(filters: &mut SearchFilters, facet: Facet, index: Option<usize>, options: &[String]) {
+    let Some(index) = index else {
+        filters.clear(facet);
+        return;
+    };
+
+    match facet {
+        Facet::Architecture => filters.architecture = options.get(index).cloned(),
+        Facet::Quantization => filters.quantization = options.get(index).cloned(),
+        Facet::Author => filters.author = options.get(index).cloned(),
+        Facet::ParameterSize => filters.parameter_size = ParameterSize::ALL.get(index).copied(),
+        Facet::FileSize => filters.file_size = FileSizeRange::ALL.get(index).copied(),
+        Facet::Ram => filters.max_ram_gb = RAM_LIMITS_GB.get(index).copied(),
+        Facet::Released => filters.released_within = ReleasedWithin::ALL.get(index).copied(),
+    }
+}
@@ -129,7 +129,7 @@ script_mod! {
 
             margin: Inset {left: 20 right: 40}
 
-            labels: ["Most Downloads", "Least Downloads", "Most Likes", "Least Likes"]
+            labels: ["Most Downloads", "Least Downloads", "Most Likes", "Least Likes", "Newest", "Smallest File"]
         }
     }
 }
@@ -160,7 +160,9 @@ impl WidgetMatchEvent for Sorting {
                 1 => SortCriteria::LeastDownloads,
                 2 => SortCriteria::MostLikes,
                 3 => SortCriteria::LeastLikes,
-                4_usize.. => panic!(),
+                4 => SortCriteria::Newest,
+                5 => SortCriteria::SmallestFile,
+                6_usize.. => panic!(),
             };
 
             cx.action(StoreAction::Sort(criteria));
@@ -185,6 +187,8 @@ impl SortingRef {
             SortCriteria::LeastDownloads => 1,
             SortCriteria::MostLikes => 2,
             SortCriteria::LeastLikes => 3,
+            SortCriteria::Newest => 4,
+            SortCriteria::SmallestFile => 5,
         };
         inner
             .drop_down(cx, ids!(options))
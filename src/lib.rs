@@ -1,6 +1,8 @@
 pub mod app;
 pub mod capture;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod runtime;
 
 mod chat;
@@ -0,0 +1,508 @@
+use makepad_widgets::*;
+
+use crate::data::mcp_status::{McpServerState, McpServerStatus, McpToolInfo};
+use crate::data::store::Store;
+
+/// Log lines shown per server.
+const LOG_LINES: usize = 200;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    let EntryButton = MolyButton {
+        padding: Inset {
+            top: 6 bottom: 6 left: 12 right: 12
+        }
+        draw_bg +: {
+            color: (TRANSPARENT)
+            border_color_1: #D0D5DD
+            border_size: 1.0
+        }
+        draw_text +: {
+            text_style: REGULAR_FONT { font_size: 10 }
+            color: #344054
+        }
+    }
+
+    mod.widgets.McpServerEntryBase = #(McpServerEntry::register_widget(vm))
+    let McpServerEntry = mod.widgets.McpServerEntryBase {
+        width: Fill
+        height: Fit
+        flow: Down
+        spacing: 6
+        padding: Inset { top: 14 bottom: 6 }
+
+        View {
+            width: Fill
+            height: Fit
+            spacing: 10
+            align: Align { x: 0.0 y: 0.5 }
+
+            server_name := Label {
+                draw_text +: {
+                    text_style: BOLD_FONT { font_size: 12 }
+                    color: #000
+                }
+            }
+
+            status := Label {
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 10 }
+                    color: #667085
+                }
+            }
+
+            View { width: Fill height: 1 }
+
+            sign_in_button := EntryButton {
+                text: "Sign in"
+                visible: false
+            }
+
+            sign_out_button := EntryButton {
+                text: "Sign out"
+                visible: false
+            }
+
+            logs_button := EntryButton {
+                text: "Logs"
+            }
+
+            restart_button := EntryButton {
+                text: "Restart"
+            }
+        }
+
+        failure := Label {
+            width: Fill
+            visible: false
+            draw_text +: {
+                text_style: REGULAR_FONT { font_size: 9 }
+                color: #xB42318
+            }
+        }
+
+        logs := RoundedView {
+            width: Fill
+            height: Fit
+            visible: false
+            padding: 10
+            show_bg: true
+            draw_bg +: {
+                color: #101828
+                border_radius: 4.0
+            }
+
+            logs_text := Label {
+                width: Fill
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 9 }
+                    color: #F2F4F7
+                }
+            }
+        }
+
+        separator := SolidView {
+            height: 1
+            draw_bg +: {
+                color: #D9D9D9
+            }
+        }
+    }
+
+    mod.widgets.McpToolEntryBase = #(McpToolEntry::register_widget(vm))
+    let McpToolEntry = mod.widgets.McpToolEntryBase {
+        width: Fill
+        height: Fit
+        flow: Down
+        spacing: 4
+        padding: Inset { top: 8 bottom: 8 left: 15 }
+
+        View {
+            width: Fill
+            height: Fit
+            spacing: 10
+            align: Align { x: 0.0 y: 0.5 }
+
+            tool_name := Label {
+                draw_text +: {
+                    text_style: BOLD_FONT { font_size: 10 }
+                    color: #000
+                }
+            }
+
+            View { width: Fill height: 1 }
+
+            schema_button := EntryButton {
+                text: "Schema"
+            }
+
+            enabled_switch := MolySwitch {}
+        }
+
+        description := Label {
+            width: Fill
+            draw_text +: {
+                text_style: REGULAR_FONT { font_size: 10 }
+                color: #475467
+            }
+        }
+
+        schema := RoundedView {
+            width: Fill
+            height: Fit
+            visible: false
+            padding: 10
+            show_bg: true
+            draw_bg +: {
+                color: #F2F4F7
+                border_radius: 4.0
+            }
+
+            schema_text := Label {
+                width: Fill
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 9 }
+                    color: #1D2939
+                }
+            }
+        }
+    }
+
+    mod.widgets.McpServerStatusListBase = #(McpServerStatusList::register_widget(vm))
+    mod.widgets.McpServerStatusList =
+        set_type_default() do mod.widgets.McpServerStatusListBase {
+        width: Fill
+        height: Fill
+        flow: Down
+
+        Label {
+            text: "Servers and tools"
+            draw_text +: {
+                text_style: BOLD_FONT { font_size: 11 }
+                color: #000
+            }
+        }
+
+        empty := Label {
+            margin: Inset { top: 10 }
+            text: "No enabled servers."
+            draw_text +: {
+                text_style: REGULAR_FONT { font_size: 10 }
+                color: #667085
+            }
+        }
+
+        ScrollYView {
+            width: Fill
+            height: Fill
+
+            servers_list := FlatList {
+                width: Fill
+                height: Fit
+                flow: Down
+                grab_key_focus: true
+                drag_scrolling: true
+
+                server_entry := McpServerEntry {}
+                tool_entry := McpToolEntry {}
+            }
+        }
+    }
+}
+
+/// Live status of each MCP server, with the tools it exposes.
+#[derive(Script, ScriptHook, Widget)]
+pub struct McpServerStatusList {
+    #[deref]
+    view: View,
+}
+
+impl Widget for McpServerStatusList {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let store = scope.data.get::<Store>().unwrap();
+
+        enum DisplayItem<'a> {
+            Server(&'a str, &'a McpServerState, bool, String),
+            Tool(&'a str, &'a McpToolInfo, bool),
+        }
+
+        let config = store.get_mcp_servers_config();
+        let mut display_items = Vec::new();
+        for (server_id, state) in &store.mcp_servers_state {
+            let uses_oauth = config
+                .get_server(server_id)
+                .is_some_and(|server| server.oauth.is_some() && server.is_network());
+            let log = store
+                .mcp_supervisor
+                .log_tail(server_id, LOG_LINES)
+                .iter()
+                .map(|line| line.to_display())
+                .collect::<Vec<_>>()
+                .join("\n");
+            display_items.push(DisplayItem::Server(server_id, state, uses_oauth, log));
+            display_items.extend(state.tools.iter().map(|tool| {
+                let enabled = config
+                    .get_server(server_id)
+                    .is_none_or(|server| server.is_tool_enabled(&tool.name));
+                DisplayItem::Tool(server_id, tool, enabled)
+            }));
+        }
+
+        self.label(cx, ids!(empty))
+            .set_visible(cx, display_items.is_empty());
+
+        while let Some(item) = self.view.draw_walk(cx, &mut Scope::empty(), walk).step() {
+            if let Some(mut list) = item.as_flat_list().borrow_mut() {
+                for display_item in &display_items {
+                    match display_item {
+                        DisplayItem::Server(server_id, state, uses_oauth, log) => {
+                            let item_id = LiveId::from_str(server_id);
+                            if let Some(item) = list.item(cx, item_id, live_id!(server_entry)) {
+                                item.as_mcp_server_entry().set_server(
+                                    cx,
+                                    server_id,
+                                    state,
+                                    *uses_oauth,
+                                    log,
+                                );
+                                item.draw_all(cx, &mut Scope::empty());
+                            }
+                        }
+                        DisplayItem::Tool(server_id, tool, enabled) => {
+                            let item_id = LiveId::from_str(&format!("{}/{}", server_id, tool.name));
+                            if let Some(item) = list.item(cx, item_id, live_id!(tool_entry)) {
+                                item.as_mcp_tool_entry()
+                                    .set_tool(cx, server_id, tool, *enabled);
+                                item.draw_all(cx, &mut Scope::empty());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        DrawStep::done()
+    }
+}
+
+impl WidgetMatchEvent for McpServerStatusList {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope) {
+        let store = scope.data.get_mut::<Store>().unwrap();
+
+        for action in actions {
+            match action.downcast_ref::<McpServerStatusAction>() {
+                Some(McpServerStatusAction::RestartServer(server_id)) => {
+                    store.restart_mcp_server(server_id);
+                    self.redraw(cx);
+                }
+                Some(McpServerStatusAction::SignIn(server_id)) => {
+                    store.sign_in_mcp_server(server_id);
+                    self.redraw(cx);
+                }
+                Some(McpServerStatusAction::SignOut(server_id)) => {
+                    store.sign_out_mcp_server(server_id);
+                    self.redraw(cx);
+                }
+                Some(McpServerStatusAction::ToolEnabledChanged(server_id, tool, enabled)) => {
+                    store.set_mcp_tool_enabled(server_id, tool, *enabled);
+                    self.redraw(cx);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+enum McpServerStatusAction {
+    #[default]
+    None,
+    RestartServer(String),
+    SignIn(String),
+    SignOut(String),
+    ToolEnabledChanged(String, String, bool),
+}
+
+#[derive(Script, ScriptHook, Widget)]
+struct McpServerEntry {
+    #[deref]
+    view: View,
+
+    #[rust]
+    server_id: String,
+
+    #[rust]
+    logs_visible: bool,
+}
+
+impl Widget for McpServerEntry {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view(cx, ids!(logs)).set_visible(cx, self.logs_visible);
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for McpServerEntry {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        if self.button(cx, ids!(restart_button)).clicked(actions) {
+            cx.action(McpServerStatusAction::RestartServer(self.server_id.clone()));
+        }
+
+        if self.button(cx, ids!(logs_button)).clicked(actions) {
+            self.logs_visible = !self.logs_visible;
+            self.redraw(cx);
+        }
+
+        if self.button(cx, ids!(sign_in_button)).clicked(actions) {
+            cx.action(McpServerStatusAction::SignIn(self.server_id.clone()));
+        }
+
+        if self.button(cx, ids!(sign_out_button)).clicked(actions) {
+            cx.action(McpServerStatusAction::SignOut(self.server_id.clone()));
+        }
+    }
+}
+
+impl McpServerEntryRef {
+    fn set_server(
+        &mut self,
+        cx: &mut Cx,
+        server_id: &str,
+        state: &McpServerState,
+        uses_oauth: bool,
+        log: &str,
+    ) {
+        let Some(mut inner) = self.borrow_mut() else {
+            return;
+        };
+
+        inner.server_id = server_id.to_string();
+        inner.label(cx, ids!(server_name)).set_text(cx, server_id);
+
+        let status_text = match &state.status {
+            McpServerStatus::Ready => format!("Ready · {} tools", state.tools.len()),
+            status => status.to_human_readable().to_string(),
+        };
+        let mut status_label = inner.label(cx, ids!(status));
+        status_label.set_text(cx, &status_text);
+
+        let text_color = match &state.status {
+            McpServerStatus::Ready => vec4(0.0, 0.576, 0.314, 1.0),
+            McpServerStatus::Connecting => vec4(0.5, 0.5, 0.5, 1.0),
+            McpServerStatus::Failed(_) => vec4(1.0, 0.0, 0.0, 1.0),
+        };
+        script_apply_eval!(cx, status_label, {
+            draw_text +: {
+                color: #(text_color)
+            }
+        });
+
+        let failure = inner.label(cx, ids!(failure));
+        match &state.status {
+            McpServerStatus::Failed(failure_info) => {
+                failure.set_text(cx, &failure_info.details());
+                failure.set_visible(cx, true);
+            }
+            _ => failure.set_visible(cx, false),
+        }
+
+        inner
+            .button(cx, ids!(restart_button))
+            .set_visible(cx, state.status != McpServerStatus::Connecting);
+        let log = if log.is_empty() {
+            "No output yet."
+        } else {
+            log
+        };
+        inner.label(cx, ids!(logs_text)).set_text(cx, log);
+
+        inner.button(cx, ids!(sign_in_button)).set_visible(
+            cx,
+            uses_oauth && matches!(state.status, McpServerStatus::Failed(_)),
+        );
+        inner
+            .button(cx, ids!(sign_out_button))
+            .set_visible(cx, uses_oauth && state.status == McpServerStatus::Ready);
+    }
+}
+
+#[derive(Script, ScriptHook, Widget)]
+struct McpToolEntry {
+    #[deref]
+    view: View,
+
+    #[rust]
+    server_id: String,
+
+    #[rust]
+    tool_name: String,
+
+    #[rust]
+    schema_visible: bool,
+}
+
+impl Widget for McpToolEntry {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view(cx, ids!(schema))
+            .set_visible(cx, self.schema_visible);
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for McpToolEntry {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        if self.button(cx, ids!(schema_button)).clicked(actions) {
+            self.schema_visible = !self.schema_visible;
+            self.redraw(cx);
+        }
+
+        if let Some(enabled) = self.check_box(cx, ids!(enabled_switch)).changed(actions) {
+            cx.action(McpServerStatusAction::ToolEnabledChanged(
+                self.server_id.clone(),
+                self.tool_name.clone(),
+                enabled,
+            ));
+        }
+    }
+}
+
+impl McpToolEntryRef {
+    fn set_tool(&mut self, cx: &mut Cx, server_id: &str, tool: &McpToolInfo, enabled: bool) {
+        let Some(mut inner) = self.borrow_mut() else {
+            return;
+        };
+
+        inner.server_id = server_id.to_string();
+        inner.tool_name = tool.name.clone();
+
+        inner.label(cx, ids!(tool_name)).set_text(cx, &tool.name);
+
+        let description = inner.label(cx, ids!(description));
+        description.set_visible(cx, !tool.description.is_empty());
+        description.set_text(cx, &tool.description);
+
+        inner
+            .label(cx, ids!(schema_text))
+            .set_text(cx, &tool.input_schema);
+        inner
+            .check_box(cx, ids!(enabled_switch))
+            .set_active(cx, enabled);
+    }
+}
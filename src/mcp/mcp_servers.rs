@@ -6,6 +6,7 @@ use makepad_code_editor::{CodeDocument, CodeEditor, CodeSession};
 
 use makepad_widgets::*;
 
+use crate::data::mcp_import::{self, McpConfigApp, McpImport};
 use crate::data::mcp_servers::McpServersConfig;
 
 script_mod! {
@@ -133,7 +134,7 @@ script_mod! {
         flow: Down, spacing: 10
         instructions := Label {
             width: Fill, height: Fit
-            text: "Add new servers by editing the list under 'servers'. You can copy paste you configuration from other applications like Clade Desktop or VSCode.
+            text: "Add new servers by editing the list under 'servers'. You can import the servers configured in other applications like Claude Desktop, Cursor or VS Code below. Every tool call made by a model is listed under 'Tool executions'.
 You can also add an \"enabled\": false flag to disable a specific server."
             draw_text +: {
                 text_style: REGULAR_FONT { font_size: 11 }
@@ -189,6 +190,91 @@ Only enable if you trust all configured MCP servers completely."
         }
     }
 
+    let ConfigButton = MolyButton {
+        padding: Inset {
+            top: 6 bottom: 6 left: 12 right: 12
+        }
+        draw_bg +: {
+            color: (TRANSPARENT)
+            border_color_1: #D0D5DD
+            border_size: 1.0
+        }
+        draw_text +: {
+            text_style: REGULAR_FONT { font_size: 10 }
+            color: #344054
+        }
+    }
+
+    let ImportExport = View {
+        width: Fill, height: Fit
+        flow: Down, spacing: 8
+
+        Label {
+            text: "Import servers"
+            draw_text +: {
+                text_style: BOLD_FONT { font_size: 11 }
+                color: #000
+            }
+        }
+
+        View {
+            width: Fill, height: Fit
+            spacing: 8
+            import_claude_desktop := ConfigButton { text: "From Claude Desktop" }
+            import_cursor := ConfigButton { text: "From Cursor" }
+            import_vscode := ConfigButton { text: "From VS Code" }
+        }
+
+        View {
+            width: Fill, height: Fit
+            spacing: 8
+            align: Align { y: 0.5 }
+            import_text := MolyTextInput {
+                width: Fill, height: Fit
+                empty_text: "Paste a configuration, or the path of a configuration file"
+            }
+            import_text_button := ConfigButton { text: "Import" }
+        }
+
+        View {
+            width: Fill, height: Fit
+            spacing: 12
+            align: Align { y: 0.5 }
+            Label {
+                text: "Replace servers already configured differently"
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 10 }
+                    color: #000
+                }
+            }
+            replace_conflicts_switch := MolySwitch {}
+        }
+
+        import_status := Label {
+            width: Fill
+            draw_text +: {
+                text_style: REGULAR_FONT { font_size: 10 }
+                color: #344054
+            }
+        }
+
+        Label {
+            text: "Export servers"
+            draw_text +: {
+                text_style: BOLD_FONT { font_size: 11 }
+                color: #000
+            }
+        }
+
+        View {
+            width: Fill, height: Fit
+            spacing: 8
+            export_claude_desktop := ConfigButton { text: "Copy for Claude Desktop" }
+            export_cursor := ConfigButton { text: "Copy for Cursor" }
+            export_vscode := ConfigButton { text: "Copy for VS Code" }
+        }
+    }
+
     mod.widgets.McpServersBase = #(McpServers::register_widget(vm))
     mod.widgets.McpServers = set_type_default() do mod.widgets.McpServersBase {
         AdaptiveView {
@@ -203,7 +289,14 @@ Only enable if you trust all configured MCP servers completely."
                     ToggleMCPWrapper {}
                     Instructions {}
                     DangerousModeWrapper {}
+                    ImportExport { width: 600 }
                     SaveStatus {}
+                    McpServerStatusList {
+                        padding: Inset { right: 20 bottom: 10 }
+                    }
+                    ToolAuditList {
+                        padding: Inset { right: 20 bottom: 10 }
+                    }
                 }
             }
             Mobile +: {
@@ -225,7 +318,16 @@ Only enable if you trust all configured MCP servers completely."
                     }
                     DangerousModeWrapper {}
                     ServersEditor { width: Fill }
+                    ImportExport { padding: Inset { right: 10 } }
                     SaveStatus {}
+                    McpServerStatusList {
+                        height: 400
+                        padding: Inset { right: 10 bottom: 10 }
+                    }
+                    ToolAuditList {
+                        height: 400
+                        padding: Inset { right: 10 bottom: 10 }
+                    }
                 }
             }
         }
@@ -293,6 +395,52 @@ impl McpServers {
         self.check_box(cx, ids!(dangerous_mode_switch))
             .set_active(cx, self.mcp_servers_config.dangerous_mode_enabled);
     }
+
+    fn import_from_app(&mut self, cx: &mut Cx, scope: &mut Scope, app: McpConfigApp) {
+        let import = match app.locate() {
+            Some(path) => read_config_file(&path),
+            None => Err(format!("No {} configuration found", app.name())),
+        };
+        self.import(cx, scope, import);
+    }
+
+    fn import(&mut self, cx: &mut Cx, scope: &mut Scope, import: Result<McpImport, String>) {
+        let status = match import {
+            Ok(import) => {
+                let replace_conflicts = self
+                    .check_box(cx, ids!(replace_conflicts_switch))
+                    .active(cx);
+                let store = scope.data.get_mut::<Store>().unwrap();
+                let report = store.import_mcp_servers(import, replace_conflicts);
+                self.set_mcp_servers_config(cx, store.get_mcp_servers_config().clone());
+                report.summary()
+            }
+            Err(e) => e,
+        };
+
+        self.label(cx, ids!(import_status)).set_text(cx, &status);
+        self.redraw(cx);
+    }
+
+    fn export_to_app(&mut self, cx: &mut Cx, scope: &mut Scope, app: McpConfigApp) {
+        let store = scope.data.get::<Store>().unwrap();
+        cx.copy_to_clipboard(&mcp_import::export(store.get_mcp_servers_config(), app));
+
+        self.label(cx, ids!(import_status)).set_text(
+            cx,
+            &format!(
+                "Copied the enabled servers for {} to the clipboard",
+                app.name()
+            ),
+        );
+        self.redraw(cx);
+    }
+}
+
+fn read_config_file(path: &std::path::Path) -> Result<McpImport, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    mcp_import::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
 }
 
 impl WidgetMatchEvent for McpServers {
@@ -332,6 +480,42 @@ impl WidgetMatchEvent for McpServers {
             }
         }
 
+        if self
+            .button(cx, ids!(import_claude_desktop))
+            .clicked(actions)
+        {
+            self.import_from_app(cx, scope, McpConfigApp::ClaudeDesktop);
+        }
+        if self.button(cx, ids!(import_cursor)).clicked(actions) {
+            self.import_from_app(cx, scope, McpConfigApp::Cursor);
+        }
+        if self.button(cx, ids!(import_vscode)).clicked(actions) {
+            self.import_from_app(cx, scope, McpConfigApp::VsCode);
+        }
+
+        if self.button(cx, ids!(import_text_button)).clicked(actions) {
+            let text = self.text_input(cx, ids!(import_text)).text();
+            let import = if text.trim_start().starts_with('{') {
+                mcp_import::parse(&text)
+            } else {
+                read_config_file(std::path::Path::new(text.trim()))
+            };
+            self.import(cx, scope, import);
+        }
+
+        if self
+            .button(cx, ids!(export_claude_desktop))
+            .clicked(actions)
+        {
+            self.export_to_app(cx, scope, McpConfigApp::ClaudeDesktop);
+        }
+        if self.button(cx, ids!(export_cursor)).clicked(actions) {
+            self.export_to_app(cx, scope, McpConfigApp::Cursor);
+        }
+        if self.button(cx, ids!(export_vscode)).clicked(actions) {
+            self.export_to_app(cx, scope, McpConfigApp::VsCode);
+        }
+
         let servers_enabled_switch = self.check_box(cx, ids!(servers_enabled_switch));
         if let Some(enabled) = servers_enabled_switch.changed(actions) {
             self.mcp_servers_config.enabled = enabled;
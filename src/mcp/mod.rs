@@ -1,9 +1,13 @@
 pub mod mcp_screen;
+pub mod mcp_server_status;
 pub mod mcp_servers;
+pub mod tool_audit_list;
 
 use makepad_widgets::ScriptVm;
 
 pub fn script_mod(vm: &mut ScriptVm) {
+    mcp_server_status::script_mod(vm);
+    tool_audit_list::script_mod(vm);
     mcp_servers::script_mod(vm);
     mcp_screen::script_mod(vm);
 }
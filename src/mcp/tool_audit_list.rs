@@ -0,0 +1,392 @@
+use makepad_widgets::defer_with_redraw::DeferWithRedraw;
+use makepad_widgets::*;
+use moly_kit::aitk::utils::asynchronous::spawn;
+
+use crate::data::store::Store;
+use crate::data::tool_audit::ToolAuditRecord;
+
+/// Records shown at once, the full log is available through the export.
+const SHOWN_RECORDS: usize = 200;
+
+/// Arguments longer than this are cut in the list.
+const ARGUMENTS_PREVIEW_CHARS: usize = 300;
+
+/// Results longer than this are cut in the list.
+const RESULT_PREVIEW_CHARS: usize = 500;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.ToolAuditEntryBase = #(ToolAuditEntry::register_widget(vm))
+    let ToolAuditEntry = mod.widgets.ToolAuditEntryBase {
+        width: Fill
+        height: Fit
+        flow: Down
+        spacing: 4
+        padding: Inset { top: 10 bottom: 10 }
+
+        View {
+            width: Fill
+            height: Fit
+            spacing: 10
+            align: Align { x: 0.0 y: 0.5 }
+
+            tool_name := Label {
+                draw_text +: {
+                    text_style: BOLD_FONT { font_size: 10 }
+                    color: #000
+                }
+            }
+
+            decision := Label {
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 9 }
+                    color: #667085
+                }
+            }
+
+            View { width: Fill height: 1 }
+
+            time := Label {
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 9 }
+                    color: #667085
+                }
+            }
+        }
+
+        context := Label {
+            width: Fill
+            draw_text +: {
+                text_style: REGULAR_FONT { font_size: 9 }
+                color: #475467
+            }
+        }
+
+        arguments := Label {
+            width: Fill
+            draw_text +: {
+                text_style: REGULAR_FONT { font_size: 9 }
+                color: #1D2939
+            }
+        }
+
+        result := Label {
+            width: Fill
+            draw_text +: {
+                text_style: REGULAR_FONT { font_size: 9 }
+                color: #344054
+            }
+        }
+
+        error := Label {
+            width: Fill
+            draw_text +: {
+                text_style: REGULAR_FONT { font_size: 9 }
+                color: #xB42318
+            }
+        }
+
+        SolidView {
+            width: Fill
+            height: 1
+            margin: Inset { top: 6 }
+            draw_bg +: {
+                color: #EAECF0
+            }
+        }
+    }
+
+    mod.widgets.ToolAuditListBase = #(ToolAuditList::register_widget(vm))
+    mod.widgets.ToolAuditList = set_type_default() do mod.widgets.ToolAuditListBase {
+        width: Fill
+        height: Fill
+        flow: Down
+        spacing: 8
+
+        View {
+            width: Fill
+            height: Fit
+            spacing: 8
+            align: Align { y: 0.5 }
+
+            Label {
+                text: "Tool executions"
+                draw_text +: {
+                    text_style: BOLD_FONT { font_size: 11 }
+                    color: #000
+                }
+            }
+
+            View { width: Fill height: 1 }
+
+            filter := MolyTextInput {
+                width: 220
+                height: Fit
+                empty_text: "Filter by server, tool or model"
+            }
+
+            export_button := MolyButton {
+                padding: Inset {
+                    top: 6 bottom: 6 left: 12 right: 12
+                }
+                text: "Export"
+                draw_bg +: {
+                    color: (TRANSPARENT)
+                    border_color_1: #D0D5DD
+                    border_size: 1.0
+                }
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 10 }
+                    color: #344054
+                }
+            }
+        }
+
+        status := Label {
+            width: Fill
+            draw_text +: {
+                text_style: REGULAR_FONT { font_size: 10 }
+                color: #667085
+            }
+        }
+
+        ScrollYView {
+            width: Fill
+            height: Fill
+
+            records_list := FlatList {
+                width: Fill
+                height: Fit
+                flow: Down
+                grab_key_focus: true
+                drag_scrolling: true
+
+                record_entry := ToolAuditEntry {}
+            }
+        }
+    }
+}
+
+/// Browsable history of the tool calls made by models, newest first.
+#[derive(Script, ScriptHook, Widget)]
+pub struct ToolAuditList {
+    #[deref]
+    view: View,
+
+    /// Records read for the filter and revision of the log in `loaded`.
+    #[rust]
+    records: Vec<ToolAuditRecord>,
+
+    #[rust]
+    loaded: Option<(String, u64)>,
+
+    /// Filter and revision being read, if any.
+    #[rust]
+    loading: Option<(String, u64)>,
+
+    /// Outcome of the last export, shown instead of the record count.
+    #[rust]
+    export_status: Option<String>,
+}
+
+impl Widget for ToolAuditList {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.ui_runner().handle(cx, event, scope, self);
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let store = scope.data.get::<Store>().unwrap();
+        let query = self.text_input(cx, ids!(filter)).text().trim().to_string();
+        self.load_records(store, &query);
+
+        let status = match &self.export_status {
+            Some(status) => status.clone(),
+            None if self.loaded.is_none() => "Reading the tool calls...".to_string(),
+            None if self.records.is_empty() && query.is_empty() => {
+                "No tool has been called yet.".to_string()
+            }
+            None if self.records.is_empty() => "No tool call matches the filter.".to_string(),
+            None if self.records.len() == SHOWN_RECORDS => {
+                format!("Showing the last {} tool calls.", SHOWN_RECORDS)
+            }
+            None => String::new(),
+        };
+        let mut status_label = self.label(cx, ids!(status));
+        status_label.set_visible(cx, !status.is_empty());
+        status_label.set_text(cx, &status);
+
+        let records = std::mem::take(&mut self.records);
+        while let Some(item) = self.view.draw_walk(cx, &mut Scope::empty(), walk).step() {
+            if let Some(mut list) = item.as_flat_list().borrow_mut() {
+                for (index, record) in records.iter().enumerate() {
+                    let item_id = LiveId::from_str(&format!("{}/{}", record.timestamp, index));
+                    if let Some(item) = list.item(cx, item_id, live_id!(record_entry)) {
+                        let running = record
+                            .call_id
+                            .as_deref()
+                            .is_some_and(|id| store.tool_audit.is_running(id));
+                        item.as_tool_audit_entry().set_record(cx, record, running);
+                        item.draw_all(cx, &mut Scope::empty());
+                    }
+                }
+            }
+        }
+        self.records = records;
+
+        DrawStep::done()
+    }
+}
+
+impl WidgetMatchEvent for ToolAuditList {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope) {
+        if self.text_input(cx, ids!(filter)).changed(actions).is_some() {
+            self.export_status = None;
+            self.redraw(cx);
+        }
+
+        if self.button(cx, ids!(export_button)).clicked(actions) {
+            let store = scope.data.get::<Store>().unwrap();
+            let content = store.tool_audit.export();
+            let ui = self.ui_runner();
+            spawn(async move {
+                let content = content.await;
+                ui.defer_with_redraw(move |me, cx, _| {
+                    me.export_status = Some(export(cx, &content));
+                });
+            });
+        }
+    }
+}
+
+impl ToolAuditList {
+    /// Read the records again when the filter or the log changed.
+    fn load_records(&mut self, store: &Store, query: &str) {
+        let key = (query.to_string(), store.tool_audit.revision());
+        if self.loaded.as_ref() == Some(&key) || self.loading.as_ref() == Some(&key) {
+            return;
+        }
+
+        self.loading = Some(key.clone());
+        let records = store.tool_audit.recent(query, SHOWN_RECORDS);
+        let ui = self.ui_runner();
+        spawn(async move {
+            let records = records.await;
+            ui.defer_with_redraw(move |me, _cx, _| {
+                // A newer read may have started meanwhile.
+                if me.loading.as_ref() != Some(&key) {
+                    return;
+                }
+                me.loading = None;
+                me.records = records;
+                me.loaded = Some(key);
+            });
+        });
+    }
+}
+
+/// Save the log to the downloads folder, or copy it when there is none.
+fn export(cx: &mut Cx, content: &str) -> String {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(dir) = directories::UserDirs::new()
+        .as_ref()
+        .and_then(|dirs| dirs.download_dir())
+    {
+        let file_name = format!(
+            "moly-tool-audit-{}.jsonl",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        );
+        let path = dir.join(file_name);
+        return match std::fs::write(&path, content) {
+            Ok(()) => format!("Exported the tool calls to {}", path.display()),
+            Err(e) => format!("Could not write {}: {}", path.display(), e),
+        };
+    }
+
+    cx.copy_to_clipboard(content);
+    "Copied the tool calls to the clipboard as JSON lines".to_string()
+}
+
+#[derive(Script, ScriptHook, Widget)]
+struct ToolAuditEntry {
+    #[deref]
+    view: View,
+}
+
+impl Widget for ToolAuditEntry {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl ToolAuditEntryRef {
+    fn set_record(&mut self, cx: &mut Cx, record: &ToolAuditRecord, running: bool) {
+        let Some(inner) = self.borrow_mut() else {
+            return;
+        };
+
+        let tool_name = match &record.server {
+            Some(server) => format!("{} · {}", server, record.tool),
+            None => record.tool.clone(),
+        };
+        inner.label(cx, ids!(tool_name)).set_text(cx, &tool_name);
+
+        let mut decision = record.decision().to_string();
+        if let Some(duration_ms) = record.duration_ms {
+            decision.push_str(&format!(" · {} ms", duration_ms));
+        } else if running {
+            decision.push_str(" · Running");
+        } else if record.lacks_outcome() {
+            decision.push_str(" · No result recorded");
+        }
+        inner.label(cx, ids!(decision)).set_text(cx, &decision);
+        inner
+            .label(cx, ids!(time))
+            .set_text(cx, &record.local_time());
+
+        let mut context = Vec::new();
+        if let Some(bot) = &record.bot {
+            context.push(bot.clone());
+        }
+        if let Some(chat_id) = record.chat_id {
+            context.push(format!("Chat {}", chat_id));
+        }
+        if record.realtime {
+            context.push("Voice session".to_string());
+        }
+        let mut context_label = inner.label(cx, ids!(context));
+        context_label.set_visible(cx, !context.is_empty());
+        context_label.set_text(cx, &context.join(" · "));
+
+        inner.label(cx, ids!(arguments)).set_text(
+            cx,
+            &preview(&record.arguments.to_string(), ARGUMENTS_PREVIEW_CHARS),
+        );
+
+        let mut result_label = inner.label(cx, ids!(result));
+        result_label.set_visible(cx, record.result.is_some());
+        if let Some(result) = &record.result {
+            result_label.set_text(cx, &preview(result, RESULT_PREVIEW_CHARS));
+        }
+
+        let mut error_label = inner.label(cx, ids!(error));
+        error_label.set_visible(cx, record.error.is_some());
+        if let Some(error) = &record.error {
+            error_label.set_text(cx, &preview(error, RESULT_PREVIEW_CHARS));
+        }
+    }
+}
+
+fn preview(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
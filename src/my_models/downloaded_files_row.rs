@@ -1,5 +1,11 @@
-use super::{delete_model_modal::DeleteModelModalAction, model_info_modal::ModelInfoModalAction};
+use super::{
+    delete_model_modal::DeleteModelModalAction, load_model_modal::LoadModelModalAction,
+    model_info_modal::ModelInfoModalAction,
+};
+use crate::data::hardware::FileFit;
+use crate::data::model_loader::LoadState;
 use crate::data::store::Store;
+use crate::shared::file_fit_tag::FileFitTagWidgetExt;
 use crate::shared::utils::format_model_size;
 use crate::shared::{actions::ChatAction, utils::human_readable_name};
 use makepad_widgets::*;
@@ -94,7 +100,7 @@ script_mod! {
     }
 
     let RowActions = View {
-        width: 250
+        width: 350
         flow: Right
         spacing: 10
         align: Align { x: 0.0 y: 0.5 }
@@ -112,6 +118,15 @@ script_mod! {
             }
         }
 
+        load_button := DownloadedFilesRowButton {
+            width: 90
+            text: "Load"
+            draw_bg +: { color_hover: #2654C033 }
+            draw_text +: {
+                color: #2654C0
+            }
+        }
+
         View { width: Fill height: Fit }
 
         info_button := DownloadedFilesRowButton {
@@ -159,6 +174,12 @@ script_mod! {
 
                 model_file := ModelFile {}
                 file_size_tag := DownloadedFilesTag {}
+                fit := View {
+                    width: 180
+                    height: Fit
+                    align: Align { x: 0.0 y: 0.5 }
+                    fit_tag := FileFitTag {}
+                }
                 date_added_tag := DownloadedFilesTag {}
                 row_actions := RowActions {}
             }
@@ -175,11 +196,18 @@ script_mod! {
                 mod.widgets.DeleteModelModal {}
             }
         }
+
+        load_modal := MolyModal {
+            content +: {
+                mod.widgets.LoadModelModal {}
+            }
+        }
     }
 }
 
 pub struct DownloadedFilesRowProps {
     pub downloaded_file: DownloadedFile,
+    pub fit: Option<FileFit>,
 }
 
 #[derive(Script, ScriptHook, Widget)]
@@ -230,10 +258,19 @@ impl Widget for DownloadedFilesRow {
         self.label(cx, ids!(h_wrapper.file_size_tag.label))
             .set_text(cx, &file_size);
 
+        self.file_fit_tag(cx, ids!(h_wrapper.fit.fit_tag))
+            .set_fit(cx, props.fit, false);
+
         let formatted_date = downloaded_file.downloaded_at.format("%d/%m/%Y").to_string();
         self.label(cx, ids!(h_wrapper.date_added_tag.label))
             .set_text(cx, &formatted_date);
 
+        let store = scope.data.get::<Store>().unwrap();
+        let load_text = load_button_text(&store.model_loader.state, &downloaded_file.file.id);
+        let load_button = self.button(cx, ids!(row_actions.load_button));
+        load_button.set_text(cx, &load_text);
+        load_button.set_visible(cx, store.model_loader.supported);
+
         self.view.draw_walk(cx, scope, walk)
     }
 }
@@ -264,6 +301,13 @@ impl WidgetMatchEvent for DownloadedFilesRow {
             self.moly_modal(cx, ids!(delete_modal)).open_as_dialog(cx);
         }
 
+        if self
+            .button(cx, ids!(row_actions.load_button))
+            .clicked(actions)
+        {
+            self.moly_modal(cx, ids!(load_modal)).open_as_dialog(cx);
+        }
+
         for action in actions {
             if let DeleteModelModalAction::ModalDismissed = action.cast() {
                 self.moly_modal(cx, ids!(delete_modal)).close(cx);
@@ -272,6 +316,10 @@ impl WidgetMatchEvent for DownloadedFilesRow {
             if let ModelInfoModalAction::ModalDismissed = action.cast() {
                 self.moly_modal(cx, ids!(info_modal)).close(cx);
             }
+
+            if let LoadModelModalAction::ModalDismissed = action.cast() {
+                self.moly_modal(cx, ids!(load_modal)).close(cx);
+            }
         }
     }
 }
@@ -285,6 +333,25 @@ impl DownloadedFilesRowRef {
     }
 }
 
+/// The load button tells how loading this file goes, if it's the one concerned.
+fn load_button_text(state: &LoadState, file_id: &FileId) -> String {
+    if state.file_id() != Some(file_id) {
+        return "Load".to_string();
+    }
+
+    match state {
+        LoadState::Loading {
+            progress: Some(progress),
+            ..
+        } => format!("Loading {:.0}%", progress * 100.0),
+        LoadState::Loading { .. } => "Loading...".to_string(),
+        LoadState::Loaded(_) => "Reload".to_string(),
+        LoadState::Ejecting(_) => "Ejecting...".to_string(),
+        LoadState::Failed { .. } => "Retry Load".to_string(),
+        LoadState::Idle => "Load".to_string(),
+    }
+}
+
 fn dash_if_empty(input: &str) -> &str {
     if input.is_empty() { "-" } else { input }
 }
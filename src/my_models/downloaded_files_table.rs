@@ -41,6 +41,9 @@ script_mod! {
         RowHeaderLabel {
             width: 100 label +: { text: "File Size" }
         }
+        RowHeaderLabel {
+            width: 180 label +: { text: "Fit" }
+        }
         RowHeaderLabel {
             width: 100 label +: { text: "Added Date" }
         }
@@ -143,8 +146,15 @@ impl Widget for DownloadedFilesTable {
                         item.as_downloaded_files_row()
                             .set_file_id(file_data.file.id.clone());
 
+                        let fit = scope
+                            .data
+                            .get::<Store>()
+                            .unwrap()
+                            .hardware
+                            .file_fit(&file_data.file, &file_data.model.requires);
                         let props = DownloadedFilesRowProps {
                             downloaded_file: file_data.clone(),
+                            fit,
                         };
                         let mut scope = Scope::with_props(&props);
                         item.draw_all(cx, &mut scope);
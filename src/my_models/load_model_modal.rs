@@ -0,0 +1,297 @@
+use makepad_widgets::*;
+use moly_protocol::data::FileId;
+
+use crate::data::model_loader::RuntimeOptions;
+use crate::data::store::Store;
+
+use super::downloaded_files_row::DownloadedFilesRowProps;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    let OptionRow = View {
+        width: Fill
+        height: Fit
+        spacing: 10
+        align: Align { y: 0.5 }
+
+        label := Label {
+            width: 200
+            draw_text +: {
+                text_style: REGULAR_FONT { font_size: 10 }
+                color: #000
+            }
+        }
+
+        input := MolyTextInput {
+            width: Fill
+            height: Fit
+            padding: Inset { top: 6 bottom: 6 left: 10 right: 10 }
+            draw_text +: {
+                text_style: REGULAR_FONT { font_size: 10 }
+            }
+        }
+    }
+
+    mod.widgets.LoadModelModalBase = #(LoadModelModal::register_widget(vm))
+    mod.widgets.LoadModelModal = set_type_default() do mod.widgets.LoadModelModalBase {
+        width: Fit
+        height: Fit
+
+        wrapper := RoundedView {
+            flow: Down
+            width: 600
+            height: Fit
+            padding: Inset { top: 44 right: 30 bottom: 30 left: 50 }
+            spacing: 12
+
+            show_bg: true
+            draw_bg +: {
+                color: #fff
+                border_radius: 3
+            }
+
+            View {
+                width: Fill
+                height: Fit
+                flow: Right
+
+                padding: Inset { top: 8 bottom: 8 }
+
+                Label {
+                    text: "Load Model"
+                    draw_text +: {
+                        text_style: BOLD_FONT { font_size: 13 }
+                        color: #000
+                    }
+                }
+
+                View { width: Fill height: Fit }
+
+                close_button := MolyButton {
+                    width: Fit
+                    height: Fit
+
+                    margin: Inset { top: -8 }
+
+                    draw_icon +: {
+                        svg: ICON_CLOSE
+                        get_color: fn() -> vec4 {
+                            return #000
+                        }
+                    }
+                    icon_walk +: { width: 12 height: 12 }
+                }
+            }
+
+            file_name := Label {
+                width: Fill
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 10 }
+                    color: #667085
+                }
+            }
+
+            context_size := OptionRow {
+                label +: { text: "Context size (tokens)" }
+                input +: { empty_text: "Model default" }
+            }
+            batch_size := OptionRow {
+                label +: { text: "Batch size" }
+                input +: { empty_text: "Model default" }
+            }
+            gpu_layers := OptionRow {
+                label +: { text: "GPU layers" }
+                input +: { empty_text: "All" }
+            }
+
+            View {
+                width: Fill
+                height: Fit
+                spacing: 10
+                align: Align { y: 0.5 }
+
+                Label {
+                    width: 200
+                    text: "Lock in memory"
+                    draw_text +: {
+                        text_style: REGULAR_FONT { font_size: 10 }
+                        color: #000
+                    }
+                }
+                use_mlock := MolySwitch {}
+            }
+
+            error := Label {
+                width: Fill
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 9 }
+                    color: #xB42318
+                }
+                text: ""
+            }
+
+            actions := View {
+                width: Fill height: Fit
+                flow: Right
+                align: Align { x: 1.0 y: 0.5 }
+                spacing: 20
+
+                cancel_button := MolyButton {
+                    width: Fit
+                    height: Fit
+                    padding: Inset {
+                        top: 10 bottom: 10 left: 14 right: 14
+                    }
+
+                    draw_bg +: {
+                        border_radius: uniform(2.0)
+                        border_color_1: #D0D5DD
+                        border_size: 1.2
+                        color: #fff
+                    }
+
+                    text: "Cancel"
+                    draw_text +: {
+                        text_style: REGULAR_FONT { font_size: 10 }
+                        color: #x0
+                    }
+                }
+
+                load_button := MolyButton {
+                    width: Fit
+                    height: Fit
+                    padding: Inset {
+                        top: 10 bottom: 10 left: 14 right: 14
+                    }
+
+                    draw_bg +: {
+                        border_radius: uniform(2.0)
+                        color: (CTA_BUTTON_COLOR)
+                    }
+
+                    text: "Load"
+                    draw_text +: {
+                        text_style: REGULAR_FONT { font_size: 10 }
+                        color: #fff
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum LoadModelModalAction {
+    #[default]
+    None,
+    ModalDismissed,
+}
+
+/// Runtime options to load a downloaded file with, filled with the last ones used.
+#[derive(Script, ScriptHook, Widget)]
+pub struct LoadModelModal {
+    #[deref]
+    view: View,
+
+    #[rust]
+    file_id: FileId,
+
+    /// The file the inputs were filled for, cleared when the modal closes.
+    #[rust]
+    filled_for: Option<FileId>,
+}
+
+impl Widget for LoadModelModal {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let props = scope.props.get::<DownloadedFilesRowProps>().unwrap();
+        let file = &props.downloaded_file.file;
+        self.file_id = file.id.clone();
+        self.label(cx, ids!(file_name)).set_text(cx, &file.name);
+
+        if self.filled_for.as_ref() != Some(&file.id) {
+            let store = scope.data.get::<Store>().unwrap();
+            let options = store.preferences.runtime_options(&file.id);
+            self.fill(cx, &options);
+            self.filled_for = Some(file.id.clone());
+        }
+
+        self.view
+            .draw_walk(cx, scope, walk.with_abs_pos(DVec2 { x: 0., y: 0. }))
+    }
+}
+
+impl WidgetMatchEvent for LoadModelModal {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope) {
+        if self.button(cx, ids!(close_button)).clicked(actions)
+            || self.button(cx, ids!(cancel_button)).clicked(actions)
+        {
+            self.dismiss(cx);
+        }
+
+        if self.button(cx, ids!(load_button)).clicked(actions) {
+            match self.read_options(cx) {
+                Ok(options) => {
+                    let store = scope.data.get_mut::<Store>().unwrap();
+                    store.load_model(self.file_id.clone(), options);
+                    self.dismiss(cx);
+                }
+                Err(error) => {
+                    self.label(cx, ids!(error)).set_text(cx, &error);
+                    self.redraw(cx);
+                }
+            }
+        }
+    }
+}
+
+impl LoadModelModal {
+    fn fill(&mut self, cx: &mut Cx, options: &RuntimeOptions) {
+        let number = |value: Option<u32>| value.map(|v| v.to_string()).unwrap_or_default();
+        self.text_input(cx, ids!(context_size.input))
+            .set_text(cx, &number(options.context_size));
+        self.text_input(cx, ids!(batch_size.input))
+            .set_text(cx, &number(options.batch_size));
+        self.text_input(cx, ids!(gpu_layers.input))
+            .set_text(cx, &number(options.gpu_layers));
+        self.check_box(cx, ids!(use_mlock))
+            .set_active(cx, options.use_mlock);
+        self.label(cx, ids!(error)).set_text(cx, "");
+    }
+
+    fn read_options(&mut self, cx: &mut Cx) -> Result<RuntimeOptions, String> {
+        Ok(RuntimeOptions {
+            context_size: self.read_number(cx, ids!(context_size.input), "Context size")?,
+            batch_size: self.read_number(cx, ids!(batch_size.input), "Batch size")?,
+            gpu_layers: self.read_number(cx, ids!(gpu_layers.input), "GPU layers")?,
+            use_mlock: self.check_box(cx, ids!(use_mlock)).active(cx),
+        })
+    }
+
+    fn read_number(
+        &mut self,
+        cx: &mut Cx,
+        input: &[LiveId],
+        name: &str,
+    ) -> Result<Option<u32>, String> {
+        let text = self.text_input(cx, input).text();
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(None);
+        }
+        text.parse::<u32>()
+            .map(Some)
+            .map_err(|_| format!("{} must be a whole number", name))
+    }
+
+    fn dismiss(&mut self, cx: &mut Cx) {
+        self.filled_for = None;
+        cx.action(LoadModelModalAction::ModalDismissed);
+    }
+}
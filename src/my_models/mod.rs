@@ -1,15 +1,19 @@
 pub mod delete_model_modal;
 pub mod downloaded_files_row;
 pub mod downloaded_files_table;
+pub mod load_model_modal;
 pub mod model_info_modal;
 pub mod my_models_screen;
+pub mod storage_panel;
 
 use makepad_widgets::ScriptVm;
 
 pub fn script_mod(vm: &mut ScriptVm) {
     delete_model_modal::script_mod(vm);
     model_info_modal::script_mod(vm);
+    load_model_modal::script_mod(vm);
     downloaded_files_row::script_mod(vm);
     downloaded_files_table::script_mod(vm);
+    storage_panel::script_mod(vm);
     my_models_screen::script_mod(vm);
 }
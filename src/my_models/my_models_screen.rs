@@ -151,10 +151,21 @@ script_mod! {
                 align: Align { x: 0.0 y: 0.5 }
 
                 show_in_files := ShowInFilesButton {}
+                storage_toggle := ShowInFilesButton {
+                    margin: 0
+                    draw_icon +: { svg: ICON_EDIT_FOLDER }
+                    text: "Storage"
+                }
                 View { width: Fill height: Fit }
+                MolyServerSwitcher {}
                 search := SearchBar {}
             }
 
+            storage := mod.widgets.StoragePanel {
+                visible: false
+                margin: Inset { top: 20 }
+            }
+
             table := mod.widgets.DownloadedFilesTable {
                 margin: Inset { top: 20 }
             }
@@ -166,6 +177,9 @@ script_mod! {
 pub struct MyModelsScreen {
     #[deref]
     view: View,
+
+    #[rust]
+    storage_shown: bool,
 }
 
 impl Widget for MyModelsScreen {
@@ -175,14 +189,8 @@ impl Widget for MyModelsScreen {
     }
 
     fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
-        let downloaded_files = &scope
-            .data
-            .get::<Store>()
-            .unwrap()
-            .downloads
-            .downloaded_files;
-
-        let summary = generate_models_summary(downloaded_files);
+        let store = scope.data.get::<Store>().unwrap();
+        let summary = generate_models_summary(&store.downloads.downloaded_files);
         let models_summary_label = self.view.label(cx, ids!(header.models_summary));
         models_summary_label.set_text(cx, &summary);
 
@@ -190,6 +198,15 @@ impl Widget for MyModelsScreen {
             .button(cx, ids!(show_in_files))
             .set_text(cx, &file_manager_label());
 
+        // Storage is the one of this computer, remote servers manage their own.
+        let is_local = store.moly_servers.is_local_active();
+        self.view
+            .button(cx, ids!(storage_toggle))
+            .set_visible(cx, is_local);
+        self.view
+            .view(cx, ids!(storage))
+            .set_visible(cx, is_local && self.storage_shown);
+
         self.view.draw_walk(cx, scope, walk)
     }
 }
@@ -225,6 +242,16 @@ impl WidgetMatchEvent for MyModelsScreen {
                 });
         }
 
+        if self.button(cx, ids!(storage_toggle)).clicked(actions) {
+            self.storage_shown = !self.storage_shown;
+            self.view(cx, ids!(storage))
+                .set_visible(cx, self.storage_shown);
+            if self.storage_shown {
+                scope.data.get_mut::<Store>().unwrap().storage.refresh();
+            }
+            self.redraw(cx);
+        }
+
         if let Some(keywords) = self.text_input(cx, ids!(search.input)).changed(actions) {
             if !keywords.is_empty() {
                 cx.action(MyModelsSearchAction::Search(keywords.to_string()));
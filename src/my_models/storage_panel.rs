@@ -0,0 +1,228 @@
+use std::path::PathBuf;
+
+use makepad_widgets::*;
+
+use crate::data::storage::{StorageTask, usage_by_model};
+use crate::data::store::Store;
+use crate::shared::utils::format_bytes;
+
+/// Models listed by name in the usage, the others are summed up.
+const LISTED_MODELS: usize = 5;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    let PanelLabel = Label {
+        draw_text +: {
+            text_style: REGULAR_FONT { font_size: 10 }
+            color: #535353
+        }
+    }
+
+    let PathInput = MolyTextInput {
+        width: 360
+        height: Fit
+        padding: Inset { top: 6 bottom: 6 left: 10 right: 10 }
+        draw_text +: {
+            text_style: REGULAR_FONT { font_size: 10 }
+        }
+    }
+
+    let PanelButton = MolyButton {
+        width: Fit
+        height: 28
+        padding: Inset { top: 6 bottom: 6 left: 14 right: 14 }
+
+        draw_bg +: {
+            border_radius: 2.0
+            color: #FEFEFE
+        }
+
+        draw_text +: {
+            text_style: REGULAR_FONT { font_size: 10 }
+            color: #000
+        }
+    }
+
+    mod.widgets.StoragePanelBase = #(StoragePanel::register_widget(vm))
+    mod.widgets.StoragePanel = set_type_default() do mod.widgets.StoragePanelBase {
+        ..mod.widgets.RoundedView
+        width: Fill
+        height: Fit
+        flow: Down
+        spacing: 10
+        padding: 16
+
+        show_bg: true
+        draw_bg +: {
+            color: #fff
+            border_radius: 3.0
+            border_color: #EAECF0
+            border_size: 1.0
+        }
+
+        usage := Label {
+            draw_text +: {
+                text_style: BOLD_FONT { font_size: 11 }
+                color: #000
+            }
+        }
+
+        per_model := PanelLabel {
+            width: Fill
+        }
+
+        location := PanelLabel {
+            width: Fill
+        }
+
+        move_row := View {
+            width: Fill
+            height: Fit
+            spacing: 10
+            align: Align { y: 0.5 }
+
+            new_dir_input := PathInput {
+                empty_text: "New models folder, like /data/models"
+            }
+            move_button := PanelButton {
+                text: "Move Models"
+            }
+        }
+
+        import_row := View {
+            width: Fill
+            height: Fit
+            spacing: 10
+            align: Align { y: 0.5 }
+
+            import_input := PathInput {
+                empty_text: "GGUF file already on disk"
+            }
+            import_button := PanelButton {
+                text: "Import"
+            }
+        }
+
+        task_status := PanelLabel {
+            width: Fill
+            text: ""
+        }
+    }
+}
+
+/// Disk usage of the models, with moving their folder and importing GGUF files
+/// once the folder of the server is known.
+#[derive(Script, ScriptHook, Widget)]
+pub struct StoragePanel {
+    #[deref]
+    view: View,
+}
+
+impl Widget for StoragePanel {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let store = scope.data.get::<Store>().unwrap();
+        let storage = &store.storage;
+        let usage = usage_by_model(&store.downloads.downloaded_files);
+        let total: u64 = usage.iter().map(|u| u.bytes).sum();
+
+        let mut summary = format!("{} used by models", format_bytes(total));
+        if let Some(free) = storage.free_bytes {
+            summary.push_str(&format!(", {} free on this volume", format_bytes(free)));
+        }
+        self.label(cx, ids!(usage)).set_text(cx, &summary);
+
+        let mut per_model: Vec<String> = usage
+            .iter()
+            .take(LISTED_MODELS)
+            .map(|u| format!("{}: {}", u.name, format_bytes(u.bytes)))
+            .collect();
+        if usage.len() > LISTED_MODELS {
+            let others: u64 = usage[LISTED_MODELS..].iter().map(|u| u.bytes).sum();
+            per_model.push(format!(
+                "{} more: {}",
+                usage.len() - LISTED_MODELS,
+                format_bytes(others)
+            ));
+        }
+        self.label(cx, ids!(per_model))
+            .set_text(cx, &per_model.join("  ·  "));
+
+        self.label(cx, ids!(location)).set_text(
+            cx,
+            &format!("Models folder: {}", storage.models_dir.display()),
+        );
+
+        let status = match &storage.task {
+            StorageTask::Idle => String::new(),
+            StorageTask::Moving { done, total } => {
+                format!("Moving models... {}", progress_text(*done, *total))
+            }
+            StorageTask::Importing { done, total } => {
+                format!("Importing... {}", progress_text(*done, *total))
+            }
+            StorageTask::Done(message) | StorageTask::Failed(message) => message.clone(),
+        };
+        self.label(cx, ids!(task_status)).set_text(cx, &status);
+
+        // Until its folder is found from its files, neither can be done.
+        self.view(cx, ids!(move_row))
+            .set_visible(cx, storage.can_manage_files);
+        self.view(cx, ids!(import_row))
+            .set_visible(cx, storage.can_manage_files);
+
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for StoragePanel {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope) {
+        let store = scope.data.get_mut::<Store>().unwrap();
+
+        let new_dir_input = self.text_input(cx, ids!(new_dir_input));
+        if self.button(cx, ids!(move_button)).clicked(actions)
+            || new_dir_input.returned(actions).is_some()
+        {
+            let target = new_dir_input.text();
+            if !target.trim().is_empty() {
+                let downloads_running = store.downloads.active_count() > 0;
+                store
+                    .storage
+                    .move_models(PathBuf::from(target.trim()), downloads_running);
+                self.redraw(cx);
+            }
+        }
+
+        let import_input = self.text_input(cx, ids!(import_input));
+        if self.button(cx, ids!(import_button)).clicked(actions)
+            || import_input.returned(actions).is_some()
+        {
+            let path = import_input.text();
+            if !path.trim().is_empty() {
+                store.storage.import_file(PathBuf::from(path.trim()));
+                self.redraw(cx);
+            }
+        }
+    }
+}
+
+/// Like "1.20 GB of 4.00 GB (30%)".
+fn progress_text(done: u64, total: u64) -> String {
+    let percent = if total == 0 {
+        100
+    } else {
+        (done.min(total) * 100 / total) as u8
+    };
+    format!(
+        "{} of {} ({}%)",
+        format_bytes(done),
+        format_bytes(total),
+        percent
+    )
+}
@@ -1,5 +1,7 @@
 pub mod add_provider_modal;
+pub mod moly_server_process_panel;
 pub mod moly_server_screen;
+pub mod moly_servers_panel;
 pub mod provider_view;
 pub mod providers;
 pub mod providers_screen;
@@ -14,5 +16,7 @@ pub fn script_mod(vm: &mut ScriptVm) {
     provider_view::script_mod(vm);
     providers::script_mod(vm);
     providers_screen::script_mod(vm);
+    moly_server_process_panel::script_mod(vm);
+    moly_servers_panel::script_mod(vm);
     moly_server_screen::script_mod(vm);
 }
@@ -0,0 +1,253 @@
+use std::path::PathBuf;
+
+use makepad_widgets::*;
+
+use crate::data::moly_server_process::MolyServerProcessStatus;
+use crate::data::store::Store;
+
+/// Log lines shown in the panel.
+const LOG_LINES: usize = 300;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    let PanelButton = MolyButton {
+        padding: Inset {
+            top: 6 bottom: 6 left: 12 right: 12
+        }
+        draw_bg +: {
+            color: (TRANSPARENT)
+            border_color_1: #D0D5DD
+            border_size: 1.0
+        }
+        draw_text +: {
+            text_style: REGULAR_FONT { font_size: 10 }
+            color: #344054
+        }
+    }
+
+    let PanelLabel = Label {
+        draw_text +: {
+            text_style: REGULAR_FONT { font_size: 10 }
+            color: #667085
+        }
+    }
+
+    mod.widgets.MolyServerProcessPanelBase = #(MolyServerProcessPanel::register_widget(vm))
+    mod.widgets.MolyServerProcessPanel =
+        set_type_default() do mod.widgets.MolyServerProcessPanelBase {
+        width: Fill
+        height: Fill
+        flow: Down
+        spacing: 12
+        padding: Inset { top: 30 left: 30 right: 30 bottom: 20 }
+
+        View {
+            width: Fill
+            height: Fit
+            spacing: 10
+            align: Align { y: 0.5 }
+
+            Label {
+                text: "Server process"
+                draw_text +: {
+                    text_style: BOLD_FONT { font_size: 12 }
+                    color: #000
+                }
+            }
+
+            status := Label {
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 10 }
+                    color: #667085
+                }
+            }
+
+            View { width: Fill height: 1 }
+
+            start_button := PanelButton {
+                text: "Start"
+            }
+            stop_button := PanelButton {
+                text: "Stop"
+            }
+        }
+
+        View {
+            width: Fill
+            height: Fit
+            spacing: 10
+            align: Align { y: 0.5 }
+
+            managed_switch := MolySwitch {}
+            PanelLabel {
+                text: "Start MolyServer with Moly when it isn't running"
+            }
+        }
+
+        View {
+            width: Fill
+            height: Fit
+            spacing: 10
+            align: Align { y: 0.5 }
+
+            binary_input := MolyTextInput {
+                width: 420
+                height: Fit
+                padding: Inset { top: 6 bottom: 6 left: 10 right: 10 }
+                empty_text: "moly-server binary, searched next to Moly and in the PATH"
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 10 }
+                }
+            }
+            save_binary_button := PanelButton {
+                text: "Save"
+            }
+        }
+
+        binary := PanelLabel {
+            width: Fill
+        }
+
+        ScrollYView {
+            width: Fill
+            height: Fill
+
+            RoundedView {
+                width: Fill
+                height: Fit
+                padding: 10
+                show_bg: true
+                draw_bg +: {
+                    color: #101828
+                    border_radius: 4.0
+                }
+
+                logs_text := Label {
+                    width: Fill
+                    draw_text +: {
+                        text_style: REGULAR_FONT { font_size: 9 }
+                        color: #F2F4F7
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Status, configuration and logs of the MolyServer process started by Moly.
+#[derive(Script, ScriptHook, Widget)]
+pub struct MolyServerProcessPanel {
+    #[deref]
+    view: View,
+
+    /// Whether the inputs were filled from the configuration.
+    #[rust]
+    filled: bool,
+}
+
+impl Widget for MolyServerProcessPanel {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let store = scope.data.get::<Store>().unwrap();
+        let process = &store.moly_server_process;
+        let config = &store.preferences.moly_server_process;
+
+        if !self.filled {
+            self.filled = true;
+            self.check_box(cx, ids!(managed_switch))
+                .set_active(cx, config.managed);
+            let binary = config
+                .binary_path
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default();
+            self.text_input(cx, ids!(binary_input))
+                .set_text(cx, &binary);
+        }
+
+        let mut status = process.status().to_human_readable();
+        if let (Some(port), true) = (process.port(), process.is_started()) {
+            status.push_str(&format!(" · port {}", port));
+        }
+        let mut status_label = self.label(cx, ids!(status));
+        status_label.set_text(cx, &status);
+        let text_color = match process.status() {
+            MolyServerProcessStatus::Running | MolyServerProcessStatus::External => {
+                vec4(0.0, 0.576, 0.314, 1.0)
+            }
+            MolyServerProcessStatus::NotFound | MolyServerProcessStatus::Failed(_) => {
+                vec4(1.0, 0.0, 0.0, 1.0)
+            }
+            _ => vec4(0.5, 0.5, 0.5, 1.0),
+        };
+        script_apply_eval!(cx, status_label, {
+            draw_text +: {
+                color: #(text_color)
+            }
+        });
+
+        let binary = match process.binary() {
+            Some(path) => format!("Binary: {}", path.display()),
+            None => String::new(),
+        };
+        self.label(cx, ids!(binary)).set_text(cx, &binary);
+
+        self.button(cx, ids!(start_button)).set_text(
+            cx,
+            if process.is_started() {
+                "Restart"
+            } else {
+                "Start"
+            },
+        );
+        self.button(cx, ids!(stop_button))
+            .set_visible(cx, process.is_started());
+
+        let log = process.log_tail(LOG_LINES).join("\n");
+        let log = if log.is_empty() {
+            "No output yet."
+        } else {
+            &log
+        };
+        self.label(cx, ids!(logs_text)).set_text(cx, log);
+
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for MolyServerProcessPanel {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope) {
+        let store = scope.data.get_mut::<Store>().unwrap();
+
+        if self.button(cx, ids!(start_button)).clicked(actions) {
+            store.start_moly_server();
+            self.redraw(cx);
+        }
+
+        if self.button(cx, ids!(stop_button)).clicked(actions) {
+            store.moly_server_process.stop();
+            self.redraw(cx);
+        }
+
+        if let Some(managed) = self.check_box(cx, ids!(managed_switch)).changed(actions) {
+            store.update_moly_server_process_config(|config| config.managed = managed);
+            self.redraw(cx);
+        }
+
+        let binary_input = self.text_input(cx, ids!(binary_input));
+        if self.button(cx, ids!(save_binary_button)).clicked(actions)
+            || binary_input.returned(actions).is_some()
+        {
+            let text = binary_input.text();
+            let binary_path = (!text.trim().is_empty()).then(|| PathBuf::from(text.trim()));
+            store.update_moly_server_process_config(|config| config.binary_path = binary_path);
+            self.redraw(cx);
+        }
+    }
+}
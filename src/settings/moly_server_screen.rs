@@ -14,6 +14,8 @@ script_mod! {
         crate_resource("self://resources/icons/cloud.svg")
     let ICON_RETRY =
         crate_resource("self://resources/icons/retry.svg")
+    let ICON_LOCAL =
+        crate_resource("self://resources/icons/local.svg")
 
     let SUBSIDEBAR_BG_COLOR = (MAIN_BG_COLOR)
     let SUBSIDEBAR_FONT_COLOR = #x2C3E50
@@ -94,7 +96,7 @@ script_mod! {
                     text_style: BOLD_FONT { font_size: 12 }
                     color: #000
                 }
-                text: "We could not reach the server.\nPlease make sure it is running and that MolyServer is enabled in the provider settings."
+                text: "We could not reach the server.\nMoly starts it when it finds the moly-server binary, see the server process below.\nAlso make sure that MolyServer is enabled in the provider settings."
             }
 
             View {
@@ -129,6 +131,14 @@ script_mod! {
                 }
             }
         }
+
+        MolyServersPanel {
+            padding: Inset { right: 30 }
+        }
+
+        process_panel := MolyServerProcessPanel {
+            padding: Inset { right: 30 bottom: 20 }
+        }
     }
 
     mod.widgets.MolyServerScreenBase = #(MolyServerScreen::register_widget(vm))
@@ -162,6 +172,12 @@ script_mod! {
                         svg: ICON_MY_MODELS
                     }
                 }
+                server_tab := SubSidebarMenuButton {
+                    text: "Server"
+                    draw_icon +: {
+                        svg: ICON_LOCAL
+                    }
+                }
             }
 
             right_border := SolidView {
@@ -182,6 +198,12 @@ script_mod! {
                     visible: false
                     MyModelsScreen {}
                 }
+                server_frame := View {
+                    visible: false
+                    flow: Down
+                    MolyServersPanel {}
+                    MolyServerProcessPanel {}
+                }
             }
         }
 
@@ -234,24 +256,21 @@ impl Widget for MolyServerScreen {
 impl WidgetMatchEvent for MolyServerScreen {
     fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope) {
         let selected_index = self
-            .radio_button_set(cx, ids_array!(menu.discover_tab, menu.my_models_tab))
+            .radio_button_set(
+                cx,
+                ids_array!(menu.discover_tab, menu.my_models_tab, menu.server_tab),
+            )
             .selected(cx, actions);
 
         let discover_frame = self.view(cx, ids!(pages.discover_frame));
         let my_models_frame = self.view(cx, ids!(pages.my_models_frame));
+        let server_frame = self.view(cx, ids!(pages.server_frame));
 
-        match selected_index {
-            Some(0) => {
-                discover_frame.set_visible(cx, true);
-                my_models_frame.set_visible(cx, false);
-                self.redraw(cx);
-            }
-            Some(1) => {
-                discover_frame.set_visible(cx, false);
-                my_models_frame.set_visible(cx, true);
-                self.redraw(cx);
-            }
-            _ => (),
+        if let Some(index) = selected_index {
+            discover_frame.set_visible(cx, index == 0);
+            my_models_frame.set_visible(cx, index == 1);
+            server_frame.set_visible(cx, index == 2);
+            self.redraw(cx);
         }
 
         if self.button(cx, ids!(go_to_providers)).clicked(actions) {
@@ -0,0 +1,168 @@
+use makepad_widgets::*;
+
+use crate::data::moly_servers::{LOCAL_SERVER_ID, MolyServerEndpoint};
+use crate::data::store::Store;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    let PanelButton = MolyButton {
+        padding: Inset {
+            top: 6 bottom: 6 left: 12 right: 12
+        }
+        draw_bg +: {
+            color: (TRANSPARENT)
+            border_color_1: #D0D5DD
+            border_size: 1.0
+        }
+        draw_text +: {
+            text_style: REGULAR_FONT { font_size: 10 }
+            color: #344054
+        }
+    }
+
+    let PanelInput = MolyTextInput {
+        width: 200
+        height: Fit
+        padding: Inset { top: 6 bottom: 6 left: 10 right: 10 }
+        draw_text +: {
+            text_style: REGULAR_FONT { font_size: 10 }
+        }
+    }
+
+    mod.widgets.MolyServersPanelBase = #(MolyServersPanel::register_widget(vm))
+    mod.widgets.MolyServersPanel =
+        set_type_default() do mod.widgets.MolyServersPanelBase {
+        width: Fill
+        height: Fit
+        flow: Down
+        spacing: 12
+        padding: Inset { top: 30 left: 30 right: 30 }
+
+        View {
+            width: Fill
+            height: Fit
+            spacing: 10
+            align: Align { y: 0.5 }
+
+            Label {
+                text: "Servers"
+                draw_text +: {
+                    text_style: BOLD_FONT { font_size: 12 }
+                    color: #000
+                }
+            }
+
+            active := Label {
+                draw_text +: {
+                    text_style: REGULAR_FONT { font_size: 10 }
+                    color: #667085
+                }
+            }
+
+            View { width: Fill height: 1 }
+
+            MolyServerSwitcher {}
+            remove_button := PanelButton {
+                text: "Remove current server"
+            }
+        }
+
+        View {
+            width: Fill
+            height: Fit
+            spacing: 10
+            align: Align { y: 0.5 }
+
+            name_input := PanelInput {
+                empty_text: "Name"
+            }
+            url_input := PanelInput {
+                width: 260
+                empty_text: "http://192.168.1.20:8765"
+            }
+            api_key_input := PanelInput {
+                is_password: true
+                empty_text: "API key (optional)"
+            }
+            add_button := PanelButton {
+                text: "Add"
+            }
+        }
+
+        error := Label {
+            width: Fill
+            draw_text +: {
+                text_style: REGULAR_FONT { font_size: 10 }
+                color: #xB42318
+            }
+        }
+    }
+}
+
+/// Add and remove the remote MolyServers Moly can use.
+#[derive(Script, ScriptHook, Widget)]
+pub struct MolyServersPanel {
+    #[deref]
+    view: View,
+}
+
+impl Widget for MolyServersPanel {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let store = scope.data.get::<Store>().unwrap();
+        let active_id = store.moly_servers.active_id();
+        let active_name = store
+            .moly_servers
+            .list()
+            .into_iter()
+            .find(|(id, _)| id == active_id)
+            .map(|(_, name)| name)
+            .unwrap_or_default();
+
+        self.label(cx, ids!(active))
+            .set_text(cx, &format!("Using {}", active_name));
+        self.button(cx, ids!(remove_button))
+            .set_visible(cx, active_id != LOCAL_SERVER_ID);
+
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for MolyServersPanel {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope) {
+        let store = scope.data.get_mut::<Store>().unwrap();
+
+        if self.button(cx, ids!(remove_button)).clicked(actions) {
+            let active_id = store.moly_servers.active_id().to_string();
+            store.remove_moly_server(&active_id);
+            cx.redraw_all();
+        }
+
+        let url_input = self.text_input(cx, ids!(url_input));
+        if self.button(cx, ids!(add_button)).clicked(actions)
+            || url_input.returned(actions).is_some()
+        {
+            let name = self.text_input(cx, ids!(name_input)).text();
+            let api_key = self.text_input(cx, ids!(api_key_input)).text();
+            let result = MolyServerEndpoint::new(&name, &url_input.text(), Some(api_key))
+                .and_then(|endpoint| store.add_moly_server(endpoint));
+
+            match result {
+                Ok(()) => {
+                    self.text_input(cx, ids!(name_input)).set_text(cx, "");
+                    url_input.set_text(cx, "");
+                    self.text_input(cx, ids!(api_key_input)).set_text(cx, "");
+                    self.label(cx, ids!(error)).set_text(cx, "");
+                }
+                Err(error) => self.label(cx, ids!(error)).set_text(cx, &error),
+            }
+            cx.redraw_all();
+        }
+    }
+}
@@ -1,7 +1,10 @@
 use makepad_widgets::*;
 use moly_kit::prelude::*;
 
+use chrono::Utc;
+
 use crate::data::{
+    provider_models_cache::fetched_ago,
     providers::{Provider, ProviderBot, ProviderConnectionStatus, ProviderType},
     store::Store,
 };
@@ -55,11 +58,26 @@ script_mod! {
             width: Fill
             height: Fill
             align: Align { x: 0.5 y: 0.5 }
-            model_name := Label {
-                text: "Model Name"
-                draw_text +: {
-                    text_style: REGULAR_FONT { font_size: 11 }
-                    color: #000
+            View {
+                width: Fit
+                height: Fit
+                flow: Down
+                spacing: 3
+
+                model_name := Label {
+                    text: "Model Name"
+                    draw_text +: {
+                        text_style: REGULAR_FONT { font_size: 11 }
+                        color: #000
+                    }
+                }
+
+                model_details := Label {
+                    visible: false
+                    draw_text +: {
+                        text_style: REGULAR_FONT { font_size: 8.5 }
+                        color: #667085
+                    }
                 }
             }
 
@@ -548,6 +566,7 @@ impl Widget for ProviderView {
         }
 
         let provider = store.chats.providers.get(&self.provider.id).cloned();
+        let stale_bots = store.chats.stale_bots.clone();
 
         if let Some(provider) = provider {
             if !self.initialized {
@@ -602,8 +621,21 @@ impl Widget for ProviderView {
                                 item.view(cx, ids!(separator))
                                     .set_visible(cx, show_separator);
 
-                                item.label(cx, ids!(model_name))
-                                    .set_text(cx, &bot.human_readable_name());
+                                // Cached bots stay listed, marked until the provider lists them.
+                                let name = match stale_bots.get(&bot.id) {
+                                    Some(fetched_at) => format!(
+                                        "{} (last listed {})",
+                                        bot.human_readable_name(),
+                                        fetched_ago(*fetched_at, Utc::now())
+                                    ),
+                                    None => bot.human_readable_name().to_string(),
+                                };
+                                item.label(cx, ids!(model_name)).set_text(cx, &name);
+
+                                let summary = bot.metadata.as_ref().and_then(|m| m.summary());
+                                let details = item.label(cx, ids!(model_details));
+                                details.set_visible(cx, summary.is_some());
+                                details.set_text(cx, summary.as_deref().unwrap_or_default());
                                 item.check_box(cx, ids!(enabled_switch))
                                     .set_active(cx, bot.enabled && self.provider.enabled);
 
@@ -876,12 +908,14 @@ impl Widget for ModelEntry {
             script_apply_eval!(cx, self.view, { height: 80 });
         }
 
-        let mut model_name = self.view.label(cx, ids!(model_name));
-        if is_desktop {
-            let fit = Size::fit();
-            script_apply_eval!(cx, model_name, { width: #(fit) });
-        } else {
-            script_apply_eval!(cx, model_name, { width: 200 });
+        for id in [ids!(model_name), ids!(model_details)] {
+            let mut label = self.view.label(cx, id);
+            if is_desktop {
+                let fit = Size::fit();
+                script_apply_eval!(cx, label, { width: #(fit) });
+            } else {
+                script_apply_eval!(cx, label, { width: 200 });
+            }
         }
 
         self.view
@@ -1,3 +1,4 @@
+use crate::data::gateway::{self, GatewayStatus};
 use crate::data::store::Store;
 use crate::shared::utils::version::{Pull, Version};
 use makepad_widgets::*;
@@ -32,6 +33,29 @@ script_mod! {
         }
     }
 
+    let SmallButton = MolyButton {
+        padding: Inset { top: 6 bottom: 6 left: 12 right: 12 }
+        draw_bg +: {
+            color: (TRANSPARENT)
+            border_color_1: #D0D5DD
+            border_size: 1.0
+        }
+        draw_text +: {
+            text_style: REGULAR_FONT { font_size: 10 }
+            color: #344054
+        }
+    }
+
+    let Note = Label {
+        width: Fill
+        height: Fit
+        draw_text +: {
+            text_style: REGULAR_FONT { font_size: 9 }
+            color: #667085
+            wrap: Word
+        }
+    }
+
     let FormGroup = View {
         width: Fill
         height: Fit
@@ -219,6 +243,105 @@ script_mod! {
                     }
                 }
             }
+
+            Label {
+                width: Fill
+                height: Fit
+                margin: Inset { top: 10 }
+                draw_text +: {
+                    text_style: BOLD_FONT { font_size: 11 }
+                    color: #666
+                }
+                text: "Local API Gateway"
+            }
+
+            Note {
+                text: "Serve the models of your providers to editors and scripts on this computer through an OpenAI-compatible API."
+            }
+
+            View {
+                width: Fill
+                height: Fit
+                flow: Right
+                align: Align { x: 0.0 y: 0.5 }
+                spacing: 10
+
+                Label {
+                    width: Fit
+                    height: Fit
+                    text: "Enable gateway"
+                    draw_text +: {
+                        text_style: REGULAR_FONT { font_size: 10 }
+                        color: #000
+                    }
+                }
+
+                gateway_toggle := MolySwitch {}
+
+                View { width: Fill height: 1 }
+
+                Label {
+                    width: Fit
+                    height: Fit
+                    text: "Use MCP tools"
+                    draw_text +: {
+                        text_style: REGULAR_FONT { font_size: 10 }
+                        color: #000
+                    }
+                }
+
+                gateway_tools_toggle := MolySwitch {}
+            }
+
+            gateway_status := Note {}
+
+            port_group := FormGroup {
+                label +: {
+                    text: "Port (press Enter to apply)"
+                }
+                input +: {
+                    port_input := MolyTextInput {
+                        width: Fill
+                        height: Fit
+                        padding: Inset { top: 10 bottom: 10 left: 10 right: 10 }
+                        draw_bg +: {
+                            color: #fff
+                            border_size: 1.0
+                            border_color_1: #D0D5DD
+                            border_radius: 2.0
+                        }
+                        draw_text +: {
+                            text_style: REGULAR_FONT { font_size: 10 }
+                            color: #000
+                        }
+                    }
+                }
+            }
+
+            token_group := FormGroup {
+                label +: {
+                    text: "Access token, sent by clients as a bearer token"
+                }
+                input +: {
+                    flow: Right
+                    spacing: 5
+                    align: Align { x: 0.0 y: 0.5 }
+
+                    token_label := Label {
+                        width: Fill
+                        height: Fit
+                        draw_text +: {
+                            text_style: REGULAR_FONT { font_size: 10 }
+                            color: #000
+                        }
+                    }
+
+                    copy_token_button := SmallButton { text: "Copy" }
+                    regenerate_token_button := SmallButton { text: "Regenerate" }
+                }
+            }
+
+            gateway_clients := Note {}
         }
     }
 }
@@ -230,6 +353,10 @@ pub struct UtilitiesModal {
 
     #[rust]
     stt_config: Option<Version>,
+
+    /// The port input is filled once, so it isn't overwritten while typing.
+    #[rust]
+    port_shown: bool,
 }
 
 impl Widget for UtilitiesModal {
@@ -240,6 +367,7 @@ impl Widget for UtilitiesModal {
     }
 
     fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.update_gateway(cx, scope);
         self.view.draw_walk(cx, scope, walk)
     }
 }
@@ -292,6 +420,38 @@ impl WidgetMatchEvent for UtilitiesModal {
                 config.model_name = value;
             });
         }
+
+        if let Some(value) = self.check_box(cx, ids!(gateway_toggle)).changed(actions) {
+            store.update_gateway_config(|config| config.enabled = value);
+        }
+
+        if let Some(value) = self
+            .check_box(cx, ids!(gateway_tools_toggle))
+            .changed(actions)
+        {
+            store.update_gateway_config(|config| config.mcp_tools = value);
+        }
+
+        if let Some((value, _)) = self.text_input(cx, ids!(port_input)).returned(actions) {
+            match value.trim().parse::<u16>() {
+                Ok(port) if port != 0 => store.update_gateway_config(|config| config.port = port),
+                // Show the port in use again.
+                _ => self.port_shown = false,
+            }
+            self.redraw(cx);
+        }
+
+        if self.button(cx, ids!(copy_token_button)).clicked(actions) {
+            cx.copy_to_clipboard(&store.preferences.gateway.token);
+        }
+
+        if self
+            .button(cx, ids!(regenerate_token_button))
+            .clicked(actions)
+        {
+            store.update_gateway_config(|config| config.token = gateway::new_token());
+            self.redraw(cx);
+        }
     }
 }
 
@@ -314,4 +474,59 @@ impl UtilitiesModal {
             self.redraw(cx);
         }
     }
+
+    fn update_gateway(&mut self, cx: &mut Cx, scope: &mut Scope) {
+        let store = scope.data.get::<Store>().unwrap();
+        let config = &store.preferences.gateway;
+
+        self.check_box(cx, ids!(gateway_toggle))
+            .set_active(cx, config.enabled);
+        self.check_box(cx, ids!(gateway_tools_toggle))
+            .set_active(cx, config.mcp_tools);
+        self.label(cx, ids!(token_label))
+            .set_text(cx, &config.token);
+
+        if !self.port_shown {
+            self.text_input(cx, ids!(port_input))
+                .set_text(cx, &config.port.to_string());
+            self.port_shown = true;
+        }
+
+        let status = match store.gateway.status() {
+            GatewayStatus::Stopped => "Stopped.".to_string(),
+            GatewayStatus::Starting => "Starting...".to_string(),
+            GatewayStatus::Running(addr) => format!("Listening on http://{}/v1", addr),
+            GatewayStatus::Failed(error) => format!("Failed to start: {}", error),
+        };
+        self.label(cx, ids!(gateway_status)).set_text(cx, &status);
+
+        let clients = store
+            .gateway
+            .logs()
+            .clients()
+            .iter()
+            .map(|(client, requests)| {
+                let failed = requests.iter().filter(|r| r.status >= 400).count();
+                let last = &requests[0];
+                format!(
+                    "{}: {} recent requests, {} failed, last {} {} ({}) at {}",
+                    client,
+                    requests.len(),
+                    failed,
+                    last.method,
+                    last.path,
+                    last.status,
+                    last.timestamp
+                        .with_timezone(&chrono::Local)
+                        .format("%H:%M:%S")
+                )
+            })
+            .collect::<Vec<_>>();
+        let clients = if clients.is_empty() {
+            "No requests yet.".to_string()
+        } else {
+            clients.join("\n")
+        };
+        self.label(cx, ids!(gateway_clients)).set_text(cx, &clients);
+    }
 }
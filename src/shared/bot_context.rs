@@ -1,12 +1,13 @@
 //! Glued copy paste of the legacy [`BotContext`] from Moly Kit.
 
 use moly_kit::prelude::*;
+use moly_kit::utils::mcp::{McpClient, McpToolsClient};
 use std::sync::{Arc, Mutex};
 
 struct InnerBotContext {
     client: Box<dyn BotClient>,
     bots: Vec<Bot>,
-    tool_manager: Option<McpManagerClient>,
+    tool_manager: Option<McpClient>,
     /// Status tracked for compatibility with [`ChatController`].
     status: Status,
     /// [`ChatController`]s "observing" this context. This is glue.
@@ -84,11 +85,11 @@ impl BotContext {
         self.bots().into_iter().find(|bot| bot.id == *id)
     }
 
-    pub fn tool_manager(&self) -> Option<McpManagerClient> {
+    pub fn tool_manager(&self) -> Option<McpClient> {
         self.0.lock().unwrap().tool_manager.clone()
     }
 
-    pub fn set_tool_manager(&mut self, tool_manager: McpManagerClient) {
+    pub fn set_tool_manager(&mut self, tool_manager: McpClient) {
         self.0.lock().unwrap().tool_manager = Some(tool_manager);
         self.synchronize_to_all();
     }
@@ -97,8 +98,11 @@ impl BotContext {
     ///
     /// This is a glue function while migrating away from [`BotContext`].
     fn synchronize_to(&self, chat_controller: &mut ChatController) {
-        chat_controller.set_tool_manager(self.tool_manager());
-        chat_controller.set_client(Some(self.client()));
+        let client = match self.tool_manager() {
+            Some(tool_manager) => Box::new(McpToolsClient::new(self.client(), tool_manager)),
+            None => self.client(),
+        };
+        chat_controller.set_client(Some(client));
         chat_controller.dispatch_mutation(VecMutation::Set(self.bots().clone()));
         chat_controller.dispatch_mutation(ChatStateMutation::SetLoadStatus(
             self.0.lock().unwrap().status,
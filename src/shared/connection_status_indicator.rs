@@ -0,0 +1,87 @@
+use crate::data::mcp_status::McpServerStatus;
+use crate::data::providers::ProviderConnectionStatus;
+use crate::data::store::Store;
+use makepad_widgets::*;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.ConnectionStatusIndicatorBase =
+        #(ConnectionStatusIndicator::register_widget(vm))
+    mod.widgets.ConnectionStatusIndicator =
+        set_type_default() do mod.widgets.ConnectionStatusIndicatorBase {
+        width: Fit
+        height: Fit
+        spacing: 5
+        align: Align {y: 0.5}
+
+        dot := RoundedView {
+            width: 8
+            height: 8
+            show_bg: true
+            draw_bg +: {
+                color: #12B76A
+                border_radius: uniform(4.0)
+            }
+        }
+
+        label := Label {
+            draw_text +: {
+                text_style: theme.font_regular {font_size: 8}
+                color: #667085
+            }
+        }
+    }
+}
+
+/// How many of the enabled providers and MCP servers can't be reached,
+/// as retried in the background by the store.
+#[derive(Script, ScriptHook, Widget)]
+pub struct ConnectionStatusIndicator {
+    #[deref]
+    view: View,
+}
+
+impl Widget for ConnectionStatusIndicator {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let store = scope.data.get::<Store>().unwrap();
+
+        let providers = store.chats.providers.values().filter(|p| p.enabled);
+        let mut total = 0;
+        let mut offline = 0;
+        for provider in providers {
+            total += 1;
+            if let ProviderConnectionStatus::Error(_) = provider.connection_status {
+                offline += 1;
+            }
+        }
+        for state in store.mcp_servers_state.values() {
+            total += 1;
+            if let McpServerStatus::Failed(_) = state.status {
+                offline += 1;
+            }
+        }
+
+        if total == 0 {
+            return DrawStep::done();
+        }
+
+        let (text, color) = match offline {
+            0 => ("All online".to_string(), vec4(0.071, 0.718, 0.416, 1.0)),
+            n if n == total => ("Offline".to_string(), vec4(0.941, 0.267, 0.22, 1.0)),
+            n => (format!("{} offline", n), vec4(0.969, 0.565, 0.035, 1.0)),
+        };
+        self.label(cx, ids!(label)).set_text(cx, &text);
+        let mut dot = self.view(cx, ids!(dot));
+        script_apply_eval!(cx, dot, {
+            draw_bg +: { color: #(color) }
+        });
+
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
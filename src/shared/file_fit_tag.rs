@@ -0,0 +1,87 @@
+use crate::data::hardware::FileFit;
+use makepad_widgets::*;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.FileFitTagBase = #(FileFitTag::register_widget(vm))
+    mod.widgets.FileFitTag = set_type_default() do mod.widgets.FileFitTagBase {
+        ..mod.widgets.RoundedView
+        visible: false
+        width: Fit
+        height: Fit
+        padding: Inset {top: 6 bottom: 6 left: 10 right: 10}
+
+        show_bg: true
+        draw_bg +: {
+            border_radius: uniform(2.0)
+            color: #ECFDF3
+        }
+
+        label := Label {
+            draw_text +: {
+                text_style: theme.font_regular {font_size: 9}
+                color: #067647
+            }
+        }
+    }
+}
+
+/// Badge telling whether a model file fits in the memory of this computer.
+#[derive(Script, ScriptHook, Widget)]
+pub struct FileFitTag {
+    #[deref]
+    view: View,
+}
+
+impl Widget for FileFitTag {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl FileFitTagRef {
+    /// Show `fit`, or hide the badge when it isn't known.
+    pub fn set_fit(&self, cx: &mut Cx, fit: Option<FileFit>, recommended: bool) {
+        let Some(mut inner) = self.borrow_mut() else {
+            return;
+        };
+        let Some(fit) = fit else {
+            inner.view.set_visible(cx, false);
+            return;
+        };
+
+        let (background, text_color) = match fit {
+            FileFit::Comfortable => (
+                vec4(0.925, 0.992, 0.953, 1.0),
+                vec4(0.024, 0.463, 0.278, 1.0),
+            ),
+            FileFit::Tight => (vec4(1.0, 0.98, 0.922, 1.0), vec4(0.71, 0.278, 0.035, 1.0)),
+            FileFit::WontFit => (
+                vec4(0.996, 0.953, 0.949, 1.0),
+                vec4(0.706, 0.137, 0.094, 1.0),
+            ),
+        };
+
+        let label = if recommended {
+            format!("Best for this computer: {}", fit.label().to_lowercase())
+        } else {
+            fit.label().to_string()
+        };
+
+        inner.view.set_visible(cx, true);
+        script_apply_eval!(cx, inner, {
+            draw_bg +: { color: #(background) }
+        });
+        let mut label_ref = inner.label(cx, ids!(label));
+        label_ref.set_text(cx, &label);
+        script_apply_eval!(cx, label_ref, {
+            draw_text +: { color: #(text_color) }
+        });
+    }
+}
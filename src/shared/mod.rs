@@ -2,12 +2,15 @@ use makepad_widgets::ScriptVm;
 
 pub mod actions;
 pub mod bot_context;
+pub mod connection_status_indicator;
 pub mod desktop_buttons;
 pub mod download_notification_popup;
 pub mod external_link;
+pub mod file_fit_tag;
 pub mod list;
 pub mod meta;
 pub mod moly_server_popup;
+pub mod moly_server_switcher;
 pub mod popup_notification;
 pub mod resource_imports;
 pub mod styles;
@@ -25,8 +28,11 @@ pub fn script_mod(vm: &mut ScriptVm) {
     toggle_panel::script_mod(vm);
     popup_notification::script_mod(vm);
     external_link::script_mod(vm);
+    file_fit_tag::script_mod(vm);
     download_notification_popup::script_mod(vm);
     tooltip::script_mod(vm);
     desktop_buttons::script_mod(vm);
     moly_server_popup::script_mod(vm);
+    moly_server_switcher::script_mod(vm);
+    connection_status_indicator::script_mod(vm);
 }
@@ -91,7 +91,7 @@ script_mod! {
                 text_style: REGULAR_FONT { font_size: 9 }
                 color: #000
             }
-            text: "Connection with MolySever interrupted.\nPlease check that the server is running and try again."
+            text: "Connection with MolySever interrupted.\nIts status and logs are in the MolyServer section."
         }
     }
 
@@ -0,0 +1,88 @@
+use crate::data::store::{Store, StoreAction};
+use makepad_widgets::*;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.MolyServerSwitcherBase = #(MolyServerSwitcher::register_widget(vm))
+    mod.widgets.MolyServerSwitcher = set_type_default() do mod.widgets.MolyServerSwitcherBase {
+        width: Fit
+        height: Fit
+        spacing: 8
+        align: Align {y: 0.5}
+
+        Label {
+            draw_text +: {
+                text_style: theme.font_regular {font_size: 9}
+                color: #667085
+            }
+            text: "Server"
+        }
+
+        servers := DropDownFlat {
+            width: Fit
+            height: Fit
+            padding: Inset {top: 4 bottom: 4 left: 8 right: 20}
+            draw_text +: {
+                text_style: theme.font_regular {font_size: 9}
+                get_color: fn() -> vec4 {
+                    return #344054
+                }
+            }
+        }
+    }
+}
+
+/// Picks the MolyServer to search, download and run models with.
+///
+/// Hidden while the local server is the only one.
+#[derive(Script, ScriptHook, Widget)]
+pub struct MolyServerSwitcher {
+    #[deref]
+    view: View,
+
+    /// Server ids, in the order of the labels.
+    #[rust]
+    ids: Vec<String>,
+}
+
+impl Widget for MolyServerSwitcher {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let store = scope.data.get::<Store>().unwrap();
+        let servers = store.moly_servers.list();
+        if servers.len() < 2 {
+            return DrawStep::done();
+        }
+
+        let (ids, labels): (Vec<_>, Vec<_>) = servers.into_iter().unzip();
+        let selected = ids
+            .iter()
+            .position(|id| id == store.moly_servers.active_id())
+            .unwrap_or(0);
+
+        let drop_down = self.drop_down(cx, ids!(servers));
+        if ids != self.ids {
+            drop_down.set_labels(cx, labels);
+            self.ids = ids;
+        }
+        drop_down.set_selected_item(cx, selected);
+
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for MolyServerSwitcher {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        if let Some(index) = self.drop_down(cx, ids!(servers)).selected(actions) {
+            if let Some(id) = self.ids.get(index) {
+                cx.action(StoreAction::SwitchMolyServer(id.clone()));
+            }
+        }
+    }
+}
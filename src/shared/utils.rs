@@ -28,6 +28,41 @@ pub fn format_model_downloaded_size(size: &str, progress: f64) -> Result<String>
     }
 }
 
+/// A size in bytes, like `4.37 GB` or `512 MB`.
+pub fn format_bytes(bytes: u64) -> String {
+    let size_mb = bytes as f64 / BYTES_PER_MB;
+    if size_mb >= 1024.0 {
+        format!("{:.2} GB", size_mb / 1024.0)
+    } else {
+        format!("{} MB", size_mb as i64)
+    }
+}
+
+/// Speed of a download, like `10.59 MB/s`.
+pub fn format_transfer_speed(bytes_per_second: f64) -> String {
+    let mb_per_second = bytes_per_second / BYTES_PER_MB;
+    if mb_per_second >= 1024.0 {
+        format!("{:.2} GB/s", mb_per_second / 1024.0)
+    } else if mb_per_second >= 1.0 {
+        format!("{:.2} MB/s", mb_per_second)
+    } else {
+        format!("{:.0} KB/s", bytes_per_second / 1024.0)
+    }
+}
+
+/// Time left of a download, like `1h 05m`, `4m 12s` or `35s`.
+pub fn format_time_left(seconds: f64) -> String {
+    let seconds = seconds.max(0.0).round() as u64;
+    let (hours, minutes) = (seconds / 3600, (seconds % 3600) / 60);
+    if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
 pub fn hugging_face_model_url(model_id: &str) -> String {
     format!("{}/{}", HUGGING_FACE_BASE_URL, model_id)
 }
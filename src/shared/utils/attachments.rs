@@ -6,6 +6,7 @@ use std::{
 };
 
 use moly_kit::prelude::*;
+use moly_kit::utils::extraction::ExtractionCache;
 
 pub fn generate_persistence_key(attachment: &Attachment) -> String {
     // If `filename.a.b` this is `.b`.
@@ -57,6 +58,14 @@ pub async fn delete_attachment(attachment: &Attachment) -> std::io::Result<()> {
         .await
         .map_err(|e| std::io::Error::other(e))?;
 
+    // The extracted text may not exist if the attachment was never read as a document.
+    let extraction_path = PathBuf::from(extraction_key(&key));
+    if fs.exists(&extraction_path).await.unwrap_or(false) {
+        fs.remove(&extraction_path)
+            .await
+            .map_err(|e| std::io::Error::other(e))?;
+    }
+
     Ok(())
 }
 
@@ -70,3 +79,43 @@ pub async fn write_attachment_to_key(attachment: &Attachment, key: &str) -> std:
         .map_err(|e| std::io::Error::other(e))?;
     Ok(())
 }
+
+/// Key where the text extracted from a persisted attachment is cached.
+pub fn extraction_key(persistence_key: &str) -> String {
+    format!("{}.extracted.md", persistence_key)
+}
+
+/// Caches text extracted from documents next to their persisted attachment.
+///
+/// Attachments that are not persisted yet (like the ones still in the prompt input)
+/// are simply not cached.
+pub struct PersistedExtractionCache;
+
+impl ExtractionCache for PersistedExtractionCache {
+    fn load(&self, attachment: &Attachment) -> BoxPlatformSendFuture<'static, Option<String>> {
+        let key = attachment
+            .get_persistence_key()
+            .map(|key| extraction_key(&key));
+        Box::pin(async move {
+            let path = PathBuf::from(key?);
+            super::filesystem::global().read_string(&path).await.ok()
+        })
+    }
+
+    fn store(&self, attachment: &Attachment, text: &str) -> BoxPlatformSendFuture<'static, ()> {
+        let key = attachment
+            .get_persistence_key()
+            .map(|key| extraction_key(&key));
+        let text = text.to_string();
+        Box::pin(async move {
+            let Some(key) = key else {
+                return;
+            };
+
+            let mut fs = super::filesystem::global();
+            if let Err(e) = fs.queue_write_string(PathBuf::from(&key), text).await {
+                ::log::warn!("Failed to cache extracted text at {}: {}", key, e);
+            }
+        })
+    }
+}
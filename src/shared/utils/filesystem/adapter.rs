@@ -27,6 +27,24 @@ pub trait Adapter: Send + Sync + 'static {
         content: &[u8],
     ) -> impl PlatformSendFuture<Output = Result<()>>;
 
+    /// Like `write`, but leaving the file readable by the current user only,
+    /// where the platform has such permissions.
+    fn write_private(
+        &mut self,
+        path: &Path,
+        content: &[u8],
+    ) -> impl PlatformSendFuture<Output = Result<()>> {
+        self.write(path, content)
+    }
+
+    /// Append some binary content to the end of a file, creating it and any
+    /// necessary directories if missing.
+    fn append(
+        &mut self,
+        path: &Path,
+        content: &[u8],
+    ) -> impl PlatformSendFuture<Output = Result<()>>;
+
     /// Read a file from the filesystem, returning its content as a byte vector.
     fn read(&mut self, path: &Path) -> impl PlatformSendFuture<Output = Result<Vec<u8>>>;
 
@@ -31,8 +31,9 @@ use std::{
 };
 
 use super::super::adapter::Adapter;
+use super::replace_file;
 use anyhow::Result;
-use futures::StreamExt;
+use futures::{AsyncWriteExt, StreamExt};
 
 /// Global storage for mobile data directory path
 static MOBILE_DATA_DIR: LazyLock<Mutex<Option<PathBuf>>> = LazyLock::new(|| Mutex::new(None));
@@ -125,9 +126,25 @@ impl Adapter for MobileAdapter {
     }
 
     async fn write(&mut self, path: &Path, content: &[u8]) -> Result<()> {
+        let path = validate_and_resolve(path);
+        replace_file(&path, content, false).await
+    }
+
+    async fn write_private(&mut self, path: &Path, content: &[u8]) -> Result<()> {
+        let path = validate_and_resolve(path);
+        replace_file(&path, content, true).await
+    }
+
+    async fn append(&mut self, path: &Path, content: &[u8]) -> Result<()> {
         let path = validate_and_resolve(path);
         async_fs::create_dir_all(path.parent().unwrap()).await?;
-        async_fs::write(path, content).await?;
+        let mut file = async_fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        file.write_all(content).await?;
+        file.flush().await?;
         Ok(())
     }
 }
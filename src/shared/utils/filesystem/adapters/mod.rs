@@ -12,3 +12,45 @@ pub use mobile::*;
 pub mod native;
 #[cfg(target_arch = "wasm32")]
 pub mod web;
+
+/// Replace the content of the file at `path`, creating any necessary directories.
+///
+/// The content goes to a temporary file next to it first, moved over `path`
+/// once complete, so exiting mid-write never leaves a truncated file.
+/// With `private`, the file is readable by the current user only.
+#[cfg(not(target_arch = "wasm32"))]
+async fn replace_file(path: &std::path::Path, content: &[u8], private: bool) -> anyhow::Result<()> {
+    use futures::AsyncWriteExt;
+
+    async_fs::create_dir_all(path.parent().unwrap()).await?;
+
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(path.file_name().unwrap());
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut options = async_fs::OpenOptions::new();
+    options.create(true).write(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use async_fs::unix::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+
+    let mut file = options.open(&temp_path).await?;
+    // The mode only applies to new files, one left by an interrupted write is
+    // restricted before anything is written to it.
+    #[cfg(unix)]
+    if private {
+        use async_fs::unix::PermissionsExt;
+        async_fs::set_permissions(&temp_path, std::fs::Permissions::from_mode(0o600)).await?;
+    }
+    file.write_all(content).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    async_fs::rename(&temp_path, path).await?;
+    Ok(())
+}
@@ -50,4 +50,16 @@ impl Adapter for WebAdapter {
         web_fs::write(path, content).await?;
         Ok(())
     }
+
+    // OPFS files are only open from a single tab, so reading the file back
+    // can't race with another writer.
+    async fn append(&mut self, path: &Path, content: &[u8]) -> Result<()> {
+        let mut existing = match web_fs::read(path).await {
+            Ok(existing) => existing,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        existing.extend_from_slice(content);
+        self.write(path, &existing).await
+    }
 }
@@ -155,6 +155,24 @@ impl<A: Adapter> FileSystem<A> {
         adapter.list(path).await
     }
 
+    /// Append some bytes content to the end of a file, creating it if missing.
+    ///
+    /// Unlike writes, appends are not queued, as they don't replace what other
+    /// writers put in the file.
+    pub async fn append(&self, path: &Path, content: &[u8]) -> Result<()> {
+        let mut adapter = self.adapter.lock().await;
+        adapter.append(path, content).await
+    }
+
+    /// Write content only the current user can read, like credentials, creating
+    /// any necessary directories.
+    ///
+    /// Like appends, these writes are not queued.
+    pub async fn write_private(&self, path: &Path, content: &[u8]) -> Result<()> {
+        let mut adapter = self.adapter.lock().await;
+        adapter.write_private(path, content).await
+    }
+
     /// Write some bytes content to a given path, creating any necessary directories.
     // TODO: Is adapter responsability to create directories, but it shouldn't.
     pub async fn queue_write(&mut self, path: PathBuf, content: Vec<u8>) -> Result<()> {